@@ -0,0 +1,24 @@
+;; Lists the account of every posting, warning about postings after the first half
+;; of 2022.
+(module
+  (import "roasted" "posting_count" (func $count (result i32)))
+  (import "roasted" "posting_date" (func $date (param i32) (result i32)))
+  (import "roasted" "posting_account" (func $account (param i32 i32 i32) (result i32)))
+  (import "roasted" "emit" (func $emit (param i32 i32 i32)))
+  (import "roasted" "write" (func $write (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "posting after 2022-06-30\n")
+  (func (export "run")
+    (local $idx i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $idx) (call $count)))
+        (call $write
+          (i32.const 1024)
+          (call $account (local.get $idx) (i32.const 1024) (i32.const 256)))
+        (call $write (i32.const 24) (i32.const 1))
+        (if (i32.gt_s (call $date (local.get $idx)) (i32.const 20220630))
+          (then (call $emit (i32.const 0) (i32.const 0) (i32.const 24))))
+        (local.set $idx (i32.add (local.get $idx) (i32.const 1)))
+        (br $next))))
+)
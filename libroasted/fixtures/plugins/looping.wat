@@ -0,0 +1,2 @@
+;; Never returns.
+(module (func (export "run") (loop br 0)))
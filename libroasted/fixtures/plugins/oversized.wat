@@ -0,0 +1,5 @@
+;; Writes text claimed to be far longer than its memory.
+(module
+  (import "roasted" "write" (func $write (param i32 i32)))
+  (memory (export "memory") 1)
+  (func (export "run") (call $write (i32.const 0) (i32.const 0x7fffffff))))
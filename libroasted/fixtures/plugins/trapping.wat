@@ -0,0 +1,2 @@
+;; Traps as soon as it runs.
+(module (func (export "run") unreachable))
@@ -2,12 +2,18 @@ use chrono::NaiveDate;
 use std::cmp::PartialEq;
 use std::collections::BTreeMap;
 use std::fmt;
+use std::ops::Bound;
 
+use crate::error::StatementError;
 use crate::parser::Rule;
+use crate::symbol::SymbolTable;
 use anyhow::{anyhow, Result};
 use camelpaste::paste;
 use pest::iterators::Pair;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ParsedAccount<'a> {
     Assets(Vec<&'a str>),
@@ -43,31 +49,25 @@ impl<'a> TryFrom<&'a str> for ParsedAccount<'a> {
     type Error = anyhow::Error;
 
     fn try_from(s: &'a str) -> Result<Self> {
-        if s.starts_with("Assets:") {
-            return Ok(ParsedAccount::Assets(ParsedAccount::base_name(s)));
-        }
-
-        if s.starts_with("Expenses:") {
-            return Ok(ParsedAccount::Expenses(ParsedAccount::base_name(s)));
-        }
-
-        if s.starts_with("Liabilities:") {
-            return Ok(ParsedAccount::Liabilities(ParsedAccount::base_name(s)));
-        }
-
-        if s.starts_with("Income:") {
-            return Ok(ParsedAccount::Income(ParsedAccount::base_name(s)));
+        // Account type names are matched regardless of case.
+        let (kind, _) = s
+            .split_once(':')
+            .ok_or(anyhow!("input `{}' is not a valid token for Account", s))?;
+        let base = ParsedAccount::base_name(s);
+
+        match kind.to_ascii_lowercase().as_str() {
+            "assets" => Ok(ParsedAccount::Assets(base)),
+            "expenses" => Ok(ParsedAccount::Expenses(base)),
+            "liabilities" => Ok(ParsedAccount::Liabilities(base)),
+            "income" => Ok(ParsedAccount::Income(base)),
+            "equity" => Ok(ParsedAccount::Equity(base)),
+            _ => Err(anyhow!("input `{}' is not a valid token for Account", s)),
         }
-
-        if s.starts_with("Equity:") {
-            return Ok(ParsedAccount::Equity(ParsedAccount::base_name(s)));
-        }
-
-        Err(anyhow!("input `{}' is not a valid token for Account", s))
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TxnAccount {
     Assets(Vec<usize>),
     Expenses(Vec<usize>),
@@ -76,20 +76,108 @@ pub enum TxnAccount {
     Equity(Vec<usize>),
 }
 
+impl TxnAccount {
+    /// Segment indexes below the account type.
+    fn segments(&self) -> &Vec<usize> {
+        match self {
+            TxnAccount::Assets(idxs)
+            | TxnAccount::Expenses(idxs)
+            | TxnAccount::Liabilities(idxs)
+            | TxnAccount::Income(idxs)
+            | TxnAccount::Equity(idxs) => idxs,
+        }
+    }
+
+    fn with_segments(&self, idxs: Vec<usize>) -> TxnAccount {
+        match self {
+            TxnAccount::Assets(_) => TxnAccount::Assets(idxs),
+            TxnAccount::Expenses(_) => TxnAccount::Expenses(idxs),
+            TxnAccount::Liabilities(_) => TxnAccount::Liabilities(idxs),
+            TxnAccount::Income(_) => TxnAccount::Income(idxs),
+            TxnAccount::Equity(_) => TxnAccount::Equity(idxs),
+        }
+    }
+
+    /// Whether the account is `ancestor` itself or one of its sub-accounts.
+    pub fn is_under(&self, ancestor: &TxnAccount) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(ancestor)
+            && self.segments().starts_with(ancestor.segments())
+    }
+
+    /// The account one segment up, the bare account type for a top-level account, `None`
+    /// for an account type.
+    pub fn parent(&self) -> Option<TxnAccount> {
+        let idxs = self.segments();
+        let (_, parent) = idxs.split_last()?;
+        Some(self.with_segments(parent.to_vec()))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AccountActivities {
     opened_at: NaiveDate,
     closed_at: Option<NaiveDate>,
 }
 
+impl AccountActivities {
+    pub fn opened_at(&self) -> &NaiveDate {
+        &self.opened_at
+    }
+
+    pub fn closed_at(&self) -> Option<&NaiveDate> {
+        self.closed_at.as_ref()
+    }
+}
+
+/// Presentation hints read from account metadata, so every frontend lays out accounts
+/// the same way.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccountHints {
+    /// `color` metadata, passed through as written.
+    pub color: Option<String>,
+    /// `order` metadata, accounts with lower order come first. Defaults to 0.
+    pub order: i64,
+    /// `hidden: "true"` keeps the account out of reports unless asked for.
+    pub hidden: bool,
+}
+
+impl AccountHints {
+    pub fn from_metadata(metadata: &BTreeMap<String, String>) -> Result<Self> {
+        Ok(AccountHints {
+            color: metadata.get("color").cloned(),
+            order: metadata
+                .get("order")
+                .map(|order| order.parse::<i64>())
+                .transpose()
+                .map_err(|err| anyhow!("invalid account order: {}", err))?
+                .unwrap_or_default(),
+            hidden: metadata
+                .get("hidden")
+                .is_some_and(|hidden| hidden == "true"),
+        })
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AccountStore {
-    segments: Vec<String>,
+    segments: SymbolTable<String>,
+    /// Lowercased segments, pointing to the first segment interned with that spelling.
+    folded: BTreeMap<String, usize>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::pairs"))]
     assets: BTreeMap<Vec<usize>, AccountActivities>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::pairs"))]
     expenses: BTreeMap<Vec<usize>, AccountActivities>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::pairs"))]
     liabilities: BTreeMap<Vec<usize>, AccountActivities>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::pairs"))]
     income: BTreeMap<Vec<usize>, AccountActivities>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::pairs"))]
     equity: BTreeMap<Vec<usize>, AccountActivities>,
+    case_insensitive: bool,
+    close_inclusive: bool,
+    forbid_parent_postings: bool,
 }
 
 impl AccountStore {
@@ -97,14 +185,42 @@ impl AccountStore {
         Default::default()
     }
 
+    /// When enabled, account segments are matched regardless of case, and displayed
+    /// with the case they were first seen with.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+    }
+
+    /// When enabled, an account closed on a date is still usable on that date and only
+    /// closed from the next one. Otherwise it's closed from the start of its closing date.
+    pub fn set_close_inclusive(&mut self, close_inclusive: bool) {
+        self.close_inclusive = close_inclusive;
+    }
+
+    /// When enabled, postings can't go to an account with open sub-accounts, e.g.
+    /// `Expenses:Food` once `Expenses:Food:Groceries` is open, so every amount of a
+    /// subtree is on one of its leaves.
+    pub fn set_forbid_parent_postings(&mut self, forbid_parent_postings: bool) {
+        self.forbid_parent_postings = forbid_parent_postings;
+    }
+
+    fn segment_position(&self, segment: &str) -> Option<usize> {
+        if self.case_insensitive {
+            return self.folded.get(&segment.to_lowercase()).copied();
+        }
+
+        self.segments.get(segment)
+    }
+
     fn index_segments(&mut self, v: &[&str]) -> Vec<usize> {
         let mut idxs: Vec<usize> = Vec::new();
         for segment in v {
-            if let Some(ppos) = self.segments.iter().position(|s| s == segment) {
+            if let Some(ppos) = self.segment_position(segment) {
                 idxs.push(ppos);
             } else {
-                self.segments.push(segment.to_string());
-                idxs.push(self.segments.len() - 1);
+                let idx = self.segments.intern(segment.to_string());
+                self.folded.entry(segment.to_lowercase()).or_insert(idx);
+                idxs.push(idx);
             }
         }
 
@@ -114,7 +230,7 @@ impl AccountStore {
     fn lookup_index(&self, v: &[&str]) -> Option<Vec<usize>> {
         let mut idxs: Vec<usize> = Vec::new();
         for segment in v {
-            let pos = self.segments.iter().position(|s| s == segment)?;
+            let pos = self.segment_position(segment)?;
             idxs.push(pos);
         }
 
@@ -139,6 +255,22 @@ impl AccountStore {
         Ok(())
     }
 
+    /// Whether the account has ever been opened, regardless of the date.
+    pub fn is_declared(&self, acc: &ParsedAccount<'_>) -> bool {
+        macro_rules! declared {
+            ($($account_type:ident),*) => {
+                match acc {$(
+                    ParsedAccount::$account_type(val) => paste! {
+                        self.lookup_index(val)
+                            .is_some_and(|idxs| self.[<$account_type:lower>].contains_key(&idxs))
+                    },
+                )*}
+            }
+        }
+
+        declared![Assets, Expenses, Income, Liabilities, Equity]
+    }
+
     fn close_account(
         account_set: &mut BTreeMap<Vec<usize>, AccountActivities>,
         idxs: &[usize],
@@ -163,58 +295,169 @@ impl AccountStore {
         Ok(())
     }
 
-    fn txn_account_valid_at(&self, date: &NaiveDate, txn_acct: TxnAccount) -> Option<TxnAccount> {
-        let activities = match &txn_acct {
+    /// Every declared account, whether it's currently open or not.
+    pub fn iter(&self) -> impl Iterator<Item = (TxnAccount, &AccountActivities)> {
+        fn accounts(
+            map: &BTreeMap<Vec<usize>, AccountActivities>,
+            variant: fn(Vec<usize>) -> TxnAccount,
+        ) -> impl Iterator<Item = (TxnAccount, &AccountActivities)> {
+            map.iter()
+                .map(move |(idxs, activities)| (variant(idxs.clone()), activities))
+        }
+
+        accounts(&self.assets, TxnAccount::Assets)
+            .chain(accounts(&self.liabilities, TxnAccount::Liabilities))
+            .chain(accounts(&self.equity, TxnAccount::Equity))
+            .chain(accounts(&self.income, TxnAccount::Income))
+            .chain(accounts(&self.expenses, TxnAccount::Expenses))
+    }
+
+    pub fn activities(&self, txn_acct: &TxnAccount) -> Option<&AccountActivities> {
+        match txn_acct {
             TxnAccount::Assets(idxs) => self.assets.get(idxs),
             TxnAccount::Expenses(idxs) => self.expenses.get(idxs),
             TxnAccount::Liabilities(idxs) => self.liabilities.get(idxs),
             TxnAccount::Income(idxs) => self.income.get(idxs),
             TxnAccount::Equity(idxs) => self.equity.get(idxs),
-        };
-        if let Some(activity) = activities {
-            match activity.closed_at {
-                Some(cdate) => {
-                    if &activity.opened_at <= date && &cdate > date {
-                        return Some(txn_acct);
-                    }
-                }
-                None => {
-                    if &activity.opened_at <= date {
-                        return Some(txn_acct);
-                    }
-                }
-            }
         }
-        None
     }
 
-    pub fn txnify(&self, date: &NaiveDate, acc: &ParsedAccount<'_>) -> Result<TxnAccount> {
-        let txn_account = match acc {
+    fn open_between(activity: &AccountActivities, date: &NaiveDate, close_inclusive: bool) -> bool {
+        &activity.opened_at <= date
+            && activity
+                .closed_at
+                .map_or(true, |cdate| match close_inclusive {
+                    true => &cdate >= date,
+                    false => &cdate > date,
+                })
+    }
+
+    /// Whether the account can be used on the given date, see
+    /// [`set_close_inclusive`][Self::set_close_inclusive] for its closing date.
+    pub fn is_open_at(&self, txn_acct: &TxnAccount, date: &NaiveDate) -> bool {
+        self.activities(txn_acct)
+            .is_some_and(|activity| Self::open_between(activity, date, self.close_inclusive))
+    }
+
+    fn accounts_of(&self, txn_acct: &TxnAccount) -> &BTreeMap<Vec<usize>, AccountActivities> {
+        match txn_acct {
+            TxnAccount::Assets(_) => &self.assets,
+            TxnAccount::Expenses(_) => &self.expenses,
+            TxnAccount::Liabilities(_) => &self.liabilities,
+            TxnAccount::Income(_) => &self.income,
+            TxnAccount::Equity(_) => &self.equity,
+        }
+    }
+
+    /// Whether any sub-account of the account is open at the given date.
+    pub fn has_open_children(&self, txn_acct: &TxnAccount, date: &NaiveDate) -> bool {
+        self.subtree(txn_acct)
+            .filter(|(child, _)| child != txn_acct)
+            .any(|(_, activity)| Self::open_between(activity, date, self.close_inclusive))
+    }
+
+    /// Resolve the account whether it's declared or not, e.g. `Expenses:Travel` when
+    /// only its sub-accounts are, or a bare `Expenses`. `None` when one of its segments
+    /// was never declared.
+    pub fn lookup(&self, acc: &ParsedAccount<'_>) -> Option<TxnAccount> {
+        match acc {
             ParsedAccount::Assets(val) => self.lookup_index(val).map(TxnAccount::Assets),
             ParsedAccount::Expenses(val) => self.lookup_index(val).map(TxnAccount::Expenses),
             ParsedAccount::Liabilities(val) => self.lookup_index(val).map(TxnAccount::Liabilities),
             ParsedAccount::Income(val) => self.lookup_index(val).map(TxnAccount::Income),
             ParsedAccount::Equity(val) => self.lookup_index(val).map(TxnAccount::Equity),
-        };
+        }
+    }
+
+    /// Every declared account under `root`, itself included when declared, whether
+    /// they're currently open or not.
+    pub fn subtree<'s>(
+        &'s self,
+        root: &'s TxnAccount,
+    ) -> impl Iterator<Item = (TxnAccount, &'s AccountActivities)> {
+        let idxs = root.segments();
+        // Sub-accounts sort right after their parent, and before any of its siblings.
+        self.accounts_of(root)
+            .range::<Vec<usize>, _>((Bound::Included(idxs), Bound::Unbounded))
+            .take_while(|(child, _)| child.starts_with(idxs))
+            .map(|(child, activities)| (root.with_segments(child.clone()), activities))
+    }
 
-        txn_account
-            .and_then(|txnacct| self.txn_account_valid_at(date, txnacct))
-            .ok_or(anyhow!(format!(
-                "account `{}' is not opened at {}",
-                acc, date
-            )))
+    /// Accounts one segment below `parent`, declared or only implied by their own
+    /// sub-accounts.
+    pub fn children(&self, parent: &TxnAccount) -> Vec<TxnAccount> {
+        let depth = parent.segments().len() + 1;
+        let mut children: Vec<TxnAccount> = self
+            .subtree(parent)
+            .filter(|(account, _)| account.segments().len() >= depth)
+            .map(|(account, _)| parent.with_segments(account.segments()[..depth].to_vec()))
+            .collect();
+        children.dedup();
+        children
+    }
+
+    pub fn txnify(&self, date: &NaiveDate, acc: &ParsedAccount<'_>) -> Result<TxnAccount> {
+        self.txnify_with(date, acc, self.close_inclusive)
+    }
+
+    /// Like [`txnify`][Self::txnify], for the account of a posting, see
+    /// [`set_forbid_parent_postings`][Self::set_forbid_parent_postings].
+    pub fn txnify_posting(&self, date: &NaiveDate, acc: &ParsedAccount<'_>) -> Result<TxnAccount> {
+        let txn_account = self.txnify(date, acc)?;
+        if self.forbid_parent_postings && self.has_open_children(&txn_account, date) {
+            return Err(anyhow!(
+                "account `{}' has sub-accounts at {}, post to one of them instead",
+                acc,
+                date
+            ));
+        }
+        Ok(txn_account)
+    }
+
+    /// Like [`txnify`][Self::txnify], with the account still usable on its closing date.
+    /// Balance assertions check the balance at the start of their date, so they're valid
+    /// on the closing date whatever the policy.
+    pub fn txnify_through_close(
+        &self,
+        date: &NaiveDate,
+        acc: &ParsedAccount<'_>,
+    ) -> Result<TxnAccount> {
+        self.txnify_with(date, acc, true)
+    }
+
+    fn txnify_with(
+        &self,
+        date: &NaiveDate,
+        acc: &ParsedAccount<'_>,
+        close_inclusive: bool,
+    ) -> Result<TxnAccount> {
+        self.lookup(acc)
+            .filter(|txnacct| {
+                self.activities(txnacct)
+                    .is_some_and(|activity| Self::open_between(activity, date, close_inclusive))
+            })
+            .ok_or_else(|| {
+                StatementError::UnopenedAccount {
+                    account: acc.to_string(),
+                    date: *date,
+                }
+                .into()
+            })
     }
 
     fn lookup_segments<'a>(&'a self, v: &[usize]) -> Result<Vec<&'a str>> {
         let mut segments = Vec::new();
         for &idx in v {
-            let segment = self.segments.get(idx).ok_or(anyhow!("undefined account"))?;
+            let segment = self
+                .segments
+                .resolve(idx)
+                .ok_or(anyhow!("undefined account"))?;
             segments.push(segment.as_str());
         }
         Ok(segments)
     }
 
-    pub fn accountify(&self, actxn: &TxnAccount) -> Result<ParsedAccount> {
+    pub fn accountify(&self, actxn: &TxnAccount) -> Result<ParsedAccount<'_>> {
         match actxn {
             TxnAccount::Assets(idxs) => Ok(ParsedAccount::Assets(self.lookup_segments(idxs)?)),
             TxnAccount::Expenses(idxs) => Ok(ParsedAccount::Expenses(self.lookup_segments(idxs)?)),
@@ -279,11 +522,68 @@ mod tests {
             ParsedAccount::Equity(vec!["Previous-Balance"]),
             "Equity:Previous-Balance".try_into()?
         );
+        assert_eq!(
+            ParsedAccount::Expenses(vec!["food"]),
+            "expenses:food".try_into()?
+        );
         let result: Result<ParsedAccount> = "Outcome:Statement".try_into();
         assert_eq!(
             "input `Outcome:Statement' is not a valid token for Account",
             format!("{}", result.unwrap_err())
         );
+        let result: Result<ParsedAccount> = "Assets".try_into();
+        assert_eq!(
+            "input `Assets' is not a valid token for Account",
+            format!("{}", result.unwrap_err())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_insensitive_accounts() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2021, 10, 25).ok_or(anyhow!("invalid date"))?;
+        let canonical: ParsedAccount = "Assets:Bank:Jawir".try_into()?;
+        let typed: ParsedAccount = "assets:BANK:jawir".try_into()?;
+
+        let mut store = AccountStore::new();
+        store.open(&canonical, date)?;
+        assert!(store.txnify(&date, &typed).is_err());
+
+        store.set_case_insensitive(true);
+        let txn_account = store.txnify(&date, &typed)?;
+        assert_eq!(txn_account, TxnAccount::Assets(vec![0, 1]));
+        assert_eq!(store.accountify(&txn_account)?, canonical);
+
+        store.open(&typed, date)?;
+        assert_eq!(store.txnify(&date, &canonical)?, txn_account);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forbid_parent_postings() -> Result<()> {
+        let opened = NaiveDate::from_ymd_opt(2022, 1, 1).ok_or(anyhow!("invalid date"))?;
+        let split = NaiveDate::from_ymd_opt(2022, 2, 1).ok_or(anyhow!("invalid date"))?;
+        let food: ParsedAccount = "Expenses:Food".try_into()?;
+        let groceries: ParsedAccount = "Expenses:Food:Groceries".try_into()?;
+        let fun: ParsedAccount = "Expenses:Fun".try_into()?;
+
+        let mut store = AccountStore::new();
+        store.open(&food, opened)?;
+        store.open(&groceries, split)?;
+        store.open(&fun, opened)?;
+        store.set_forbid_parent_postings(true);
+
+        assert!(store.txnify_posting(&opened, &food).is_ok());
+        assert!(store.txnify_posting(&split, &food).is_err());
+        assert!(store.txnify_posting(&split, &groceries).is_ok());
+        assert!(store.txnify_posting(&split, &fun).is_ok());
+        // Only postings are checked, the parent can still be closed or asserted.
+        assert!(store.txnify(&split, &food).is_ok());
+
+        store.set_forbid_parent_postings(false);
+        assert!(store.txnify_posting(&split, &food).is_ok());
+
         Ok(())
     }
 
@@ -1,44 +1,271 @@
 use crate::parser::Rule;
 use anyhow::{anyhow, Result};
 use pest::iterators::Pair;
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// `a + b`, an error rather than a panic when the sum is out of the range of `Decimal`.
+pub(crate) fn checked_add(a: Decimal, b: Decimal) -> Result<Decimal> {
+    a.checked_add(b)
+        .ok_or(anyhow!("amount overflow at `{} + {}'", a, b))
+}
+
+/// `a - b`, see [`checked_add`].
+pub(crate) fn checked_sub(a: Decimal, b: Decimal) -> Result<Decimal> {
+    a.checked_sub(b)
+        .ok_or(anyhow!("amount overflow at `{} - {}'", a, b))
+}
+
+/// `a * b`, see [`checked_add`].
+pub(crate) fn checked_mul(a: Decimal, b: Decimal) -> Result<Decimal> {
+    a.checked_mul(b)
+        .ok_or(anyhow!("amount overflow at `{} * {}'", a, b))
+}
+
+/// `a / b`, see [`checked_add`].
+pub(crate) fn checked_div(a: Decimal, b: Decimal) -> Result<Decimal> {
+    a.checked_div(b)
+        .ok_or(anyhow!("amount overflow at `{} / {}'", a, b))
+}
 
 #[derive(Debug, PartialEq)]
 pub struct ParsedAmount<'s> {
-    pub(crate) nominal: f64,
+    pub(crate) nominal: Decimal,
     pub(crate) unit: &'s str,
+    /// The amount as written, e.g. `1_250.00 USD` or `50% of 300 USD`.
+    pub(crate) text: Option<&'s str>,
+    /// Price annotation following a posting amount.
+    pub(crate) price: Option<ParsedPrice<'s>>,
+}
+
+/// A `@ 15000 IDR` price per unit, or a `@@ 150000 IDR` total cost, as written.
+#[derive(Debug, PartialEq)]
+pub struct ParsedPrice<'s> {
+    pub(crate) nominal: Decimal,
+    pub(crate) unit: &'s str,
+    pub(crate) total: bool,
+}
+
+impl<'a> ParsedPrice<'a> {
+    pub fn nominal(&self) -> Decimal {
+        self.nominal
+    }
+
+    pub fn unit(&self) -> &'a str {
+        self.unit
+    }
+
+    /// Whether it's the `@@` cost of the whole amount rather than a price per unit.
+    pub fn is_total(&self) -> bool {
+        self.total
+    }
+
+    pub fn parse(token: Pair<'a, Rule>) -> Result<ParsedPrice<'a>> {
+        let mut pairs = token.into_inner().peekable();
+        let total = pairs
+            .next_if(|pair| pair.as_rule() == Rule::total_price)
+            .is_some();
+        let amount = ParsedAmount::parse(
+            pairs
+                .next()
+                .ok_or(anyhow!("invalid price: missing amount"))?,
+        )?;
+        if amount.nominal.is_sign_negative() {
+            return Err(anyhow!("price can't be negative: {}", amount.nominal));
+        }
+        Ok(Self {
+            nominal: amount.nominal,
+            unit: amount.unit,
+            total,
+        })
+    }
 }
 
 impl<'a> ParsedAmount<'a> {
+    pub fn nominal(&self) -> Decimal {
+        self.nominal
+    }
+
+    pub fn unit(&self) -> &'a str {
+        self.unit
+    }
+
+    pub fn text(&self) -> Option<&'a str> {
+        self.text
+    }
+
+    pub fn price(&self) -> Option<&ParsedPrice<'a>> {
+        self.price.as_ref()
+    }
+
     pub fn parse(token: Pair<'a, Rule>) -> Result<ParsedAmount<'a>> {
+        if token.as_rule() == Rule::split_amount {
+            return Self::parse_split(token);
+        }
+
+        let text = token.as_str();
         let mut amount = token.into_inner();
         Ok(Self {
             nominal: amount
                 .next()
                 .ok_or(anyhow!(format!("invalid nominal: '{}'", amount.as_str())))?
                 .as_str()
-                .parse::<f64>()?,
+                .replace('_', "")
+                .parse::<Decimal>()?,
             unit: amount
                 .next()
                 .ok_or(anyhow!(format!("invalid currency: '{}'", amount.as_str())))?
                 .as_str(),
+            text: Some(text),
+            price: None,
+        })
+    }
+
+    /// Expand `50% of -1200 USD` into the concrete share of the given amount.
+    fn parse_split(token: Pair<'a, Rule>) -> Result<ParsedAmount<'a>> {
+        let text = token.as_str();
+        let mut split = token.into_inner();
+        let percentage = split
+            .next()
+            .ok_or(anyhow!(format!("invalid percentage: '{}'", split.as_str())))?
+            .as_str()
+            .trim_end_matches('%')
+            .replace('_', "")
+            .parse::<Decimal>()?;
+        let total = Self::parse(split.next().ok_or(anyhow!(format!(
+            "invalid split amount: '{}'",
+            split.as_str()
+        )))?)?;
+
+        Ok(Self {
+            nominal: (total.nominal * percentage / Decimal::ONE_HUNDRED).normalize(),
+            unit: total.unit,
+            text: Some(text),
+            price: None,
         })
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Amount {
-    pub nominal: f64,
+    pub nominal: Decimal,
     pub unit: usize,
 }
 
 impl Amount {
     pub fn zero(unit: usize) -> Self {
         Self {
-            nominal: 0f64,
+            nominal: Decimal::ZERO,
             unit,
         }
     }
     pub fn is_zero(&self) -> bool {
-        self.nominal == 0f64
+        self.nominal.is_zero()
+    }
+
+    /// Sum of both amounts, `None` when their units differ.
+    pub fn checked_add(&self, other: &Amount) -> Option<Amount> {
+        (self.unit == other.unit).then_some(Amount {
+            nominal: self.nominal + other.nominal,
+            unit: self.unit,
+        })
+    }
+
+    /// Difference of both amounts, `None` when their units differ.
+    pub fn checked_sub(&self, other: &Amount) -> Option<Amount> {
+        (self.unit == other.unit).then_some(Amount {
+            nominal: self.nominal - other.nominal,
+            unit: self.unit,
+        })
+    }
+
+    /// Amount scaled by a plain factor, `None` when the result overflows.
+    pub fn checked_mul(&self, factor: Decimal) -> Option<Amount> {
+        Some(Amount {
+            nominal: self.nominal.checked_mul(factor)?,
+            unit: self.unit,
+        })
+    }
+}
+
+/// Price of a posting amount, per unit or for the whole amount, see [`Price::weight`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Price {
+    pub amount: Amount,
+    /// Whether it's the `@@` cost of the whole amount rather than a price per unit.
+    pub total: bool,
+}
+
+impl Price {
+    /// What `units` cost in the price unit, with the sign of `units`. This is what the
+    /// posting counts for when checking the transaction balances.
+    pub fn weight(&self, units: &Amount) -> Result<Amount> {
+        let nominal = match self.total {
+            true if units.nominal.is_sign_negative() => -self.amount.nominal,
+            true => self.amount.nominal,
+            false => checked_mul(units.nominal, self.amount.nominal)?,
+        };
+        Ok(Amount {
+            nominal,
+            unit: self.amount.unit,
+        })
+    }
+}
+
+/// Amounts are only ordered against amounts of the same unit, comparing different units
+/// gives `None`.
+impl PartialOrd for Amount {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.unit != other.unit {
+            return None;
+        }
+        self.nominal.partial_cmp(&other.nominal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amount::Amount;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_amount_unit_safety() {
+        let usd = |nominal| Amount { nominal, unit: 0 };
+        let idr = Amount {
+            nominal: dec!(15000),
+            unit: 1,
+        };
+
+        assert!(usd(dec!(10)) < usd(dec!(12)));
+        assert_eq!(
+            usd(dec!(3)).partial_cmp(&usd(dec!(3))),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(usd(dec!(10)).partial_cmp(&idr), None);
+
+        assert_eq!(
+            usd(dec!(10)).checked_add(&usd(dec!(2.5))),
+            Some(usd(dec!(12.5)))
+        );
+        assert_eq!(
+            usd(dec!(10)).checked_sub(&usd(dec!(2.5))),
+            Some(usd(dec!(7.5)))
+        );
+        assert_eq!(usd(dec!(10)).checked_add(&idr), None);
+        assert_eq!(usd(dec!(10)).checked_sub(&idr), None);
+        assert_eq!(usd(dec!(10)).checked_mul(dec!(0.5)), Some(usd(dec!(5))));
+        assert_eq!(usd(Decimal::MAX).checked_mul(dec!(2)), None);
+        // Unlike floats, decimal amounts add up exactly.
+        assert_eq!(
+            usd(dec!(0.1)).checked_add(&usd(dec!(0.2))),
+            Some(usd(dec!(0.3)))
+        );
     }
 }
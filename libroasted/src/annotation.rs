@@ -0,0 +1,265 @@
+use crate::{ledger::Ledger, redact::fnv1a, transaction::Transaction};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Identifier of a transaction that survives reloading the ledger, and edits elsewhere in
+/// the ledger text.
+///
+/// It's derived from the date, payee, title, and postings of the transaction, leaving
+/// out its state and metadata so marking a transaction settled keeps its id. Identical
+/// transactions on the same date are told apart by their order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransactionId(u64);
+
+impl fmt::Display for TransactionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for TransactionId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.len() != 16 {
+            return Err(anyhow!("invalid transaction id: `{}'", s));
+        }
+        Ok(TransactionId(
+            u64::from_str_radix(s, 16).map_err(|_| anyhow!("invalid transaction id: `{}'", s))?,
+        ))
+    }
+}
+
+fn content(ledger: &Ledger, date: &NaiveDate, txn: &Transaction) -> Result<String> {
+    let mut out = String::new();
+    write!(
+        out,
+        "{}\t{}\t{}",
+        date,
+        txn.payee.as_deref().unwrap_or(""),
+        txn.title
+    )?;
+    for exchange in &txn.exchanges {
+        write!(out, "\t{}", ledger.account_name(&exchange.account)?)?;
+        if let Some(amount) = exchange.amount.as_ref().filter(|_| !exchange.elided) {
+            write!(
+                out,
+                " {} {}",
+                amount.nominal.normalize(),
+                ledger.unit_name(amount.unit)?
+            )?;
+        }
+    }
+    Ok(out)
+}
+
+/// Ids of every transaction booked on the date, in booking order.
+pub(crate) fn transaction_ids(ledger: &Ledger, date: &NaiveDate) -> Result<Vec<TransactionId>> {
+    let Some(book) = ledger.get_bookings_on(date) else {
+        return Ok(Vec::new());
+    };
+
+    let mut seen: BTreeMap<String, u64> = BTreeMap::new();
+    book.transactions()
+        .iter()
+        .map(|txn| {
+            let content = content(ledger, date, txn)?;
+            let occurrence = seen.entry(content.clone()).or_default();
+            let id = TransactionId(fnv1a(&format!("{}\t#{}", content, occurrence)));
+            *occurrence += 1;
+            Ok(id)
+        })
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> Result<String> {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            other => return Err(anyhow!("invalid escape `\\{}'", other.unwrap_or(' '))),
+        }
+    }
+    Ok(out)
+}
+
+/// Runtime-only notes on transactions, e.g. a review status or an import batch id,
+/// kept apart from the ledger text.
+///
+/// Annotations are keyed by [`TransactionId`], and can be kept in a sidecar file next to
+/// the ledger, one `id<TAB>key<TAB>value` line per annotation.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Annotations {
+    #[cfg_attr(feature = "serde", serde(with = "crate::pairs"))]
+    entries: BTreeMap<TransactionId, BTreeMap<String, String>>,
+}
+
+impl Annotations {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the annotation, returning its previous value. Keys can't be empty nor contain
+    /// whitespace.
+    pub fn set(&mut self, id: TransactionId, key: &str, value: &str) -> Result<Option<String>> {
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(anyhow!("invalid annotation key: `{}'", key));
+        }
+        Ok(self
+            .entries
+            .entry(id)
+            .or_default()
+            .insert(key.to_string(), value.to_string()))
+    }
+
+    pub fn get(&self, id: &TransactionId, key: &str) -> Option<&str> {
+        self.entries.get(id)?.get(key).map(String::as_str)
+    }
+
+    /// Every annotation of the transaction.
+    pub fn of(&self, id: &TransactionId) -> Option<&BTreeMap<String, String>> {
+        self.entries.get(id)
+    }
+
+    pub fn remove(&mut self, id: &TransactionId, key: &str) -> Option<String> {
+        let annotations = self.entries.get_mut(id)?;
+        let value = annotations.remove(key);
+        if annotations.is_empty() {
+            self.entries.remove(id);
+        }
+        value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Render the annotations in their sidecar format.
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        for (id, annotations) in &self.entries {
+            for (key, value) in annotations {
+                writeln!(w, "{}\t{}\t{}", id, key, escape(value))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse annotations from their sidecar format.
+    pub fn read(input: &str) -> Result<Self> {
+        let mut annotations = Annotations::new();
+        for (lineno, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.splitn(3, '\t');
+            let (Some(id), Some(key), Some(value)) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(anyhow!("invalid annotation at line {}", lineno + 1));
+            };
+            annotations.set(id.parse()?, key, &unescape(value)?)?;
+        }
+        Ok(annotations)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        Self::read(&std::fs::read_to_string(path)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
+        let mut out = String::new();
+        self.write(&mut out)?;
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::annotation::Annotations;
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    const INPUT: &str = r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 ! "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-03 ! "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+"#;
+
+    #[test]
+    fn test_annotations() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let mut ledger = parser::parse(INPUT, None)?;
+        let ids = ledger.transaction_ids(&date)?;
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(ids[1].to_string().parse::<crate::TransactionId>()?, ids[1]);
+
+        ledger.annotations_mut().set(ids[1], "review", "done")?;
+        ledger
+            .annotations_mut()
+            .set(ids[1], "batch", "2022-01\timport")?;
+        let err = ledger
+            .annotations_mut()
+            .set(ids[0], "import batch", "x")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid annotation key: `import batch'");
+        let err = "xyz".parse::<crate::TransactionId>().unwrap_err();
+        assert_eq!(err.to_string(), "invalid transaction id: `xyz'");
+
+        let mut sidecar = String::new();
+        ledger.annotations().write(&mut sidecar)?;
+
+        // Settling the transactions and adding another one keeps the ids.
+        let edited = format!(
+            "{}\n2022-01-02 * \"Diner\" \"Lunch\"\n  Assets:Bank\n  Expenses:Food  12 USD\n",
+            INPUT.replace('!', "*")
+        );
+        let reloaded = parser::parse(&edited, None)?;
+        assert_eq!(reloaded.transaction_ids(&date)?, ids);
+
+        let annotations = Annotations::read(&sidecar)?;
+        assert_eq!(annotations.get(&ids[1], "review"), Some("done"));
+        assert_eq!(annotations.get(&ids[1], "batch"), Some("2022-01\timport"));
+        assert_eq!(annotations.of(&ids[0]), None);
+
+        let (found_date, txn) = reloaded
+            .find_transaction(&ids[1])?
+            .ok_or(anyhow!("transaction not found"))?;
+        assert_eq!((found_date, txn.title.as_str()), (date, "Weekly groceries"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,411 @@
+use crate::{
+    account::TxnAccount,
+    amount::{checked_add, checked_div, checked_mul, Amount},
+    ledger::Ledger,
+    reports::posting_amount,
+    transaction::{Exchange, Transaction, TransactionState},
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use indexmap::IndexSet;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// Share of the transaction total for every posting, or `None` if the transaction
+/// can't be used as a template for the given unit.
+fn shares(txn: &Transaction, unit: usize) -> Result<Option<Vec<Decimal>>> {
+    let mut nominals = Vec::new();
+    for exchange in &txn.exchanges {
+        match &exchange.amount {
+            Some(amount) if amount.unit == unit => nominals.push(Some(amount.nominal)),
+            Some(_) => return Ok(None),
+            None => nominals.push(None),
+        }
+    }
+
+    let residue = nominals
+        .iter()
+        .flatten()
+        .try_fold(Decimal::ZERO, |sum, &n| checked_add(sum, n))?;
+    let resolved: Vec<Decimal> = nominals
+        .iter()
+        .map(|nominal| nominal.unwrap_or(-residue))
+        .collect();
+    let total = resolved
+        .iter()
+        .filter(|&&n| n > Decimal::ZERO)
+        .try_fold(Decimal::ZERO, |sum, &n| checked_add(sum, n))?;
+    if total == Decimal::ZERO {
+        return Ok(None);
+    }
+
+    resolved
+        .iter()
+        .map(|&n| checked_div(n, total))
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+/// Past transactions posting to the same accounts, in the same order.
+struct Shape<'l> {
+    accounts: Vec<&'l TxnAccount>,
+    shares: Vec<Vec<Decimal>>,
+    latest: &'l Transaction,
+}
+
+/// Draft a transaction for the given payee, see [`Ledger::autofill`].
+pub fn autofill(
+    ledger: &Ledger,
+    date: &NaiveDate,
+    payee: &str,
+    amount: &Amount,
+) -> Result<Option<Transaction>> {
+    let mut shapes: Vec<Shape> = Vec::new();
+    for (_, book) in ledger.bookings().range(..=date) {
+        for txn in book.transactions() {
+            if !txn
+                .payee
+                .as_deref()
+                .is_some_and(|p| p.eq_ignore_ascii_case(payee))
+            {
+                continue;
+            }
+            let Some(shares) = shares(txn, amount.unit)? else {
+                continue;
+            };
+
+            let accounts: Vec<&TxnAccount> = txn.exchanges.iter().map(|e| &e.account).collect();
+            match shapes.iter_mut().find(|shape| shape.accounts == accounts) {
+                Some(shape) => {
+                    shape.shares.push(shares);
+                    shape.latest = txn;
+                }
+                None => shapes.push(Shape {
+                    accounts,
+                    shares: vec![shares],
+                    latest: txn,
+                }),
+            }
+        }
+    }
+
+    // Most used shape wins, ties go to the most recently added.
+    let Some(shape) = shapes
+        .into_iter()
+        .rev()
+        .max_by_key(|shape| shape.shares.len())
+    else {
+        return Ok(None);
+    };
+
+    let count = Decimal::from(shape.shares.len());
+    let exchanges = shape
+        .latest
+        .exchanges
+        .iter()
+        .enumerate()
+        .map(|(idx, exchange)| {
+            let amount = match exchange.amount {
+                Some(_) => {
+                    let share = shape
+                        .shares
+                        .iter()
+                        .try_fold(Decimal::ZERO, |sum, shares| checked_add(sum, shares[idx]))?;
+                    Some(Amount {
+                        nominal: checked_mul(checked_div(share, count)?, amount.nominal)?,
+                        unit: amount.unit,
+                    })
+                }
+                None => None,
+            };
+            Ok(Exchange {
+                account: exchange.account.clone(),
+                amount,
+                amount_text: None,
+                elided: exchange.elided,
+                metadata: BTreeMap::new(),
+                price: None,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Some(Transaction {
+        state: TransactionState::Unsettled,
+        payee: shape.latest.payee.clone(),
+        title: shape.latest.title.clone(),
+        exchanges,
+        tags: IndexSet::new(),
+        links: IndexSet::new(),
+        metadata: BTreeMap::new(),
+    }))
+}
+
+/// Likely amount of a new posting to the account, see [`Ledger::expected_amount`].
+pub fn expected_amount(
+    ledger: &Ledger,
+    account: &TxnAccount,
+    payee: Option<&str>,
+    date: &NaiveDate,
+) -> Option<Amount> {
+    // Past amounts with how many times they were posted, the most recent last.
+    let mut history: Vec<(Amount, usize)> = Vec::new();
+    for (_, book) in ledger.bookings().range(..=date) {
+        for txn in book.transactions() {
+            let same_payee = payee.map_or(true, |payee| {
+                txn.payee
+                    .as_deref()
+                    .is_some_and(|p| p.eq_ignore_ascii_case(payee))
+            });
+            if !same_payee {
+                continue;
+            }
+            for idx in 0..txn.exchanges.len() {
+                if &txn.exchanges[idx].account != account {
+                    continue;
+                }
+                let Some(amount) = posting_amount(txn, idx) else {
+                    continue;
+                };
+                let count = match history.iter().position(|(past, _)| past == &amount) {
+                    Some(pos) => history.remove(pos).1,
+                    None => 0,
+                };
+                history.push((amount, count + 1));
+            }
+        }
+    }
+
+    // The most frequent amount, the most recent one among equally frequent ones.
+    let recurring = history
+        .iter()
+        .enumerate()
+        .max_by_key(|(recency, (_, count))| (*count, *recency))
+        .filter(|(_, (_, count))| *count > 1);
+    if let Some((_, (amount, _))) = recurring {
+        return Some(amount.clone());
+    }
+
+    if matches!(account, TxnAccount::Liabilities(_)) {
+        let balances = ledger.balances_at(date).ok()?;
+        let mut owed = balances
+            .units(account)
+            .into_iter()
+            .flatten()
+            .filter(|(_, &nominal)| !nominal.is_zero());
+        if let (Some((&unit, &nominal)), None) = (owed.next(), owed.next()) {
+            return Some(Amount {
+                nominal: -nominal,
+                unit,
+            });
+        }
+    }
+
+    history.pop().map(|(amount, _)| amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::TxnAccount;
+    use crate::amount::Amount;
+    use crate::ledger::ReferenceLookup;
+    use crate::parser;
+    use crate::transaction::TransactionState;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_autofill() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Liabilities:Card
+2022-01-01 open Expenses:Groceries
+2022-01-01 open Expenses:Household
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Groceries     75 USD
+  Expenses:Household     25 USD
+
+2022-01-10 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Groceries     50 USD
+  Expenses:Household     50 USD
+
+2022-01-12 * "Grocer" "Snacks"
+  Liabilities:Card
+  Expenses:Groceries     5 USD
+
+2022-01-17 * "grocer" "Weekly shopping"
+  Assets:Bank
+  Expenses:Groceries     62.5 USD
+  Expenses:Household     37.5 USD
+"#,
+            None,
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 24).ok_or(anyhow!("invalid date"))?;
+        let amount = Amount {
+            nominal: dec!(50),
+            unit: 0,
+        };
+        let draft = ledger
+            .autofill(&date, "GROCER", &amount)?
+            .ok_or(anyhow!("no draft"))?;
+
+        assert_eq!(draft.state, TransactionState::Unsettled);
+        assert_eq!(draft.title, "Weekly shopping");
+        assert_eq!(draft.exchanges.len(), 3);
+        assert_eq!(
+            ledger.account_name(&draft.exchanges[0].account)?,
+            "Assets:Bank"
+        );
+        assert!(draft.exchanges[0].elided);
+        assert_eq!(
+            draft.exchanges[0].amount,
+            Some(Amount {
+                nominal: dec!(-50),
+                unit: 0
+            })
+        );
+        assert_eq!(
+            draft.exchanges[1].amount,
+            Some(Amount {
+                nominal: dec!(31.25),
+                unit: 0
+            })
+        );
+        assert_eq!(
+            draft.exchanges[2].amount,
+            Some(Amount {
+                nominal: dec!(18.75),
+                unit: 0
+            })
+        );
+
+        let early = NaiveDate::from_ymd_opt(2022, 1, 2).ok_or(anyhow!("invalid date"))?;
+        assert!(ledger.autofill(&early, "Grocer", &amount)?.is_none());
+        assert!(ledger.autofill(&date, "Bakery", &amount)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_autofill_overflow() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Savings
+2022-01-01 open Expenses:Groceries
+2022-01-01 open Expenses:Household
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Expenses:Groceries     50000000000000000000000000000 USD
+  Assets:Bank           -50000000000000000000000000000 USD
+  Expenses:Household     50000000000000000000000000000 USD
+  Assets:Savings        -50000000000000000000000000000 USD
+"#,
+            None,
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 24).ok_or(anyhow!("invalid date"))?;
+        let amount = Amount {
+            nominal: dec!(50),
+            unit: 0,
+        };
+        let err = ledger
+            .autofill(&date, "Grocer", &amount)
+            .expect_err("total of the past transaction overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expected_amount() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Liabilities:Card
+2022-01-01 open Expenses:Rent
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Gym
+
+2022-01-01 * "Landlord" "Rent"
+  Assets:Bank
+  Expenses:Rent        1200 USD
+
+2022-02-01 * "Landlord" "Rent"
+  Assets:Bank
+  Expenses:Rent        1200 USD
+
+2022-02-15 * "Landlord" "Repairs"
+  Assets:Bank
+  Expenses:Rent          80 USD
+
+2022-02-20 * "Grocer" "Groceries"
+  Liabilities:Card
+  Expenses:Food          42 USD
+
+2022-02-25 * "Card issuer" "Card payment"
+  Assets:Bank
+  Liabilities:Card       30 USD
+
+2022-02-26 * "Grocer" "Groceries"
+  Liabilities:Card
+  Expenses:Food          18 USD
+
+2022-01-05 * "Gym" "Membership"
+  Assets:Bank
+  Expenses:Gym           50 USD
+
+2022-01-20 * "Gym" "Membership"
+  Assets:Bank
+  Expenses:Gym           50 USD
+
+2022-02-05 * "Gym" "Membership"
+  Assets:Bank
+  Expenses:Gym           60 USD
+
+2022-02-20 * "Gym" "Membership"
+  Assets:Bank
+  Expenses:Gym           60 USD
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 3, 1).ok_or(anyhow!("invalid date"))?;
+        let account =
+            |name: &str| -> Result<TxnAccount> { ledger.account_lookup(&date, &name.try_into()?) };
+        let usd = |nominal| Some(Amount { nominal, unit: 0 });
+
+        // Recurring amounts win over the latest one.
+        assert_eq!(
+            ledger.expected_amount(&account("Expenses:Rent")?, Some("landlord"), &date),
+            usd(dec!(1200))
+        );
+        // The latest of equally recurring amounts, after a price change.
+        assert_eq!(
+            ledger.expected_amount(&account("Expenses:Gym")?, Some("Gym"), &date),
+            usd(dec!(60))
+        );
+        // Paying the card off.
+        assert_eq!(
+            ledger.expected_amount(&account("Liabilities:Card")?, Some("Card issuer"), &date),
+            usd(dec!(30))
+        );
+        assert_eq!(
+            ledger.expected_amount(&account("Expenses:Food")?, None, &date),
+            usd(dec!(18))
+        );
+        assert_eq!(
+            ledger.expected_amount(&account("Expenses:Food")?, Some("Landlord"), &date),
+            None
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{account::TxnAccount, balance::UnitBalances, ledger::Ledger};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use std::ops::RangeInclusive;
+
+/// How end-of-day balances are combined into an average.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BalanceWeighting {
+    /// Every day in the range weighs the same, like an average daily balance.
+    Daily,
+    /// Average of each month's lowest balance, for accounts paying interest on the
+    /// minimum monthly balance. Partial months at the edges of the range count as months.
+    MonthlyMinimum,
+}
+
+/// Average balance of an account over a date range, see [`Ledger::average_balance`].
+pub fn average_balance(
+    ledger: &Ledger,
+    account: &TxnAccount,
+    range: RangeInclusive<NaiveDate>,
+    weighting: BalanceWeighting,
+) -> Result<UnitBalances> {
+    let (start, end) = range.into_inner();
+    if end < start {
+        return Err(anyhow!("invalid date range: {} to {}", start, end));
+    }
+
+    let name = ledger.account_name(account)?;
+    let add = |total: &mut UnitBalances, unit: usize, nominal: Decimal| -> Result<()> {
+        let sum = total.entry(unit).or_default();
+        *sum = sum
+            .checked_add(nominal)
+            .ok_or_else(|| anyhow!("average balance of `{}' overflows", name))?;
+        Ok(())
+    };
+
+    // Running totals of the end-of-day balances, and of each month's lowest one.
+    let mut days = 0u32;
+    let mut total = UnitBalances::new();
+    let mut months = 0u32;
+    let mut month_total = UnitBalances::new();
+    let mut month: Option<((i32, u32), UnitBalances)> = None;
+
+    let pads = ledger.resolve_pads()?;
+    let mut balances = ledger.balances_with_pads(&start, &pads)?;
+    for date in start.iter_days().take_while(|date| *date <= end) {
+        if date > start {
+            ledger.replay_day(&date, &mut balances, &pads, &mut |_, _, _| Ok(()))?;
+        }
+        let units = balances.units(account).cloned().unwrap_or_default();
+        for (&unit, &nominal) in &units {
+            add(&mut total, unit, nominal)?;
+        }
+
+        let key = (date.year(), date.month());
+        if month.as_ref().is_some_and(|(current, _)| *current != key) {
+            if let Some((_, minimums)) = month.take() {
+                for (unit, nominal) in minimums {
+                    add(&mut month_total, unit, nominal)?;
+                }
+                months += 1;
+            }
+        }
+        // A unit missing on any day of the month had a zero balance that day.
+        let first_day = month.is_none();
+        let (_, minimums) = month.get_or_insert_with(|| (key, UnitBalances::new()));
+        for (unit, min) in minimums.iter_mut() {
+            *min = (*min).min(units.get(unit).copied().unwrap_or(Decimal::ZERO));
+        }
+        for (&unit, &nominal) in &units {
+            minimums.entry(unit).or_insert(if first_day {
+                nominal
+            } else {
+                nominal.min(Decimal::ZERO)
+            });
+        }
+        days += 1;
+    }
+    if let Some((_, minimums)) = month {
+        for (unit, nominal) in minimums {
+            add(&mut month_total, unit, nominal)?;
+        }
+        months += 1;
+    }
+
+    let (total, count) = match weighting {
+        BalanceWeighting::Daily => (total, days),
+        BalanceWeighting::MonthlyMinimum => (month_total, months),
+    };
+    let average = total
+        .into_iter()
+        .map(|(unit, nominal)| (unit, nominal / Decimal::from(count)))
+        .collect();
+
+    Ok(average)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::TxnAccount;
+    use crate::average::BalanceWeighting;
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_average_balance() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Savings
+2022-01-01 open Income:Salary
+
+2022-01-01 * "Deposit"
+  Income:Salary
+  Assets:Savings      1000 USD
+
+2022-01-21 * "Deposit"
+  Income:Salary
+  Assets:Savings      3000 USD
+
+2022-02-11 * "Withdrawal"
+  Income:Salary
+  Assets:Savings     -2000 USD
+"#,
+            None,
+        )?;
+        let savings = TxnAccount::Assets(vec![0]);
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        // 20 days at 1000, 10 days at 4000.
+        let january = ledger.average_balance(
+            &savings,
+            date(1, 1)?..=date(1, 30)?,
+            BalanceWeighting::Daily,
+        )?;
+        assert_eq!(january.get(&0), Some(&dec!(2000)));
+
+        let minimum = ledger.average_balance(
+            &savings,
+            date(1, 1)?..=date(2, 28)?,
+            BalanceWeighting::MonthlyMinimum,
+        )?;
+        assert_eq!(minimum.get(&0), Some(&dec!(1500)));
+
+        assert!(ledger
+            .average_balance(&savings, date(2, 1)?..=date(1, 1)?, BalanceWeighting::Daily)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_average_padded() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Savings
+2022-01-01 open Equity:Opening-Balances
+
+2022-01-01 pad Assets:Savings Equity:Opening-Balances
+2022-01-11 balance Assets:Savings 1000 USD
+"#,
+            None,
+        )?;
+        let savings = TxnAccount::Assets(vec![0]);
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        let january = ledger.average_balance(
+            &savings,
+            date(1, 1)?..=date(1, 31)?,
+            BalanceWeighting::Daily,
+        )?;
+        assert_eq!(january.get(&0), Some(&dec!(1000)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_average_overflow() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Savings
+2022-01-01 open Income:Salary
+
+2022-01-01 * "Deposit"
+  Income:Salary
+  Assets:Savings      50000000000000000000000000000 USD
+"#,
+            None,
+        )?;
+        let savings = TxnAccount::Assets(vec![0]);
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        for weighting in [BalanceWeighting::Daily, BalanceWeighting::MonthlyMinimum] {
+            let range = match weighting {
+                BalanceWeighting::Daily => date(1, 1)?..=date(1, 5)?,
+                BalanceWeighting::MonthlyMinimum => date(1, 1)?..=date(2, 5)?,
+            };
+            let err = ledger
+                .average_balance(&savings, range, weighting)
+                .expect_err("sum of balances overflows");
+            assert_eq!(
+                err.to_string(),
+                "average balance of `Assets:Savings' overflows"
+            );
+        }
+
+        Ok(())
+    }
+}
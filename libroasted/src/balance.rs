@@ -0,0 +1,334 @@
+use crate::{
+    account::TxnAccount,
+    amount::{checked_add, Amount},
+    ledger::Ledger,
+    transaction::Transaction,
+};
+use anyhow::Result;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+pub type UnitBalances = BTreeMap<usize, Decimal>;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Balances {
+    accounts: BTreeMap<TxnAccount, UnitBalances>,
+}
+
+impl Balances {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Fails when the balance is out of the range of `Decimal`, leaving it unchanged.
+    pub fn add(&mut self, account: &TxnAccount, amount: &Amount) -> Result<()> {
+        let balance = self
+            .accounts
+            .entry(account.clone())
+            .or_default()
+            .entry(amount.unit)
+            .or_insert(Decimal::ZERO);
+        *balance = checked_add(*balance, amount.nominal)?;
+        Ok(())
+    }
+
+    /// Apply every posting of the given transaction, the elided posting (if any)
+    /// receives the negated sum of the other postings for each unit.
+    pub fn apply_transaction(&mut self, txn: &Transaction) -> Result<()> {
+        for exchange in &txn.exchanges {
+            if let Some(amount) = &exchange.amount {
+                self.add(&exchange.account, amount)?;
+            }
+        }
+
+        if let Some(elided) = txn.exchanges.iter().find(|e| e.amount.is_none()) {
+            for (unit, nominal) in txn.explicit_totals()? {
+                self.add(
+                    &elided.account,
+                    &Amount {
+                        nominal: -nominal,
+                        unit,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, account: &TxnAccount, unit: usize) -> Decimal {
+        self.accounts
+            .get(account)
+            .and_then(|units| units.get(&unit))
+            .copied()
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    pub fn units(&self, account: &TxnAccount) -> Option<&UnitBalances> {
+        self.accounts.get(account)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&TxnAccount, &UnitBalances)> {
+        self.accounts.iter()
+    }
+
+    /// Balance of the account and all its sub-accounts, per unit.
+    pub fn subtree(&self, root: &TxnAccount) -> Result<UnitBalances> {
+        let mut total = UnitBalances::new();
+        for (_, units) in self.iter().filter(|(account, _)| account.is_under(root)) {
+            for (&unit, &nominal) in units {
+                let sum = total.entry(unit).or_default();
+                *sum = checked_add(*sum, nominal)?;
+            }
+        }
+        Ok(total)
+    }
+
+    /// Every balance added to each of its ancestors as well, up to the bare account
+    /// types, so every account holds the total of its subtree. Parents only implied by
+    /// their sub-accounts are included.
+    pub fn rollup(&self) -> Result<Balances> {
+        let mut rolled = Balances::new();
+        for (account, units) in self.iter() {
+            let mut node = Some(account.clone());
+            while let Some(account) = node {
+                for (&unit, &nominal) in units {
+                    rolled.add(&account, &Amount { nominal, unit })?;
+                }
+                node = account.parent();
+            }
+        }
+        Ok(rolled)
+    }
+
+    /// Accounts in presentation order, following their `order` hint then their name.
+    /// Accounts hinted as hidden are left out unless `show_hidden` is set.
+    pub fn layout(
+        &self,
+        ledger: &Ledger,
+        show_hidden: bool,
+    ) -> Result<Vec<(&TxnAccount, &UnitBalances)>> {
+        let mut entries = Vec::new();
+        for (account, units) in &self.accounts {
+            let hints = ledger.account_hints(account)?;
+            if hints.hidden && !show_hidden {
+                continue;
+            }
+            entries.push((hints.order, ledger.account_name(account)?, account, units));
+        }
+        entries.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+        Ok(entries
+            .into_iter()
+            .map(|(_, _, account, units)| (account, units))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::TxnAccount;
+    use crate::amount::Amount;
+    use crate::balance::Balances;
+    use crate::parser;
+    use crate::transaction::{Exchange, Transaction, TransactionState};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use indexmap::IndexSet;
+    use rust_decimal_macros::dec;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_apply_transaction_with_elided_amount() -> Result<()> {
+        let mut balances = Balances::new();
+        let cash = TxnAccount::Assets(vec![0]);
+        let food = TxnAccount::Expenses(vec![1]);
+        let fee = TxnAccount::Expenses(vec![2]);
+
+        balances.apply_transaction(&Transaction {
+            state: TransactionState::Settled,
+            payee: None,
+            title: "Lunch".to_string(),
+            tags: IndexSet::new(),
+            links: IndexSet::new(),
+            exchanges: vec![
+                Exchange {
+                    account: cash.clone(),
+                    amount: None,
+                    amount_text: None,
+                    elided: false,
+                    metadata: BTreeMap::new(),
+                    price: None,
+                },
+                Exchange {
+                    account: food.clone(),
+                    amount: Some(Amount {
+                        nominal: dec!(12),
+                        unit: 0,
+                    }),
+                    amount_text: None,
+                    elided: false,
+                    metadata: BTreeMap::new(),
+                    price: None,
+                },
+                Exchange {
+                    account: fee.clone(),
+                    amount: Some(Amount {
+                        nominal: dec!(1.5),
+                        unit: 0,
+                    }),
+                    amount_text: None,
+                    elided: false,
+                    metadata: BTreeMap::new(),
+                    price: None,
+                },
+            ],
+            metadata: BTreeMap::new(),
+        })?;
+
+        assert_eq!(balances.get(&cash, 0), dec!(-13.5));
+        assert_eq!(balances.get(&food, 0), dec!(12));
+        assert_eq!(balances.get(&fee, 0), dec!(1.5));
+        assert_eq!(balances.get(&fee, 1), dec!(0));
+
+        let huge = Amount {
+            nominal: dec!(50000000000000000000000000000),
+            unit: 0,
+        };
+        balances.add(&cash, &huge)?;
+        assert!(balances.add(&cash, &huge).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_layout() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+  order: "2"
+2022-01-01 open Assets:Cash
+  color: "green"
+2022-01-01 open Expenses:Food
+  order: "-1"
+2022-01-01 open Expenses:Fees
+  hidden: "true"
+
+2022-01-02 * "Lunch"
+  Assets:Cash
+  Expenses:Food     10 USD
+
+2022-01-03 * "Transfer"
+  Assets:Bank
+  Assets:Cash       50 USD
+  Expenses:Fees      1 USD
+"#,
+            None,
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?;
+        let balances = ledger.balances_at(&date)?;
+        let names = |show_hidden| -> Result<Vec<String>> {
+            balances
+                .layout(&ledger, show_hidden)?
+                .into_iter()
+                .map(|(account, _)| ledger.account_name(account))
+                .collect()
+        };
+
+        assert_eq!(
+            names(false)?,
+            vec!["Expenses:Food", "Assets:Cash", "Assets:Bank"]
+        );
+        assert_eq!(
+            names(true)?,
+            vec![
+                "Expenses:Food",
+                "Assets:Cash",
+                "Expenses:Fees",
+                "Assets:Bank"
+            ]
+        );
+        assert_eq!(
+            ledger
+                .account_hints(&TxnAccount::Assets(vec![1]))?
+                .color
+                .as_deref(),
+            Some("green")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subtree() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Travel:Flights
+2022-01-01 open Expenses:Travel:Lodging:Hotels
+2022-01-01 open Expenses:Food
+
+2022-01-02 * "Airline"
+  Assets:Bank
+  Expenses:Travel:Flights           400 USD
+
+2022-01-03 * "Hotel"
+  Assets:Bank
+  Expenses:Travel:Lodging:Hotels    250 USD
+
+2022-01-04 * "Grocer"
+  Assets:Bank
+  Expenses:Food                      30 USD
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?;
+        let accounts = ledger.accounts();
+        let travel = accounts
+            .lookup(&"Expenses:Travel".try_into()?)
+            .ok_or(anyhow!("unknown account"))?;
+        let names = |accounts: Vec<TxnAccount>| -> Result<Vec<String>> {
+            accounts
+                .iter()
+                .map(|account| ledger.account_name(account))
+                .collect()
+        };
+
+        assert_eq!(
+            names(accounts.subtree(&travel).map(|(a, _)| a).collect())?,
+            vec!["Expenses:Travel:Flights", "Expenses:Travel:Lodging:Hotels"]
+        );
+        assert_eq!(
+            names(accounts.children(&travel))?,
+            vec!["Expenses:Travel:Flights", "Expenses:Travel:Lodging"]
+        );
+        assert_eq!(accounts.children(&TxnAccount::Expenses(vec![])).len(), 2);
+
+        assert_eq!(
+            ledger.subtree_balance(&date, "Expenses:Travel")?,
+            BTreeMap::from([(0, dec!(650))])
+        );
+        assert!(ledger.subtree_balance(&date, "Expenses:Rent").is_err());
+        // Transactions on the date count.
+        let hotel = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        assert_eq!(
+            ledger.subtree_balance(&hotel, "Expenses:Travel:Lodging")?,
+            BTreeMap::from([(0, dec!(250))])
+        );
+
+        let rolled = ledger.balances_at(&date)?.rollup()?;
+        assert_eq!(rolled.get(&TxnAccount::Expenses(vec![]), 0), dec!(680));
+        assert_eq!(rolled.get(&TxnAccount::Assets(vec![]), 0), dec!(-680));
+        assert_eq!(rolled.get(&travel, 0), dec!(650));
+        let lodging = accounts
+            .lookup(&"Expenses:Travel:Lodging".try_into()?)
+            .ok_or(anyhow!("unknown account"))?;
+        assert_eq!(rolled.get(&lodging, 0), dec!(250));
+        assert_eq!(lodging.parent(), Some(travel.clone()));
+        assert!(lodging.is_under(&travel));
+        assert!(!travel.is_under(&lodging));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,561 @@
+use crate::{diagnostic::Diagnostic, ledger::Ledger, parser};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Beancount options carried over as they are, others are dropped with a warning.
+const KNOWN_OPTIONS: &[&str] = &["title", "operating_currency"];
+
+/// Beancount source converted to roasted syntax, see [`convert`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conversion {
+    pub text: String,
+    /// What couldn't be converted, or only partly, located in the Beancount source.
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// A converted dated directive, sorted by date since Beancount doesn't care about the
+/// order directives are written in.
+struct Entry {
+    date: NaiveDate,
+    /// Opens come first and closes last on the same date.
+    rank: u8,
+    text: String,
+}
+
+/// Split the line into whitespace separated tokens, keeping strings and `{...}` costs
+/// whole, up to a `;` comment.
+fn tokens(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == ';' {
+            break;
+        }
+        let mut end = line.len();
+        match c {
+            '"' => {
+                let mut escaped = false;
+                for (idx, c) in chars.by_ref() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => {
+                            end = idx + 1;
+                            break;
+                        }
+                        _ => escaped = false,
+                    }
+                }
+            }
+            '{' => {
+                let mut depth = 1;
+                for (idx, c) in chars.by_ref() {
+                    depth += match c {
+                        '{' => 1,
+                        '}' => -1,
+                        _ => 0,
+                    };
+                    if depth == 0 {
+                        end = idx + 1;
+                        break;
+                    }
+                }
+            }
+            _ => {
+                while let Some((idx, c)) = chars.peek() {
+                    if c.is_whitespace() {
+                        end = *idx;
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+        }
+        tokens.push(&line[start..end]);
+    }
+    tokens
+}
+
+fn number(token: &str) -> Option<String> {
+    let cleaned: String = token
+        .trim_start_matches('+')
+        .chars()
+        .filter(|c| *c != ',')
+        .collect();
+    Decimal::from_str(&cleaned)
+        .ok()
+        .map(|nominal| nominal.to_string())
+}
+
+/// Beancount currencies start with a capital letter and end with one or a digit, e.g.
+/// `USD`, `SP500` or `VBTLX.X`.
+fn is_currency(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_uppercase())
+        && token.ends_with(|c: char| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-".contains(c))
+}
+
+fn is_account(token: &str) -> bool {
+    parser::parse_account(token).is_ok()
+}
+
+fn is_metadata(tokens: &[&str]) -> bool {
+    tokens.first().is_some_and(|key| {
+        key.len() > 1
+            && key.ends_with(':')
+            && key.starts_with(|c: char| c.is_ascii_lowercase())
+            && key[..key.len() - 1]
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+/// The metadata line in roasted syntax, values being quoted when they aren't already.
+fn metadata(tokens: &[&str], indent: &str) -> String {
+    let value = tokens[1..].join(" ");
+    let value = match value.starts_with('"') && value.ends_with('"') && value.len() > 1 {
+        true => value,
+        false => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")),
+    };
+    format!("\n{}{} {}", indent, tokens[0], value)
+}
+
+struct Converter<'s> {
+    source: &'s str,
+    options: Vec<String>,
+    units: Vec<String>,
+    entries: Vec<Entry>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl<'s> Converter<'s> {
+    fn warn(&mut self, code: &'static str, message: String, span: &Range<usize>) {
+        self.warnings
+            .push(Diagnostic::warning(code, message).with_span(span.clone()));
+    }
+
+    fn unit(&mut self, currency: &str) -> Result<String, String> {
+        if !is_currency(currency) {
+            return Err(format!("unsupported currency `{}'", currency));
+        }
+        if !self.units.iter().any(|unit| unit == currency) {
+            self.units.push(currency.to_string());
+        }
+        Ok(currency.to_string())
+    }
+
+    /// `<number> <currency>` at the start of the tokens.
+    fn amount(&mut self, tokens: &[&str]) -> Result<String, String> {
+        let (Some(nominal), Some(currency)) = (tokens.first(), tokens.get(1)) else {
+            return Err(format!("missing amount: `{}'", tokens.join(" ")));
+        };
+        let nominal = number(nominal).ok_or(format!("unsupported amount `{}'", nominal))?;
+        Ok(format!("{} {}", nominal, self.unit(currency)?))
+    }
+
+    fn string(&self, token: Option<&&str>) -> Result<String, String> {
+        match token {
+            Some(text) if text.len() > 1 && text.starts_with('"') && text.ends_with('"') => {
+                Ok(text.to_string())
+            }
+            Some(text) => Err(format!("expected a string, found `{}'", text)),
+            None => Err("missing string".to_string()),
+        }
+    }
+
+    fn account(&self, token: Option<&&str>) -> Result<String, String> {
+        match token {
+            Some(account) if is_account(account) => Ok(account.to_string()),
+            Some(account) => Err(format!("unsupported account `{}'", account)),
+            None => Err("missing account".to_string()),
+        }
+    }
+
+    /// Convert the directive spanning the lines, `None` when it has nothing to convert.
+    fn directive(&mut self, span: &Range<usize>, lines: &[&str]) -> Result<Option<Entry>, String> {
+        let head = tokens(lines[0]);
+        let body = &lines[1..];
+        let Some(first) = head.first() else {
+            return Ok(None);
+        };
+        let date = NaiveDate::parse_from_str(first, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(first, "%Y/%m/%d"));
+        let Ok(date) = date else {
+            self.undated(span, &head);
+            return Ok(None);
+        };
+
+        let directive = head.get(1).copied().unwrap_or_default();
+        let (rank, text) = match directive {
+            "open" => {
+                let mut text = format!("{} open {}", date, self.account(head.get(2))?);
+                for line in body {
+                    let tokens = tokens(line);
+                    if is_metadata(&tokens) {
+                        text.push_str(&metadata(&tokens, "  "));
+                    }
+                }
+                (0, text)
+            }
+            "close" => (2, format!("{} close {}", date, self.account(head.get(2))?)),
+            "commodity" => {
+                self.unit(head.get(2).copied().unwrap_or_default())?;
+                return Ok(None);
+            }
+            "pad" => (
+                1,
+                format!(
+                    "{} pad {} {}",
+                    date,
+                    self.account(head.get(2))?,
+                    self.account(head.get(3))?
+                ),
+            ),
+            // `<number> [~ <tolerance>] <currency>`
+            "balance" => {
+                let account = self.account(head.get(2))?;
+                let mut amount = head.get(3..).unwrap_or_default().to_vec();
+                let tolerance = amount.get(1) == Some(&"~");
+                if tolerance {
+                    amount.drain(1..3.min(amount.len()));
+                }
+                let amount = self.amount(&amount)?;
+                if tolerance {
+                    self.warn(
+                        "tolerance-dropped",
+                        format!("tolerance of the balance of `{}' is dropped", account),
+                        span,
+                    );
+                }
+                (1, format!("{} balance {} {}", date, account, amount))
+            }
+            "price" => {
+                let unit = self.unit(head.get(2).copied().unwrap_or_default())?;
+                let amount = self.amount(head.get(3..).unwrap_or_default())?;
+                (1, format!("{} price {} {}", date, unit, amount))
+            }
+            "note" | "document" => (
+                1,
+                format!(
+                    "{} {} {} {}",
+                    date,
+                    directive,
+                    self.account(head.get(2))?,
+                    self.string(head.get(3))?
+                ),
+            ),
+            "event" => (
+                1,
+                format!(
+                    "{} event {} {}",
+                    date,
+                    self.string(head.get(2))?,
+                    self.string(head.get(3))?
+                ),
+            ),
+            // Flags other than `!` are taken as settled, e.g. `P` of padding entries.
+            flag if flag == "txn"
+                || flag.chars().count() == 1 && !flag.starts_with(char::is_lowercase) =>
+            {
+                (1, self.transaction(span, date, &head, body)?)
+            }
+            other => {
+                self.warn(
+                    "unsupported-directive",
+                    format!("`{}' directives aren't supported", other),
+                    span,
+                );
+                return Ok(None);
+            }
+        };
+        Ok(Some(Entry { date, rank, text }))
+    }
+
+    /// Undated directives, only options are carried over.
+    fn undated(&mut self, span: &Range<usize>, head: &[&str]) {
+        match head {
+            ["option", key, value] => {
+                let name = key.trim_matches('"');
+                if KNOWN_OPTIONS.contains(&name) {
+                    self.options.push(format!("option {} {}", key, value));
+                } else {
+                    self.warn(
+                        "unsupported-option",
+                        format!("option `{}' isn't supported", name),
+                        span,
+                    );
+                }
+            }
+            [keyword, ..] => self.warn(
+                "unsupported-directive",
+                format!("`{}' directives aren't supported", keyword),
+                span,
+            ),
+            [] => {}
+        }
+    }
+
+    fn transaction(
+        &mut self,
+        span: &Range<usize>,
+        date: NaiveDate,
+        head: &[&str],
+        body: &[&str],
+    ) -> Result<String, String> {
+        let state = match head[1] {
+            "!" => "!",
+            _ => "*",
+        };
+        let strings: Vec<&str> = head[2..]
+            .iter()
+            .copied()
+            .filter(|token| token.starts_with('"'))
+            .collect();
+        let marks: Vec<&str> = head[2..]
+            .iter()
+            .copied()
+            .filter(|token| token.starts_with(['#', '^']))
+            .collect();
+        let mut text = match strings.as_slice() {
+            [] => format!("{} {} \"\"", date, state),
+            [title] => format!("{} {} {}", date, state, title),
+            [payee, title, ..] => format!("{} {} {} {}", date, state, payee, title),
+        };
+        for mark in marks {
+            text.push(' ');
+            text.push_str(mark);
+        }
+
+        let mut postings = 0;
+        let mut costs = false;
+        for line in body {
+            let mut words = tokens(line);
+            if words.is_empty() {
+                continue;
+            }
+            if is_metadata(&words) {
+                let indent = if postings == 0 { "  " } else { "    " };
+                text.push_str(&metadata(&words, indent));
+                continue;
+            }
+            if matches!(words[0], "!" | "*") {
+                words.remove(0);
+            }
+            let mut posting = format!("\n  {}", self.account(words.first())?);
+            let mut rest = &words[1..];
+            if !rest.is_empty() && !rest[0].starts_with(['{', '@']) {
+                posting.push_str(&format!("  {}", self.amount(rest)?));
+                rest = &rest[2.min(rest.len())..];
+            }
+            let mut price = None;
+            if let Some(cost) = rest.first().filter(|token| token.starts_with('{')) {
+                costs = true;
+                let total = cost.starts_with("{{");
+                let inner = cost.trim_matches(['{', '}']);
+                let components: Vec<&str> = inner.split(',').map(str::trim).collect();
+                if let Some(amount) = components.iter().find_map(|component| {
+                    let tokens = tokens(component);
+                    (tokens.len() == 2 && number(tokens[0]).is_some()).then_some(tokens)
+                }) {
+                    let marker = if total { "@@" } else { "@" };
+                    price = Some(format!(" {} {}", marker, self.amount(&amount)?));
+                }
+                rest = &rest[1..];
+            }
+            if let Some(marker) = rest.first().filter(|token| matches!(**token, "@" | "@@")) {
+                let amount = self.amount(&rest[1..])?;
+                price.get_or_insert(format!(" {} {}", marker, amount));
+            }
+            if let Some(price) = price {
+                posting.push_str(&price);
+            }
+            text.push_str(&posting);
+            postings += 1;
+        }
+        if postings < 2 {
+            return Err("transactions need at least two postings".to_string());
+        }
+        if costs {
+            self.warn(
+                "cost-as-price",
+                "lots aren't tracked, posting costs are kept as prices".to_string(),
+                span,
+            );
+        }
+        Ok(text)
+    }
+}
+
+/// Convert Beancount source into roasted syntax, so existing Beancount ledgers can be
+/// migrated.
+///
+/// `open`, `close`, `commodity`, `pad`, `balance`, `price` and transactions are converted,
+/// along with the `title` and `operating_currency` options. Every currency used is
+/// declared with `unit`, and directives are sorted by date. Currency constraints and
+/// booking methods of accounts are dropped, so is metadata of directives other than
+/// `open` and transactions. Lot costs are kept as prices, which weigh the same in the
+/// balance of the transaction. Anything else is left out with a warning.
+pub fn convert(source: &str) -> Conversion {
+    let mut converter = Converter {
+        source,
+        options: Vec::new(),
+        units: Vec::new(),
+        entries: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let mut offset = 0;
+    let lines: Vec<(usize, &str)> = source
+        .split_inclusive('\n')
+        .map(|line| {
+            let start = offset;
+            offset += line.len();
+            (start, line.trim_end_matches(['\n', '\r']))
+        })
+        .collect();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let (start, line) = lines[idx];
+        idx += 1;
+        if line.trim().is_empty() || line.starts_with([' ', '\t', ';', '*', '#']) {
+            continue;
+        }
+        let mut block = vec![line];
+        while let Some((_, next)) = lines.get(idx) {
+            if !next.starts_with([' ', '\t']) || next.trim().is_empty() {
+                break;
+            }
+            block.push(*next);
+            idx += 1;
+        }
+        let end = lines
+            .get(idx)
+            .map_or(converter.source.len(), |(end, _)| *end);
+        let span = start..start + converter.source[start..end].trim_end().len();
+        match converter.directive(&span, &block) {
+            Ok(Some(entry)) => converter.entries.push(entry),
+            Ok(None) => {}
+            Err(message) => converter.warn("invalid-entry", message, &span),
+        }
+    }
+
+    converter
+        .entries
+        .sort_by_key(|entry| (entry.date, entry.rank));
+    let mut text = String::new();
+    for option in &converter.options {
+        text.push_str(option);
+        text.push('\n');
+    }
+    for unit in &converter.units {
+        text.push_str(&format!("unit {}\n", unit));
+    }
+    for entry in &converter.entries {
+        text.push('\n');
+        text.push_str(&entry.text);
+        text.push('\n');
+    }
+    Conversion {
+        text,
+        warnings: converter.warnings,
+    }
+}
+
+/// Load Beancount source into a ledger, along with the warnings of [`convert`]. Errors
+/// are located in the converted text.
+pub fn parse(source: &str) -> Result<(Ledger, Vec<Diagnostic>)> {
+    let conversion = convert(source);
+    let ledger = parser::parse(&conversion.text, None)?;
+    Ok((ledger, conversion.warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::beancount::{convert, parse};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_beancount() -> Result<()> {
+        let source = r#"option "title" "Household"
+option "booking_method" "FIFO"
+plugin "beancount.plugins.auto_accounts"
+
+2022-01-01 commodity USD
+2022-01-01 open Assets:Bank USD "STRICT"
+  bank: "Jawir"
+2022-01-01 open Assets:Broker
+2022-01-01 open Expenses:Food
+2022-01-01 open Equity:Opening-Balances
+
+2022-01-05 * "Grocer" "Weekly groceries" #food
+  store: 12
+  Assets:Bank          -1,030.50 USD
+  Expenses:Food
+    receipt: "r-1"
+
+2022-01-02 pad Assets:Bank Equity:Opening-Balances
+2022-01-03 balance Assets:Bank  5000 ~ 0.01 USD
+2022-01-04 balance Assets:Bank  5000 USD
+
+2022-01-06 txn "Buy shares"
+  Assets:Broker        10 HOOL {100 USD, 2022-01-06}
+  Assets:Bank
+
+2022-01-07 price HOOL 110 USD
+2022-01-07 price VBTLX.X 120 USD
+2022-01-08 note Assets:Bank "Called the bank"
+2022-01-09 query "food" "SELECT *"
+"#;
+
+        let conversion = convert(source);
+        let codes: Vec<&str> = conversion.warnings.iter().map(|w| w.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                "unsupported-option",
+                "unsupported-directive",
+                "tolerance-dropped",
+                "cost-as-price",
+                "unsupported-directive"
+            ]
+        );
+        let span = conversion.warnings[4]
+            .span
+            .clone()
+            .ok_or(anyhow!("no span"))?;
+        assert_eq!(&source[span], "2022-01-09 query \"food\" \"SELECT *\"");
+        assert!(conversion.text.starts_with(
+            "option \"title\" \"Household\"\nunit USD\nunit HOOL\nunit VBTLX.X\n\n2022-01-01 open Assets:Bank\n  bank: \"Jawir\"\n"
+        ));
+        assert!(conversion.text.contains(
+            "\n2022-01-05 * \"Grocer\" \"Weekly groceries\" #food\n  store: \"12\"\n  Assets:Bank  -1030.50 USD\n  Expenses:Food\n    receipt: \"r-1\"\n"
+        ));
+        assert!(conversion
+            .text
+            .contains("\n  Assets:Broker  10 HOOL @ 100 USD\n"));
+        assert!(conversion
+            .text
+            .contains("\n2022-01-03 balance Assets:Bank 5000 USD\n"));
+        assert!(conversion
+            .text
+            .contains("\n2022-01-08 note Assets:Bank \"Called the bank\"\n"));
+
+        let (ledger, _) = parse(source)?;
+        assert_eq!(ledger.transactions_between(..).count(), 2);
+        let date = NaiveDate::from_ymd_opt(2022, 1, 9).ok_or(anyhow!("invalid date"))?;
+        assert_eq!(ledger.price_of("HOOL", "USD", &date)?, Some(dec!(110)));
+        assert_eq!(ledger.price_of("VBTLX.X", "USD", &date)?, Some(dec!(120)));
+        assert!(ledger.failed_assertions()?.is_empty());
+
+        Ok(())
+    }
+}
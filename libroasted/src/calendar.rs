@@ -0,0 +1,150 @@
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// Longest run of non business days the helpers look through before giving up.
+const MAX_SHIFT: u64 = 366;
+
+/// Days on which payments are processed. Recurrences falling on any other day are moved
+/// according to their [`BusinessDayAdjustment`][crate::recurrence::BusinessDayAdjustment].
+pub trait HolidayCalendar {
+    fn is_business_day(&self, date: &NaiveDate) -> bool;
+
+    /// The date itself when it's a business day, else the first business day after it.
+    fn next_business_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let mut date = date;
+        for _ in 0..=MAX_SHIFT {
+            if self.is_business_day(&date) {
+                return Some(date);
+            }
+            date = date.checked_add_days(Days::new(1))?;
+        }
+        None
+    }
+
+    /// The date itself when it's a business day, else the last business day before it.
+    fn previous_business_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let mut date = date;
+        for _ in 0..=MAX_SHIFT {
+            if self.is_business_day(&date) {
+                return Some(date);
+            }
+            date = date.checked_sub_days(Days::new(1))?;
+        }
+        None
+    }
+
+    /// The business day `days` business days after the date, or before it when negative.
+    /// The date itself doesn't need to be a business day, e.g. adding one day to a
+    /// Saturday gives the Monday.
+    fn add_business_days(&self, date: NaiveDate, days: i64) -> Option<NaiveDate> {
+        let mut date = date;
+        for _ in 0..days.unsigned_abs() {
+            date = if days < 0 {
+                self.previous_business_day(date.checked_sub_days(Days::new(1))?)?
+            } else {
+                self.next_business_day(date.checked_add_days(Days::new(1))?)?
+            };
+        }
+        Some(date)
+    }
+
+    /// Number of business days in the range, end excluded.
+    fn business_days_between(&self, range: Range<NaiveDate>) -> usize {
+        range
+            .start
+            .iter_days()
+            .take_while(|date| *date < range.end)
+            .filter(|date| self.is_business_day(date))
+            .count()
+    }
+}
+
+/// Every day but Saturday and Sunday is a business day.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WeekendCalendar;
+
+impl HolidayCalendar for WeekendCalendar {
+    fn is_business_day(&self, date: &NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+    }
+}
+
+/// Weekends and the listed holidays are no business days.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HolidayList {
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl HolidayList {
+    pub fn new<I: IntoIterator<Item = NaiveDate>>(holidays: I) -> Self {
+        HolidayList {
+            holidays: holidays.into_iter().collect(),
+        }
+    }
+
+    pub fn insert(&mut self, date: NaiveDate) -> bool {
+        self.holidays.insert(date)
+    }
+
+    pub fn holidays(&self) -> impl Iterator<Item = &NaiveDate> {
+        self.holidays.iter()
+    }
+}
+
+impl HolidayCalendar for HolidayList {
+    fn is_business_day(&self, date: &NaiveDate) -> bool {
+        WeekendCalendar.is_business_day(date) && !self.holidays.contains(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calendar::{HolidayCalendar, HolidayList, WeekendCalendar};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_business_days() -> Result<()> {
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        // 2022-12-24 is a Saturday.
+        assert_eq!(
+            WeekendCalendar.next_business_day(date(12, 24)?),
+            Some(date(12, 26)?)
+        );
+        assert_eq!(
+            WeekendCalendar.previous_business_day(date(12, 24)?),
+            Some(date(12, 23)?)
+        );
+
+        let holidays = HolidayList::new([date(12, 26)?, date(12, 27)?]);
+        assert!(!holidays.is_business_day(&date(12, 26)?));
+        assert_eq!(
+            holidays.next_business_day(date(12, 24)?),
+            Some(date(12, 28)?)
+        );
+        assert_eq!(
+            holidays.add_business_days(date(12, 23)?, 2),
+            Some(date(12, 29)?)
+        );
+        assert_eq!(
+            holidays.add_business_days(date(12, 28)?, -1),
+            Some(date(12, 23)?)
+        );
+        assert_eq!(
+            holidays.add_business_days(date(12, 24)?, 0),
+            Some(date(12, 24)?)
+        );
+        assert_eq!(
+            holidays.business_days_between(date(12, 19)?..date(12, 31)?),
+            8
+        );
+        assert_eq!(
+            WeekendCalendar.business_days_between(date(12, 19)?..date(12, 31)?),
+            10
+        );
+
+        Ok(())
+    }
+}
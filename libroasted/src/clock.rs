@@ -0,0 +1,117 @@
+use chrono::{Local, NaiveDate, NaiveTime};
+use std::fmt;
+use std::time::SystemTime;
+
+/// Where the ledger gets the current date and time from, for the features relative to
+/// today, e.g. [`Ledger::stale_unsettled`][crate::ledger::Ledger::stale_unsettled] or
+/// [`Ledger::future_transactions`][crate::ledger::Ledger::future_transactions].
+///
+/// Set with [`Ledger::set_clock`][crate::ledger::Ledger::set_clock], pinning it with a
+/// [`FixedClock`] makes tests and report runs reproducible.
+pub trait Clock: Send + Sync {
+    fn today(&self) -> NaiveDate;
+    /// Recorded in the audit log.
+    fn now(&self) -> SystemTime;
+}
+
+/// The system clock, today being the local date. Used unless set otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn today(&self) -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock stopped at the start of the date, UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedClock(pub NaiveDate);
+
+impl Clock for FixedClock {
+    fn today(&self) -> NaiveDate {
+        self.0
+    }
+
+    fn now(&self) -> SystemTime {
+        self.0.and_time(NaiveTime::MIN).and_utc().into()
+    }
+}
+
+pub(crate) struct LedgerClock(pub(crate) Box<dyn Clock>);
+
+impl Default for LedgerClock {
+    fn default() -> Self {
+        LedgerClock(Box::new(SystemClock))
+    }
+}
+
+impl fmt::Debug for LedgerClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LedgerClock({})", self.0.today())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::FixedClock;
+    use crate::ledger::Ledger;
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    #[test]
+    fn test_fixed_clock() -> Result<()> {
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+        let mut ledger = Ledger::new();
+        ledger.set_clock(Box::new(FixedClock(date(3, 1)?)));
+        let ledger = parser::parse(
+            r#"
+option "audit_log" "true"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-15 ! "Old pending"
+  Assets:Bank
+  Expenses:Food              10 USD
+
+2022-02-25 ! "Recent pending"
+  Assets:Bank
+  Expenses:Food              20 USD
+
+2022-03-01 * "Today"
+  Assets:Bank
+  Expenses:Food              30 USD
+
+2022-04-01 * "Planned"
+  Assets:Bank
+  Expenses:Food              40 USD
+"#,
+            Some(ledger),
+        )?;
+        assert_eq!(ledger.today(), date(3, 1)?);
+
+        let stale: Vec<_> = ledger
+            .stale_unsettled(30)
+            .into_iter()
+            .map(|(_, txn)| txn.title())
+            .collect();
+        assert_eq!(stale, vec!["Old pending"]);
+        let future: Vec<_> = ledger
+            .future_transactions()
+            .map(|(_, txn)| txn.title())
+            .collect();
+        assert_eq!(future, vec!["Planned"]);
+
+        let log = ledger.audit_log().ok_or(anyhow!("no audit log"))?;
+        let recorded_at: DateTime<Utc> = log[log.len() - 1].recorded_at.into();
+        assert_eq!(recorded_at.date_naive(), date(3, 1)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{import::import_directive, ledger::Ledger, schema::schema_directive};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+
+/// Handler for `custom` statements, called with the arguments following the handler name.
+pub type CustomHandler = fn(&mut Ledger, &NaiveDate, &[&str]) -> Result<()>;
+
+/// Handler for directives starting with a keyword unknown to roasted, called with the
+/// directive text following the keyword, continuation lines included. Pieces of it can be
+/// parsed with [`parser::parse_amount`][crate::parser::parse_amount] and friends.
+pub type DirectiveHandler = fn(&mut Ledger, &NaiveDate, &str) -> Result<()>;
+
+/// Keywords of the directives built into the grammar, they can't be taken by handlers.
+///
+/// Kept in sync with the `known_keyword` rule of `ledger.pest`, which a test checks.
+pub(crate) const KNOWN_KEYWORDS: &[&str] = &[
+    "custom",
+    "open",
+    "close",
+    "price",
+    "pad",
+    "balance",
+    "balance-change",
+    "checkpoint",
+    "note",
+    "document",
+    "event",
+];
+
+/// Built-in handlers registered to every new [`Ledger`].
+pub(crate) const BUILTIN_HANDLERS: &[(&str, CustomHandler)] = &[
+    ("assert-option", assert_option),
+    ("import", import_directive),
+    ("schema", schema_directive),
+];
+
+/// `custom "assert-option" "key" "value"`, fails if the option is not set to the given value.
+pub fn assert_option(ledger: &mut Ledger, date: &NaiveDate, args: &[&str]) -> Result<()> {
+    let [key, expected] = args else {
+        return Err(anyhow!(
+            "assert-option at {} expects option name and value, got {} argument(s)",
+            date,
+            args.len()
+        ));
+    };
+
+    match ledger.get_option(key) {
+        Some(val) if val == expected => Ok(()),
+        Some(val) => Err(anyhow!(
+            "option `{}' is `{}', expected `{}' at {}",
+            key,
+            val,
+            expected,
+            date
+        )),
+        None => Err(anyhow!(
+            "option `{}' is not set, expected `{}' at {}",
+            key,
+            expected,
+            date
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ledger::Ledger;
+    use crate::parser;
+    use crate::statement::Statement;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_known_keywords_match_grammar() -> Result<()> {
+        let grammar = include_str!("ledger.pest");
+        let rule = grammar
+            .split_once("known_keyword = {")
+            .and_then(|(_, rest)| rest.split_once('}'))
+            .ok_or(anyhow!("no known_keyword rule in the grammar"))?
+            .0;
+        let in_grammar: BTreeSet<&str> = rule
+            .split('|')
+            .map(|keyword| keyword.trim().trim_matches('"'))
+            .collect();
+        let known: BTreeSet<&str> = crate::custom::KNOWN_KEYWORDS.iter().copied().collect();
+        assert_eq!(known, in_grammar);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_option() -> Result<()> {
+        let input = r#"
+option "operating_currency" "USD"
+2021-01-01 custom "assert-option" "operating_currency" "USD"
+"#;
+        let ledger = parser::parse(input, None)?;
+        let date = NaiveDate::from_ymd_opt(2021, 1, 1).ok_or(anyhow!("invalid date"))?;
+        assert_eq!(
+            ledger.get_bookings_on(&date).map(|b| b.custom().len()),
+            Some(1)
+        );
+
+        let err = parser::parse(&input.replacen("USD", "JPY", 1), None).unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "3:1: option `operating_currency' is `JPY', expected `USD' at 2021-01-01"
+        );
+
+        let err = parser::parse(
+            r#"2021-01-01 custom "assert-option" "operating_currency""#,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "1:1: assert-option at 2021-01-01 expects option name and value, got 1 argument(s)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_directive() -> Result<()> {
+        fn budget(ledger: &mut Ledger, date: &NaiveDate, text: &str) -> Result<()> {
+            let (account, amount) = text.split_once(char::is_whitespace).ok_or(anyhow!(
+                "budget at {} expects an account and an amount",
+                date
+            ))?;
+            let amount = parser::parse_amount(amount.trim())?;
+            ledger.set_option(
+                &format!("budget:{}", parser::parse_account(account)?),
+                &format!("{} {}", amount.nominal(), amount.unit()),
+            );
+            Ok(())
+        }
+
+        let mut ledger = Ledger::new();
+        ledger.register_directive("budget", budget)?;
+        assert!(ledger.register_directive("balance", budget).is_err());
+        assert!(ledger.register_directive("Budget", budget).is_err());
+
+        let ledger = parser::parse(
+            r#"
+unit USD
+2021-05-01 budget Expenses:Food 1_500 USD
+2021-05-01 forecast Expenses:Food 1_400 USD
+"#,
+            Some(ledger),
+        )?;
+        assert_eq!(
+            ledger
+                .get_option("budget:Expenses:Food")
+                .map(String::as_str),
+            Some("1500 USD")
+        );
+
+        // Handled or not, directives are kept as written.
+        let date = NaiveDate::from_ymd_opt(2021, 5, 1).ok_or(anyhow!("invalid date"))?;
+        assert_eq!(
+            ledger.get_bookings_on(&date).map(|book| book.raw().len()),
+            Some(2)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_custom_handler() -> Result<()> {
+        fn budget(ledger: &mut Ledger, _date: &NaiveDate, args: &[&str]) -> Result<()> {
+            ledger.set_option("budget", args.join(" ").as_str());
+            Ok(())
+        }
+
+        let mut ledger = Ledger::new();
+        ledger.register_custom_handler("budget", budget);
+
+        let date = NaiveDate::from_ymd_opt(2021, 5, 20).ok_or(anyhow!("invalid date"))?;
+        ledger.process_statement(Statement::Custom(date, vec!["budget", "100", "USD"]))?;
+        ledger.process_statement(Statement::Custom(date, vec!["author", "team rocket"]))?;
+
+        assert_eq!(ledger.get_option("budget").unwrap(), "100 USD");
+        assert_eq!(ledger.get_bookings_on(&date).unwrap().custom().len(), 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Replace the `span` byte range of the source with `replacement`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub span: Range<usize>,
+    pub replacement: String,
+}
+
+/// A suggested change resolving a diagnostic, safe to apply without review.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+    pub description: String,
+    pub edits: Vec<TextEdit>,
+}
+
+/// A problem found while checking a ledger, reported instead of aborting so every
+/// problem can be fixed at once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable kebab-case identifier of the check, e.g. `missing-option`.
+    pub code: &'static str,
+    pub message: String,
+    /// Byte range of the offending source, when the check worked on source text.
+    pub span: Option<Range<usize>>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            span: None,
+            fixes: Vec::new(),
+        }
+    }
+
+    pub fn warning(code: &'static str, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message,
+            span: None,
+            fixes: Vec::new(),
+        }
+    }
+
+    pub fn with_span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fixes.push(fix);
+        self
+    }
+}
+
+/// Apply the edits to the source, edits may come in any order but must not overlap.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> Result<String> {
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    edits.sort_by_key(|edit| (edit.span.start, edit.span.end));
+
+    let mut out = String::with_capacity(source.len());
+    let mut pos = 0;
+    for edit in edits {
+        if edit.span.start < pos {
+            return Err(anyhow!("edit at {:?} overlaps another edit", edit.span));
+        }
+        out.push_str(
+            source
+                .get(pos..edit.span.start)
+                .ok_or(anyhow!("edit at {:?} is out of bounds", edit.span))?,
+        );
+        out.push_str(&edit.replacement);
+        pos = edit.span.end;
+    }
+    out.push_str(
+        source
+            .get(pos..)
+            .ok_or(anyhow!("edit ending at {} is out of bounds", pos))?,
+    );
+    Ok(out)
+}
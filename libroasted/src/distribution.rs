@@ -0,0 +1,293 @@
+use crate::{
+    account::TxnAccount,
+    amount::{checked_add, checked_div, checked_mul},
+    ledger::Ledger,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How posting amounts are grouped into buckets.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BucketSpec {
+    /// Buckets of the same width aligned on zero, e.g. `[0, 10)`, `[10, 20)` for 10. Only
+    /// buckets holding postings are returned, so a sparse distribution stays small.
+    Width(Decimal),
+    /// Boundaries in ascending order. Every bucket is returned, the first and the last
+    /// one open ended, e.g. `[10, 100]` gives `(, 10)`, `[10, 100)` and `[100, )`.
+    Edges(Vec<Decimal>),
+}
+
+/// Postings with an amount from `low`, included, to `high`, excluded.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Bucket {
+    /// `None` when the bucket has no lower bound.
+    pub low: Option<Decimal>,
+    /// `None` when the bucket has no upper bound.
+    pub high: Option<Decimal>,
+    pub count: usize,
+    pub total: Decimal,
+}
+
+/// Histogram of the amounts posted to an account in a unit, see
+/// [`Ledger::amount_distribution`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AmountDistribution {
+    pub unit: usize,
+    pub count: usize,
+    pub min: Decimal,
+    pub max: Decimal,
+    /// By amount.
+    pub buckets: Vec<Bucket>,
+}
+
+impl AmountDistribution {
+    pub fn mean(&self) -> Result<Decimal> {
+        let total = self
+            .buckets
+            .iter()
+            .try_fold(Decimal::ZERO, |total, bucket| {
+                checked_add(total, bucket.total)
+            })?;
+        checked_div(total, Decimal::from(self.count))
+    }
+}
+
+/// Buckets of one unit while postings are streamed in.
+enum Buckets<'s> {
+    Width(Decimal, BTreeMap<i64, Bucket>),
+    Edges(&'s [Decimal], Vec<Bucket>),
+}
+
+impl<'s> Buckets<'s> {
+    fn new(spec: &'s BucketSpec) -> Self {
+        match spec {
+            BucketSpec::Width(width) => Buckets::Width(*width, BTreeMap::new()),
+            BucketSpec::Edges(edges) => Buckets::Edges(
+                edges,
+                (0..=edges.len())
+                    .map(|idx| Bucket {
+                        low: idx.checked_sub(1).map(|low| edges[low]),
+                        high: edges.get(idx).copied(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn bucket(&mut self, nominal: Decimal) -> Result<&mut Bucket> {
+        match self {
+            Buckets::Width(width, buckets) => {
+                let width = *width;
+                let key = checked_div(nominal, width)?
+                    .floor()
+                    .to_i64()
+                    .ok_or(anyhow!("amount out of bucket range: {}", nominal))?;
+                let low = checked_mul(Decimal::from(key), width)?;
+                let high = checked_add(low, width)?;
+                Ok(buckets.entry(key).or_insert_with(|| Bucket {
+                    low: Some(low),
+                    high: Some(high),
+                    ..Default::default()
+                }))
+            }
+            Buckets::Edges(edges, buckets) => {
+                let idx = edges.partition_point(|edge| *edge <= nominal);
+                Ok(&mut buckets[idx])
+            }
+        }
+    }
+
+    fn into_vec(self) -> Vec<Bucket> {
+        match self {
+            Buckets::Width(_, buckets) => buckets.into_values().collect(),
+            Buckets::Edges(_, buckets) => buckets,
+        }
+    }
+}
+
+/// Histogram of the amounts posted to the account, see [`Ledger::amount_distribution`].
+pub(crate) fn amount_distribution<R: RangeBounds<NaiveDate>>(
+    ledger: &Ledger,
+    account: &TxnAccount,
+    range: R,
+    spec: &BucketSpec,
+) -> Result<Vec<AmountDistribution>> {
+    match spec {
+        BucketSpec::Width(width) if *width <= Decimal::ZERO => {
+            return Err(anyhow!("invalid bucket width: {}", width))
+        }
+        BucketSpec::Edges(edges) if edges.windows(2).any(|pair| pair[0] >= pair[1]) => {
+            return Err(anyhow!("bucket edges have to be in ascending order"))
+        }
+        _ => {}
+    }
+
+    let mut units: BTreeMap<usize, (AmountDistribution, Buckets)> = BTreeMap::new();
+    for amount in ledger
+        .postings_for_account(account, range)
+        .filter_map(|posting| posting.amount())
+    {
+        let (distribution, buckets) = units.entry(amount.unit).or_insert_with(|| {
+            (
+                AmountDistribution {
+                    unit: amount.unit,
+                    count: 0,
+                    min: amount.nominal,
+                    max: amount.nominal,
+                    buckets: Vec::new(),
+                },
+                Buckets::new(spec),
+            )
+        });
+        distribution.count += 1;
+        distribution.min = distribution.min.min(amount.nominal);
+        distribution.max = distribution.max.max(amount.nominal);
+        let bucket = buckets.bucket(amount.nominal)?;
+        bucket.count += 1;
+        bucket.total = checked_add(bucket.total, amount.nominal)?;
+    }
+
+    Ok(units
+        .into_values()
+        .map(|(mut distribution, buckets)| {
+            distribution.buckets = buckets.into_vec();
+            distribution
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distribution::BucketSpec;
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_amount_distribution() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit EUR
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-02 * "Coffee"
+  Assets:Bank
+  Expenses:Food               4 USD
+
+2022-01-03 * "Lunch"
+  Assets:Bank
+  Expenses:Food              12 USD
+
+2022-01-04 * "Lunch"
+  Assets:Bank
+  Expenses:Food              15 USD
+
+2022-01-05 * "Dinner"
+  Assets:Bank
+  Expenses:Food              48 USD
+
+2022-01-06 * "Refund"
+  Expenses:Food              -5 USD
+  Assets:Bank
+
+2022-01-07 * "Croissant"
+  Assets:Bank
+  Expenses:Food               3 EUR
+"#,
+            None,
+        )?;
+        let food = ledger
+            .accounts()
+            .lookup(&parser::parse_account("Expenses:Food")?)
+            .ok_or(anyhow!("account not found"))?;
+
+        let distributions = ledger.amount_distribution(&food, .., &BucketSpec::Width(dec!(10)))?;
+        assert_eq!(distributions.len(), 2);
+        let usd = &distributions[0];
+        assert_eq!((usd.count, usd.min, usd.max), (5, dec!(-5), dec!(48)));
+        assert_eq!(usd.mean()?, dec!(14.8));
+        let buckets: Vec<_> = usd
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.low, bucket.count))
+            .collect();
+        assert_eq!(
+            buckets,
+            vec![
+                (Some(dec!(-10)), 1),
+                (Some(dec!(0)), 1),
+                (Some(dec!(10)), 2),
+                (Some(dec!(40)), 1)
+            ]
+        );
+        assert_eq!(usd.buckets[2].total, dec!(27));
+
+        let distributions = ledger.amount_distribution(
+            &food,
+            ..,
+            &BucketSpec::Edges(vec![dec!(0), dec!(10), dec!(20)]),
+        )?;
+        let counts: Vec<_> = distributions[0]
+            .buckets
+            .iter()
+            .map(|bucket| bucket.count)
+            .collect();
+        assert_eq!(counts, vec![1, 1, 2, 1]);
+        assert_eq!(distributions[0].buckets[0].low, None);
+        assert_eq!(distributions[0].buckets[3].high, None);
+
+        assert!(ledger
+            .amount_distribution(&food, .., &BucketSpec::Width(dec!(0)))
+            .is_err());
+
+        Ok(())
+    }
+    #[test]
+    fn test_distribution_overflow() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-02 * "Banquet"
+  Assets:Bank
+  Expenses:Food      50000000000000000000000000000 USD
+
+2022-01-03 * "Banquet"
+  Assets:Bank
+  Expenses:Food      40000000000000000000000000000 USD
+"#,
+            None,
+        )?;
+        let food = ledger
+            .accounts()
+            .lookup(&parser::parse_account("Expenses:Food")?)
+            .ok_or(anyhow!("account not found"))?;
+
+        // Each bucket holds one amount, only their sum overflows.
+        let distributions = ledger.amount_distribution(
+            &food,
+            ..,
+            &BucketSpec::Edges(vec![dec!(45000000000000000000000000000)]),
+        )?;
+        let err = distributions[0]
+            .mean()
+            .expect_err("total of the buckets overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+}
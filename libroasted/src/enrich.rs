@@ -0,0 +1,113 @@
+use crate::transaction::Transaction;
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Attach metadata to transactions from sources outside the ledger, e.g. a merchant
+/// database giving the category and location of a payee.
+///
+/// Enrichers registered with [`Ledger::register_enricher`][crate::ledger::Ledger::register_enricher]
+/// run on every transaction as it's added, [`Ledger::enrich`][crate::ledger::Ledger::enrich]
+/// runs one over the transactions already in a ledger.
+pub trait Enricher: Send + Sync {
+    /// Metadata to attach to the transaction.
+    fn enrich(&self, date: &NaiveDate, txn: &Transaction) -> Result<BTreeMap<String, String>>;
+}
+
+/// Add the metadata found by the enricher, keys already set on the transaction are kept
+/// so the ledger always wins over external sources.
+pub(crate) fn apply(
+    enricher: &dyn Enricher,
+    date: &NaiveDate,
+    txn: &mut Transaction,
+) -> Result<()> {
+    for (key, value) in enricher.enrich(date, txn)? {
+        txn.metadata.entry(key).or_insert(value);
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub(crate) struct Enrichers(pub(crate) Vec<Box<dyn Enricher>>);
+
+impl fmt::Debug for Enrichers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Enrichers({})", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enrich::Enricher;
+    use crate::ledger::Ledger;
+    use crate::parser;
+    use crate::transaction::Transaction;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use std::collections::BTreeMap;
+
+    struct Merchants;
+
+    impl Enricher for Merchants {
+        fn enrich(&self, _: &NaiveDate, txn: &Transaction) -> Result<BTreeMap<String, String>> {
+            let mut metadata = BTreeMap::new();
+            if txn.payee.as_deref() == Some("Grocer") {
+                metadata.insert("category".to_string(), "groceries".to_string());
+                metadata.insert("location".to_string(), "Bandung".to_string());
+            }
+            Ok(metadata)
+        }
+    }
+
+    const INPUT: &str = r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  location: "Jakarta"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-04 * "Diner" "Lunch"
+  Assets:Bank
+  Expenses:Food            12 USD
+"#;
+
+    #[test]
+    fn test_enricher() -> Result<()> {
+        let mut ledger = Ledger::new();
+        ledger.register_enricher(Box::new(Merchants));
+        let ledger = parser::parse(INPUT, Some(ledger))?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let txn = &ledger
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions()[0];
+        assert_eq!(
+            txn.metadata.get("category").map(String::as_str),
+            Some("groceries")
+        );
+        assert_eq!(
+            txn.metadata.get("location").map(String::as_str),
+            Some("Jakarta")
+        );
+
+        let filter = ledger.compile_filter("meta:category=groceries")?;
+        assert_eq!(ledger.postings().filter(|p| filter(p)).count(), 2);
+
+        // Enriching after the fact gives the same result.
+        let mut later = parser::parse(INPUT, None)?;
+        later.enrich(&Merchants)?;
+        assert_eq!(
+            later
+                .get_bookings_on(&date)
+                .map(|book| &book.transactions()[0].metadata),
+            Some(&txn.metadata)
+        );
+
+        Ok(())
+    }
+}
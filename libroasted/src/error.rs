@@ -0,0 +1,221 @@
+use chrono::NaiveDate;
+use pest::error::{Error, InputLocation};
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::parser::Rule;
+
+/// Where an error happened in the ledger source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    /// File the source was read from, `None` for text parsed directly.
+    pub path: Option<PathBuf>,
+    /// Byte range of the offending source, within its file.
+    pub span: Range<usize>,
+    /// 1-based line of the start of the span.
+    pub line: usize,
+    /// 1-based column of the start of the span, in characters.
+    pub column: usize,
+}
+
+impl SourceLocation {
+    fn new(input: &str, span: Range<usize>) -> Self {
+        let before = &input[..span.start];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        SourceLocation {
+            path: None,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span,
+        }
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A ledger that couldn't be loaded, along with the location of the culprit so it can be
+/// pointed at, e.g. by an editor. Returned by [`parse`][crate::parse] and
+/// [`parse_file`][crate::parser::parse_file] wrapped in an [`anyhow::Error`], get it back
+/// with `err.downcast_ref::<RoastedError>()`.
+#[derive(Debug)]
+pub enum RoastedError {
+    /// The source doesn't follow the grammar.
+    Syntax {
+        location: SourceLocation,
+        message: String,
+    },
+    /// A well-formed statement couldn't be applied to the ledger, e.g. it uses an account
+    /// that isn't open or a unit that isn't declared.
+    Statement {
+        location: SourceLocation,
+        /// The statement as written, without trailing whitespace.
+        text: String,
+        error: anyhow::Error,
+    },
+}
+
+impl RoastedError {
+    pub fn location(&self) -> &SourceLocation {
+        match self {
+            RoastedError::Syntax { location, .. } | RoastedError::Statement { location, .. } => {
+                location
+            }
+        }
+    }
+
+    /// The pest error found `offset` bytes into the input.
+    pub(crate) fn syntax(input: &str, offset: usize, err: &Error<Rule>) -> anyhow::Error {
+        let span = match err.location {
+            InputLocation::Pos(pos) => offset + pos..offset + pos,
+            InputLocation::Span((start, end)) => offset + start..offset + end,
+        };
+        RoastedError::Syntax {
+            location: SourceLocation::new(input, span),
+            message: err.variant.message().to_string(),
+        }
+        .into()
+    }
+
+    /// The error of the statement at `span` of the input. Errors already located, from an
+    /// included file, are kept as they are.
+    pub(crate) fn statement(
+        input: &str,
+        span: Range<usize>,
+        error: anyhow::Error,
+    ) -> anyhow::Error {
+        if error.is::<RoastedError>() {
+            return error;
+        }
+        RoastedError::Statement {
+            location: SourceLocation::new(input, span.clone()),
+            text: input[span].trim_end().to_string(),
+            error,
+        }
+        .into()
+    }
+
+    /// Set the file of errors located in the source read from `path`.
+    #[cfg(feature = "fs")]
+    pub(crate) fn in_file(error: anyhow::Error, path: PathBuf) -> anyhow::Error {
+        match error.downcast::<RoastedError>() {
+            Ok(mut located) => {
+                match &mut located {
+                    RoastedError::Syntax { location, .. }
+                    | RoastedError::Statement { location, .. } => {
+                        location.path.get_or_insert(path);
+                    }
+                }
+                located.into()
+            }
+            Err(error) => error,
+        }
+    }
+}
+
+/// Why a statement couldn't be applied, for the failures callers may want to tell apart,
+/// e.g. to pick a diagnostic code. Found in the chain of the error, with
+/// `err.chain().find_map(|cause| cause.downcast_ref::<StatementError>())`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StatementError {
+    /// The account isn't open at the date it's used.
+    UnopenedAccount {
+        account: String,
+        date: NaiveDate,
+    },
+    UndeclaredUnit {
+        unit: String,
+    },
+    /// An option or unit after dated statements, with `option "directive_order"` set to
+    /// `strict`.
+    LateDirective {
+        directive: String,
+    },
+}
+
+impl fmt::Display for StatementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatementError::UnopenedAccount { account, date } => {
+                write!(f, "account `{}' is not opened at {}", account, date)
+            }
+            StatementError::UndeclaredUnit { unit } => write!(f, "unit `{}' is not declared", unit),
+            StatementError::LateDirective { directive } => write!(
+                f,
+                "{} has to come before dated statements with option \"directive_order\" set to \"strict\"",
+                directive
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StatementError {}
+
+impl fmt::Display for RoastedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoastedError::Syntax { location, message } => write!(f, "{}: {}", location, message),
+            RoastedError::Statement {
+                location, error, ..
+            } => write!(f, "{}: {}", location, error),
+        }
+    }
+}
+
+impl std::error::Error for RoastedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RoastedError::Syntax { .. } => None,
+            // The statement error is part of the message already, its own causes aren't.
+            RoastedError::Statement { error, .. } => error.source(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::RoastedError;
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+
+    #[test]
+    fn test_statement_location() -> Result<()> {
+        let input = r#"unit USD
+2022-01-01 open Assets:Bank
+
+2022-01-03 * "Grocer"
+  Assets:Bank
+  Expenses:Food     30 USD
+"#;
+        let err = parser::parse(input, None).unwrap_err();
+        let located = err
+            .downcast_ref::<RoastedError>()
+            .ok_or(anyhow!("no location"))?;
+        let location = located.location();
+        assert_eq!((location.line, location.column), (4, 1));
+        let RoastedError::Statement { text, .. } = located else {
+            return Err(anyhow!("not a statement error"));
+        };
+        assert_eq!(input[location.span.clone()].trim_end(), text);
+        assert_eq!(
+            format!("{}", err),
+            "4:1: account `Expenses:Food' is not opened at 2022-01-03"
+        );
+
+        let err = parser::parse("unit USD\n2022-01-01 * Grocer\n", None).unwrap_err();
+        let located = err
+            .downcast_ref::<RoastedError>()
+            .ok_or(anyhow!("no location"))?;
+        assert!(matches!(located, RoastedError::Syntax { .. }));
+        assert_eq!(located.location().line, 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::account::TxnAccount;
+use chrono::NaiveDate;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// A change applied to a [`Ledger`][crate::ledger::Ledger], see
+/// [`Ledger::subscribe`][crate::ledger::Ledger::subscribe].
+///
+/// Dates are those of the daybook the entry was booked in, for transactions the first
+/// date of their book with a coarser
+/// [`BookingGranularity`][crate::ledger::BookingGranularity].
+#[derive(Clone, Debug, PartialEq)]
+pub enum LedgerEvent {
+    OptionSet {
+        key: String,
+        value: String,
+    },
+    UnitDeclared {
+        unit: usize,
+    },
+    /// Opened with an `open` statement, or automatically in permissive mode.
+    AccountOpened {
+        date: NaiveDate,
+        account: TxnAccount,
+    },
+    AccountClosed {
+        date: NaiveDate,
+        account: TxnAccount,
+    },
+    /// A transaction was booked, `index` is its position among the transactions of the
+    /// daybook.
+    TransactionAdded {
+        date: NaiveDate,
+        index: usize,
+    },
+    PadAdded {
+        date: NaiveDate,
+        target: TxnAccount,
+    },
+    BalanceAsserted {
+        date: NaiveDate,
+        account: TxnAccount,
+    },
+    PriceSet {
+        date: NaiveDate,
+        unit: usize,
+        price_unit: usize,
+    },
+    CheckpointAdded {
+        date: NaiveDate,
+    },
+    /// Everything before the date was replaced by opening balances, see
+    /// [`Ledger::truncate_before`][crate::ledger::Ledger::truncate_before].
+    Truncated {
+        before: NaiveDate,
+    },
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Subscribers(Vec<Sender<LedgerEvent>>);
+
+impl Subscribers {
+    pub(crate) fn subscribe(&mut self) -> Receiver<LedgerEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.0.push(sender);
+        receiver
+    }
+
+    /// Send the event to every subscriber, dropping those whose receiver is gone. The
+    /// event is only built when there's someone to send it to.
+    pub(crate) fn emit(&mut self, event: impl FnOnce() -> LedgerEvent) {
+        if self.0.is_empty() {
+            return;
+        }
+        let event = event();
+        self.0.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::LedgerEvent;
+    use crate::ledger::Ledger;
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_subscribe() -> Result<()> {
+        let mut ledger = Ledger::new();
+        let events = ledger.subscribe();
+        let ignored = ledger.subscribe();
+        drop(ignored);
+
+        let mut ledger = parser::parse(
+            r#"
+option "title" "Household"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-04 balance Assets:Bank -30 USD
+2022-01-31 close Expenses:Food
+"#,
+            Some(ledger),
+        )?;
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+        let bank = ledger
+            .accounts()
+            .txnify(&date(1, 1)?, &"Assets:Bank".try_into()?)?;
+        let food = ledger
+            .accounts()
+            .txnify(&date(1, 1)?, &"Expenses:Food".try_into()?)?;
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                LedgerEvent::OptionSet {
+                    key: "title".to_string(),
+                    value: "Household".to_string()
+                },
+                LedgerEvent::UnitDeclared { unit: 0 },
+                LedgerEvent::AccountOpened {
+                    date: date(1, 1)?,
+                    account: bank.clone()
+                },
+                LedgerEvent::AccountOpened {
+                    date: date(1, 1)?,
+                    account: food.clone()
+                },
+                LedgerEvent::TransactionAdded {
+                    date: date(1, 3)?,
+                    index: 0
+                },
+                LedgerEvent::BalanceAsserted {
+                    date: date(1, 4)?,
+                    account: bank
+                },
+                LedgerEvent::AccountClosed {
+                    date: date(1, 31)?,
+                    account: food
+                },
+            ]
+        );
+
+        ledger.truncate_before(&date(1, 4)?)?;
+        assert_eq!(
+            events.try_iter().last(),
+            Some(LedgerEvent::Truncated {
+                before: date(1, 4)?
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use crate::ledger::Ledger;
+use crate::parser;
+use anyhow::Result;
+
+/// Three years of a household in USD: salary, rent, groceries and utilities on a credit
+/// card paid off monthly, savings with yearly interest, and a `#trip-lisbon` tagged
+/// trip. Balance assertions every quarter, and budget options set.
+pub const HOUSEHOLD: &str = include_str!("../fixtures/household.ledger");
+
+/// Two years of a remote worker paid in USD and spending in IDR, the operating
+/// currency, with monthly prices, conversions paying a fee, and a trip paid in JPY.
+pub const EXPAT: &str = include_str!("../fixtures/expat.ledger");
+
+/// Every example ledger along with its name.
+pub const ALL: &[(&str, &str)] = &[("household", HOUSEHOLD), ("expat", EXPAT)];
+
+/// [`HOUSEHOLD`], parsed.
+pub fn household() -> Result<Ledger> {
+    parser::parse(HOUSEHOLD, None)
+}
+
+/// [`EXPAT`], parsed.
+pub fn expat() -> Result<Ledger> {
+    parser::parse(EXPAT, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{self, ALL};
+    use crate::ledger::RatePolicy;
+    use crate::parser;
+    use crate::reports::{self, ReportSpec};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_examples() -> Result<()> {
+        for (name, text) in ALL {
+            let ledger = parser::parse(text, None)?;
+            assert_eq!(ledger.failed_assertions()?, vec![], "{}", name);
+            assert!(ledger.pad_diagnostics()?.is_empty(), "{}", name);
+            assert!(ledger.date_range().is_some(), "{}", name);
+        }
+
+        let household = examples::household()?;
+        assert_eq!(household.transactions_with_tag("trip-lisbon").count(), 2);
+        assert_eq!(reports::budget::budget(&household)?.months.len(), 37);
+
+        let expat = examples::expat()?;
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).ok_or(anyhow!("invalid date"))?;
+        assert!(reports::run(
+            &expat,
+            &ReportSpec::Converted(date, None, RatePolicy::Previous)
+        )
+        .is_ok());
+
+        Ok(())
+    }
+}
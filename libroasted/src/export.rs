@@ -0,0 +1,137 @@
+use crate::{account::TxnAccount, ledger::Ledger, query::PostingRef, writer};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Render the transactions matching the filter as a standalone ledger, see
+/// [`Ledger::export_filtered`].
+pub fn filtered(ledger: &Ledger, query: &str) -> Result<String> {
+    let filter = ledger.compile_filter(query)?;
+
+    let mut matched = Vec::new();
+    let mut accounts: BTreeSet<&TxnAccount> = BTreeSet::new();
+    let mut units: BTreeSet<usize> = ledger.operating_unit().into_iter().collect();
+    for (date, book) in ledger.bookings() {
+        for transaction in book.transactions() {
+            let matches = (0..transaction.exchanges.len()).any(|index| {
+                filter(&PostingRef {
+                    date: *date,
+                    transaction,
+                    index,
+                })
+            });
+            if !matches {
+                continue;
+            }
+            for exchange in &transaction.exchanges {
+                accounts.insert(&exchange.account);
+                units.extend(exchange.amount.as_ref().map(|amount| amount.unit));
+            }
+            matched.push((date, transaction));
+        }
+    }
+
+    let mut out = String::new();
+    for (key, value) in ledger.options() {
+        writeln!(out, "option \"{}\" \"{}\"", key, value)?;
+    }
+    for unit in units {
+        writeln!(out, "{}", ledger.unit_info(unit)?)?;
+    }
+
+    let mut opens: Vec<_> = ledger
+        .accounts()
+        .iter()
+        .filter(|(account, _)| accounts.contains(account))
+        .collect();
+    opens.sort_by_key(|(_, activities)| *activities.opened_at());
+    for (account, activities) in &opens {
+        writer::write_open(&mut out, ledger, activities.opened_at(), account)?;
+    }
+    out.push('\n');
+
+    for (date, transaction) in matched {
+        writer::write_transaction(&mut out, ledger, date, transaction)?;
+        out.push('\n');
+    }
+
+    let mut closes: BTreeMap<NaiveDate, Vec<String>> = BTreeMap::new();
+    for (account, activities) in &opens {
+        if let Some(closed) = activities.closed_at() {
+            closes
+                .entry(*closed)
+                .or_default()
+                .push(ledger.account_name(account)?);
+        }
+    }
+    for (date, accounts) in closes {
+        for account in accounts {
+            writeln!(out, "{} close {}", date, account)?;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_export_filtered() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Health
+  deductible: "yes"
+2022-01-01 open Expenses:Food
+2022-01-01 open Income:Salary
+
+2022-01-02 price USD 15_000 IDR
+
+2022-01-25 * "ACME Corp" "January salary"
+  Income:Salary       -4000 USD
+  Assets:Bank
+
+2022-02-03 * "Clinic" "Checkup"
+  Assets:Bank
+  Expenses:Health       120 USD
+    receipt: "c-17"
+
+2022-02-04 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food          30 USD
+
+2022-03-01 balance Assets:Bank 3850 USD
+"#,
+            None,
+        )?;
+
+        let exported = ledger.export_filtered("account:Expenses:Health date>=2022-02-01")?;
+        assert_eq!(
+            exported,
+            concat!(
+                "unit USD\n",
+                "2022-01-01 open Assets:Bank\n",
+                "2022-01-01 open Expenses:Health\n",
+                "  deductible: \"yes\"\n",
+                "\n",
+                "2022-02-03 * \"Clinic\" \"Checkup\"\n",
+                "  Assets:Bank\n",
+                "  Expenses:Health  120 USD\n",
+                "    receipt: \"c-17\"\n",
+                "\n",
+            )
+        );
+
+        let reparsed = parser::parse(&exported, None)?;
+        assert_eq!(reparsed.postings().count(), 2);
+        assert!(ledger.export_filtered("amount>").is_err());
+
+        Ok(())
+    }
+}
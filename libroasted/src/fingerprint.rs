@@ -0,0 +1,95 @@
+use crate::{
+    ledger::Ledger,
+    redact::fnv1a,
+    writer::{self, AmountStyle},
+};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Hash of what a ledger holds, see [`Ledger::fingerprint`]. Written as 16 hex digits,
+/// so it can be kept alongside a cache and read back.
+///
+/// The hash is stable across runs of the same version. A version writing ledgers
+/// differently may give other fingerprints, which only costs a reparse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fingerprint(u64);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.len() != 16 {
+            return Err(anyhow!("invalid fingerprint: `{}'", s));
+        }
+        Ok(Fingerprint(
+            u64::from_str_radix(s, 16).map_err(|_| anyhow!("invalid fingerprint: `{}'", s))?,
+        ))
+    }
+}
+
+/// Hash the ledger as written back by the writer, which lays statements out the same
+/// way whatever the source looked like, with amounts written by value rather than as
+/// they were in the source.
+pub(crate) fn fingerprint(ledger: &Ledger) -> Result<Fingerprint> {
+    let mut text = String::new();
+    writer::write_styled_ledger(&mut text, ledger, AmountStyle::Normalized)?;
+    Ok(Fingerprint(fnv1a(&text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fingerprint::Fingerprint;
+    use crate::parser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_fingerprint() -> Result<()> {
+        let source = r#"unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+"#;
+        let reformatted = r#"; Reformatted, with a comment.
+unit USD
+
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-03 * "Groceries"
+    Assets:Bank
+    Expenses:Food  30 USD
+"#;
+        let fingerprint = parser::parse(source, None)?.fingerprint()?;
+        assert_eq!(
+            parser::parse(reformatted, None)?.fingerprint()?,
+            fingerprint
+        );
+        // Amounts formatted differently but worth the same.
+        for amount in ["30    USD", "30.00 USD", "30.0 USD"] {
+            let edited = source.replace("30 USD", amount);
+            assert_eq!(parser::parse(&edited, None)?.fingerprint()?, fingerprint);
+        }
+        let changed = source.replace("30 USD", "31 USD");
+        assert_ne!(parser::parse(&changed, None)?.fingerprint()?, fingerprint);
+
+        let text = fingerprint.to_string();
+        assert_eq!(text.len(), 16);
+        assert_eq!(text.parse::<Fingerprint>()?, fingerprint);
+        assert!("xyz".parse::<Fingerprint>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{ledger::Ledger, parser};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// CSV column read by an [`ImportProfile`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ImportField {
+    Date,
+    Payee,
+    Title,
+    /// Signed amount from the point of view of the imported account, deposits are
+    /// positive.
+    Amount,
+}
+
+/// Counter account for rows whose payee or title contains `pattern`, ignoring case.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImportRule {
+    pub pattern: String,
+    pub account: String,
+}
+
+/// How to turn rows of a bank's CSV export into transactions, declared in the ledger
+/// with `custom "import"` statements.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImportProfile {
+    /// Account the CSV file is a statement of.
+    pub account: Option<String>,
+    pub unit: Option<String>,
+    /// Zero-based column of each field.
+    pub columns: BTreeMap<ImportField, usize>,
+    /// `chrono` format of the date column.
+    pub date_format: String,
+    pub delimiter: char,
+    /// `.` or `,`, the other one may group thousands, see [`parse_amount`].
+    pub decimal_separator: char,
+    /// Rows to skip at the start of the file, e.g. a header.
+    pub skip_rows: usize,
+    /// Checked in order, the first match wins.
+    pub rules: Vec<ImportRule>,
+    /// Counter account for rows no rule matches.
+    pub fallback: Option<String>,
+}
+
+impl Default for ImportProfile {
+    fn default() -> Self {
+        ImportProfile {
+            account: None,
+            unit: None,
+            columns: BTreeMap::new(),
+            date_format: "%Y-%m-%d".to_string(),
+            delimiter: ',',
+            decimal_separator: '.',
+            skip_rows: 0,
+            rules: Vec::new(),
+            fallback: None,
+        }
+    }
+}
+
+impl ImportProfile {
+    fn column<'r>(&self, record: &'r [String], field: ImportField) -> Result<Option<&'r str>> {
+        let Some(&idx) = self.columns.get(&field) else {
+            return Ok(None);
+        };
+        let value = record
+            .get(idx)
+            .ok_or(anyhow!("row has no column {}", idx + 1))?;
+        Ok(Some(value.trim()))
+    }
+
+    fn counter_account(&self, texts: &[&str]) -> Result<&str> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                let pattern = rule.pattern.to_lowercase();
+                texts
+                    .iter()
+                    .any(|text| text.to_lowercase().contains(&pattern))
+            })
+            .map(|rule| rule.account.as_str())
+            .or(self.fallback.as_deref())
+            .ok_or(anyhow!("no rule matches `{}'", texts.join(" ")))
+    }
+}
+
+fn parse_column(field: &str, column: &str) -> Result<(ImportField, usize)> {
+    let field = match field {
+        "date" => ImportField::Date,
+        "payee" => ImportField::Payee,
+        "title" => ImportField::Title,
+        "amount" => ImportField::Amount,
+        other => return Err(anyhow!("unknown import column `{}'", other)),
+    };
+    match column.parse::<usize>() {
+        Ok(column) if column > 0 => Ok((field, column - 1)),
+        _ => Err(anyhow!("invalid import column number `{}'", column)),
+    }
+}
+
+/// `custom "import" "<profile>" <setting> ...` declares how CSV files of a bank are
+/// imported, settings are:
+///
+/// - `"account" "Assets:Bank"`, the account the file is a statement of.
+/// - `"unit" "USD"`, the unit of the amounts.
+/// - `"column" "date" "1"`, the one-based column of `date`, `payee`, `title`, or
+///   `amount`.
+/// - `"date-format" "%d/%m/%Y"`, defaulting to `%Y-%m-%d`.
+/// - `"delimiter" ";"`, defaulting to a comma.
+/// - `"decimal-separator" ","`, `.` or `,`, defaulting to a dot.
+/// - `"skip" "1"`, the number of header rows.
+/// - `"rule" "grocer" "Expenses:Food"`, the counter account of rows whose payee or title
+///   contains the text, checked in order.
+/// - `"fallback" "Expenses:Uncategorized"`, the counter account when no rule matches.
+pub fn import_directive(ledger: &mut Ledger, date: &NaiveDate, args: &[&str]) -> Result<()> {
+    let [name, setting @ ..] = args else {
+        return Err(anyhow!("import at {} expects a profile name", date));
+    };
+    let profile = ledger.import_profile_mut(name);
+    match setting {
+        ["account", account] => {
+            profile.account = Some(parser::parse_account(account)?.to_string());
+        }
+        ["unit", unit] => profile.unit = Some(unit.to_string()),
+        ["column", field, column] => {
+            let (field, column) = parse_column(field, column)?;
+            profile.columns.insert(field, column);
+        }
+        ["date-format", format] => profile.date_format = format.to_string(),
+        ["delimiter", delimiter] => {
+            let mut chars = delimiter.chars();
+            let (Some(delimiter), None) = (chars.next(), chars.next()) else {
+                return Err(anyhow!("invalid import delimiter `{}'", delimiter));
+            };
+            profile.delimiter = delimiter;
+        }
+        ["decimal-separator", separator @ ("." | ",")] => {
+            profile.decimal_separator = separator.chars().next().unwrap_or('.');
+        }
+        ["skip", rows] => {
+            profile.skip_rows = rows
+                .parse()
+                .map_err(|_| anyhow!("invalid import skip count `{}'", rows))?;
+        }
+        ["rule", pattern, account] => profile.rules.push(ImportRule {
+            pattern: pattern.to_string(),
+            account: parser::parse_account(account)?.to_string(),
+        }),
+        ["fallback", account] => {
+            profile.fallback = Some(parser::parse_account(account)?.to_string());
+        }
+        _ => {
+            return Err(anyhow!(
+                "import at {} expects account, unit, column, date-format, delimiter, decimal-separator, skip, rule, or fallback, got `{}'",
+                date,
+                setting.join(" ")
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Split one CSV line into fields, handling quoted fields and `""` escapes. Quoted
+/// fields can't span lines.
+fn split_record(line: &str, delimiter: char) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(anyhow!("unterminated quote in `{}'", line));
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// The amount written with the decimal separator, its digits optionally grouped by
+/// thousands with the other one of `.` and `,`, e.g. `-1,234.50` or `-1.234,50`.
+///
+/// Amounts grouped otherwise are rejected, so `12,50` written with a `,` decimal
+/// separator fails with a `.` one instead of being read as 1250.
+fn parse_amount(text: &str, decimal: char) -> Option<Decimal> {
+    let grouping = if decimal == ',' { '.' } else { ',' };
+    let (integral, fraction) = match text.split_once(decimal) {
+        Some((integral, fraction)) => (integral, Some(fraction)),
+        None => (text, None),
+    };
+    let mut groups = integral.split(grouping);
+    let mut normalized = groups.next()?.to_string();
+    let digits = normalized.trim_start_matches(['-', '+']).len();
+    for group in groups {
+        if !(1..=3).contains(&digits) || group.len() != 3 {
+            return None;
+        }
+        normalized.push_str(group);
+    }
+    if let Some(fraction) = fraction {
+        normalized.push('.');
+        normalized.push_str(fraction);
+    }
+    if normalized.contains(['_', ',']) {
+        return None;
+    }
+    normalized.parse().ok()
+}
+
+/// Ledger strings can't hold double quotes.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'"))
+}
+
+/// Turn a CSV file into transactions following the named profile, see
+/// [`Ledger::import_csv`].
+pub fn import_csv(ledger: &Ledger, profile_name: &str, csv: &str) -> Result<String> {
+    let profile = ledger
+        .import_profiles()
+        .get(profile_name)
+        .ok_or(anyhow!("no import profile `{}'", profile_name))?;
+    let account = profile
+        .account
+        .as_deref()
+        .ok_or(anyhow!("import profile `{}' has no account", profile_name))?;
+    let unit = profile
+        .unit
+        .as_deref()
+        .ok_or(anyhow!("import profile `{}' has no unit", profile_name))?;
+    for field in [ImportField::Date, ImportField::Amount] {
+        if !profile.columns.contains_key(&field) {
+            return Err(anyhow!(
+                "import profile `{}' has no {:?} column",
+                profile_name,
+                field
+            ));
+        }
+    }
+
+    let mut out = String::new();
+    let rows = csv
+        .lines()
+        .enumerate()
+        .skip(profile.skip_rows)
+        .filter(|(_, line)| !line.trim().is_empty());
+    for (idx, line) in rows {
+        let row = || format!("row {}", idx + 1);
+        let record = split_record(line, profile.delimiter)?;
+        let date = profile
+            .column(&record, ImportField::Date)?
+            .unwrap_or_default();
+        let date = NaiveDate::parse_from_str(date, &profile.date_format)
+            .map_err(|err| anyhow!("{}: invalid date `{}': {}", row(), date, err))?;
+        let amount = profile
+            .column(&record, ImportField::Amount)?
+            .unwrap_or_default();
+        let amount = parse_amount(amount, profile.decimal_separator).ok_or_else(|| {
+            anyhow!(
+                "{}: invalid amount `{}' with `{}' as the decimal separator",
+                row(),
+                amount,
+                profile.decimal_separator
+            )
+        })?;
+        let payee = profile
+            .column(&record, ImportField::Payee)?
+            .filter(|payee| !payee.is_empty());
+        let title = profile
+            .column(&record, ImportField::Title)?
+            .filter(|title| !title.is_empty());
+        let texts: Vec<&str> = payee.into_iter().chain(title).collect();
+        let counter = profile
+            .counter_account(&texts)
+            .map_err(|err| anyhow!("{}: {}", row(), err))?;
+
+        // Imported transactions are left unsettled until reviewed.
+        write!(out, "{} !", date)?;
+        match (payee, title) {
+            (Some(payee), Some(title)) => write!(out, " {} {}", quote(payee), quote(title))?,
+            (Some(text), None) | (None, Some(text)) => write!(out, " {}", quote(text))?,
+            (None, None) => write!(out, " {}", quote("Imported"))?,
+        }
+        writeln!(out)?;
+        writeln!(out, "  {}  {} {}", account, amount, unit)?;
+        writeln!(out, "  {}", counter)?;
+        writeln!(out)?;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::{parse_amount, ImportField, ImportRule};
+    use crate::parser;
+    use anyhow::Result;
+    use rust_decimal_macros::dec;
+
+    const LEDGER: &str = r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Uncategorized
+2022-01-01 open Income:Salary
+
+2022-01-01 custom "import" "acme" "account" "Assets:Bank"
+2022-01-01 custom "import" "acme" "unit" "USD"
+2022-01-01 custom "import" "acme" "delimiter" ";"
+2022-01-01 custom "import" "acme" "skip" "1"
+2022-01-01 custom "import" "acme" "date-format" "%d/%m/%Y"
+2022-01-01 custom "import" "acme" "column" "date" "1"
+2022-01-01 custom "import" "acme" "column" "payee" "2"
+2022-01-01 custom "import" "acme" "column" "amount" "3"
+2022-01-01 custom "import" "acme" "rule" "grocer" "Expenses:Food"
+2022-01-01 custom "import" "acme" "rule" "ACME Corp" "Income:Salary"
+2022-01-01 custom "import" "acme" "fallback" "Expenses:Uncategorized"
+"#;
+
+    #[test]
+    fn test_import_csv() -> Result<()> {
+        let ledger = parser::parse(LEDGER, None)?;
+        let profile = &ledger.import_profiles()["acme"];
+        assert_eq!(profile.columns[&ImportField::Amount], 2);
+        assert_eq!(
+            profile.rules[0],
+            ImportRule {
+                pattern: "grocer".to_string(),
+                account: "Expenses:Food".to_string(),
+            }
+        );
+
+        let csv = concat!(
+            "Date;Description;Amount\n",
+            "25/01/2022;ACME CORP PAYROLL;4,000.00\n",
+            "27/01/2022;\"The \"\"Corner\"\" Grocer\";-30.50\n",
+            "28/01/2022;Parking;-5\n",
+        );
+        let imported = ledger.import_csv("acme", csv)?;
+        assert_eq!(
+            imported,
+            concat!(
+                "2022-01-25 ! \"ACME CORP PAYROLL\"\n",
+                "  Assets:Bank  4000.00 USD\n",
+                "  Income:Salary\n",
+                "\n",
+                "2022-01-27 ! \"The 'Corner' Grocer\"\n",
+                "  Assets:Bank  -30.50 USD\n",
+                "  Expenses:Food\n",
+                "\n",
+                "2022-01-28 ! \"Parking\"\n",
+                "  Assets:Bank  -5 USD\n",
+                "  Expenses:Uncategorized\n",
+                "\n",
+            )
+        );
+        let merged = parser::parse(&format!("{}\n{}", LEDGER, imported), None)?;
+        assert_eq!(merged.postings().count(), 6);
+
+        assert!(ledger.import_csv("acme", "25/01/2022;x\n").is_ok());
+        // A comma decimal separator isn't taken for grouping.
+        let err = ledger
+            .import_csv("acme", "h\n25/01/2022;Grocer;12,50\n")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "row 2: invalid amount `12,50' with `.' as the decimal separator"
+        );
+        assert!(ledger.import_csv("acme", "h\n2022-01-25;x;1\n").is_err());
+        assert!(ledger.import_csv("other", csv).is_err());
+        assert!(parser::parse(
+            "2022-01-01 custom \"import\" \"acme\" \"column\" \"balance\" \"4\"\n",
+            None
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        let amount = |text| parse_amount(text, '.');
+        assert_eq!(amount("4,000.00"), Some(dec!(4000.00)));
+        assert_eq!(amount("-1,234,567"), Some(dec!(-1234567)));
+        assert_eq!(amount("-30.50"), Some(dec!(-30.50)));
+        assert_eq!(amount("12,50"), None);
+        assert_eq!(amount("1234,567"), None);
+        assert_eq!(amount(",500"), None);
+        assert_eq!(amount("1.5,00"), None);
+
+        let amount = |text| parse_amount(text, ',');
+        assert_eq!(amount("12,50"), Some(dec!(12.50)));
+        assert_eq!(amount("-1.234,50"), Some(dec!(-1234.50)));
+        assert_eq!(amount("1.234"), Some(dec!(1234)));
+        assert_eq!(amount("12.50"), None);
+    }
+}
@@ -1,24 +1,76 @@
 use crate::{
-    account::{AccountStore, ParsedAccount, TxnAccount},
-    amount::{Amount, ParsedAmount},
-    parser::inner_str,
-    statement::Statement,
-    transaction::{BalanceAssertion, PadTransaction, ParsedTransaction, Transaction, TxnHeader},
+    account::{AccountHints, AccountStore, ParsedAccount, TxnAccount},
+    amount::{checked_add, checked_div, checked_mul, checked_sub, Amount, ParsedAmount},
+    annotation::{self, Annotations, TransactionId},
+    autofill,
+    average::{self, BalanceWeighting},
+    balance::{Balances, UnitBalances},
+    clock::{Clock, LedgerClock},
+    custom::{CustomHandler, DirectiveHandler, BUILTIN_HANDLERS, KNOWN_KEYWORDS},
+    diagnostic::{Diagnostic, Severity},
+    distribution::{self, AmountDistribution, BucketSpec},
+    enrich::{self, Enricher, Enrichers},
+    error::StatementError,
+    event::{LedgerEvent, Subscribers},
+    export,
+    fingerprint::{self, Fingerprint},
+    import::{self, ImportProfile},
+    note::{Document, LifeEvent, Note},
+    parser::{inner_str, unescape_str},
+    precision::{self, Precision},
+    query::{self, CompiledFilter, PostingRef},
+    reader::{ArcLedger, LedgerReader},
+    recurrence::Recurrence,
+    redact::{self, Redaction},
+    register::{self, RegisterCursor, RegisterFilter, RegisterPage},
+    reports::{
+        tax::{self, TaxReport},
+        ReportSpec,
+    },
+    reprice::{self, Quote, Repriced},
+    schema::{self, LedgerSchema},
+    search::{self, SearchHit},
+    seed,
+    series::{self, PriceSeries},
+    statement::{Metadata, Statement},
+    symbol::SymbolTable,
+    transaction::{
+        AssertionFailure, AssertionKind, BalanceAssertion, Exchange, PadTransaction,
+        ParsedTransaction, Transaction, TransactionState, TxnHeader,
+    },
+    unit::{UnitInfo, UnitStore},
+    validate::{self, ValidationReport},
+    writer,
 };
 use anyhow::{anyhow, Result};
-use chrono::naive::NaiveDate;
+use chrono::{naive::NaiveDate, Datelike, Days};
 use indexmap::IndexSet;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::ops::{Bound, ControlFlow, Range, RangeBounds, RangeInclusive};
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::time::SystemTime;
 
 use crate::parser::Rule;
 use pest::iterators::Pair;
+use rust_decimal::Decimal;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DayBook {
     custom: Vec<Vec<String>>,
     pads: Vec<PadTransaction>,
     balance_asserts: Vec<BalanceAssertion>,
+    balance_changes: Vec<BalanceAssertion>,
     transactions: Vec<Transaction>,
+    notes: Vec<Note>,
+    documents: Vec<Document>,
+    events: Vec<LifeEvent>,
+    raw: Vec<String>,
 }
 
 impl DayBook {
@@ -27,7 +79,12 @@ impl DayBook {
             custom: Vec::new(),
             pads: Vec::new(),
             balance_asserts: Vec::new(),
+            balance_changes: Vec::new(),
             transactions: Vec::new(),
+            notes: Vec::new(),
+            documents: Vec::new(),
+            events: Vec::new(),
+            raw: Vec::new(),
         }
     }
 
@@ -43,24 +100,218 @@ impl DayBook {
         &self.balance_asserts
     }
 
+    /// Assertions of the net change since the previous assertion of the account, from
+    /// `balance-change`.
+    pub fn balance_changes(&self) -> &Vec<BalanceAssertion> {
+        &self.balance_changes
+    }
+
     pub fn transactions(&self) -> &Vec<Transaction> {
         &self.transactions
     }
+
+    /// Whether a transaction or a pad of the day uses the account.
+    pub fn uses_account(&self, account: &TxnAccount) -> bool {
+        self.transactions
+            .iter()
+            .any(|txn| txn.exchanges.iter().any(|e| &e.account == account))
+            || self
+                .pads
+                .iter()
+                .any(|pad| &pad.target == account || &pad.source == account)
+    }
+
+    pub fn notes(&self) -> &Vec<Note> {
+        &self.notes
+    }
+
+    pub fn documents(&self) -> &Vec<Document> {
+        &self.documents
+    }
+
+    pub fn events(&self) -> &Vec<LifeEvent> {
+        &self.events
+    }
+
+    /// Source text of directives not understood by this version, without their date.
+    pub fn raw(&self) -> &Vec<String> {
+        &self.raw
+    }
 }
 
-pub type PriceBook = HashMap<usize, HashMap<usize, f64>>;
+pub type PriceBook = BTreeMap<usize, BTreeMap<usize, Decimal>>;
 
-#[derive(Debug, Default)]
+/// The amount a pad moves from its source to its target, see [`Ledger::balances_at`].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PadPosting {
+    pub(crate) target: TxnAccount,
+    pub(crate) source: TxnAccount,
+    pub(crate) amount: Amount,
+}
+
+impl PadPosting {
+    fn apply(&self, balances: &mut Balances) -> Result<()> {
+        balances.add(&self.target, &self.amount)?;
+        balances.add(
+            &self.source,
+            &Amount {
+                nominal: -self.amount.nominal,
+                unit: self.amount.unit,
+            },
+        )
+    }
+}
+
+/// Resolved pads by pad date.
+pub(crate) type PadPostings = BTreeMap<NaiveDate, Vec<PadPosting>>;
+
+/// Rate to convert one `from` unit into `to` unit from the prices of a single day, in
+/// either direction.
+fn quote_in(book: &PriceBook, from: usize, to: usize) -> Option<Decimal> {
+    book.get(&from)
+        .and_then(|prices| prices.get(&to))
+        .copied()
+        .or_else(|| {
+            book.get(&to)
+                .and_then(|prices| prices.get(&from))
+                .and_then(|price| Decimal::ONE.checked_div(*price))
+        })
+}
+
+/// Rate used to convert on a date without a price between the two units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RatePolicy {
+    /// The most recent price before the date.
+    #[default]
+    Previous,
+    /// The closest price either side of the date, the earlier one on ties.
+    Nearest,
+    /// Linearly interpolated between the prices around the date, or the closest one
+    /// when there's only one side.
+    Linear,
+}
+
+/// A region of the input skipped in permissive mode because it failed to parse.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnparsedRegion {
+    /// Byte range within the parsed input, spans from included files are relative to
+    /// their own content.
+    pub span: Range<usize>,
+    pub text: String,
+}
+
+/// A statement applied to the ledger, see [`Ledger::audit_log`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AuditEntry {
+    pub recorded_at: SystemTime,
+    /// Where the statement came from, the file path for files, see
+    /// [`Ledger::set_audit_origin`] otherwise.
+    pub origin: String,
+    /// Source text of the statement, replaying every entry in order rebuilds the ledger.
+    pub text: String,
+}
+
+/// How finely transactions are bucketed by date.
+///
+/// Other entries, e.g. balance assertions and pads, stay at their own date, so a
+/// month-end assertion is still checked at the end of the month. Transactions count from
+/// the start of their bucket, an assertion in the middle of a bucket sees all of the
+/// bucket's transactions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BookingGranularity {
+    #[default]
+    Daily,
+    /// Books start on Mondays.
+    Weekly,
+    Monthly,
+}
+
+impl BookingGranularity {
+    /// First date of the book the date falls in.
+    pub fn bucket(&self, date: &NaiveDate) -> NaiveDate {
+        match self {
+            Self::Daily => *date,
+            Self::Weekly => date
+                .checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))
+                .unwrap_or(*date),
+            Self::Monthly => date.with_day(1).unwrap_or(*date),
+        }
+    }
+}
+
+/// Settings fixed for the lifetime of a ledger, see [`Ledger::with_config`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LedgerConfig {
+    pub granularity: BookingGranularity,
+}
+
+/// Origin recorded for statements not read from a file, unless set otherwise.
+const DEFAULT_AUDIT_ORIGIN: &str = "<input>";
+
+/// With the `serde` feature the ledger can be serialized, e.g. to JSON, and loaded back
+/// without reparsing. Registered handlers and enrichers are code, they're left out and
+/// a deserialized ledger only has the built-in handlers and no subscribers.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ledger {
     accounts: AccountStore,
     bookings: BTreeMap<NaiveDate, DayBook>,
-    options: HashMap<String, String>,
-    units: IndexSet<String>,
+    options: BTreeMap<String, String>,
+    units: UnitStore,
+    payees: SymbolTable<String>,
     pricebooks: BTreeMap<NaiveDate, PriceBook>,
+    checkpoints: BTreeMap<NaiveDate, Vec<BalanceAssertion>>,
+    inferred_opens: Vec<(NaiveDate, TxnAccount)>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "builtin_handlers"))]
+    custom_handlers: HashMap<String, CustomHandler>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    directive_handlers: HashMap<String, DirectiveHandler>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::pairs"))]
+    account_metadata: BTreeMap<TxnAccount, BTreeMap<String, String>>,
+    unparsed: Vec<UnparsedRegion>,
+    schema: LedgerSchema,
+    import_profiles: BTreeMap<String, ImportProfile>,
+    included: Vec<PathBuf>,
+    /// Whether a dated statement was applied yet, for `option "directive_order"`.
+    dated_seen: bool,
+    audit_log: Option<Vec<AuditEntry>>,
+    audit_origin: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    enrichers: Enrichers,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    subscribers: Subscribers,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clock: LedgerClock,
+    config: LedgerConfig,
+    annotations: Annotations,
+}
+
+fn builtin_handlers() -> HashMap<String, CustomHandler> {
+    BUILTIN_HANDLERS
+        .iter()
+        .map(|&(name, handler)| (name.to_string(), handler))
+        .collect()
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The ledger as roasted syntax, see [`Ledger::to_ledger_string`].
+impl fmt::Display for Ledger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writer::write_ledger(f, self).map_err(|_| fmt::Error)
+    }
 }
 
 macro_rules! daybook_insert {
-    ($self:ident, $date:ident, $field:ident, $val:expr) => {
+    ($self:ident, $date:ident, $field:ident, $val:expr) => {{
         if let Some(book) = $self.get_mut_bookings_on(&$date) {
             book.$field.push($val);
             Ok(())
@@ -70,21 +321,111 @@ macro_rules! daybook_insert {
             $self.bookings.insert($date, book);
             Ok(())
         }
-    };
+    }};
 }
 
 impl Ledger {
     pub fn new() -> Ledger {
+        Self::with_config(LedgerConfig::default())
+    }
+
+    /// Ledger with coarser books, e.g. monthly for analytical workloads that never look
+    /// at single days. Entries are booked under the first date of their book, so date
+    /// based queries, balances and assertions included, only see book boundaries.
+    /// Accounts are still checked against the actual date of each entry.
+    pub fn with_config(config: LedgerConfig) -> Ledger {
         Ledger {
             accounts: AccountStore::new(),
             bookings: BTreeMap::new(),
-            options: HashMap::new(),
-            units: IndexSet::new(),
+            options: BTreeMap::new(),
+            units: UnitStore::new(),
+            payees: SymbolTable::new(),
             pricebooks: BTreeMap::new(),
+            checkpoints: BTreeMap::new(),
+            inferred_opens: Vec::new(),
+            custom_handlers: builtin_handlers(),
+            directive_handlers: HashMap::new(),
+            account_metadata: BTreeMap::new(),
+            unparsed: Vec::new(),
+            schema: LedgerSchema::default(),
+            import_profiles: BTreeMap::new(),
+            included: Vec::new(),
+            dated_seen: false,
+            audit_log: None,
+            audit_origin: DEFAULT_AUDIT_ORIGIN.to_string(),
+            enrichers: Enrichers::default(),
+            subscribers: Subscribers::default(),
+            clock: LedgerClock::default(),
+            config,
+            annotations: Annotations::new(),
+        }
+    }
+
+    /// Load the source like [`parse`][crate::parse], carrying on past statements that
+    /// can't be applied, and report every problem found along with what could be loaded:
+    /// syntax errors, unopened accounts, undeclared units, unbalanced transactions,
+    /// failed balance assertions and accounts opened twice. Included files aren't
+    /// followed.
+    pub fn validate(source: &str) -> ValidationReport {
+        validate::validate(source)
+    }
+
+    /// Read-only view of the ledger, safe to share across threads, see [`LedgerReader`].
+    pub fn reader(&self) -> LedgerReader<'_> {
+        LedgerReader::new(self)
+    }
+
+    /// Move the ledger behind an [`ArcLedger`], to share it across threads once it's
+    /// loaded.
+    pub fn into_shared(self) -> ArcLedger {
+        ArcLedger::new(self)
+    }
+
+    pub fn config(&self) -> &LedgerConfig {
+        &self.config
+    }
+
+    /// Register a handler called for `custom` statements whose first argument is `name`.
+    /// The statement is still recorded in its daybook after the handler succeeds.
+    pub fn register_custom_handler(&mut self, name: &str, handler: CustomHandler) {
+        self.custom_handlers.insert(name.to_string(), handler);
+    }
+
+    /// Register a handler called for directives starting with `keyword`, e.g.
+    /// `2024-01-01 budget Expenses:Food 300 USD` for `budget`, so experimental directives
+    /// can live outside the grammar. The directive is still recorded in its daybook after
+    /// the handler succeeds, like unknown ones.
+    ///
+    /// Keywords start with a lowercase letter, and can't be one of the built-in ones.
+    pub fn register_directive(&mut self, keyword: &str, handler: DirectiveHandler) -> Result<()> {
+        let valid = keyword.starts_with(|c: char| c.is_ascii_lowercase())
+            && keyword
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid || KNOWN_KEYWORDS.contains(&keyword) {
+            return Err(anyhow!("invalid directive keyword: `{}'", keyword));
+        }
+        self.directive_handlers.insert(keyword.to_string(), handler);
+        Ok(())
+    }
+
+    /// Register an enricher run on every transaction added from now on, in registration
+    /// order. Metadata keys set by the ledger or an earlier enricher are kept.
+    pub fn register_enricher(&mut self, enricher: Box<dyn Enricher>) {
+        self.enrichers.0.push(enricher);
+    }
+
+    /// Run the enricher over every transaction already in the ledger.
+    pub fn enrich(&mut self, enricher: &dyn Enricher) -> Result<()> {
+        for (date, book) in self.bookings.iter_mut() {
+            for txn in book.transactions.iter_mut() {
+                enrich::apply(enricher, date, txn)?;
+            }
         }
+        Ok(())
     }
 
-    pub fn parse_option(&mut self, token: Pair<Rule>) -> Result<()> {
+    pub(crate) fn parse_option(&mut self, token: Pair<Rule>) -> Result<()> {
         let mut option = token.into_inner();
         let key = inner_str(
             option
@@ -96,41 +437,212 @@ impl Ledger {
                 .next()
                 .ok_or(anyhow!(format!("invalid next token: {}", option.as_str()),))?,
         );
-        self.set_option(key, val);
+        self.check_directive_order(&format!("option \"{}\"", key))?;
+        self.set_option(&unescape_str(key), &unescape_str(val));
         Ok(())
     }
+
+    /// With `option "directive_order" "strict"`, options and units have to come before
+    /// any dated statement, across included files too, since changing them later would
+    /// only affect the statements after them.
+    fn check_directive_order(&self, directive: &str) -> Result<()> {
+        match self.get_option("directive_order").map(String::as_str) {
+            None | Some("any") => Ok(()),
+            Some("strict") if self.dated_seen => Err(StatementError::LateDirective {
+                directive: directive.to_string(),
+            }
+            .into()),
+            Some("strict") => Ok(()),
+            Some(other) => Err(anyhow!("invalid directive_order option: `{}'", other)),
+        }
+    }
     pub fn set_option(&mut self, key: &str, val: &str) {
+        if key == "case_insensitive_accounts" {
+            self.accounts.set_case_insensitive(val == "true");
+        }
+        if key == "same_day_close" {
+            self.accounts.set_close_inclusive(val == "allow");
+        }
+        if key == "parent_postings" {
+            self.accounts.set_forbid_parent_postings(val == "forbid");
+        }
         self.options.insert(key.to_string(), val.to_string());
+        if key == "audit_log" {
+            match self.is_option_enabled("audit_log") {
+                true => self.enable_audit_log(),
+                false => self.audit_log = None,
+            }
+        }
+        self.subscribers.emit(|| LedgerEvent::OptionSet {
+            key: key.to_string(),
+            value: val.to_string(),
+        });
     }
 
     pub fn get_option(&self, key: &str) -> Option<&String> {
         self.options.get(key)
     }
 
-    pub fn parse_unit(&mut self, token: Pair<Rule>) -> Result<()> {
-        let mut unit_token = token.into_inner();
-        let unit = unit_token
-            .next()
-            .ok_or(anyhow!(format!(
-                "invalid next token: {}",
-                unit_token.as_str()
-            )))?
-            .as_str();
+    /// Start recording every statement applied from now on, also enabled with
+    /// `option "audit_log" "true"`, any other value of the option disables it.
+    pub fn enable_audit_log(&mut self) {
+        self.audit_log.get_or_insert_with(Vec::new);
+    }
+
+    /// Statements applied since the audit log was enabled, `None` if it never was.
+    pub fn audit_log(&self) -> Option<&[AuditEntry]> {
+        self.audit_log.as_deref()
+    }
+
+    /// Set the origin recorded with the next statements, e.g. the user or service
+    /// applying them, returns the previous one.
+    pub fn set_audit_origin(&mut self, origin: &str) -> String {
+        std::mem::replace(&mut self.audit_origin, origin.to_string())
+    }
+
+    /// Receive every change applied to the ledger from now on, by statements, parsing
+    /// more input into it, or [`reprice`][Self::reprice] and
+    /// [`truncate_before`][Self::truncate_before], so a long running frontend can update
+    /// without reloading and diffing the whole ledger.
+    ///
+    /// Dropping the receiver unsubscribes it.
+    pub fn subscribe(&mut self) -> Receiver<LedgerEvent> {
+        self.subscribers.subscribe()
+    }
+
+    /// Take today's date and the audit log times from the clock instead of the system
+    /// clock, e.g. a [`FixedClock`][crate::FixedClock] for reproducible reports.
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = LedgerClock(clock);
+    }
+
+    /// Today's date according to the ledger's clock, see [`set_clock`][Self::set_clock].
+    pub fn today(&self) -> NaiveDate {
+        self.clock.0.today()
+    }
+
+    /// Unsettled transactions, `!`, left pending for more than `max_age` days as of today.
+    pub fn stale_unsettled(&self, max_age: u64) -> Vec<(NaiveDate, &Transaction)> {
+        let Some(cutoff) = self.today().checked_sub_days(Days::new(max_age)) else {
+            return Vec::new();
+        };
+        self.transactions_between(..cutoff)
+            .filter(|(_, txn)| txn.state == TransactionState::Unsettled)
+            .collect()
+    }
+
+    /// Transactions dated after today, e.g. planned or recurring ones, to tell a forecast
+    /// apart from what already happened.
+    pub fn future_transactions(&self) -> impl Iterator<Item = (NaiveDate, &Transaction)> {
+        self.transactions_between((Bound::Excluded(self.today()), Bound::Unbounded))
+    }
+
+    pub(crate) fn record_audit(&mut self, text: &str) {
+        if let Some(log) = &mut self.audit_log {
+            log.push(AuditEntry {
+                recorded_at: self.clock.0.now(),
+                origin: self.audit_origin.clone(),
+                text: text.to_string(),
+            });
+        }
+    }
+
+    /// Every option set, sorted by key.
+    pub fn options(&self) -> Vec<(&String, &String)> {
+        self.options.iter().collect()
+    }
 
-        self.units.insert(unit.to_string());
+    pub fn is_option_enabled(&self, key: &str) -> bool {
+        self.get_option(key).is_some_and(|val| val == "true")
+    }
+
+    pub(crate) fn parse_unit(&mut self, token: Pair<Rule>) -> Result<()> {
+        self.declare_unit(UnitInfo::parse(token)?)
+    }
 
+    fn declare_unit(&mut self, info: UnitInfo) -> Result<()> {
+        self.check_directive_order(&format!("unit `{}'", info.name))?;
+        if let (unit, true) = self.units.declare(info) {
+            self.subscribers.emit(|| LedgerEvent::UnitDeclared { unit });
+        }
         Ok(())
     }
 
+    /// Apply a single statement, e.g. one of [`parser::parse_statements`] to feed the
+    /// ledger incrementally.
+    ///
+    /// Statements dated outside the years 0 to 9999 are rejected, the ledger syntax
+    /// can't write them back.
     pub fn process_statement(&mut self, statement: Statement) -> Result<()> {
+        if let Some(date) = statement.date() {
+            if !(0..=9999).contains(&date.year()) {
+                return Err(anyhow!(
+                    "date {} is outside the years 0 to 9999 ledgers can hold",
+                    date
+                ));
+            }
+        }
+
+        if self.is_option_enabled("permissive") && !matches!(statement, Statement::OpenAccount(..))
+        {
+            if let Some(date) = statement.date() {
+                for account in statement.accounts() {
+                    self.auto_open(*date, account)?;
+                }
+            }
+        }
+
+        if statement.date().is_some() {
+            self.dated_seen = true;
+        }
+
         match statement {
+            Statement::Option(key, value) => {
+                self.check_directive_order(&format!("option \"{}\"", key))?;
+                self.set_option(&unescape_str(key), &unescape_str(value));
+                Ok(())
+            }
+            Statement::Unit(info) => self.declare_unit(info),
+            Statement::Include(path) => Err(anyhow!(
+                "include of `{}' has to be loaded by the caller, e.g. with parse_file",
+                path
+            )),
             Statement::Custom(date, args) => self.custom(date, &args),
-            Statement::OpenAccount(date, account) => self.open_account(date, &account),
+            Statement::OpenAccount(date, account, amounts, metadata) => {
+                self.open_account(date, &account, &amounts, &metadata)
+            }
             Statement::CloseAccount(date, account) => self.close_account(date, &account),
             Statement::Pad(date, target, source) => self.pad(date, &target, &source),
-            Statement::Balance(date, account, amount) => self.balance(date, &account, &amount),
+            Statement::Balance(date, account, amounts, severity) => {
+                let assertion = self.balance_assertion(date, &account, &amounts, severity)?;
+                daybook_insert!(self, date, balance_asserts, assertion)
+            }
+            Statement::BalanceChange(date, account, amounts, severity) => {
+                let assertion = self.balance_assertion(date, &account, &amounts, severity)?;
+                daybook_insert!(self, date, balance_changes, assertion)
+            }
             Statement::Transaction(date, h, txn) => self.transaction(date, h, txn),
             Statement::Price(date, commodity, amount) => self.price(date, commodity, &amount),
+            Statement::Checkpoint(date, entries) => self.checkpoint(date, &entries),
+            Statement::Note(date, account, text) => {
+                let note = Note::new(self.accounts.txnify(&date, &account)?, text);
+                daybook_insert!(self, date, notes, note)
+            }
+            Statement::Document(date, account, path) => {
+                let document = Document::new(self.accounts.txnify(&date, &account)?, path);
+                daybook_insert!(self, date, documents, document)
+            }
+            Statement::Event(date, name, value) => {
+                daybook_insert!(self, date, events, LifeEvent::new(name, value))
+            }
+            Statement::Raw(date, text) => self.raw(date, text),
+            Statement::Unparsed(span, text) => {
+                self.unparsed.push(UnparsedRegion {
+                    span,
+                    text: text.to_string(),
+                });
+                Ok(())
+            }
         }
     }
 
@@ -138,21 +650,1029 @@ impl Ledger {
         self.bookings.get_mut(date)
     }
 
+    pub fn bookings(&self) -> &BTreeMap<NaiveDate, DayBook> {
+        &self.bookings
+    }
+
+    pub fn accounts(&self) -> &AccountStore {
+        &self.accounts
+    }
+
+    /// Periods longer than `max_gap_days` in which the account has no balance assertion,
+    /// balance changes and checkpoints included. Periods run from the account opening to its closing date,
+    /// or to the latest date in the ledger if it's still open.
+    pub fn assertion_gaps(
+        &self,
+        account: &TxnAccount,
+        max_gap_days: i64,
+    ) -> Vec<(NaiveDate, NaiveDate)> {
+        let Some(activities) = self.accounts.activities(account) else {
+            return Vec::new();
+        };
+        let end = match (activities.closed_at(), self.date_range()) {
+            (Some(closed_at), _) => *closed_at,
+            (None, Some((_, last))) => last.max(*activities.opened_at()),
+            (None, None) => *activities.opened_at(),
+        };
+
+        let mut asserted: Vec<NaiveDate> = self
+            .bookings
+            .range(activities.opened_at()..=&end)
+            .filter(|(_, book)| {
+                book.balance_asserts
+                    .iter()
+                    .chain(&book.balance_changes)
+                    .any(|assertion| &assertion.account == account)
+            })
+            .map(|(date, _)| *date)
+            .chain(
+                self.checkpoints
+                    .range(activities.opened_at()..=&end)
+                    .filter(|(_, entries)| entries.iter().any(|e| &e.account == account))
+                    .map(|(date, _)| *date),
+            )
+            .collect();
+        asserted.sort();
+        asserted.dedup();
+
+        let mut gaps = Vec::new();
+        let mut start = *activities.opened_at();
+        for date in asserted.into_iter().chain([end]) {
+            if (date - start).num_days() > max_gap_days {
+                gaps.push((start, date));
+            }
+            start = date;
+        }
+
+        gaps
+    }
+
+    /// Case-insensitive search over transaction payees, titles and metadata values, falling
+    /// back to fuzzy (in order subsequence) matching. Hits are ranked by match quality,
+    /// then most recent.
+    pub fn search(&self, text: &str) -> Vec<SearchHit<'_>> {
+        search::search(self, text)
+    }
+
+    /// Draft a transaction for the given payee and total amount from past transactions
+    /// on or before the date, using the accounts most often used with that payee and
+    /// their average split. The draft is marked as unsettled.
+    pub fn autofill(
+        &self,
+        date: &NaiveDate,
+        payee: &str,
+        amount: &Amount,
+    ) -> Result<Option<Transaction>> {
+        autofill::autofill(self, date, payee, amount)
+    }
+
+    /// Suggest the amount of a new posting to the account on the date, e.g. as a default
+    /// in entry forms and importers: the amount posted most often to it, with the payee
+    /// when given, if any was posted more than once, like a monthly rent. Otherwise the
+    /// amount clearing a liability's balance, or else the latest amount posted.
+    pub fn expected_amount(
+        &self,
+        account: &TxnAccount,
+        payee: Option<&str>,
+        date: &NaiveDate,
+    ) -> Option<Amount> {
+        autofill::expected_amount(self, account, payee, date)
+    }
+
+    /// Average end-of-day balance of the account over the date range, per unit, e.g. to
+    /// estimate the interest a savings account should earn.
+    pub fn average_balance(
+        &self,
+        account: &TxnAccount,
+        range: RangeInclusive<NaiveDate>,
+        weighting: BalanceWeighting,
+    ) -> Result<UnitBalances> {
+        average::average_balance(self, account, range, weighting)
+    }
+
+    /// Histogram of the amounts posted to the account within the date range, one per
+    /// unit, built in a single pass over its postings. Subaccounts aren't included.
+    pub fn amount_distribution<R: RangeBounds<NaiveDate>>(
+        &self,
+        account: &TxnAccount,
+        range: R,
+        spec: &BucketSpec,
+    ) -> Result<Vec<AmountDistribution>> {
+        distribution::amount_distribution(self, account, range, spec)
+    }
+
+    /// Every posting in ledger order.
+    pub fn postings(&self) -> impl Iterator<Item = PostingRef<'_>> {
+        self.bookings.iter().flat_map(|(date, book)| {
+            book.transactions().iter().flat_map(move |transaction| {
+                (0..transaction.exchanges.len()).map(move |index| PostingRef {
+                    date: *date,
+                    transaction,
+                    index,
+                })
+            })
+        })
+    }
+
+    /// Compile a posting filter expression once, to be applied to [`Ledger::postings`].
+    ///
+    /// Predicates are written `field op value`, with `account`, `payee`, `title`, `unit`,
+    /// `amount`, `date`, or `meta` as fields, e.g. `account:Expenses:Food`,
+    /// `payee="Grocer"`, `amount>=100`, `date<2024-01-01`, or `meta:reimburse=ACME`.
+    /// `account:` includes subaccounts and text fields match substrings with `:`.
+    /// Predicates are combined with `and` (implied between them), `or`, `not` or `-`,
+    /// and parentheses.
+    pub fn compile_filter(&self, query: &str) -> Result<CompiledFilter> {
+        query::compile(self, query)
+    }
+
+    /// Render `balance` statements asserting the computed balances of the accounts, given
+    /// by name, on every occurrence of the recurrence within the range, or the whole ledger
+    /// if unset.
+    /// Each statement asserts the balance at the start of its date, like a handwritten one.
+    ///
+    /// Dates where the account isn't open, has no balance yet, or is already asserted are
+    /// left out, so the output can be pasted into the ledger as is.
+    pub fn seed_balance_assertions(
+        &self,
+        accounts: &[&str],
+        recurrence: &Recurrence,
+        range: Option<RangeInclusive<NaiveDate>>,
+    ) -> Result<String> {
+        seed::seed_balance_assertions(self, accounts, recurrence, range)
+    }
+
+    /// Render the ledger as text safe to share publicly, e.g. to reproduce a parser bug.
+    ///
+    /// Payees, titles, and metadata values are replaced by stable hashes, and amounts are
+    /// disguised following the redaction. Accounts, units, options, and the shape of
+    /// every transaction are kept, `custom` and unknown directives are left out.
+    pub fn redacted(&self, redaction: &Redaction) -> Result<String> {
+        redact::redacted(self, redaction)
+    }
+
+    /// Render the transactions with a posting matching the filter, see
+    /// [`compile_filter`][Self::compile_filter], as a standalone ledger, e.g. every
+    /// tax-deductible posting of a year for a focused sub-file.
+    ///
+    /// Matching transactions are kept whole so they still balance, along with the options
+    /// and the declarations they need: their units and the opening and closing of their
+    /// accounts. Prices, pads, and balance assertions are left out.
+    pub fn export_filtered(&self, query: &str) -> Result<String> {
+        export::filtered(self, query)
+    }
+
+    /// The whole ledger as roasted syntax, e.g. to format a ledger file or save a ledger
+    /// built in code. Parsing the output gives back the same ledger, see
+    /// [`writer::write_ledger`] for what isn't kept.
+    pub fn to_ledger_string(&self) -> Result<String> {
+        let mut out = String::new();
+        writer::write_ledger(&mut out, self)?;
+        Ok(out)
+    }
+
+    /// Hash of the ledger's content, for telling whether reloading a file changed
+    /// anything. Comments, layout and how amounts are written don't count, e.g.
+    /// whitespace edits of a formatter or `30.00 USD` for `30 USD`, see [`parse_file_if_changed`][crate::parser::parse_file_if_changed].
+    pub fn fingerprint(&self) -> Result<Fingerprint> {
+        fingerprint::fingerprint(self)
+    }
+
+    /// Write the ledger into a new SQLite database at the given path, as normalized
+    /// `units`, `accounts`, `transactions`, `postings`, `posting_metadata`, and `prices`
+    /// tables. Elided amounts are stored resolved, flagged by `postings.elided`.
+    ///
+    /// `schema_version` holds the version of the table layout, it only changes when
+    /// existing queries may break. Fails if the database already has these tables.
+    #[cfg(feature = "sqlite")]
+    pub fn export_sqlite<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
+        crate::sqlite::export(self, path.as_ref())
+    }
+
+    /// Run a WebAssembly plugin over the postings, collecting the diagnostics it emits
+    /// and what it writes. Fails when the plugin traps or doesn't link.
+    #[cfg(feature = "wasm")]
+    pub fn run_plugin(
+        &self,
+        plugin: &crate::plugin::Plugin,
+    ) -> Result<crate::plugin::PluginOutput> {
+        crate::plugin::run(self, plugin)
+    }
+
+    /// Stable ids of the transactions booked on the date, in booking order.
+    pub fn transaction_ids(&self, date: &NaiveDate) -> Result<Vec<TransactionId>> {
+        annotation::transaction_ids(self, date)
+    }
+
+    /// The transaction with the given id, along with its date.
+    pub fn find_transaction(
+        &self,
+        id: &TransactionId,
+    ) -> Result<Option<(NaiveDate, &Transaction)>> {
+        for (date, book) in &self.bookings {
+            if let Some(idx) = self
+                .transaction_ids(date)?
+                .iter()
+                .position(|other| other == id)
+            {
+                return Ok(Some((*date, &book.transactions[idx])));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Transactions tagged with `#tag`, along with their date, in date order. The leading
+    /// `#` is optional.
+    pub fn transactions_with_tag<'l>(
+        &'l self,
+        tag: &'l str,
+    ) -> impl Iterator<Item = (NaiveDate, &'l Transaction)> {
+        let tag = tag.strip_prefix('#').unwrap_or(tag);
+        self.transactions_between(..)
+            .filter(move |(_, txn)| txn.tags.contains(tag))
+    }
+
+    /// Transactions linked with `^link`, along with their date, in date order. The leading
+    /// `^` is optional.
+    pub fn transactions_with_link<'l>(
+        &'l self,
+        link: &'l str,
+    ) -> impl Iterator<Item = (NaiveDate, &'l Transaction)> {
+        let link = link.strip_prefix('^').unwrap_or(link);
+        self.transactions_between(..)
+            .filter(move |(_, txn)| txn.links.contains(link))
+    }
+
+    /// Transactions booked within the date range, along with their date, in date order,
+    /// e.g. `start..end` or `start..=end`. Nothing is returned when the range starts after
+    /// it ends.
+    pub fn transactions_between<R: RangeBounds<NaiveDate>>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = (NaiveDate, &Transaction)> {
+        // `BTreeMap::range` panics on such ranges.
+        let reversed = match (range.start_bound(), range.end_bound()) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Excluded(end))
+            | (Bound::Excluded(start), Bound::Included(end)) => start >= end,
+            _ => false,
+        };
+        (!reversed)
+            .then(|| self.bookings.range(range))
+            .into_iter()
+            .flatten()
+            .flat_map(|(date, book)| book.transactions.iter().map(move |txn| (*date, txn)))
+    }
+
+    /// Call `f` with every posting within the date range matching the filter, in ledger
+    /// order, until it breaks. Postings are visited in place rather than collected, so
+    /// scans over millions of postings run in constant memory.
+    pub fn try_for_each_posting<B, R: RangeBounds<NaiveDate>>(
+        &self,
+        range: R,
+        filter: &CompiledFilter,
+        mut f: impl FnMut(PostingRef<'_>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        for (date, transaction) in self.transactions_between(range) {
+            for index in 0..transaction.exchanges.len() {
+                let posting = PostingRef {
+                    date,
+                    transaction,
+                    index,
+                };
+                if filter(&posting) {
+                    f(posting)?;
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Postings to the account within the date range, in ledger order, see
+    /// [`transactions_between`][Self::transactions_between]. Subaccounts aren't included.
+    pub fn postings_for_account<'l, R: RangeBounds<NaiveDate>>(
+        &'l self,
+        account: &'l TxnAccount,
+        range: R,
+    ) -> impl Iterator<Item = PostingRef<'l>> {
+        self.transactions_between(range)
+            .flat_map(move |(date, transaction)| {
+                transaction
+                    .exchanges
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, exchange)| &exchange.account == account)
+                    .map(move |(index, _)| PostingRef {
+                        date,
+                        transaction,
+                        index,
+                    })
+            })
+    }
+
+    /// Runtime-only transaction annotations, never written back to the ledger text.
+    pub fn annotations(&self) -> &Annotations {
+        &self.annotations
+    }
+
+    pub fn annotations_mut(&mut self) -> &mut Annotations {
+        &mut self.annotations
+    }
+
+    /// Schema declared with `custom "schema"` statements.
+    pub fn schema(&self) -> &LedgerSchema {
+        &self.schema
+    }
+
+    pub(crate) fn schema_mut(&mut self) -> &mut LedgerSchema {
+        &mut self.schema
+    }
+
+    /// CSV import profiles declared with `custom "import"` statements, by name.
+    pub fn import_profiles(&self) -> &BTreeMap<String, ImportProfile> {
+        &self.import_profiles
+    }
+
+    pub(crate) fn import_profile_mut(&mut self, name: &str) -> &mut ImportProfile {
+        self.import_profiles.entry(name.to_string()).or_default()
+    }
+
+    /// Turn a bank's CSV export into unsettled transactions, as ledger text ready to be
+    /// reviewed and appended, following the profile declared in the ledger with
+    /// `custom "import"` statements. Each row posts its amount to the profile account,
+    /// balanced by the counter account of the first matching rule.
+    pub fn import_csv(&self, profile: &str, csv: &str) -> Result<String> {
+        import::import_csv(self, profile, csv)
+    }
+
+    /// Expenses and income of the year per tax category, for handing to an accountant.
+    ///
+    /// Categories are read from `tax: "<category>"` metadata, on postings or on the
+    /// `open` statement of their account or of its closest parent having one. Only
+    /// `Expenses` and `Income` postings count, a posting with `tax: "none"` is left out of
+    /// its account's category.
+    pub fn tax_report(&self, year: i32) -> Result<TaxReport> {
+        tax::tax_report(self, year)
+    }
+
+    /// Check required options, required account subtrees, and allowed units.
+    pub fn validate_schema(&self, schema: &LedgerSchema) -> Result<Vec<Diagnostic>> {
+        schema::validate(self, schema)
+    }
+
+    /// List postings matching the filter in ledger order, at most `page_size` of them
+    /// starting at `cursor`. Pass the returned `next` cursor to get the following page.
+    /// A `page_size` of 0 is an error.
+    pub fn register_page(
+        &self,
+        filter: &RegisterFilter,
+        cursor: Option<&RegisterCursor>,
+        page_size: usize,
+    ) -> Result<RegisterPage<'_>> {
+        register::register_page(self, filter, cursor, page_size)
+    }
+
+    /// The earliest and latest dates having bookings or checkpoints.
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let first = [self.bookings.keys().next(), self.checkpoints.keys().next()]
+            .into_iter()
+            .flatten()
+            .min()?;
+        let last = [
+            self.bookings.keys().next_back(),
+            self.checkpoints.keys().next_back(),
+        ]
+        .into_iter()
+        .flatten()
+        .max()?;
+
+        Some((*first, *last))
+    }
+
     pub fn get_bookings_on(&self, date: &NaiveDate) -> Option<&DayBook> {
         self.bookings.get(date)
     }
 
+    /// Declared unit names, in declaration order.
+    pub fn units(&self) -> impl Iterator<Item = &str> {
+        self.units.iter().map(String::as_str)
+    }
+
+    /// Declared units along with their attributes, in declaration order.
+    pub fn unit_infos(&self) -> impl Iterator<Item = &UnitInfo> {
+        self.units.infos()
+    }
+
+    /// Every payee seen in transactions, in order of first appearance.
+    pub fn payees(&self) -> impl Iterator<Item = &str> {
+        self.payees.iter().map(String::as_str)
+    }
+
+    /// Declared attributes of the unit, to display its amounts.
+    pub fn unit_info(&self, unit: usize) -> Result<&UnitInfo> {
+        self.units
+            .info(unit)
+            .ok_or(anyhow!(format!("undefined unit index: {}", unit)))
+    }
+
+    pub fn unit_name(&self, unit: usize) -> Result<&str> {
+        self.units
+            .resolve(unit)
+            .map(String::as_str)
+            .ok_or(anyhow!(format!("undefined unit index: {}", unit)))
+    }
+
+    pub fn account_name(&self, account: &TxnAccount) -> Result<String> {
+        Ok(self.accounts.accountify(account)?.to_string())
+    }
+
+    pub fn checkpoints(&self) -> &BTreeMap<NaiveDate, Vec<BalanceAssertion>> {
+        &self.checkpoints
+    }
+
+    pub fn pricebooks(&self) -> &BTreeMap<NaiveDate, PriceBook> {
+        &self.pricebooks
+    }
+
+    /// Add a batch of prices, e.g. fresh quotes in a long running dashboard, and tell which
+    /// of the given report specs are affected, so only those need to be run again.
+    ///
+    /// Balance assertions compare amounts unit by unit, no price ever changes their result.
+    /// The batch is rejected as a whole when a quote uses an undeclared unit.
+    pub fn reprice(&mut self, quotes: &[Quote], specs: &[ReportSpec]) -> Result<Repriced> {
+        reprice::reprice(self, quotes, specs)
+    }
+
+    /// Decimals of each unit, per account and ledger-wide, inferred from the amounts
+    /// written in the ledger, to display amounts the way they're recorded.
+    pub fn precision(&self) -> Precision {
+        precision::precision(self)
+    }
+
+    /// Price of one `unit` in `in_unit` for every day of the range, e.g. to chart currency
+    /// exposure, with days without a price filled as told by the policy. Days before any
+    /// usable price are left out.
+    pub fn price_series(
+        &self,
+        unit: &str,
+        in_unit: &str,
+        range: RangeInclusive<NaiveDate>,
+        policy: RatePolicy,
+    ) -> Result<PriceSeries> {
+        series::price_series(self, unit, in_unit, range, policy)
+    }
+
+    pub fn get_checkpoint_on(&self, date: &NaiveDate) -> Option<&Vec<BalanceAssertion>> {
+        self.checkpoints.get(date)
+    }
+
+    /// Balance of the account and all its sub-accounts at the end of the date, including
+    /// the date's transactions like [`Ledger::balances_at`], per unit, e.g. everything
+    /// spent under `Expenses:Travel`. The account itself doesn't
+    /// need to be declared as long as some of its sub-accounts are.
+    pub fn subtree_balance(&self, date: &NaiveDate, account: &str) -> Result<UnitBalances> {
+        let parsed: ParsedAccount<'_> = account.try_into()?;
+        let root = self
+            .accounts
+            .lookup(&parsed)
+            .ok_or(anyhow!("unknown account `{}'", account))?;
+        self.balances_at(date)?.subtree(&root)
+    }
+
+    /// Compute account balances at the end of the given date.
+    ///
+    /// If there is a checkpoint on or before the date, its recorded balances are used as
+    /// the starting point and only bookings from the checkpoint date onward are summed.
+    /// Accounts not mentioned in the checkpoint are considered to have zero balance.
+    ///
+    /// A `pad` moves the amount its target lacks at the balance assertion it leads to,
+    /// from its source, on the pad date.
+    pub fn balances_at(&self, date: &NaiveDate) -> Result<Balances> {
+        self.balances_with_pads(date, &self.resolve_pads()?)
+    }
+
+    pub(crate) fn balances_with_pads(
+        &self,
+        date: &NaiveDate,
+        pads: &PadPostings,
+    ) -> Result<Balances> {
+        self.replay(Some(date), pads, &mut |_, _, _| Ok(()))
+    }
+
+    /// Walk the dates up to `until`, or all of them, from the latest checkpoint before
+    /// `until`, see [`Ledger::replay_day`].
+    fn replay<'l>(
+        &'l self,
+        until: Option<&NaiveDate>,
+        pads: &PadPostings,
+        check: &mut impl FnMut(NaiveDate, &'l DayBook, &mut Balances) -> Result<()>,
+    ) -> Result<Balances> {
+        let start = until
+            .and_then(|until| self.checkpoints.range(..=until).next_back())
+            .map_or(Bound::Unbounded, |(date, _)| Bound::Included(*date));
+        let end = until.map_or(Bound::Unbounded, |until| Bound::Included(*until));
+        let dates: BTreeSet<&NaiveDate> = self
+            .bookings
+            .range((start, end))
+            .map(|(date, _)| date)
+            .chain(self.checkpoints.range((start, end)).map(|(date, _)| date))
+            .collect();
+
+        let mut balances = Balances::new();
+        for date in dates {
+            self.replay_day(date, &mut balances, pads, check)?;
+        }
+        Ok(balances)
+    }
+
+    /// Bring the balances from the end of the previous date to the end of `date`: start
+    /// over from the date's checkpoint if there is one, call `check` with the balances at
+    /// the start of the date, then apply the date's pads and transactions.
+    pub(crate) fn replay_day<'l>(
+        &'l self,
+        date: &NaiveDate,
+        balances: &mut Balances,
+        pads: &PadPostings,
+        check: &mut impl FnMut(NaiveDate, &'l DayBook, &mut Balances) -> Result<()>,
+    ) -> Result<()> {
+        if let Some(entries) = self.checkpoints.get(date) {
+            *balances = Balances::new();
+            for entry in entries {
+                for amount in &entry.amounts {
+                    balances.add(&entry.account, amount)?;
+                }
+            }
+        }
+        let Some(book) = self.bookings.get(date) else {
+            return Ok(());
+        };
+
+        check(*date, book, balances)?;
+        for pad in pads.get(date).into_iter().flatten() {
+            pad.apply(balances)?;
+        }
+        for txn in book.transactions() {
+            balances.apply_transaction(txn)?;
+        }
+        Ok(())
+    }
+
+    /// Find the amount each pad moves, what its target lacks at the start of the date of
+    /// its next balance assertion. Transactions in between count first, and a checkpoint
+    /// drops pads still waiting for their assertion.
+    pub(crate) fn resolve_pads(&self) -> Result<PadPostings> {
+        let mut resolved = PadPostings::new();
+        let mut pending: HashMap<&TxnAccount, (NaiveDate, &TxnAccount)> = HashMap::new();
+        self.replay(None, &PadPostings::new(), &mut |date, book, balances| {
+            if self.checkpoints.contains_key(&date) {
+                pending.clear();
+            }
+            for assertion in book.balance_assertions() {
+                let Some((pad_date, source)) = pending.remove(&assertion.account) else {
+                    continue;
+                };
+                for expected in &assertion.amounts {
+                    let actual = balances.get(&assertion.account, expected.unit);
+                    if actual == expected.nominal {
+                        continue;
+                    }
+                    let posting = PadPosting {
+                        target: assertion.account.clone(),
+                        source: source.clone(),
+                        amount: Amount {
+                            nominal: checked_sub(expected.nominal, actual)?,
+                            unit: expected.unit,
+                        },
+                    };
+                    posting.apply(balances)?;
+                    resolved.entry(pad_date).or_default().push(posting);
+                }
+            }
+            for pad in book.pads() {
+                pending.insert(&pad.target, (date, &pad.source));
+            }
+            Ok(())
+        })?;
+        Ok(resolved)
+    }
+
+    /// The latest checkpoint on or before the date, and the bookings from that checkpoint
+    /// up to the date which make up the balances on top of it.
+    pub(crate) fn balance_window(
+        &self,
+        date: &NaiveDate,
+    ) -> (
+        Option<&Vec<BalanceAssertion>>,
+        btree_map::Range<'_, NaiveDate, DayBook>,
+    ) {
+        match self.checkpoints.range(..=date).next_back() {
+            Some((checkpoint_date, entries)) => {
+                (Some(entries), self.bookings.range(*checkpoint_date..=*date))
+            }
+            None => (None, self.bookings.range(..=*date)),
+        }
+    }
+
+    /// Check every balance assertion against the balance at the start of its date, each
+    /// asserted unit separately. Units not mentioned in an assertion aren't checked.
+    ///
+    /// A `pad` targeting the account since its previous assertion makes up for any
+    /// difference, so such assertions always pass. Transactions posted to the account
+    /// between the pad and the assertion come first, the pad only covers what they leave
+    /// out, see [`pad_diagnostics`][Self::pad_diagnostics]. Failures of pending
+    /// assertions, flagged with `!`, have a `Warning` severity.
+    ///
+    /// Balance changes are checked against the balance at the previous assertion of the
+    /// account, of either kind and on an earlier date, or against zero when there's none.
+    pub fn failed_assertions(&self) -> Result<Vec<AssertionFailure>> {
+        let mut failures = Vec::new();
+        // Balances of accounts at their latest assertion.
+        let mut baselines: HashMap<&TxnAccount, UnitBalances> = HashMap::new();
+
+        let pads = self.resolve_pads()?;
+        self.replay(None, &pads, &mut |date, book, balances| {
+            for change in book.balance_changes() {
+                let baseline = baselines.get(&change.account);
+                for expected in &change.amounts {
+                    let start = baseline
+                        .and_then(|units| units.get(&expected.unit))
+                        .copied()
+                        .unwrap_or_default();
+                    let actual = checked_sub(balances.get(&change.account, expected.unit), start)?;
+                    if actual != expected.nominal {
+                        failures.push(AssertionFailure {
+                            date,
+                            account: change.account.clone(),
+                            expected: expected.clone(),
+                            actual,
+                            severity: change.severity,
+                            kind: AssertionKind::Change,
+                        });
+                    }
+                }
+            }
+            for assertion in book.balance_assertions() {
+                for expected in &assertion.amounts {
+                    let actual = balances.get(&assertion.account, expected.unit);
+                    if actual == expected.nominal {
+                        continue;
+                    }
+                    failures.push(AssertionFailure {
+                        date,
+                        account: assertion.account.clone(),
+                        expected: expected.clone(),
+                        actual,
+                        severity: assertion.severity,
+                        kind: AssertionKind::Balance,
+                    });
+                }
+            }
+            for assertion in book
+                .balance_assertions()
+                .iter()
+                .chain(book.balance_changes())
+            {
+                let units = balances.units(&assertion.account).cloned();
+                baselines.insert(&assertion.account, units.unwrap_or_default());
+            }
+            Ok(())
+        })?;
+
+        Ok(failures)
+    }
+
+    /// Warn about pads whose target also gets explicit transactions before the balance
+    /// assertion the pad leads to, on the pad date included. Both end up making the
+    /// asserted balance, the transactions take precedence and the pad only covers what
+    /// they leave out, which hides a missing or mistyped transaction.
+    pub fn pad_diagnostics(&self) -> Result<Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        // Pending pads by target, with their date and the transactions posted since.
+        let mut padded: HashMap<&TxnAccount, (NaiveDate, usize)> = HashMap::new();
+
+        for (date, book) in &self.bookings {
+            for assertion in book.balance_assertions() {
+                let Some((pad_date, count)) = padded.remove(&assertion.account) else {
+                    continue;
+                };
+                if count > 0 {
+                    diagnostics.push(Diagnostic::warning(
+                        "ambiguous-pad",
+                        format!(
+                            "pad of `{}' on {} and {} transaction(s) both lead to the balance asserted on {}, the pad only covers what the transactions leave out",
+                            self.account_name(&assertion.account)?,
+                            pad_date,
+                            count,
+                            date
+                        ),
+                    ));
+                }
+            }
+            for pad in book.pads() {
+                padded.insert(&pad.target, (*date, 0));
+            }
+            for txn in book.transactions() {
+                for exchange in &txn.exchanges {
+                    if let Some((_, count)) = padded.get_mut(&exchange.account) {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Remove every booking and checkpoint before the given date, replacing them with
+    /// synthesized opening balance transactions dated the day before, so balances from
+    /// the given date onward stay the same. Amounts moved by pads are part of the opening
+    /// balances.
+    ///
+    /// With a [`BookingGranularity`] coarser than a day, the date is moved back to the
+    /// start of its bucket, and the opening balances are booked in the previous bucket.
+    ///
+    /// The counter account for the opening balances is taken from
+    /// `option "opening_balance_account"`, defaulting to `Equity:Opening-Balances`,
+    /// and is opened if needed.
+    ///
+    /// Returns the removed portion as ledger text, suitable for archival.
+    pub fn truncate_before(&mut self, date: &NaiveDate) -> Result<String> {
+        // A bucket can't be split, its transactions don't keep their own date.
+        let date = &self.config.granularity.bucket(date);
+        let Some(last_day) = date.pred_opt() else {
+            return Ok(String::new());
+        };
+        let balances = self.balances_at(&last_day)?;
+
+        let kept_bookings = self.bookings.split_off(date);
+        let removed_bookings = std::mem::replace(&mut self.bookings, kept_bookings);
+        let kept_checkpoints = self.checkpoints.split_off(date);
+        let removed_checkpoints = std::mem::replace(&mut self.checkpoints, kept_checkpoints);
+
+        let mut archive = String::new();
+        let mut dates: Vec<&NaiveDate> = removed_bookings
+            .keys()
+            .chain(removed_checkpoints.keys())
+            .collect();
+        dates.sort();
+        dates.dedup();
+        for day in dates {
+            if let Some(entries) = removed_checkpoints.get(day) {
+                writer::write_checkpoint(&mut archive, self, day, entries)?;
+                archive.push('\n');
+            }
+            if let Some(book) = removed_bookings.get(day) {
+                writer::write_daybook(&mut archive, self, day, book)?;
+            }
+        }
+
+        let opening_day = self.config.granularity.bucket(&last_day);
+        let equity = self.opening_balance_account(&opening_day)?;
+
+        for (account, units) in balances.iter() {
+            if account == &equity {
+                continue;
+            }
+
+            let mut exchanges: Vec<Exchange> = units
+                .iter()
+                .filter(|(_, &nominal)| nominal != Decimal::ZERO)
+                .map(|(&unit, &nominal)| Exchange {
+                    account: account.clone(),
+                    amount: Some(Amount { nominal, unit }),
+                    amount_text: None,
+                    elided: false,
+                    metadata: BTreeMap::new(),
+                    price: None,
+                })
+                .collect();
+            if exchanges.is_empty() {
+                continue;
+            }
+            exchanges.push(Exchange {
+                account: equity.clone(),
+                amount: None,
+                amount_text: None,
+                elided: false,
+                metadata: BTreeMap::new(),
+                price: None,
+            });
+
+            let mut transaction = Transaction {
+                state: TransactionState::Settled,
+                payee: None,
+                title: format!("Opening balance for {}", self.account_name(account)?),
+                exchanges,
+                tags: IndexSet::new(),
+                links: IndexSet::new(),
+                metadata: BTreeMap::new(),
+            };
+            transaction.fill_elided()?;
+            self.book_transaction(opening_day, transaction)?;
+        }
+
+        self.subscribers
+            .emit(|| LedgerEvent::Truncated { before: *date });
+        Ok(archive)
+    }
+
     fn custom(&mut self, date: NaiveDate, args: &[&str]) -> Result<()> {
+        if let Some((name, params)) = args.split_first() {
+            if let Some(handler) = self.custom_handlers.get(*name).copied() {
+                handler(self, &date, params)?;
+            }
+        }
+
         let params = args.iter().map(|s| s.to_string()).collect();
         daybook_insert!(self, date, custom, params)
     }
 
-    fn open_account(&mut self, date: NaiveDate, account: &ParsedAccount<'_>) -> Result<()> {
-        self.accounts.open(account, date)
+    fn raw(&mut self, date: NaiveDate, text: &str) -> Result<()> {
+        let (keyword, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        if let Some(handler) = self.directive_handlers.get(keyword).copied() {
+            handler(self, &date, rest.trim_start())?;
+        }
+
+        daybook_insert!(self, date, raw, text.to_string())
+    }
+
+    /// Open accounts that were never declared, at the date they are first used.
+    fn auto_open(&mut self, date: NaiveDate, account: &ParsedAccount<'_>) -> Result<()> {
+        if self.accounts.is_declared(account) {
+            return Ok(());
+        }
+
+        let txn_account = self.open(date, account)?;
+        self.inferred_opens.push((date, txn_account));
+        Ok(())
+    }
+
+    fn open(&mut self, date: NaiveDate, account: &ParsedAccount<'_>) -> Result<TxnAccount> {
+        self.accounts.open(account, date)?;
+        let txn_account = self.accounts.txnify(&date, account)?;
+        self.subscribers.emit(|| LedgerEvent::AccountOpened {
+            date,
+            account: txn_account.clone(),
+        });
+        Ok(txn_account)
+    }
+
+    /// Regions skipped in permissive mode because they failed to parse, in input order.
+    pub fn unparsed(&self) -> &[UnparsedRegion] {
+        &self.unparsed
+    }
+
+    /// Canonical paths of every file parsed into this ledger, in the order they were
+    /// first read.
+    pub fn included_files(&self) -> &[PathBuf] {
+        &self.included
+    }
+
+    /// Record a file about to be parsed, returns false if it was already parsed before.
+    #[cfg(feature = "fs")]
+    pub(crate) fn record_include(&mut self, path: PathBuf) -> bool {
+        if self.included.contains(&path) {
+            return false;
+        }
+        self.included.push(path);
+        true
+    }
+
+    /// Accounts opened automatically in permissive mode, along with the date of their
+    /// first use. Adding these `open` statements makes the ledger valid without
+    /// `option "permissive" "true"`.
+    pub fn inferred_opens(&self) -> &[(NaiveDate, TxnAccount)] {
+        &self.inferred_opens
+    }
+
+    fn open_account(
+        &mut self,
+        date: NaiveDate,
+        account: &ParsedAccount<'_>,
+        amounts: &[ParsedAmount],
+        metadata: &Metadata,
+    ) -> Result<()> {
+        let txn_account = self.open(date, account)?;
+
+        // Reopening an account adds to, or overrides, its previous metadata.
+        if !metadata.is_empty() {
+            let entries = self
+                .account_metadata
+                .entry(txn_account.clone())
+                .or_default();
+            for (key, value) in metadata {
+                entries.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        if amounts.is_empty() {
+            return Ok(());
+        }
+        let mut exchanges = amounts
+            .iter()
+            .map(|amount| {
+                Ok(Exchange {
+                    account: txn_account.clone(),
+                    amount: Some(self.amount(amount)?),
+                    amount_text: amount.text.map(str::to_string),
+                    elided: false,
+                    metadata: BTreeMap::new(),
+                    price: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        exchanges.push(Exchange {
+            account: self.opening_balance_account(&date)?,
+            amount: None,
+            amount_text: None,
+            elided: false,
+            metadata: BTreeMap::new(),
+            price: None,
+        });
+
+        let mut transaction = Transaction {
+            state: TransactionState::Settled,
+            payee: None,
+            title: "Opening balance".to_string(),
+            exchanges,
+            tags: IndexSet::new(),
+            links: IndexSet::new(),
+            metadata: BTreeMap::new(),
+        };
+        transaction.fill_elided()?;
+        self.book_transaction(date, transaction)
+    }
+
+    fn book_transaction(&mut self, date: NaiveDate, transaction: Transaction) -> Result<()> {
+        let book = self.config.granularity.bucket(&date);
+        let index = self
+            .bookings
+            .get(&book)
+            .map_or(0, |book| book.transactions.len());
+        self.subscribers
+            .emit(|| LedgerEvent::TransactionAdded { date: book, index });
+        daybook_insert!(self, book, transactions, transaction)
+    }
+
+    /// Counter account for opening balances, from `option "opening_balance_account"`,
+    /// defaulting to `Equity:Opening-Balances`, opened at the date if needed.
+    fn opening_balance_account(&mut self, date: &NaiveDate) -> Result<TxnAccount> {
+        let name = self
+            .get_option("opening_balance_account")
+            .cloned()
+            .unwrap_or("Equity:Opening-Balances".to_string());
+        let equity: ParsedAccount = name.as_str().try_into()?;
+        if let Ok(account) = self.accounts.txnify(date, &equity) {
+            return Ok(account);
+        }
+
+        self.open(*date, &equity)
+    }
+
+    /// Metadata attached to the account's `open` statements.
+    pub fn account_metadata(&self, account: &TxnAccount) -> Option<&BTreeMap<String, String>> {
+        self.account_metadata.get(account)
+    }
+
+    /// Presentation hints for the account, read from its metadata.
+    pub fn account_hints(&self, account: &TxnAccount) -> Result<AccountHints> {
+        self.account_metadata(account)
+            .map_or(Ok(AccountHints::default()), AccountHints::from_metadata)
     }
 
+    /// Close the account from the start of the date, or from the next date with
+    /// `option "same_day_close" "allow"`.
+    ///
+    /// When the account closes at the start of the date, closing fails if transactions or
+    /// pads on that date already use it, just like those coming after the `close`, so the
+    /// outcome doesn't depend on statement order. Balance assertions check the start of
+    /// the date and are always accepted on the closing date.
     fn close_account(&mut self, date: NaiveDate, account: &ParsedAccount<'_>) -> Result<()> {
-        self.accounts.close(account, date)
+        match self.get_option("same_day_close").map(String::as_str) {
+            Some("allow") => {}
+            None | Some("reject") => {
+                let txn_account = self.accounts.txnify(&date, account)?;
+                if self
+                    .bookings
+                    .get(&date)
+                    .is_some_and(|book| book.uses_account(&txn_account))
+                {
+                    return Err(anyhow!(
+                        "`{}' is used on {}, its closing date, set option \"same_day_close\" to \"allow\" to keep it usable that day",
+                        account,
+                        date
+                    ));
+                }
+            }
+            Some(other) => return Err(anyhow!("invalid same_day_close option: `{}'", other)),
+        }
+        self.accounts.close(account, date)?;
+        let txn_account = self.accounts.txnify_through_close(&date, account)?;
+        self.subscribers.emit(|| LedgerEvent::AccountClosed {
+            date,
+            account: txn_account,
+        });
+        Ok(())
     }
 
     fn pad(
@@ -165,6 +1685,10 @@ impl Ledger {
             target: self.accounts.txnify(&date, target)?,
             source: self.accounts.txnify(&date, source)?,
         };
+        self.subscribers.emit(|| LedgerEvent::PadAdded {
+            date,
+            target: pad_trx.target.clone(),
+        });
         daybook_insert!(self, date, pads, pad_trx)
     }
 
@@ -173,50 +1697,298 @@ impl Ledger {
             nominal: amount.nominal,
             unit: self
                 .units
-                .get_index_of(amount.unit)
-                .ok_or(anyhow!(format!("unit `{}' is not declared", amount.unit)))?,
+                .get(amount.unit)
+                .ok_or_else(|| StatementError::UndeclaredUnit {
+                    unit: amount.unit.to_string(),
+                })?,
         })
     }
 
-    fn balance(
+    fn balance_assertion(
         &mut self,
         date: NaiveDate,
         account: &ParsedAccount<'_>,
-        amount: &ParsedAmount<'_>,
-    ) -> Result<()> {
+        amounts: &[ParsedAmount<'_>],
+        severity: Severity,
+    ) -> Result<BalanceAssertion> {
+        let mut asserted = Vec::new();
+        for amount in amounts {
+            let amount = self.amount(amount)?;
+            if asserted.iter().any(|a: &Amount| a.unit == amount.unit) {
+                return Err(anyhow!(
+                    "unit `{}' asserted more than once for `{}'",
+                    self.unit_name(amount.unit)?,
+                    account
+                ));
+            }
+            asserted.push(amount);
+        }
+
         let balance_assert = BalanceAssertion {
-            account: self.account_lookup(&date, account)?,
-            amount: self.amount(amount)?,
+            account: self.accounts.txnify_through_close(&date, account)?,
+            amounts: asserted,
+            severity,
         };
 
-        daybook_insert!(self, date, balance_asserts, balance_assert)
+        self.subscribers.emit(|| LedgerEvent::BalanceAsserted {
+            date,
+            account: balance_assert.account.clone(),
+        });
+        Ok(balance_assert)
+    }
+
+    fn transaction(
+        &mut self,
+        date: NaiveDate,
+        header: TxnHeader<'_>,
+        txn: ParsedTransaction<'_>,
+    ) -> Result<()> {
+        let mut transaction = Transaction::create(self, date, &header, &txn)?;
+        self.post_fx_residue(&date, &mut transaction)?;
+        for enricher in &self.enrichers.0 {
+            enrich::apply(enricher.as_ref(), &date, &mut transaction)?;
+        }
+        if let Some(payee) = header.payee {
+            self.payees.intern(payee.to_string());
+        }
+        self.book_transaction(date, transaction)
+    }
+
+    fn price(&mut self, date: NaiveDate, unit: &str, amount: &ParsedAmount) -> Result<()> {
+        let unit_idx = self.unit_lookup(&date, unit)?;
+        let amount_unit_idx = self.unit_lookup(&date, amount.unit)?;
+        self.set_price(date, unit_idx, amount_unit_idx, amount.nominal);
+
+        Ok(())
+    }
+
+    /// Set the price of one `unit` in `price_unit` at the date, returning the previous one.
+    pub(crate) fn set_price(
+        &mut self,
+        date: NaiveDate,
+        unit: usize,
+        price_unit: usize,
+        price: Decimal,
+    ) -> Option<Decimal> {
+        self.subscribers.emit(|| LedgerEvent::PriceSet {
+            date,
+            unit,
+            price_unit,
+        });
+        self.pricebooks
+            .entry(date)
+            .or_default()
+            .entry(unit)
+            .or_default()
+            .insert(price_unit, price)
+    }
+
+    /// Rate to convert one `from` unit into `to` unit, using the most recent price
+    /// on or before the given date, in either direction.
+    pub(crate) fn conversion_rate(
+        &self,
+        from: usize,
+        to: usize,
+        date: &NaiveDate,
+    ) -> Result<Option<Decimal>> {
+        self.conversion_rate_with(from, to, date, RatePolicy::Previous)
+    }
+
+    /// Rate to convert one `from` unit into `to` unit at the date, filling days without
+    /// a price as told by the policy.
+    pub(crate) fn conversion_rate_with(
+        &self,
+        from: usize,
+        to: usize,
+        date: &NaiveDate,
+        policy: RatePolicy,
+    ) -> Result<Option<Decimal>> {
+        if from == to {
+            return Ok(Some(Decimal::ONE));
+        }
+
+        let quoted = |(quote_date, book): (&NaiveDate, &PriceBook)| {
+            quote_in(book, from, to).map(|rate| (*quote_date, rate))
+        };
+        let previous = self.previous_quote(from, to, date);
+        if policy == RatePolicy::Previous || previous.is_some_and(|(day, _)| &day == date) {
+            return Ok(previous.map(|(_, rate)| rate));
+        }
+
+        let next = self
+            .pricebooks
+            .range((Bound::Excluded(date), Bound::Unbounded))
+            .find_map(quoted);
+        Ok(match (previous, next) {
+            (Some((_, rate)), None) | (None, Some((_, rate))) => Some(rate),
+            (None, None) => None,
+            (Some((before, low)), Some((after, high))) => {
+                let span = Decimal::from((after - before).num_days());
+                let elapsed = Decimal::from((*date - before).num_days());
+                match policy {
+                    RatePolicy::Nearest if elapsed * Decimal::TWO <= span => Some(low),
+                    RatePolicy::Nearest => Some(high),
+                    _ => {
+                        let change = checked_mul(checked_sub(high, low)?, elapsed)?;
+                        Some(checked_add(low, checked_div(change, span)?)?)
+                    }
+                }
+            }
+        })
+    }
+
+    /// The most recent price between the two units on or before the date, with its date.
+    fn previous_quote(
+        &self,
+        from: usize,
+        to: usize,
+        date: &NaiveDate,
+    ) -> Option<(NaiveDate, Decimal)> {
+        self.pricebooks
+            .range(..=date)
+            .rev()
+            .find_map(|(quote_date, book)| quote_in(book, from, to).map(|rate| (*quote_date, rate)))
+    }
+
+    /// Like [`previous_quote`][Self::previous_quote], going through an intermediate unit
+    /// when the two units are never quoted against each other, the operating unit first.
+    /// The date is the one of the older of both prices.
+    fn transitive_quote(
+        &self,
+        from: usize,
+        to: usize,
+        date: &NaiveDate,
+    ) -> Option<(NaiveDate, Decimal)> {
+        if from == to {
+            return Some((*date, Decimal::ONE));
+        }
+        self.previous_quote(from, to, date).or_else(|| {
+            self.operating_unit()
+                .into_iter()
+                .chain(0..self.units.len())
+                .filter(|&via| via != from && via != to)
+                .find_map(|via| {
+                    let (first_date, first) = self.previous_quote(from, via, date)?;
+                    let (second_date, second) = self.previous_quote(via, to, date)?;
+                    Some((first_date.min(second_date), first.checked_mul(second)?))
+                })
+        })
+    }
+
+    /// Price of one `unit` in `in_unit` from the most recent price on or before the date,
+    /// quoted either way round, or through an intermediate unit when the two are never
+    /// quoted against each other, e.g. JPY in IDR from JPY/USD and USD/IDR prices.
+    pub fn price_of(&self, unit: &str, in_unit: &str, date: &NaiveDate) -> Result<Option<Decimal>> {
+        let from = self.unit_lookup(date, unit)?;
+        let to = self.unit_lookup(date, in_unit)?;
+        Ok(self.transitive_quote(from, to, date).map(|(_, rate)| rate))
     }
 
-    fn transaction(
-        &mut self,
-        date: NaiveDate,
-        header: TxnHeader<'_>,
-        txn: ParsedTransaction<'_>,
-    ) -> Result<()> {
-        let transaction = Transaction::create(self, date, &header, &txn)?;
-        daybook_insert!(self, date, transactions, transaction)
+    /// The most recent price of one `unit` in `in_unit` found like
+    /// [`price_of`][Self::price_of], along with the date it was quoted on.
+    pub fn latest_price(&self, unit: &str, in_unit: &str) -> Result<Option<(NaiveDate, Decimal)>> {
+        let Some(&last) = self.pricebooks.keys().next_back() else {
+            return Ok(None);
+        };
+        let from = self.unit_lookup(&last, unit)?;
+        let to = self.unit_lookup(&last, in_unit)?;
+        Ok(self.transitive_quote(from, to, &last))
     }
 
-    fn price(&mut self, date: NaiveDate, unit: &str, amount: &ParsedAmount) -> Result<()> {
-        let unit_idx = self.unit_lookup(&date, unit)?;
-        let amount_unit_idx = self.unit_lookup(&date, amount.unit)?;
+    /// The unit set with `option "operating_currency"`, or the first declared unit.
+    pub fn operating_unit(&self) -> Option<usize> {
+        match self.get_option("operating_currency") {
+            Some(unit) => self.units.get(unit.as_str()),
+            None => (!self.units.is_empty()).then_some(0),
+        }
+    }
 
-        if let Some(pricebook) = self
-            .pricebooks
-            .get_mut(&date)
-            .and_then(|hmap| hmap.get_mut(&unit_idx))
+    /// Post the conversion residue of a multi-unit transaction to the account configured
+    /// with `option "fx_gain_account"` or `option "fx_loss_account"`.
+    ///
+    /// Only residues within `option "fx_tolerance"`, a fraction of the converted
+    /// transaction size (default 0.01), are considered to come from exchange rates,
+    /// anything larger is left as is.
+    fn post_fx_residue(&self, date: &NaiveDate, txn: &mut Transaction) -> Result<()> {
+        let (Some(gain), Some(loss)) = (
+            self.get_option("fx_gain_account"),
+            self.get_option("fx_loss_account"),
+        ) else {
+            return Ok(());
+        };
+        let Some(operating_unit) = self.operating_unit() else {
+            return Ok(());
+        };
+
+        // A price annotation sets the rate the transaction balances with.
+        if txn.exchanges.iter().any(|e| e.price.is_some()) {
+            return Ok(());
+        }
+
+        let mut units = Vec::new();
+        let mut residue = Decimal::ZERO;
+        let mut size = Decimal::ZERO;
+        for exchange in &txn.exchanges {
+            let Some(amount) = &exchange.amount else {
+                return Ok(());
+            };
+            let Some(rate) = self.conversion_rate(amount.unit, operating_unit, date)? else {
+                return Ok(());
+            };
+            if !units.contains(&amount.unit) {
+                units.push(amount.unit);
+            }
+            let converted = checked_mul(amount.nominal, rate)?;
+            residue = checked_add(residue, converted)?;
+            size = checked_add(size, converted.max(Decimal::ZERO))?;
+        }
+
+        let tolerance: Decimal = match self.get_option("fx_tolerance") {
+            Some(tolerance) => tolerance.parse()?,
+            None => Decimal::new(1, 2),
+        };
+        // Rates derived from inverted prices aren't exact, ignore what's left of them.
+        if units.len() < 2
+            || residue.round_dp(9).is_zero()
+            || residue.abs() > checked_mul(size, tolerance)?
         {
-            pricebook.insert(amount_unit_idx, amount.nominal);
             return Ok(());
         }
 
-        self.pricebooks.insert(date, HashMap::new());
+        let account = if residue > Decimal::ZERO { gain } else { loss };
+        let account: ParsedAccount = account.as_str().try_into()?;
+        txn.exchanges.push(Exchange {
+            account: self.account_lookup(date, &account)?,
+            amount: Some(Amount {
+                nominal: -residue,
+                unit: operating_unit,
+            }),
+            amount_text: None,
+            elided: false,
+            metadata: BTreeMap::new(),
+            price: None,
+        });
+
+        Ok(())
+    }
+
+    fn checkpoint(
+        &mut self,
+        date: NaiveDate,
+        entries: &[(ParsedAccount<'_>, ParsedAmount<'_>)],
+    ) -> Result<()> {
+        let mut asserts = Vec::new();
+        for (account, amount) in entries {
+            asserts.push(BalanceAssertion {
+                account: self.accounts.txnify(&date, account)?,
+                amounts: vec![self.amount(amount)?],
+                severity: Severity::Error,
+            });
+        }
 
+        self.checkpoints.insert(date, asserts);
+        self.subscribers
+            .emit(|| LedgerEvent::CheckpointAdded { date });
         Ok(())
     }
 }
@@ -228,14 +2000,16 @@ pub trait ReferenceLookup {
 
 impl ReferenceLookup for Ledger {
     fn account_lookup(&self, date: &NaiveDate, account: &ParsedAccount) -> Result<TxnAccount> {
-        self.accounts.txnify(date, account)
+        self.accounts.txnify_posting(date, account)
     }
 
     fn unit_lookup(&self, _date: &NaiveDate, unit: &str) -> Result<usize> {
         let idx = self
             .units
-            .get_index_of(unit)
-            .ok_or(anyhow!(format!("Unit `{}' is not declared", unit)))?;
+            .get(unit)
+            .ok_or_else(|| StatementError::UndeclaredUnit {
+                unit: unit.to_string(),
+            })?;
 
         Ok(idx)
     }
@@ -245,14 +2019,22 @@ impl ReferenceLookup for Ledger {
 mod tests {
     use crate::account::{ParsedAccount, TxnAccount};
     use crate::amount::{Amount, ParsedAmount};
-    use crate::ledger::{Ledger, ReferenceLookup};
+    use crate::diagnostic::Severity;
+    use crate::ledger::{BookingGranularity, Ledger, LedgerConfig, ReferenceLookup};
     use crate::parser::{LedgerParser, Rule};
     use crate::statement::Statement;
-    use crate::transaction::{Exchange, ParsedTransaction, TransactionState, TxnHeader};
+    use crate::transaction::{
+        AssertionKind, Exchange, ParsedTransaction, TransactionState, TxnHeader,
+    };
+    use crate::writer;
     use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
 
     use anyhow::{anyhow, Result};
     use pest::Parser;
+    use std::collections::BTreeMap;
+    use std::ops::ControlFlow;
 
     #[test]
     fn test_parse_option() -> Result<()> {
@@ -293,7 +2075,12 @@ mod tests {
         let date3 = NaiveDate::from_ymd_opt(2022, 5, 21).ok_or(anyhow!("invalid date"))?;
         let acct = ParsedAccount::Assets(vec!["Cash", "On-Hand"]);
 
-        ledger.process_statement(Statement::OpenAccount(date, acct.clone()))?;
+        ledger.process_statement(Statement::OpenAccount(
+            date,
+            acct.clone(),
+            Vec::new(),
+            Vec::new(),
+        ))?;
 
         assert_eq!(
             TxnAccount::Assets(vec![0, 1]),
@@ -317,8 +2104,18 @@ mod tests {
         let acct_source = ParsedAccount::Assets(vec!["Bank", "Suisse"]);
         let acct_target = ParsedAccount::Expenses(vec!["Travels", "Airplane", "Emirates"]);
 
-        ledger.process_statement(Statement::OpenAccount(date, acct_source.clone()))?;
-        ledger.process_statement(Statement::OpenAccount(date, acct_target.clone()))?;
+        ledger.process_statement(Statement::OpenAccount(
+            date,
+            acct_source.clone(),
+            Vec::new(),
+            Vec::new(),
+        ))?;
+        ledger.process_statement(Statement::OpenAccount(
+            date,
+            acct_target.clone(),
+            Vec::new(),
+            Vec::new(),
+        ))?;
         ledger.process_statement(Statement::Pad(date, acct_target, acct_source))?;
 
         let bookings = ledger.get_bookings_on(&date).ok_or(anyhow!("no daybook"))?;
@@ -333,6 +2130,48 @@ mod tests {
         Ok(())
     }
 
+    const PADDED: &str = r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Equity:Opening-Balances
+2022-01-01 open Expenses:Food
+
+2022-01-02 pad Assets:Bank Equity:Opening-Balances
+2022-01-05 balance Assets:Bank 1000 USD
+
+2022-02-10 * "Grocer"
+  Assets:Bank
+  Expenses:Food       10 USD
+
+2022-03-01 balance Assets:Bank 990 USD
+2022-03-01 balance Equity:Opening-Balances -1000 USD
+"#;
+
+    #[test]
+    fn test_padded_balances() -> Result<()> {
+        let ledger = crate::parser::parse(PADDED, None)?;
+        assert!(ledger.failed_assertions()?.is_empty());
+
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+        let account = |name: &str| ledger.accounts().txnify(&date(1, 1)?, &name.try_into()?);
+        let bank = account("Assets:Bank")?;
+        let equity = account("Equity:Opening-Balances")?;
+
+        assert_eq!(ledger.balances_at(&date(1, 1)?)?.get(&bank, 0), dec!(0));
+        // The pad moves the amount on its own date.
+        let balances = ledger.balances_at(&date(1, 2)?)?;
+        assert_eq!(balances.get(&bank, 0), dec!(1000));
+        assert_eq!(balances.get(&equity, 0), dec!(-1000));
+        assert_eq!(ledger.balances_at(&date(2, 1)?)?.get(&bank, 0), dec!(1000));
+        assert_eq!(ledger.balances_at(&date(3, 1)?)?.get(&bank, 0), dec!(990));
+        assert_eq!(
+            ledger.subtree_balance(&date(2, 1)?, "Assets:Bank")?.get(&0),
+            Some(&dec!(1000))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_balance_transaction() -> Result<()> {
         let mut ledger = Ledger::new();
@@ -340,16 +2179,28 @@ mod tests {
         let tomorrow = NaiveDate::from_ymd_opt(2021, 5, 21).ok_or(anyhow!("invalid date"))?;
         let account = ParsedAccount::Assets(vec!["Bank", "SVB"]);
         let amount = ParsedAmount {
-            nominal: 10_000_000f64,
+            nominal: dec!(10000000),
             unit: "USD",
+            text: None,
+            price: None,
         };
 
         let mut unit_ast = LedgerParser::parse(Rule::unit, "unit USD")?;
         ledger.parse_unit(unit_ast.next().ok_or(anyhow!("invalid unit ast"))?)?;
 
-        ledger.process_statement(Statement::OpenAccount(date, account.clone()))?;
+        ledger.process_statement(Statement::OpenAccount(
+            date,
+            account.clone(),
+            Vec::new(),
+            Vec::new(),
+        ))?;
 
-        ledger.process_statement(Statement::Balance(tomorrow, account.clone(), amount))?;
+        ledger.process_statement(Statement::Balance(
+            tomorrow,
+            account.clone(),
+            vec![amount],
+            Severity::Error,
+        ))?;
 
         let bookings = ledger
             .get_bookings_on(&tomorrow)
@@ -361,11 +2212,11 @@ mod tests {
             TxnAccount::Assets(vec![0, 1])
         );
         assert_eq!(
-            bookings.balance_assertions()[0].amount,
-            Amount {
-                nominal: 10_000_000f64,
+            bookings.balance_assertions()[0].amounts,
+            vec![Amount {
+                nominal: dec!(10000000),
                 unit: 0,
-            }
+            }]
         );
 
         Ok(())
@@ -382,13 +2233,26 @@ mod tests {
         let mut unit_ast = LedgerParser::parse(Rule::unit, "unit USD")?;
         ledger.parse_unit(unit_ast.next().ok_or(anyhow!("invalid unit ast"))?)?;
 
-        ledger.process_statement(Statement::OpenAccount(date, asset.clone()))?;
-        ledger.process_statement(Statement::OpenAccount(date, expense.clone()))?;
+        ledger.process_statement(Statement::OpenAccount(
+            date,
+            asset.clone(),
+            Vec::new(),
+            Vec::new(),
+        ))?;
+        ledger.process_statement(Statement::OpenAccount(
+            date,
+            expense.clone(),
+            Vec::new(),
+            Vec::new(),
+        ))?;
 
         let txn_header = TxnHeader {
             state: TransactionState::Settled,
             payee: Some("travel-agent"),
             title: "Europe Travel",
+            tags: Vec::new(),
+            links: Vec::new(),
+            metadata: Vec::new(),
         };
 
         let txn_list = ParsedTransaction {
@@ -396,10 +2260,13 @@ mod tests {
             exchanges: vec![
                 None,
                 Some(ParsedAmount {
-                    nominal: 199_f64,
+                    nominal: dec!(199),
                     unit: "USD",
+                    text: None,
+                    price: None,
                 }),
             ],
+            metadata: vec![Vec::new(), Vec::new()],
         };
 
         ledger.process_statement(Statement::Transaction(date, txn_header, txn_list))?;
@@ -412,7 +2279,14 @@ mod tests {
             bookings.transactions()[0].exchanges[0],
             Exchange {
                 account: TxnAccount::Assets(vec![0, 1]),
-                amount: None,
+                amount: Some(Amount {
+                    nominal: dec!(-199),
+                    unit: 0,
+                }),
+                amount_text: None,
+                elided: true,
+                metadata: BTreeMap::new(),
+                price: None,
             },
         );
 
@@ -421,9 +2295,13 @@ mod tests {
             Exchange {
                 account: TxnAccount::Expenses(vec![2, 3]),
                 amount: Some(Amount {
-                    nominal: 199_f64,
+                    nominal: dec!(199),
                     unit: 0,
                 }),
+                amount_text: None,
+                elided: false,
+                metadata: BTreeMap::new(),
+                price: None,
             },
         );
 
@@ -436,8 +2314,1102 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_checkpoint_balances() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+
+2020-01-01 open Assets:Cash
+2020-01-01 open Expenses:Food
+
+2020-02-01 * "Lunch"
+  Assets:Cash
+  Expenses:Food     10 USD
+
+2021-01-01 checkpoint
+  Assets:Cash      -500 USD
+  Expenses:Food     500 USD
+
+2021-01-01 * "Dinner"
+  Assets:Cash
+  Expenses:Food     25 USD
+"#,
+            None,
+        )?;
+
+        let cash = TxnAccount::Assets(vec![0]);
+        let food = TxnAccount::Expenses(vec![1]);
+        let before = NaiveDate::from_ymd_opt(2020, 12, 31).ok_or(anyhow!("invalid date"))?;
+        let after = NaiveDate::from_ymd_opt(2021, 1, 1).ok_or(anyhow!("invalid date"))?;
+
+        assert_eq!(ledger.balances_at(&before)?.get(&cash, 0), dec!(-10));
+        assert_eq!(ledger.balances_at(&after)?.get(&cash, 0), dec!(-525));
+        assert_eq!(ledger.balances_at(&after)?.get(&food, 0), dec!(525));
+        assert_eq!(ledger.get_checkpoint_on(&after).map(|c| c.len()), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_before() -> Result<()> {
+        let mut ledger = crate::parser::parse(
+            r#"
+unit USD
+
+2020-01-01 open Assets:Cash
+2020-01-01 open Expenses:Food
+2020-01-01 open Equity:Opening-Balances
+
+2020-01-01 * "Initial cash"
+  Equity:Opening-Balances
+  Assets:Cash       100 USD
+
+2020-02-01 * "Lunch"
+  Assets:Cash
+  Expenses:Food     10 USD
+
+2021-01-05 * "Dinner"
+  Assets:Cash
+  Expenses:Food     25 USD
+"#,
+            None,
+        )?;
+
+        let cutoff = NaiveDate::from_ymd_opt(2021, 1, 1).ok_or(anyhow!("invalid date"))?;
+        let later = NaiveDate::from_ymd_opt(2021, 2, 1).ok_or(anyhow!("invalid date"))?;
+        let before = ledger.balances_at(&later)?;
+
+        let archive = ledger.truncate_before(&cutoff)?;
+
+        assert_eq!(ledger.balances_at(&later)?, before);
+        assert!(ledger
+            .get_bookings_on(&NaiveDate::from_ymd_opt(2020, 2, 1).ok_or(anyhow!("invalid date"))?)
+            .is_none());
+        assert_eq!(
+            ledger
+                .get_bookings_on(
+                    &NaiveDate::from_ymd_opt(2020, 12, 31).ok_or(anyhow!("invalid date"))?
+                )
+                .ok_or(anyhow!("no daybook"))?
+                .transactions()
+                .len(),
+            2
+        );
+        assert!(
+            archive.contains("2020-02-01 * \"Lunch\"\n  Assets:Cash\n  Expenses:Food  10 USD\n")
+        );
+        assert!(!archive.contains("Dinner"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_padded() -> Result<()> {
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+        for (granularity, cutoff) in [
+            (BookingGranularity::Daily, date(2, 1)?),
+            (BookingGranularity::Daily, date(1, 4)?),
+            (BookingGranularity::Monthly, date(2, 15)?),
+        ] {
+            let ledger = Ledger::with_config(LedgerConfig { granularity });
+            let mut ledger = crate::parser::parse(PADDED, Some(ledger))?;
+            let failures = ledger.failed_assertions()?;
+            let balances = ledger.balances_at(&date(3, 1)?)?;
+
+            let archive = ledger.truncate_before(&cutoff)?;
+            assert!(archive.contains("pad Assets:Bank Equity:Opening-Balances"));
+            assert_eq!(ledger.failed_assertions()?, failures);
+            assert_eq!(ledger.balances_at(&date(3, 1)?)?, balances);
+        }
+
+        // The February bucket holds the grocer, it's kept whole.
+        let ledger = Ledger::with_config(LedgerConfig {
+            granularity: BookingGranularity::Monthly,
+        });
+        let mut ledger = crate::parser::parse(PADDED, Some(ledger))?;
+        let archive = ledger.truncate_before(&date(2, 15)?)?;
+        assert!(!archive.contains("Grocer"));
+        let opening = ledger
+            .get_bookings_on(&date(1, 1)?)
+            .ok_or(anyhow!("no opening balances"))?;
+        assert_eq!(opening.transactions().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_range() -> Result<()> {
+        let header = r#"
+unit USD
+0850-01-01 open Assets:Treasury
+0850-01-01 open Income:Tribute
+"#;
+        let input = r#"
+0850-03-01 * "Tribute"
+  Assets:Treasury      12 USD
+  Income:Tribute
+
+1899-12-31 * "Tribute"
+  Assets:Treasury      30 USD
+  Income:Tribute
+
+9999-12-31 * "Tribute"
+  Assets:Treasury      1 USD
+  Income:Tribute
+"#;
+        let mut ledger = crate::parser::parse(&format!("{}{}", header, input), None)?;
+        let first = NaiveDate::from_ymd_opt(850, 3, 1).ok_or(anyhow!("invalid date"))?;
+        let last = NaiveDate::from_ymd_opt(9999, 12, 31).ok_or(anyhow!("invalid date"))?;
+        let treasury = TxnAccount::Assets(vec![0]);
+
+        assert_eq!(Ledger::new().date_range(), None);
+        assert_eq!(ledger.date_range(), Some((first, last)));
+        assert_eq!(
+            ledger.balances_at(&NaiveDate::MIN)?.get(&treasury, 0),
+            dec!(0)
+        );
+        assert_eq!(
+            ledger.balances_at(&NaiveDate::MAX)?.get(&treasury, 0),
+            dec!(43)
+        );
+
+        let archive = ledger.truncate_before(&NaiveDate::MIN)?;
+        assert!(archive.is_empty());
+
+        let archive = ledger.truncate_before(&last)?;
+        assert!(archive.starts_with("0850-03-01 * \"Tribute\""));
+        assert_eq!(ledger.balances_at(&last)?.get(&treasury, 0), dec!(43));
+
+        let archived = crate::parser::parse(&format!("{}{}", header, archive), None)?;
+        assert_eq!(
+            archived.date_range(),
+            Some((
+                first,
+                NaiveDate::from_ymd_opt(1899, 12, 31).ok_or(anyhow!("invalid date"))?
+            ))
+        );
+
+        // Dates the writer couldn't read back are rejected.
+        for year in [-1, 10000] {
+            let date = NaiveDate::from_ymd_opt(year, 1, 1).ok_or(anyhow!("invalid date"))?;
+            let err = ledger
+                .process_statement(Statement::Event(date, "location", "Byzantium"))
+                .unwrap_err();
+            assert!(err
+                .to_string()
+                .ends_with("is outside the years 0 to 9999 ledgers can hold"));
+        }
+        assert_eq!(ledger.date_range().map(|(_, last)| last), Some(last));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interned_names() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+unit IDR
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-04 * "Lunch"
+  Assets:Bank
+  Expenses:Food            10 USD
+
+2022-01-05 * "Landlord" "Rent"
+  Assets:Bank
+  Expenses:Food            50 USD
+
+2022-01-10 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            35 USD
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 1).ok_or(anyhow!("invalid date"))?;
+
+        // Each name is interned once, in order of first appearance.
+        assert_eq!(ledger.units().collect::<Vec<_>>(), vec!["USD", "IDR"]);
+        assert_eq!(ledger.unit_lookup(&date, "IDR")?, 1);
+        assert_eq!(ledger.unit_name(1)?, "IDR");
+        assert!(ledger.unit_name(2).is_err());
+        assert_eq!(
+            ledger.payees().collect::<Vec<_>>(),
+            vec!["Grocer", "Landlord"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_insensitive_accounts() -> Result<()> {
+        let input = r#"
+option "case_insensitive_accounts" "true"
+unit USD
+2022-01-01 open Assets:Cash
+2022-01-01 open Expenses:Food
+
+2022-01-02 * "Lunch"
+  assets:cash
+  expenses:FOOD     10 USD
+"#;
+        let ledger = crate::parser::parse(input, None)?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 2).ok_or(anyhow!("invalid date"))?;
+        let txn = &ledger
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions()[0];
+        assert_eq!(
+            ledger.account_name(&txn.exchanges[0].account)?,
+            "Assets:Cash"
+        );
+        assert_eq!(
+            ledger.account_name(&txn.exchanges[1].account)?,
+            "Expenses:Food"
+        );
+
+        let err = crate::parser::parse(&input.replacen("true", "false", 1), None).unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "7:1: account `Assets:cash' is not opened at 2022-01-02"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_inferred_opens() -> Result<()> {
+        let input = r#"
+option "permissive" "true"
+unit USD
+2022-01-01 open Assets:Cash
+
+2022-01-02 * "Lunch"
+  Assets:Cash
+  Expenses:Food     10 USD
+
+2022-01-05 * "Dinner"
+  Liabilities:Card
+  Expenses:Food     25 USD
+"#;
+        let ledger = crate::parser::parse(input, None)?;
+        let opens: Vec<String> = ledger
+            .inferred_opens()
+            .iter()
+            .map(|(date, account)| {
+                let mut out = String::new();
+                writer::write_open(&mut out, &ledger, date, account)?;
+                Ok(out)
+            })
+            .collect::<Result<_>>()?;
+
+        assert_eq!(
+            opens,
+            vec![
+                "2022-01-02 open Expenses:Food\n",
+                "2022-01-05 open Liabilities:Card\n",
+            ]
+        );
+
+        let strict = format!(
+            "{}{}",
+            opens.concat(),
+            input.replacen(r#"option "permissive" "true""#, "", 1)
+        );
+        assert!(crate::parser::parse(&strict, None)?
+            .inferred_opens()
+            .is_empty());
+        assert!(crate::parser::parse(&input.replacen("true", "false", 1), None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fx_residue() -> Result<()> {
+        let input = r#"
+option "fx_gain_account" "Income:FX"
+option "fx_loss_account" "Expenses:FX"
+unit JPY
+unit USD
+
+2024-10-01 open Assets:Bank
+2024-10-01 open Expenses:Foreign
+2024-10-01 open Income:FX
+2024-10-01 open Expenses:FX
+
+2024-10-13 price USD 100.5 JPY
+
+2024-10-14 * "Bill payable"
+  Assets:Bank          -14200 JPY
+  Expenses:Foreign        142 USD
+
+2024-10-15 * "Refund"
+  Assets:Bank           14250 JPY
+  Expenses:Foreign       -142 USD
+
+2024-10-16 * "Way off"
+  Assets:Bank           -1000 JPY
+  Expenses:Foreign        142 USD
+"#;
+        let ledger = crate::parser::parse(input, None)?;
+        let day = |d| NaiveDate::from_ymd_opt(2024, 10, d).ok_or(anyhow!("invalid date"));
+
+        let bill = &ledger.get_bookings_on(&day(14)?).unwrap().transactions()[0];
+        assert_eq!(bill.exchanges.len(), 3);
+        assert_eq!(
+            ledger.account_name(&bill.exchanges[2].account)?,
+            "Income:FX"
+        );
+        assert_eq!(
+            bill.exchanges[2].amount,
+            Some(Amount {
+                nominal: dec!(-71),
+                unit: 0
+            })
+        );
+
+        let refund = &ledger.get_bookings_on(&day(15)?).unwrap().transactions()[0];
+        assert_eq!(
+            ledger.account_name(&refund.exchanges[2].account)?,
+            "Expenses:FX"
+        );
+
+        let off = &ledger.get_bookings_on(&day(16)?).unwrap().transactions()[0];
+        assert_eq!(off.exchanges.len(), 2);
+
+        let plain = crate::parser::parse(&input.replace("option \"fx_", "option \"no_fx_"), None)?;
+        assert_eq!(
+            plain.get_bookings_on(&day(14)?).unwrap().transactions()[0]
+                .exchanges
+                .len(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fx_residue_overflow() -> Result<()> {
+        let input = r#"
+option "fx_gain_account" "Income:FX"
+option "fx_loss_account" "Expenses:FX"
+unit JPY
+unit USD
+
+2024-10-01 open Assets:Bank
+2024-10-01 open Expenses:Foreign
+2024-10-01 open Income:FX
+2024-10-01 open Expenses:FX
+
+2024-10-13 price USD 100.5 JPY
+
+2024-10-14 * "Bill payable"
+  Assets:Bank                             -1 JPY
+  Expenses:Foreign     50000000000000000000000000000 USD
+"#;
+        let err = crate::parser::parse(input, None).expect_err("converted amount overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assertion_gaps() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Cash
+2022-01-01 open Expenses:Food
+
+2022-01-31 balance Assets:Bank 0 USD
+2022-02-28 balance Assets:Bank 0 USD
+
+2022-06-01 checkpoint
+  Assets:Bank      0 USD
+
+2022-06-30 balance Assets:Bank 0 USD
+
+2022-07-15 * "Lunch"
+  Assets:Cash
+  Expenses:Food     10 USD
+"#,
+            None,
+        )?;
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        assert_eq!(
+            ledger.assertion_gaps(&TxnAccount::Assets(vec![0]), 35),
+            vec![(date(2, 28)?, date(6, 1)?)]
+        );
+        assert_eq!(
+            ledger.assertion_gaps(&TxnAccount::Assets(vec![0]), 10),
+            vec![
+                (date(1, 1)?, date(1, 31)?),
+                (date(1, 31)?, date(2, 28)?),
+                (date(2, 28)?, date(6, 1)?),
+                (date(6, 1)?, date(6, 30)?),
+                (date(6, 30)?, date(7, 15)?),
+            ]
+        );
+        assert_eq!(
+            ledger.assertion_gaps(&TxnAccount::Assets(vec![1]), 180),
+            vec![(date(1, 1)?, date(7, 15)?)]
+        );
+        assert!(ledger
+            .assertion_gaps(&TxnAccount::Income(vec![0]), 1)
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_percentage_split() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Shared:Rent
+2022-01-01 open Assets:Receivable:Roommate
+
+2022-01-05 * "Landlord" "January rent"
+  Assets:Bank                  -1200 USD
+  Expenses:Shared:Rent         50% of 1200 USD
+  Assets:Receivable:Roommate   50% of 1200 USD
+"#,
+            None,
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 5).ok_or(anyhow!("invalid date"))?;
+        let book = ledger.get_bookings_on(&date).ok_or(anyhow!("no daybook"))?;
+        let amounts: Vec<Option<Amount>> = book.transactions()[0]
+            .exchanges
+            .iter()
+            .map(|e| e.amount.clone())
+            .collect();
+        assert_eq!(
+            amounts,
+            vec![
+                Some(Amount {
+                    nominal: dec!(-1200),
+                    unit: 0
+                }),
+                Some(Amount {
+                    nominal: dec!(600),
+                    unit: 0
+                }),
+                Some(Amount {
+                    nominal: dec!(600),
+                    unit: 0
+                }),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pad_diagnostics() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Wallet
+2022-01-01 open Equity:Opening-Balances
+2022-01-01 open Expenses:Food
+
+2022-01-02 pad Assets:Bank Equity:Opening-Balances
+2022-01-02 pad Assets:Wallet Equity:Opening-Balances
+
+2022-01-03 * "Grocer"
+  Assets:Bank
+  Expenses:Food     30 USD
+
+2022-01-05 balance Assets:Bank 1000 USD
+2022-01-05 balance Assets:Wallet 50 USD
+
+2022-01-06 * "Grocer"
+  Assets:Bank
+  Expenses:Food     20 USD
+
+2022-01-07 balance Assets:Bank 980 USD
+"#,
+            None,
+        )?;
+
+        // The pad still makes up the difference, the transaction counting first.
+        assert!(ledger.failed_assertions()?.is_empty());
+        let diagnostics = ledger.pad_diagnostics()?;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].code, "ambiguous-pad");
+        assert_eq!(
+            diagnostics[0].message,
+            "pad of `Assets:Bank' on 2022-01-02 and 1 transaction(s) both lead to the balance asserted on 2022-01-05, the pad only covers what the transactions leave out"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_balance_changes() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Salary
+2022-01-01 open Income:Job
+2022-01-01 open Expenses:Food
+
+2022-01-25 * "Salary"
+  Income:Job
+  Assets:Salary     3000 USD
+
+2022-02-01 balance-change Assets:Salary 3000 USD
+
+2022-02-10 * "Coffee"
+  Assets:Salary
+  Expenses:Food        5 USD
+
+2022-02-25 * "Salary"
+  Income:Job
+  Assets:Salary     3000 USD
+
+2022-03-01 balance-change Assets:Salary 3000 USD
+2022-03-01 balance Assets:Salary 5995 USD
+
+2022-03-25 * "Salary"
+  Income:Job
+  Assets:Salary     3000 USD
+
+2022-04-01 ! balance-change Assets:Salary 2900 USD
+"#,
+            None,
+        )?;
+        let date = |m| NaiveDate::from_ymd_opt(2022, m, 1).ok_or(anyhow!("invalid date"));
+
+        let failures: Vec<_> = ledger
+            .failed_assertions()?
+            .into_iter()
+            .map(|failure| {
+                (
+                    failure.date,
+                    failure.kind,
+                    failure.expected.nominal,
+                    failure.actual,
+                    failure.severity,
+                )
+            })
+            .collect();
+        assert_eq!(
+            failures,
+            vec![
+                (
+                    date(3)?,
+                    AssertionKind::Change,
+                    dec!(3000),
+                    dec!(2995),
+                    Severity::Error
+                ),
+                (
+                    date(4)?,
+                    AssertionKind::Change,
+                    dec!(2900),
+                    dec!(3000),
+                    Severity::Warning
+                ),
+            ]
+        );
+
+        let book = ledger
+            .get_bookings_on(&date(4)?)
+            .ok_or(anyhow!("no daybook"))?;
+        let mut out = String::new();
+        crate::writer::write_daybook(&mut out, &ledger, &date(4)?, book)?;
+        assert_eq!(
+            out,
+            "2022-04-01 ! balance-change Assets:Salary 2900 USD\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_assertions() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Wallet
+2022-01-01 open Assets:Bank
+2022-01-01 open Equity:Opening-Balances
+2022-01-01 open Expenses:Food
+
+2022-01-02 * "Exchange"
+  Assets:Bank
+  Assets:Wallet     100 USD
+  Assets:Wallet     1_500_000 IDR
+
+2022-01-03 balance Assets:Wallet 100 USD, 1_500_000 IDR
+2022-01-03 balance Assets:Bank -100 USD, -1_400_000 IDR
+
+2022-01-04 pad Assets:Bank Equity:Opening-Balances
+
+2022-01-05 * "Lunch"
+  Assets:Wallet
+  Expenses:Food     50_000 IDR
+
+2022-01-06 balance Assets:Bank 2000 USD
+2022-01-06 balance Assets:Wallet 100 USD, 1_400_000 IDR
+2022-01-07 balance Assets:Bank 2000 USD, -1_500_000 IDR
+"#,
+            None,
+        )?;
+        let date = |d| NaiveDate::from_ymd_opt(2022, 1, d).ok_or(anyhow!("invalid date"));
+
+        let failures: Vec<(NaiveDate, String, Decimal, Decimal)> = ledger
+            .failed_assertions()?
+            .into_iter()
+            .map(|failure| {
+                Ok((
+                    failure.date,
+                    ledger.account_name(&failure.account)?,
+                    failure.expected.nominal,
+                    failure.actual,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        assert_eq!(
+            failures,
+            vec![
+                (
+                    date(3)?,
+                    "Assets:Bank".to_string(),
+                    dec!(-1400000),
+                    dec!(-1500000)
+                ),
+                (
+                    date(6)?,
+                    "Assets:Wallet".to_string(),
+                    dec!(1400000),
+                    dec!(1450000)
+                ),
+            ]
+        );
+
+        let err = crate::parser::parse(
+            "unit USD\n2022-01-01 open Assets:Wallet\n2022-01-02 balance Assets:Wallet 1 USD, 2 USD\n",
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "3:1: unit `USD' asserted more than once for `Assets:Wallet'"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_more_transactions() -> Result<()> {
         Ok(())
     }
+
+    #[test]
+    fn test_audit_log() -> Result<()> {
+        let ledger = crate::parser::parse("unit USD\n2022-01-01 open Assets:Bank\n", None)?;
+        assert_eq!(ledger.audit_log(), None);
+        let ledger = crate::parser::parse("option \"audit_log\" \"false\"\nunit USD\n", None)?;
+        assert_eq!(ledger.audit_log(), None);
+        let ledger = crate::parser::parse(
+            "option \"audit_log\" \"true\"\noption \"audit_log\" \"false\"\nunit USD\n",
+            None,
+        )?;
+        assert_eq!(ledger.audit_log(), None);
+
+        let input = r#"
+option "audit_log" "true"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+"#;
+        let mut ledger = crate::parser::parse(input, None)?;
+        ledger.set_audit_origin("api:alice");
+        let ledger = crate::parser::parse(
+            r#"
+2022-01-03 * "Groceries"
+  Assets:Bank
+  Expenses:Food       30 USD
+"#,
+            Some(ledger),
+        )?;
+
+        let log = ledger.audit_log().ok_or(anyhow!("audit log disabled"))?;
+        let entries: Vec<(&str, &str)> = log
+            .iter()
+            .map(|entry| {
+                (
+                    entry.origin.as_str(),
+                    entry.text.lines().next().unwrap_or(""),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("<input>", "option \"audit_log\" \"true\""),
+                ("<input>", "unit USD"),
+                ("<input>", "2022-01-01 open Assets:Bank"),
+                ("<input>", "2022-01-01 open Expenses:Food"),
+                ("api:alice", "2022-01-03 * \"Groceries\""),
+            ]
+        );
+
+        // Replaying the log rebuilds the same balances.
+        let replay: Vec<&str> = log.iter().map(|entry| entry.text.as_str()).collect();
+        let replayed = crate::parser::parse(&replay.join("\n"), None)?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        assert_eq!(replayed.balances_at(&date)?, ledger.balances_at(&date)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_day_close() -> Result<()> {
+        for kind in ["Assets", "Liabilities", "Income", "Expenses", "Equity"] {
+            let opened =
+                format!("unit USD\n2022-01-01 open {kind}:Old\n2022-01-01 open Equity:Other\n");
+            let txn = format!("2022-01-31 * \"Last\"\n  {kind}:Old  5 USD\n  Equity:Other\n\n");
+            let close = format!("2022-01-31 close {kind}:Old\n");
+            let balance = format!("2022-01-31 balance {kind}:Old 0 USD\n");
+
+            // Used on the closing date is rejected whether it comes before or after the
+            // close, the start-of-day balance assertion is fine either way.
+            let before = format!("{opened}{txn}{close}");
+            let after = format!("{opened}{close}{txn}");
+            assert!(crate::parser::parse(&before, None).is_err(), "{kind}");
+            assert!(crate::parser::parse(&after, None).is_err(), "{kind}");
+            crate::parser::parse(&format!("{opened}{close}{balance}"), None)?;
+
+            let allow = "option \"same_day_close\" \"allow\"\n";
+            let date = NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?;
+            for input in [format!("{allow}{before}"), format!("{allow}{after}")] {
+                let ledger = crate::parser::parse(&input, None)?;
+                let account =
+                    ledger.account_lookup(&date, &format!("{kind}:Old").as_str().try_into()?)?;
+                assert_eq!(
+                    ledger.balances_at(&date)?.get(&account, 0),
+                    dec!(5),
+                    "{kind}"
+                );
+                let next = date.succ_opt().ok_or(anyhow!("invalid date"))?;
+                assert!(!ledger.accounts().is_open_at(&account, &next), "{kind}");
+            }
+        }
+
+        assert!(crate::parser::parse(
+            "option \"same_day_close\" \"maybe\"\n2022-01-01 open Assets:Old\n2022-01-31 close Assets:Old\n",
+            None
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_booking_granularity() -> Result<()> {
+        let input = r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-09 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            20 USD
+
+2022-01-10 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            25 USD
+
+2022-01-31 balance Assets:Bank  -75 USD
+
+2022-02-14 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            10 USD
+"#;
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+        let parse = |granularity| {
+            crate::parser::parse(
+                input,
+                Some(Ledger::with_config(LedgerConfig { granularity })),
+            )
+        };
+        let booked = |granularity| -> Result<Vec<(NaiveDate, usize)>> {
+            Ok(parse(granularity)?
+                .bookings()
+                .iter()
+                .filter(|(_, book)| !book.transactions().is_empty())
+                .map(|(date, book)| (*date, book.transactions().len()))
+                .collect())
+        };
+
+        assert_eq!(booked(BookingGranularity::Daily)?.len(), 4);
+        assert_eq!(
+            booked(BookingGranularity::Weekly)?,
+            vec![(date(1, 3)?, 2), (date(1, 10)?, 1), (date(2, 14)?, 1)]
+        );
+        assert_eq!(
+            booked(BookingGranularity::Monthly)?,
+            vec![(date(1, 1)?, 3), (date(2, 1)?, 1)]
+        );
+
+        // The month-end assertion stays at its date and sees the whole month.
+        let monthly = parse(BookingGranularity::Monthly)?;
+        assert_eq!(
+            monthly
+                .get_bookings_on(&date(1, 31)?)
+                .map(|book| book.balance_assertions().len()),
+            Some(1)
+        );
+        assert!(monthly.failed_assertions()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_with_balance() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+option "opening_balance_account" "Equity:Opening"
+unit USD
+unit EUR
+2021-01-01 open Assets:Bank:Jago 5000 USD, 20 EUR
+2021-01-01 open Assets:Cash
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2021, 1, 1).ok_or(anyhow!("invalid date"))?;
+        let jago = ledger
+            .accounts()
+            .txnify(&date, &"Assets:Bank:Jago".try_into()?)?;
+        let equity = ledger
+            .accounts()
+            .txnify(&date, &"Equity:Opening".try_into()?)?;
+
+        let balances = ledger.balances_at(&date)?;
+        assert_eq!(balances.get(&jago, 0), dec!(5000));
+        assert_eq!(balances.get(&jago, 1), dec!(20));
+        assert_eq!(balances.get(&equity, 0), dec!(-5000));
+
+        let mut out = String::new();
+        crate::writer::write_daybook(
+            &mut out,
+            &ledger,
+            &date,
+            ledger.get_bookings_on(&date).ok_or(anyhow!("no daybook"))?,
+        )?;
+        assert_eq!(
+            out,
+            concat!(
+                "2021-01-01 * \"Opening balance\"\n",
+                "  Assets:Bank:Jago  5000 USD\n",
+                "  Assets:Bank:Jago  20 EUR\n",
+                "  Equity:Opening\n",
+                "\n",
+            )
+        );
+
+        // The checkpoint already carries opening balances made before it.
+        let ledger = crate::parser::parse(
+            r#"
+option "skip_before_checkpoint" "true"
+unit USD
+2021-01-01 open Assets:Bank:Jago 5000 USD
+2021-02-01 checkpoint
+  Assets:Bank:Jago   4000 USD
+"#,
+            None,
+        )?;
+        assert!(ledger.get_bookings_on(&date).is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() -> Result<()> {
+        let input = r#"
+option "operating_currency" "USD"
+option "title" "Household"
+option "author" "me"
+unit USD
+unit IDR
+unit JPY
+2022-01-01 open Assets:Bank
+  institution: "ACME Bank"
+  branch: "Main"
+2022-01-01 open Expenses:Food
+  budget: "groceries"
+2022-01-02 price USD 15_000 IDR
+2022-01-02 price USD 130 JPY
+2022-01-02 price JPY 115 IDR
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-04 ! balance Assets:Bank -30 USD
+"#;
+        let ledger = crate::parser::parse(input, None)?;
+        // The output doesn't depend on hashing order.
+        assert_eq!(
+            serde_json::to_string(&ledger)?,
+            serde_json::to_string(&crate::parser::parse(input, None)?)?
+        );
+        let json = serde_json::to_value(&ledger)?;
+        let loaded: Ledger = serde_json::from_value(json.clone())?;
+        assert_eq!(serde_json::to_value(&loaded)?, json);
+        assert_eq!(
+            loaded.get_option("operating_currency").map(String::as_str),
+            Some("USD")
+        );
+        assert_eq!(loaded.operating_unit(), Some(0));
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let food = loaded
+            .accounts()
+            .txnify(&date, &"Expenses:Food".try_into()?)?;
+        assert_eq!(loaded.balances_at(&date)?.get(&food, 0), dec!(30));
+        assert!(loaded.failed_assertions()?.is_empty());
+        let write = |ledger: &Ledger| -> Result<String> {
+            let mut out = String::new();
+            for (date, book) in ledger.bookings() {
+                writer::write_daybook(&mut out, ledger, date, book)?;
+            }
+            Ok(out)
+        };
+        assert_eq!(write(&loaded)?, write(&ledger)?);
+        // Built-in handlers are registered again.
+        assert!(loaded.custom_handlers.contains_key("assert-option"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transactions_between() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Rent
+
+2022-01-03 * "Grocer"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-05 * "Landlord"
+  Assets:Bank
+  Expenses:Rent           500 USD
+
+2022-01-09 * "Grocer"
+  Assets:Bank
+  Expenses:Food            20 USD
+"#,
+            None,
+        )?;
+        let date = |d| NaiveDate::from_ymd_opt(2022, 1, d).ok_or(anyhow!("invalid date"));
+
+        let titles: Vec<(NaiveDate, &str)> = ledger
+            .transactions_between(date(3)?..date(9)?)
+            .map(|(date, txn)| (date, txn.title.as_str()))
+            .collect();
+        assert_eq!(titles, vec![(date(3)?, "Grocer"), (date(5)?, "Landlord")]);
+        assert_eq!(ledger.transactions_between(date(4)?..=date(9)?).count(), 2);
+        assert_eq!(ledger.transactions_between(..).count(), 3);
+
+        let food = ledger
+            .accounts()
+            .txnify(&date(1)?, &"Expenses:Food".try_into()?)?;
+        let postings: Vec<(NaiveDate, usize)> = ledger
+            .postings_for_account(&food, date(1)?..=date(31)?)
+            .map(|posting| (posting.date, posting.index))
+            .collect();
+        assert_eq!(postings, vec![(date(3)?, 1), (date(9)?, 1)]);
+        assert_eq!(ledger.postings_for_account(&food, date(4)?..).count(), 1);
+        assert_eq!(ledger.transactions_between(date(9)?..date(3)?).count(), 0);
+        assert_eq!(ledger.transactions_between(date(3)?..date(3)?).count(), 0);
+
+        // Stops at the first posting over 25 USD in the window.
+        let filter = ledger.compile_filter("account:Expenses")?;
+        let mut seen = 0;
+        let found = ledger.try_for_each_posting(date(1)?..=date(31)?, &filter, |posting| {
+            seen += 1;
+            match posting.amount() {
+                Some(amount) if amount.nominal > dec!(25) => ControlFlow::Break(posting.date),
+                _ => ControlFlow::Continue(()),
+            }
+        });
+        assert_eq!(found, ControlFlow::Break(date(3)?));
+        assert_eq!(seen, 1);
+        let mut total = Decimal::ZERO;
+        let done = ledger.try_for_each_posting(date(4)?.., &filter, |posting| {
+            total += posting.amount().map_or(Decimal::ZERO, |a| a.nominal);
+            ControlFlow::<()>::Continue(())
+        });
+        assert_eq!((done, total), (ControlFlow::Continue(()), dec!(520)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_price_of() -> Result<()> {
+        let ledger = crate::parser::parse(
+            r#"
+unit USD
+unit IDR
+unit JPY
+unit EUR
+2022-01-01 price USD 15_000 IDR
+2022-01-05 price JPY 0.008 USD
+2022-01-10 price USD 15_500 IDR
+"#,
+            None,
+        )?;
+        let date = |d| NaiveDate::from_ymd_opt(2022, 1, d).ok_or(anyhow!("invalid date"));
+
+        assert_eq!(ledger.price_of("USD", "IDR", &date(9)?)?, Some(dec!(15000)));
+        assert_eq!(
+            ledger.price_of("USD", "IDR", &date(10)?)?,
+            Some(dec!(15500))
+        );
+        assert_eq!(ledger.price_of("USD", "JPY", &date(5)?)?, Some(dec!(125)));
+        // No price on or before the date.
+        assert_eq!(ledger.price_of("JPY", "USD", &date(4)?)?, None);
+        // JPY is only quoted in USD, which is quoted in IDR.
+        assert_eq!(ledger.price_of("JPY", "IDR", &date(9)?)?, Some(dec!(120)));
+        assert_eq!(ledger.price_of("EUR", "IDR", &date(9)?)?, None);
+        assert!(ledger.price_of("GBP", "IDR", &date(9)?).is_err());
+
+        assert_eq!(
+            ledger.latest_price("USD", "IDR")?,
+            Some((date(10)?, dec!(15500)))
+        );
+        assert_eq!(
+            ledger.latest_price("JPY", "IDR")?,
+            Some((date(5)?, dec!(124.000)))
+        );
+
+        Ok(())
+    }
 }
@@ -21,13 +21,134 @@ extern crate pest_derive;
 pub mod account;
 
 mod amount;
+mod annotation;
+mod autofill;
+mod average;
+mod balance;
+
+/// Conversion of Beancount ledgers, to migrate existing files.
+pub mod beancount;
+/// Business days, shared by recurrence adjustments, reports and tools built on roasted.
+///
+/// [`WeekendCalendar`][calendar::WeekendCalendar] is the default, implement
+/// [`HolidayCalendar`][calendar::HolidayCalendar] for local holidays.
+pub mod calendar;
+mod clock;
+
+/// Handlers interpreting `custom` statements, see
+/// [`Ledger::register_custom_handler`][ledger::Ledger::register_custom_handler], and
+/// directives unknown to the grammar, see
+/// [`Ledger::register_directive`][ledger::Ledger::register_directive].
+pub mod custom;
+
+mod diagnostic;
+mod distribution;
+mod enrich;
+mod error;
+mod event;
+mod export;
+mod fingerprint;
+
+/// Realistic multi-year example ledgers, to develop and test reports against meaningful
+/// data without writing fixtures by hand.
+pub mod examples;
+
+mod import;
+
 /// Ledger representation.
 pub mod ledger;
 
 /// Our main parser entrypoints.
 pub mod parser;
 
+/// Commonly used types, meant to be glob imported with `use libroasted::prelude::*`.
+///
+/// Everything reachable from here is considered the stable API surface.
+pub mod prelude;
+
+/// Reports computed from a parsed [`Ledger`][ledger::Ledger].
+pub mod reports;
+
+mod lint;
+mod locale;
+mod note;
+#[cfg(feature = "serde")]
+mod pairs;
+#[cfg(feature = "wasm")]
+mod plugin;
+mod precision;
+mod query;
+mod reader;
+mod recurrence;
+mod redact;
+mod register;
+mod reprice;
+mod scaffold;
+mod schema;
+mod search;
+mod seed;
+mod series;
+mod session;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 mod statement;
+mod symbol;
+#[cfg(all(test, any(feature = "fs", feature = "sqlite")))]
+mod tempdir;
 mod transaction;
+mod unit;
+mod validate;
+
+/// Owned, optionally serializable views of the ledger for API servers, see
+/// [`viewmodel::postings`] for paginated postings.
+pub mod viewmodel;
+
+/// Render ledger structures back into roasted syntax.
+pub mod writer;
+
+/// Versioned binary encoding of statements, to sync entries captured on another device
+/// into a ledger file.
+pub mod wire;
+
+pub use amount::{Amount, ParsedAmount, ParsedPrice, Price};
+pub use annotation::{Annotations, TransactionId};
+pub use average::BalanceWeighting;
+pub use balance::{Balances, UnitBalances};
+pub use calendar::{HolidayCalendar, WeekendCalendar};
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use diagnostic::{apply_edits, Diagnostic, Fix, Severity, TextEdit};
+pub use distribution::{AmountDistribution, Bucket, BucketSpec};
+pub use enrich::Enricher;
+pub use error::{RoastedError, SourceLocation, StatementError};
+pub use event::LedgerEvent;
+pub use fingerprint::Fingerprint;
+pub use import::{ImportField, ImportProfile, ImportRule};
+pub use lint::{lint, lint_with};
+pub use locale::DisplayLocale;
+pub use note::{Document, LifeEvent, Note};
+pub use parser::{parse, parse_statements};
+#[cfg(feature = "wasm")]
+pub use plugin::{Plugin, PluginOutput};
+pub use precision::Precision;
+pub use query::{CompiledFilter, PostingRef};
+pub use reader::{ArcLedger, LedgerReader};
+pub use recurrence::{BusinessDayAdjustment, Recurrence, RecurrenceDay, RecurrenceRule};
+pub use redact::{AmountRedaction, Redaction};
+pub use register::{RegisterCursor, RegisterEntry, RegisterFilter, RegisterPage};
+pub use reprice::{Quote, Repriced};
+pub use scaffold::{scaffold, ScaffoldProfile};
+pub use schema::LedgerSchema;
+pub use search::{MatchKind, SearchField, SearchHit};
+pub use series::{PricePoint, PriceSeries};
+pub use session::Session;
+pub use statement::Statement;
+pub use symbol::SymbolTable;
+pub use transaction::{
+    AssertionFailure, AssertionKind, BalanceAssertion, Exchange, PadTransaction, ParsedTransaction,
+    Transaction, TransactionState, TxnHeader,
+};
+pub use unit::UnitInfo;
+pub use validate::ValidationReport;
 
-pub use parser::parse;
+/// Decimal type of amount nominals and prices.
+pub use rust_decimal::Decimal;
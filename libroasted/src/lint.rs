@@ -0,0 +1,486 @@
+use crate::{
+    amount::ParsedAmount,
+    diagnostic::{Diagnostic, Fix, TextEdit},
+    ledger::Ledger,
+    parser::{inner_str, LedgerParser, Rule},
+};
+use anyhow::Result;
+use pest::{iterators::Pair, Parser};
+use rust_decimal::Decimal;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// Most suggestions offered for a misspelled name.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Span of the whole lines covering `span`, including the line break ending the last
+/// one, or the one before the first line when the last line doesn't end with one.
+fn line_span(source: &str, span: Range<usize>) -> Range<usize> {
+    let start = source[..span.start].rfind('\n').map_or(0, |idx| idx + 1);
+    let end = match source[..span.end].ends_with('\n') {
+        true => span.end,
+        false => source[span.end..]
+            .find('\n')
+            .map_or(source.len(), |idx| span.end + idx + 1),
+    };
+    if start > 0 && !source[..end].ends_with('\n') {
+        return start - 1..end;
+    }
+    start..end
+}
+
+/// Levenshtein distance between the two names, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Known names close enough to `name` to be what was meant, closest first.
+fn nearest<'k>(name: &str, known: &'k BTreeSet<String>) -> Vec<&'k str> {
+    let threshold = (name.chars().count() / 3).max(1);
+    let mut candidates: Vec<(usize, &str)> = known
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate.as_str()))
+        .filter(|&(distance, _)| distance <= threshold)
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Names declared with `unit` and `open`, in the source and in the context ledger.
+struct Declared {
+    units: BTreeSet<String>,
+    accounts: BTreeSet<String>,
+    case_insensitive_accounts: bool,
+}
+
+impl Declared {
+    fn collect(source: &str, context: &Ledger) -> Result<Self> {
+        let mut declared = Declared {
+            units: context.units().map(str::to_string).collect(),
+            accounts: context
+                .accounts()
+                .iter()
+                .map(|(account, _)| context.account_name(&account))
+                .collect::<Result<_>>()?,
+            case_insensitive_accounts: context.is_option_enabled("case_insensitive_accounts"),
+        };
+        for pair in LedgerParser::parse(Rule::ledger, source)?.flatten() {
+            match pair.as_rule() {
+                Rule::unit => declared.units.extend(
+                    pair.into_inner()
+                        .find(|pair| pair.as_rule() == Rule::currency)
+                        .map(|currency| currency.as_str().to_string()),
+                ),
+                Rule::open_statement => declared.accounts.extend(
+                    pair.into_inner()
+                        .find(|pair| pair.as_rule() == Rule::account)
+                        .map(|account| account.as_str().to_string()),
+                ),
+                Rule::option => {
+                    let mut strings = pair.into_inner().map(inner_str);
+                    if let (Some("case_insensitive_accounts"), Some("true")) =
+                        (strings.next(), strings.next())
+                    {
+                        declared.case_insensitive_accounts = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(declared)
+    }
+
+    /// Whether the account is opened, or is the parent of an opened account.
+    fn has_account(&self, name: &str) -> bool {
+        let matches = |known: &String| {
+            let prefix = known.get(..name.len());
+            prefix.is_some_and(|prefix| match self.case_insensitive_accounts {
+                true => prefix.eq_ignore_ascii_case(name),
+                false => prefix == name,
+            }) && known[name.len()..]
+                .chars()
+                .next()
+                .map_or(true, |c| c == ':')
+        };
+        self.accounts.iter().any(matches)
+    }
+}
+
+/// Replace the misspelled name at the span with each suggestion, one fix per suggestion.
+fn suggest(diagnostic: Diagnostic, span: Range<usize>, suggestions: Vec<&str>) -> Diagnostic {
+    suggestions
+        .into_iter()
+        .fold(diagnostic.with_span(span.clone()), |diagnostic, name| {
+            diagnostic.with_fix(Fix {
+                description: format!("replace with `{}'", name),
+                edits: vec![TextEdit {
+                    span: span.clone(),
+                    replacement: name.to_string(),
+                }],
+            })
+        })
+}
+
+fn lint_references(
+    statement: &Pair<'_, Rule>,
+    declared: &Declared,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for pair in statement.clone().into_inner().flatten() {
+        let span = pair.as_span().start()..pair.as_span().end();
+        match pair.as_rule() {
+            Rule::currency if !declared.units.contains(pair.as_str()) => {
+                diagnostics.push(suggest(
+                    Diagnostic::error(
+                        "undeclared-unit",
+                        format!("unit `{}' is not declared", pair.as_str()),
+                    ),
+                    span,
+                    nearest(pair.as_str(), &declared.units),
+                ));
+            }
+            Rule::account if !declared.has_account(pair.as_str()) => {
+                diagnostics.push(suggest(
+                    Diagnostic::error(
+                        "unknown-account",
+                        format!("account `{}' is never opened", pair.as_str()),
+                    ),
+                    span,
+                    nearest(pair.as_str(), &declared.accounts),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_zero(posting: &Pair<'_, Rule>) -> Result<bool> {
+    let amount = posting
+        .clone()
+        .into_inner()
+        .find(|pair| matches!(pair.as_rule(), Rule::amount | Rule::split_amount));
+    match amount {
+        Some(amount) => Ok(ParsedAmount::parse(amount)?.nominal == Decimal::ZERO),
+        None => Ok(false),
+    }
+}
+
+fn postings<'i>(statement: &Pair<'i, Rule>) -> Option<Pair<'i, Rule>> {
+    statement
+        .clone()
+        .into_inner()
+        .find(|pair| pair.as_rule() == Rule::transaction)?
+        .into_inner()
+        .find(|pair| pair.as_rule() == Rule::trx_list)
+}
+
+fn date<'i>(statement: &Pair<'i, Rule>) -> Option<&'i str> {
+    statement
+        .clone()
+        .into_inner()
+        .find(|pair| pair.as_rule() == Rule::date)
+        .map(|date| date.as_str())
+}
+
+/// Move the single posting left in `statement` to the end of `previous`, a transaction
+/// on the same date it was likely split from, dropping the rest of `statement`.
+fn merge_fix(
+    source: &str,
+    statement: &Pair<'_, Rule>,
+    posting: &Pair<'_, Rule>,
+    previous: &Pair<'_, Rule>,
+) -> Option<Fix> {
+    if date(previous)? != date(statement)? {
+        return None;
+    }
+    let target = postings(previous)?.as_span();
+    let insert_at = line_span(source, target.start()..target.end()).end;
+    let posting = posting.as_span();
+    let line_start = source[..posting.start()]
+        .rfind('\n')
+        .map_or(0, |idx| idx + 1);
+    let span = statement.as_span();
+    Some(Fix {
+        description: format!(
+            "merge the posting into `{}'",
+            previous.as_str().lines().next().unwrap_or_default()
+        ),
+        edits: vec![
+            TextEdit {
+                span: insert_at..insert_at,
+                replacement: format!("{}\n", &source[line_start..posting.end()]),
+            },
+            TextEdit {
+                span: line_span(source, span.start()..span.end()),
+                replacement: String::new(),
+            },
+        ],
+    })
+}
+
+fn lint_transaction(
+    source: &str,
+    statement: &Pair<'_, Rule>,
+    previous: Option<&Pair<'_, Rule>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<()> {
+    let Some(postings) = postings(statement) else {
+        return Ok(());
+    };
+
+    let mut zeros = Vec::new();
+    let mut others = Vec::new();
+    for posting in postings.into_inner() {
+        match is_zero(&posting)? {
+            true => zeros.push(posting),
+            false => others.push(posting),
+        }
+    }
+
+    if others.len() < 2 {
+        let span = statement.as_span();
+        let mut diagnostic = Diagnostic::warning(
+            "empty-transaction",
+            format!(
+                "transaction `{}' moves nothing, it has {} posting(s) besides zero amounts",
+                statement.as_str().lines().next().unwrap_or_default(),
+                others.len()
+            ),
+        )
+        .with_span(span.start()..span.end())
+        .with_fix(Fix {
+            description: "remove the transaction".to_string(),
+            edits: vec![TextEdit {
+                span: line_span(source, span.start()..span.end()),
+                replacement: String::new(),
+            }],
+        });
+        if let ([posting], Some(previous)) = (others.as_slice(), previous) {
+            if let Some(fix) = merge_fix(source, statement, posting, previous) {
+                diagnostic = diagnostic.with_fix(fix);
+            }
+        }
+        diagnostics.push(diagnostic);
+        return Ok(());
+    }
+
+    for posting in zeros {
+        let span = posting.as_span();
+        diagnostics.push(
+            Diagnostic::warning(
+                "zero-amount",
+                format!("posting `{}' has a zero amount", posting.as_str().trim()),
+            )
+            .with_span(span.start()..span.end())
+            .with_fix(Fix {
+                description: "remove the posting".to_string(),
+                edits: vec![TextEdit {
+                    span: line_span(source, span.start()..span.end()),
+                    replacement: String::new(),
+                }],
+            }),
+        );
+    }
+
+    Ok(())
+}
+
+/// Find postings with an explicit zero amount, and transactions left with less than two
+/// postings once those are ignored. Each diagnostic comes with a fix removing the
+/// offending posting or transaction, see [`apply_edits`][crate::apply_edits]. A
+/// transaction left with a single posting can also be merged into the transaction
+/// right before it when both are on the same date.
+///
+/// Undeclared units and accounts which are never opened are reported too, with a fix
+/// for each of the closest declared names, e.g. `USD` for `US`.
+///
+/// Only the given source is checked, included files aren't followed, see [`lint_with`].
+pub fn lint(source: &str) -> Result<Vec<Diagnostic>> {
+    lint_with(source, &Ledger::new())
+}
+
+/// Like [`lint`], with the units and accounts of `context` known as well, e.g. a ledger
+/// loaded from the files the source includes.
+pub fn lint_with(source: &str, context: &Ledger) -> Result<Vec<Diagnostic>> {
+    let declared = Declared::collect(source, context)?;
+    let mut diagnostics = Vec::new();
+    let mut previous = None;
+    for statement in LedgerParser::parse(Rule::ledger, source)? {
+        if statement.as_rule() != Rule::statement {
+            continue;
+        }
+        if !statement
+            .clone()
+            .into_inner()
+            .any(|pair| pair.as_rule() == Rule::open_statement)
+        {
+            lint_references(&statement, &declared, &mut diagnostics);
+        }
+        if postings(&statement).is_none() {
+            continue;
+        }
+        lint_transaction(source, &statement, previous.as_ref(), &mut diagnostics)?;
+        previous = Some(statement);
+    }
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::apply_edits;
+    use crate::lint::{lint, lint_with};
+    use crate::parser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_lint_zero_amounts() -> Result<()> {
+        let source = r#"unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Fees
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Fees             0 USD
+  Expenses:Food            30 USD
+
+2022-01-04 * "Voided"
+  Assets:Bank               0 USD
+  Expenses:Food             0 USD
+"#;
+
+        let diagnostics = lint(source)?;
+        let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["zero-amount", "empty-transaction"]);
+
+        let edits: Vec<_> = diagnostics
+            .iter()
+            .flat_map(|d| d.fixes.iter().flat_map(|fix| fix.edits.clone()))
+            .collect();
+        let fixed = apply_edits(source, &edits)?;
+        assert_eq!(
+            fixed,
+            r#"unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Fees
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+"#
+        );
+        assert!(lint(&fixed)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_merge_single_posting() -> Result<()> {
+        let source = r#"unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Tips
+
+2022-01-03 * "Diner" "Dinner"
+  Assets:Bank
+  Expenses:Food            30 USD
+2022-01-03 * "Diner" "Tip"
+  Expenses:Tips             5 USD
+  Assets:Bank               0 USD
+
+2022-01-04 * "Diner" "Tip"
+  Expenses:Tips             5 USD
+  Assets:Bank               0 USD
+"#;
+
+        let diagnostics = lint(source)?;
+        let fixes: Vec<Vec<&str>> = diagnostics
+            .iter()
+            .map(|d| d.fixes.iter().map(|fix| fix.description.as_str()).collect())
+            .collect();
+        assert_eq!(
+            fixes,
+            vec![
+                vec![
+                    "remove the transaction",
+                    "merge the posting into `2022-01-03 * \"Diner\" \"Dinner\"'"
+                ],
+                // Not on the same date as the transaction before it.
+                vec!["remove the transaction"],
+            ]
+        );
+
+        let fixed = apply_edits(source, &diagnostics[0].fixes[1].edits)?;
+        assert!(fixed.contains(
+            "  Expenses:Food            30 USD\n  Expenses:Tips             5 USD\n\n2022-01-04"
+        ));
+        assert_eq!(parser::parse(&fixed, None)?.postings().count(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_misspelled_names() -> Result<()> {
+        let source = r#"unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food:Groceries
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Fod:Groceries    30 US
+
+2022-01-04 * "Landlord" "Rent"
+  Assets:Bank
+  Expenses:Housing         500 USD
+"#;
+
+        let diagnostics = lint(source)?;
+        let found: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.code, d.span.clone().map_or("", |span| &source[span])))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("unknown-account", "Expenses:Fod:Groceries"),
+                ("undeclared-unit", "US"),
+                ("unknown-account", "Expenses:Housing"),
+            ]
+        );
+        // Nothing is close enough to the housing account.
+        assert!(diagnostics[2].fixes.is_empty());
+
+        let edits: Vec<_> = diagnostics
+            .iter()
+            .filter_map(|d| d.fixes.first())
+            .flat_map(|fix| fix.edits.clone())
+            .collect();
+        let fixed = apply_edits(source, &edits)?;
+        assert!(fixed.contains("  Expenses:Food:Groceries    30 USD\n"));
+
+        // Declarations from included files can be passed along.
+        let context = parser::parse("unit USD\n2022-01-01 open Expenses:Housing\n", None)?;
+        let diagnostics = lint_with(&fixed, &context)?;
+        assert!(diagnostics.is_empty());
+
+        Ok(())
+    }
+}
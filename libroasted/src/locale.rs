@@ -0,0 +1,173 @@
+use crate::{amount::Amount, ledger::Ledger};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use std::fmt::Write;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How numbers and dates are written in rendered reports.
+///
+/// Only affects output, input is still read as the ledger syntax and the import
+/// profiles say, e.g. `date-format`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DisplayLocale {
+    /// Between the integral and the fractional digits, `.` by default.
+    pub decimal_separator: char,
+    /// Separator between groups of thousands for units not declaring their own, and
+    /// for those declaring the decimal separator of the locale.
+    pub thousands_separator: Option<char>,
+    /// `chrono` format of dates, `%Y-%m-%d` by default. `%B` and `%b` are written with
+    /// `month_names`, the latter cut to three letters.
+    pub date_format: String,
+    /// From January to December.
+    pub month_names: [String; 12],
+}
+
+impl Default for DisplayLocale {
+    fn default() -> Self {
+        DisplayLocale {
+            decimal_separator: '.',
+            thousands_separator: None,
+            date_format: "%Y-%m-%d".to_string(),
+            month_names: [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ]
+            .map(String::from),
+        }
+    }
+}
+
+impl DisplayLocale {
+    /// Decimal comma, dots between thousands and Indonesian month names, e.g.
+    /// `17 Agustus 2022` and `1.500.000,50 IDR`.
+    pub fn indonesian() -> Self {
+        DisplayLocale {
+            decimal_separator: ',',
+            thousands_separator: Some('.'),
+            date_format: "%-d %B %Y".to_string(),
+            month_names: [
+                "Januari",
+                "Februari",
+                "Maret",
+                "April",
+                "Mei",
+                "Juni",
+                "Juli",
+                "Agustus",
+                "September",
+                "Oktober",
+                "November",
+                "Desember",
+            ]
+            .map(String::from),
+        }
+    }
+
+    /// Name of the month, from 1 for January.
+    pub fn month_name(&self, month: u32) -> Option<&str> {
+        let idx = month.checked_sub(1)?;
+        self.month_names.get(idx as usize).map(String::as_str)
+    }
+
+    pub fn format_date(&self, date: &NaiveDate) -> Result<String> {
+        let name = self.month_name(date.month()).unwrap_or_default();
+        let mut format = String::new();
+        let mut chars = self.date_format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                format.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('B') => format.push_str(&name.replace('%', "%%")),
+                Some('b') => {
+                    let short: String = name.chars().take(3).collect();
+                    format.push_str(&short.replace('%', "%%"));
+                }
+                Some(other) => {
+                    format.push('%');
+                    format.push(other);
+                }
+                None => format.push('%'),
+            }
+        }
+
+        let mut text = String::new();
+        write!(text, "{}", date.format(&format))
+            .map_err(|_| anyhow!("invalid date format: `{}'", self.date_format))?;
+        Ok(text)
+    }
+
+    /// The amount as displayed in its unit, see
+    /// [`UnitInfo::format_in`][crate::UnitInfo::format_in].
+    pub fn format_amount(&self, ledger: &Ledger, amount: &Amount) -> Result<String> {
+        Ok(ledger
+            .unit_info(amount.unit)?
+            .format_in(self, amount.nominal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::locale::DisplayLocale;
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_display_locale() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD precision=2 symbol="$" thousands=","
+unit IDR precision=2
+"#,
+            None,
+        )?;
+        let usd = ledger.unit_info(0)?;
+        let idr = ledger.unit_info(1)?;
+        let date = NaiveDate::from_ymd_opt(2022, 8, 17).ok_or(anyhow!("invalid date"))?;
+
+        let english = DisplayLocale::default();
+        assert_eq!(usd.format_in(&english, dec!(1234.5)), "$1,234.50");
+        assert_eq!(english.format_date(&date)?, "2022-08-17");
+
+        let indonesian = DisplayLocale::indonesian();
+        // The unit's separator gives way to the decimal comma.
+        assert_eq!(usd.format_in(&indonesian, dec!(1234.5)), "$1.234,50");
+        assert_eq!(
+            idr.format_in(&indonesian, dec!(-1500000.5)),
+            "-1.500.000,50 IDR"
+        );
+        assert_eq!(indonesian.format_date(&date)?, "17 Agustus 2022");
+        assert_eq!(indonesian.month_name(3), Some("Maret"));
+        assert_eq!(indonesian.month_name(13), None);
+
+        let short = DisplayLocale {
+            date_format: "%d %b %Y".to_string(),
+            ..indonesian
+        };
+        assert_eq!(short.format_date(&date)?, "17 Agu 2022");
+
+        let broken = DisplayLocale {
+            date_format: "%Q".to_string(),
+            ..Default::default()
+        };
+        assert!(broken.format_date(&date).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::account::TxnAccount;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Free text about an account, from `note Assets:Bank "Called about the fee"`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Note {
+    pub(crate) account: TxnAccount,
+    pub(crate) text: String,
+}
+
+impl Note {
+    pub fn new(account: TxnAccount, text: &str) -> Self {
+        Note {
+            account,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn account(&self) -> &TxnAccount {
+        &self.account
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// A file attached to an account, e.g. a receipt or a statement, from
+/// `document Assets:Bank "statements/2022-01.pdf"`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Document {
+    pub(crate) account: TxnAccount,
+    pub(crate) path: String,
+}
+
+impl Document {
+    pub fn new(account: TxnAccount, path: &str) -> Self {
+        Document {
+            account,
+            path: path.to_string(),
+        }
+    }
+
+    pub fn account(&self) -> &TxnAccount {
+        &self.account
+    }
+
+    /// As written, relative paths aren't resolved.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// The value of something tracked over time taking effect, e.g. a move with
+/// `event "location" "Bandung"`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LifeEvent {
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+impl LifeEvent {
+    pub fn new(name: &str, value: &str) -> Self {
+        LifeEvent {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
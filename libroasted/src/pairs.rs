@@ -0,0 +1,28 @@
+//! (De)serialize maps as a sequence of `[key, value]` pairs, for maps keyed by accounts
+//! or other compound keys formats like JSON can't use as object keys.
+//!
+//! Used through `#[serde(with = "crate::pairs")]`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub(crate) fn serialize<'m, M, K, V, S>(map: M, serializer: S) -> Result<S::Ok, S::Error>
+where
+    M: IntoIterator<Item = (&'m K, &'m V)>,
+    K: Serialize + 'm,
+    V: Serialize + 'm,
+    S: Serializer,
+{
+    serializer.collect_seq(map)
+}
+
+pub(crate) fn deserialize<'de, M, K, V, D>(deserializer: D) -> Result<M, D::Error>
+where
+    M: FromIterator<(K, V)>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<(K, V)>::deserialize(deserializer)?
+        .into_iter()
+        .collect())
+}
@@ -1,22 +1,255 @@
+use crate::account::ParsedAccount;
+use crate::amount::ParsedAmount;
+use crate::error::RoastedError;
+#[cfg(feature = "fs")]
+use crate::fingerprint::Fingerprint;
 use crate::ledger::Ledger;
+use crate::statement::Statement;
+use crate::transaction::{ParsedTransaction, TxnHeader};
+use crate::unit::UnitInfo;
 use anyhow::{anyhow, Result};
-use pest::iterators::Pair;
+use chrono::NaiveDate;
+use pest::error::{Error, InputLocation};
+use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 
+#[cfg(feature = "fs")]
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
 use std::fs;
-use std::path::Path;
+use std::ops::Range;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "fs")]
+use std::thread;
 
 #[derive(Parser)]
 #[grammar = "ledger.pest"]
 pub struct LedgerParser;
 
+/// Parse the ledger file, loading its includes in place.
+///
+/// Unit and account segment indices, exposed through [`Amount`][crate::Amount] and
+/// [`TxnAccount`][crate::account::TxnAccount], are given in the order statements appear in
+/// the ledger with every include expanded where it's written. Loading includes any other
+/// way, e.g. concurrently, has to keep that order so the indices stay the same.
+#[cfg(feature = "fs")]
 pub fn parse_file<P: AsRef<Path>>(path: P, carried_ledger: Option<Ledger>) -> Result<Ledger> {
     if carried_ledger.is_none() {
         return parse_file(path, Some(Ledger::new()));
     }
 
+    let mut ledger = carried_ledger.unwrap();
+    let path = path.as_ref();
+    if !record_include(&mut ledger, path, fs::canonicalize(path)?)? {
+        return Ok(ledger);
+    }
+
     let fcontent = fs::read_to_string(path)?;
-    parse(&fcontent, carried_ledger)
+    let origin = ledger.set_audit_origin(&path.display().to_string());
+    let mut ledger = parse(&fcontent, Some(ledger))
+        .map_err(|err| RoastedError::in_file(err, path.to_path_buf()))?;
+    ledger.set_audit_origin(&origin);
+    Ok(ledger)
+}
+
+/// Like [`parse_file`], reading and parsing the included files concurrently, then
+/// applying them one after the other in include order, so the ledger and its indices are
+/// the same as with [`parse_file`].
+///
+/// Files are parsed statement by statement as with [`parse_statements`], so in permissive
+/// mode an unparsed region is the offending statement rather than its whole block.
+#[cfg(feature = "fs")]
+pub fn parse_file_parallel<P: AsRef<Path>>(
+    path: P,
+    carried_ledger: Option<Ledger>,
+) -> Result<Ledger> {
+    let path = path.as_ref();
+    let sources = read_ahead(path)?;
+    let sources: Vec<_> = sources.iter().collect();
+    let mut parsed = concurrently(&sources, |&(canonical, content)| {
+        (
+            canonical.to_path_buf(),
+            (content.as_str(), spanned_statements(content).collect()),
+        )
+    })?
+    .into_iter()
+    .collect();
+    apply_file(carried_ledger.unwrap_or_default(), path, &mut parsed)
+}
+
+/// Record the file as included in the ledger, `false` when it already was and is to be
+/// skipped.
+#[cfg(feature = "fs")]
+fn record_include(ledger: &mut Ledger, path: &Path, canonical: PathBuf) -> Result<bool> {
+    if ledger.record_include(canonical) {
+        return Ok(true);
+    }
+    // Diamond or cyclic includes would otherwise load the same statements twice and fail
+    // on whatever they declare first.
+    match ledger.get_option("duplicate_includes").map(String::as_str) {
+        Some("skip") => Ok(false),
+        None | Some("error") => Err(anyhow!(
+            "`{}' is included more than once, set option \"duplicate_includes\" to \"skip\" to load it only once",
+            path.display()
+        )),
+        Some(other) => Err(anyhow!("invalid duplicate_includes option: `{}'", other)),
+    }
+}
+
+/// Map the items on as many threads as there are cores, keeping their order.
+#[cfg(feature = "fs")]
+fn concurrently<T, R, F>(items: &[T], f: F) -> Result<Vec<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    if items.is_empty() {
+        return Ok(Vec::new());
+    }
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = items.len().div_ceil(workers);
+    let f = &f;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect();
+
+        let mut results = Vec::with_capacity(items.len());
+        for handle in handles {
+            results.extend(
+                handle
+                    .join()
+                    .map_err(|_| anyhow!("parsing thread panicked"))?,
+            );
+        }
+        Ok(results)
+    })
+}
+
+/// Read the file and every file it includes, transitively, by canonical path. Files that
+/// can't be read are left out, loading them reports the error where they're included.
+#[cfg(feature = "fs")]
+fn read_ahead(root: &Path) -> Result<HashMap<PathBuf, String>> {
+    let mut sources = HashMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while !pending.is_empty() {
+        let read = concurrently(&pending, |path| -> Option<(PathBuf, String)> {
+            Some((fs::canonicalize(path).ok()?, fs::read_to_string(path).ok()?))
+        })?;
+
+        pending = Vec::new();
+        for (canonical, content) in read.into_iter().flatten() {
+            if sources.contains_key(&canonical) {
+                continue;
+            }
+            // Only the include lines are parsed here, the rest is left to the workers.
+            pending.extend(
+                statement_spans(&content)
+                    .filter(|span| content[span.clone()].trim_start().starts_with("include"))
+                    .filter_map(|span| match parse_entry(&content, span) {
+                        Ok(Statement::Include(include)) => Some(PathBuf::from(include)),
+                        _ => None,
+                    })
+                    .filter(|include| {
+                        fs::canonicalize(include).is_ok_and(|c| !sources.contains_key(&c))
+                    }),
+            );
+            sources.insert(canonical, content);
+        }
+    }
+    Ok(sources)
+}
+
+/// A file's content along with its statements.
+#[cfg(feature = "fs")]
+type ParsedSource<'s> = (&'s str, Vec<(Range<usize>, Result<Statement<'s>>)>);
+
+/// Apply the parsed file to the ledger as [`parse_file`] does, parsing it on the spot
+/// when it wasn't read ahead.
+#[cfg(feature = "fs")]
+fn apply_file(
+    mut ledger: Ledger,
+    path: &Path,
+    parsed: &mut HashMap<PathBuf, ParsedSource<'_>>,
+) -> Result<Ledger> {
+    let canonical = fs::canonicalize(path)?;
+    let Some((input, statements)) = parsed.remove(&canonical) else {
+        return parse_file(path, Some(ledger));
+    };
+    if !record_include(&mut ledger, path, canonical)? {
+        return Ok(ledger);
+    }
+
+    let origin = ledger.set_audit_origin(&path.display().to_string());
+    let last_checkpoint = statements
+        .iter()
+        .filter_map(|(_, statement)| match statement {
+            Ok(Statement::Checkpoint(date, _)) => Some(*date),
+            _ => None,
+        })
+        .max();
+    for (span, statement) in statements {
+        let text = input[span.clone()].trim_end();
+        let statement = match statement {
+            Ok(statement) => statement,
+            Err(err)
+                if ledger.is_option_enabled("permissive")
+                    && matches!(err.downcast_ref(), Some(RoastedError::Syntax { .. })) =>
+            {
+                Statement::Unparsed(span.clone(), text)
+            }
+            Err(err) => return Err(RoastedError::in_file(err, path.to_path_buf())),
+        };
+        ledger = apply_parsed(ledger, statement, text, last_checkpoint.as_ref(), parsed).map_err(
+            |err| {
+                RoastedError::in_file(
+                    RoastedError::statement(input, span, err),
+                    path.to_path_buf(),
+                )
+            },
+        )?;
+    }
+    ledger.set_audit_origin(&origin);
+    Ok(ledger)
+}
+
+#[cfg(feature = "fs")]
+fn apply_parsed(
+    mut ledger: Ledger,
+    mut statement: Statement<'_>,
+    text: &str,
+    last_checkpoint: Option<&NaiveDate>,
+    parsed: &mut HashMap<PathBuf, ParsedSource<'_>>,
+) -> Result<Ledger> {
+    match statement {
+        Statement::Include(include) => return apply_file(ledger, Path::new(include), parsed),
+        Statement::Unparsed(..) => ledger.process_statement(statement)?,
+        _ => {
+            if !skipped_by_checkpoint(&ledger, &mut statement, last_checkpoint) {
+                ledger.process_statement(statement)?;
+                ledger.record_audit(text);
+            }
+        }
+    }
+    Ok(ledger)
+}
+
+/// Parse the ledger file unless its content is the same as when it had the `previous`
+/// fingerprint, see [`Ledger::fingerprint`]. Returns the ledger along with its new
+/// fingerprint when it changed, so a daemon watching the file can skip rebuilding its
+/// reports when the file was only touched or reformatted.
+#[cfg(feature = "fs")]
+pub fn parse_file_if_changed<P: AsRef<Path>>(
+    path: P,
+    previous: Option<Fingerprint>,
+) -> Result<Option<(Ledger, Fingerprint)>> {
+    let ledger = parse_file(path, None)?;
+    let fingerprint = ledger.fingerprint()?;
+    Ok((previous != Some(fingerprint)).then_some((ledger, fingerprint)))
 }
 
 pub fn parse(input: &str, carried_ledger: Option<Ledger>) -> Result<Ledger> {
@@ -24,43 +257,334 @@ pub fn parse(input: &str, carried_ledger: Option<Ledger>) -> Result<Ledger> {
         return parse(input, Some(Ledger::new()));
     }
 
-    let statements = LedgerParser::parse(Rule::ledger, input)?;
     let mut ledger = carried_ledger.unwrap();
+    let mut offset = 0;
+    loop {
+        let rest = &input[offset..];
+        let err = match LedgerParser::parse(Rule::ledger, rest) {
+            Ok(statements) => return process(ledger, statements, input, offset),
+            Err(err) => err,
+        };
 
-    for statement in statements {
-        match statement.as_rule() {
-            Rule::include => {
-                let statement_str = statement.as_str().to_string();
-                ledger = parse_file(
-                    Path::new(inner_str(statement.into_inner().nth(1).ok_or(anyhow!(
-                        format!("unexpected token at `include`: {}", statement_str)
-                    ))?)),
-                    Some(ledger),
-                )?
+        // Load everything before the offending block, then in permissive mode record
+        // the block as unparsed and carry on after it.
+        let region = unparsed_region(rest, &err);
+        if region.is_empty() {
+            return Err(RoastedError::syntax(input, offset, &err));
+        }
+        let prefix = LedgerParser::parse(Rule::ledger, &rest[..region.start])
+            .map_err(|_| RoastedError::syntax(input, offset, &err))?;
+        ledger = process(ledger, prefix, input, offset)?;
+        if !ledger.is_option_enabled("permissive") {
+            return Err(RoastedError::syntax(input, offset, &err));
+        }
+
+        ledger.process_statement(Statement::Unparsed(
+            offset + region.start..offset + region.end,
+            &rest[region.clone()],
+        ))?;
+        offset += region.end;
+    }
+}
+
+/// Parse the whole input with the given fragment rule, returning its single pair.
+fn parse_fragment(rule: Rule, input: &str) -> Result<Pair<'_, Rule>> {
+    LedgerParser::parse(rule, input)?
+        .next()
+        .ok_or(anyhow!("empty fragment: `{}'", input))
+}
+
+/// Parse a single amount, e.g. `12.50 USD` or `50% of 1200 USD`.
+pub fn parse_amount(input: &str) -> Result<ParsedAmount<'_>> {
+    ParsedAmount::parse(parse_fragment(Rule::amount_fragment, input)?)
+}
+
+/// Parse a single account name, e.g. `Assets:Bank:Jawir`.
+pub fn parse_account(input: &str) -> Result<ParsedAccount<'_>> {
+    ParsedAccount::parse(parse_fragment(Rule::account_fragment, input)?)
+}
+
+/// Parse a single dated statement, without applying it to any ledger.
+pub fn parse_statement(input: &str) -> Result<Statement<'_>> {
+    parse_fragment(Rule::statement_fragment, input)?.try_into()
+}
+
+/// Parse the input lazily, one statement at a time, without applying them to any ledger,
+/// e.g. to scan a very large ledger in bounded memory or to feed a ledger incrementally
+/// with [`Ledger::process_statement`]. `option`, `unit`, and `include` lines are returned
+/// as statements too, comments are skipped.
+///
+/// Each statement is its first line along with the indented lines following it. Errors
+/// are located in the input, see [`RoastedError`], and parsing carries on with the next
+/// statement.
+pub fn parse_statements(input: &str) -> impl Iterator<Item = Result<Statement<'_>>> {
+    spanned_statements(input).map(|(_, statement)| statement)
+}
+
+/// Like [`parse_statements`], along with the byte range of each statement in the input.
+pub(crate) fn spanned_statements(
+    input: &str,
+) -> impl Iterator<Item = (Range<usize>, Result<Statement<'_>>)> {
+    statement_spans(input).map(|span| (span.clone(), parse_entry(input, span)))
+}
+
+/// Byte ranges of the statements in the input, see [`parse_statements`].
+pub(crate) fn statement_spans(input: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut offset = 0;
+    std::iter::from_fn(move || loop {
+        let rest = &input[offset..];
+        let mut lines = rest.split_inclusive('\n');
+        let first = lines.next()?;
+        let start = offset;
+        offset += first.len();
+        let head = first.trim();
+        if head.is_empty() || head.starts_with(';') {
+            continue;
+        }
+        for line in lines {
+            if !line.starts_with([' ', '\t']) || line.trim().is_empty() {
+                break;
             }
-            Rule::option => ledger.parse_option(statement)?,
-            Rule::statement => ledger.process_statement(statement.try_into()?)?,
-            Rule::unit => ledger.parse_unit(statement)?,
-            Rule::EOI => break,
-            _ => {
-                return Err(anyhow!(format!(
-                    "unexpected token at `{:?}`: {}",
-                    statement.as_rule(),
-                    statement.as_str()
-                )))
+            offset += line.len();
+        }
+        return Some(start..offset);
+    })
+}
+
+fn parse_entry(input: &str, span: Range<usize>) -> Result<Statement<'_>> {
+    let entry = LedgerParser::parse(Rule::entry_fragment, &input[span.clone()])
+        .map_err(|err| RoastedError::syntax(input, span.start, &err))?
+        .next()
+        .ok_or(anyhow!("empty statement"))?;
+    let statement = match entry.as_rule() {
+        Rule::include => entry
+            .into_inner()
+            .next()
+            .map(inner_str)
+            .map(Statement::Include)
+            .ok_or(anyhow!("invalid include")),
+        Rule::option => {
+            let mut pairs = entry.into_inner();
+            match (pairs.next(), pairs.next()) {
+                (Some(key), Some(value)) => Ok(Statement::Option(inner_str(key), inner_str(value))),
+                _ => Err(anyhow!("invalid option")),
             }
-        };
+        }
+        Rule::unit => UnitInfo::parse(entry).map(Statement::Unit),
+        _ => entry.try_into(),
+    };
+    statement.map_err(|err| RoastedError::statement(input, span, err))
+}
+
+/// Parse a single dated transaction along with its postings.
+pub fn parse_transaction(input: &str) -> Result<(NaiveDate, TxnHeader<'_>, ParsedTransaction<'_>)> {
+    match parse_statement(input)? {
+        Statement::Transaction(date, header, txn) => Ok((date, header, txn)),
+        _ => Err(anyhow!("not a transaction: `{}'", input.trim())),
+    }
+}
+
+/// The block of non-blank lines around the parse error position.
+fn unparsed_region(input: &str, err: &Error<Rule>) -> Range<usize> {
+    let pos = match err.location {
+        InputLocation::Pos(pos) => pos,
+        InputLocation::Span((start, _)) => start,
+    };
+
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    for line in input.split_inclusive('\n') {
+        lines.push(line_start..line_start + line.len());
+        line_start += line.len();
+    }
+    let is_blank = |line: &Range<usize>| input[line.clone()].trim().is_empty();
+
+    let Some(mut current) = lines.iter().position(|line| line.contains(&pos)) else {
+        return lines.last().map_or(0..0, |line| line.clone());
+    };
+    // Errors reported at a blank line belong to the block right before it.
+    while current > 0 && is_blank(&lines[current]) {
+        current -= 1;
+    }
+
+    let mut first = current;
+    while first > 0 && !is_blank(&lines[first - 1]) {
+        first -= 1;
+    }
+    let mut last = current;
+    while last + 1 < lines.len() && !is_blank(&lines[last + 1]) {
+        last += 1;
+    }
+
+    lines[first].start..lines[last].end
+}
+
+/// Apply the statements, found `offset` bytes into the input, to the ledger. Errors are
+/// located in the input, see [`RoastedError`].
+fn process(
+    mut ledger: Ledger,
+    statements: Pairs<'_, Rule>,
+    input: &str,
+    offset: usize,
+) -> Result<Ledger> {
+    let last_checkpoint = last_checkpoint(&statements)?;
+
+    for statement in statements {
+        if statement.as_rule() == Rule::EOI {
+            break;
+        }
+        let span = statement.as_span();
+        let span = offset + span.start()..offset + span.end();
+        ledger = apply(ledger, statement, last_checkpoint.as_ref())
+            .map_err(|err| RoastedError::statement(input, span, err))?;
     }
 
     Ok(ledger)
 }
 
-pub fn inner_str(token: Pair<Rule>) -> &str {
+fn apply(
+    mut ledger: Ledger,
+    statement: Pair<'_, Rule>,
+    last_checkpoint: Option<&NaiveDate>,
+) -> Result<Ledger> {
+    match statement.as_rule() {
+        #[cfg(feature = "fs")]
+        Rule::include => {
+            let statement_str = statement.as_str().to_string();
+            ledger = parse_file(
+                Path::new(inner_str(statement.into_inner().next().ok_or(anyhow!(
+                    format!("unexpected token at `include`: {}", statement_str)
+                ))?)),
+                Some(ledger),
+            )?
+        }
+        #[cfg(not(feature = "fs"))]
+        Rule::include => {
+            return Err(anyhow!(format!(
+                "`include` requires the `fs` feature: {}",
+                statement.as_str()
+            )))
+        }
+        Rule::option => {
+            let text = statement.as_str();
+            ledger.parse_option(statement)?;
+            ledger.record_audit(text);
+        }
+        Rule::statement => {
+            let text = statement.as_str();
+            let mut statement: Statement = statement.try_into()?;
+            if !skipped_by_checkpoint(&ledger, &mut statement, last_checkpoint) {
+                ledger.process_statement(statement)?;
+                ledger.record_audit(text);
+            }
+        }
+        Rule::unit => {
+            let text = statement.as_str();
+            ledger.parse_unit(statement)?;
+            ledger.record_audit(text);
+        }
+        _ => {
+            return Err(anyhow!(format!(
+                "unexpected token at `{:?}`: {}",
+                statement.as_rule(),
+                statement.as_str()
+            )))
+        }
+    };
+
+    Ok(ledger)
+}
+
+/// Find the date of the latest `checkpoint` statement among the given pairs.
+fn last_checkpoint(statements: &Pairs<Rule>) -> Result<Option<NaiveDate>> {
+    let mut last = None;
+    for statement in statements.clone() {
+        if statement.as_rule() != Rule::statement {
+            continue;
+        }
+
+        let mut pairs = statement.into_inner();
+        let (Some(date), Some(kind)) = (pairs.next(), pairs.next()) else {
+            continue;
+        };
+
+        if kind.as_rule() == Rule::checkpoint_statement {
+            let date = NaiveDate::parse_from_str(date.as_str(), "%Y-%m-%d")?;
+            last = last.max(Some(date));
+        }
+    }
+
+    Ok(last)
+}
+
+/// With `option "skip_before_checkpoint" "true"`, statements dated before the latest
+/// checkpoint are not loaded, since the checkpoint already carries the balances for that
+/// period. Account opening, closing, and prices are still processed so later statements
+/// can refer to them, opening balances are dropped though.
+fn skipped_by_checkpoint(
+    ledger: &Ledger,
+    statement: &mut Statement,
+    checkpoint: Option<&NaiveDate>,
+) -> bool {
+    if !ledger.is_option_enabled("skip_before_checkpoint") {
+        return false;
+    }
+
+    if let (Statement::OpenAccount(date, _, amounts, _), Some(checkpoint)) =
+        (&mut *statement, checkpoint)
+    {
+        if &*date < checkpoint {
+            amounts.clear();
+        }
+    }
+
+    match (&*statement, checkpoint) {
+        (Statement::OpenAccount(..) | Statement::CloseAccount(..) | Statement::Price(..), _) => {
+            false
+        }
+        (_, Some(checkpoint)) => statement.date().is_some_and(|date| date < checkpoint),
+        (_, None) => false,
+    }
+}
+
+pub(crate) fn inner_str(token: Pair<'_, Rule>) -> &str {
     token.into_inner().next().unwrap().as_str()
 }
 
+/// The content of a string token with its escapes, e.g. `\"`, resolved.
+pub(crate) fn unescape_str(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                let c = u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER);
+                out.push(c);
+            }
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "fs")]
+    use crate::tempdir::TempDir;
     use crate::{
         account::{ParsedAccount, TxnAccount},
         ledger::ReferenceLookup,
@@ -68,6 +592,8 @@ mod tests {
     };
     use anyhow::{anyhow, Result};
     use chrono::naive::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_ledger_content() -> Result<()> {
@@ -178,6 +704,297 @@ unit USD
         Ok(())
     }
 
+    #[test]
+    fn test_resume_after_unparsed_statement() -> Result<()> {
+        let input = r#"option "permissive" "true"
+unit USD
+2022-01-01 open Assets:Cash
+2022-01-01 open Expenses:Food
+
+2022-01-02 * "Lunch"
+  Assets:Cash
+  Expenses:Food     10 USD
+
+2022-01-03 * "Snack"
+  Assets:Cash
+  Expenses:Food     ten USD
+
+2022-01-04 * "Dinner"
+  Assets:Cash
+  Expenses:Food     20 USD
+"#;
+        let ledger = parser::parse(input, None)?;
+
+        let broken = "2022-01-03 * \"Snack\"\n  Assets:Cash\n  Expenses:Food     ten USD\n";
+        assert_eq!(ledger.unparsed().len(), 1);
+        assert_eq!(ledger.unparsed()[0].text, broken);
+        assert_eq!(&input[ledger.unparsed()[0].span.clone()], broken);
+        assert_eq!(ledger.bookings().len(), 2);
+
+        assert!(parser::parse(&input.replacen("true", "false", 1), None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_before_checkpoint() -> Result<()> {
+        let input = r#"
+option "skip_before_checkpoint" "true"
+unit USD
+
+2020-01-01 open Assets:Cash
+2020-01-01 open Expenses:Food
+
+2020-02-01 * "Lunch"
+  Assets:Cash
+  Expenses:Food     10 USD
+
+2020-03-01 balance Assets:Cash -10 USD
+
+2021-01-01 checkpoint
+  Assets:Cash      -500 USD
+  Expenses:Food     500 USD
+
+2021-01-05 * "Dinner"
+  Assets:Cash
+  Expenses:Food     25 USD
+"#;
+        let ledger = parser::parse(input, None)?;
+        let lunch = NaiveDate::from_ymd_opt(2020, 2, 1).ok_or(anyhow!("invalid date"))?;
+        let assert = NaiveDate::from_ymd_opt(2020, 3, 1).ok_or(anyhow!("invalid date"))?;
+        let later = NaiveDate::from_ymd_opt(2021, 2, 1).ok_or(anyhow!("invalid date"))?;
+
+        assert!(ledger.get_bookings_on(&lunch).is_none());
+        assert!(ledger.get_bookings_on(&assert).is_none());
+        assert_eq!(
+            ledger
+                .balances_at(&later)?
+                .get(&TxnAccount::Assets(vec![0]), 0),
+            dec!(-525)
+        );
+
+        let full = parser::parse(&input.replacen("true", "false", 1), None)?;
+        assert!(full.get_bookings_on(&lunch).is_some());
+        assert_eq!(
+            full.balances_at(&later)?
+                .get(&TxnAccount::Assets(vec![0]), 0),
+            dec!(-525)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_fragments() -> Result<()> {
+        let amount = parser::parse_amount(" 1_250.50 USD ")?;
+        assert_eq!((amount.nominal(), amount.unit()), (dec!(1250.5), "USD"));
+        assert_eq!(parser::parse_amount("10% of 300 IDR")?.nominal(), dec!(30));
+        assert!(parser::parse_amount("12 USD extra").is_err());
+
+        assert_eq!(
+            parser::parse_account("Assets:Bank:Jawir")?,
+            ParsedAccount::Assets(vec!["Bank", "Jawir"])
+        );
+        assert!(parser::parse_account("Bank").is_err());
+
+        let (date, header, txn) = parser::parse_transaction(
+            r#"2024-05-01 * "Cafe" "Coffee"
+  Assets:Cash
+  Expenses:Coffee     4 USD"#,
+        )?;
+        assert_eq!(
+            date,
+            NaiveDate::from_ymd_opt(2024, 5, 1).ok_or(anyhow!("invalid date"))?
+        );
+        assert_eq!((header.payee(), header.title()), (Some("Cafe"), "Coffee"));
+        let postings: Vec<(String, Option<Decimal>)> = txn
+            .postings()
+            .map(|(account, amount)| (account.to_string(), amount.map(|a| a.nominal())))
+            .collect();
+        assert_eq!(
+            postings,
+            vec![
+                ("Assets:Cash".to_string(), None),
+                ("Expenses:Coffee".to_string(), Some(dec!(4)))
+            ]
+        );
+
+        assert!(parser::parse_transaction("2024-05-01 open Assets:Cash").is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_duplicate_includes() -> Result<()> {
+        let dir = TempDir::new("includes")?;
+
+        let common = dir.write(
+            "common.ledger",
+            "unit USD\n2022-01-01 open Assets:Bank\n2022-01-01 open Expenses:Food\n",
+        )?;
+        let personal = dir.write("personal.ledger", format!("include \"{}\"\n", common))?;
+        let business = dir.write("business.ledger", format!("include \"{}\"\n", common))?;
+        let main = dir.write(
+            "main.ledger",
+            format!("include \"{}\"\ninclude \"{}\"\n", personal, business),
+        )?;
+
+        let err = parser::parse_file(&main, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("common.ledger' is included more than once"));
+
+        let mut ledger = crate::ledger::Ledger::new();
+        ledger.set_option("duplicate_includes", "skip");
+        let ledger = parser::parse_file(&main, Some(ledger))?;
+        assert_eq!(ledger.included_files().len(), 4);
+        assert!(ledger.included_files()[2].ends_with("common.ledger"));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_include_indices_follow_source_order() -> Result<()> {
+        let dir = TempDir::new("indices")?;
+
+        let accounts = dir.write(
+            "accounts.ledger",
+            "unit IDR\n2022-01-01 open Assets:Wallet\n",
+        )?;
+        let main = dir.write(
+            "main.ledger",
+            format!(
+                "unit USD\n2022-01-01 open Expenses:Food\ninclude \"{}\"\nunit JPY\n2022-01-01 open Assets:Bank\n",
+                accounts
+            ),
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 1).ok_or(anyhow!("invalid date"))?;
+        for _ in 0..2 {
+            let ledger = parser::parse_file(&main, None)?;
+            assert_eq!(
+                ledger.units().collect::<Vec<_>>(),
+                vec!["USD", "IDR", "JPY"]
+            );
+            let account = |name| ledger.account_lookup(&date, &parser::parse_account(name)?);
+            assert_eq!(account("Expenses:Food")?, TxnAccount::Expenses(vec![0]));
+            assert_eq!(account("Assets:Wallet")?, TxnAccount::Assets(vec![1]));
+            assert_eq!(account("Assets:Bank")?, TxnAccount::Assets(vec![2]));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_parse_file_parallel() -> Result<()> {
+        let dir = TempDir::new("parallel")?;
+
+        let accounts = dir.write(
+            "accounts.ledger",
+            "unit IDR\n2022-01-01 open Assets:Wallet\n2022-01-01 open Expenses:Food\n",
+        )?;
+        let mut years = Vec::new();
+        for year in 2022..2026 {
+            years.push(dir.write(
+                &format!("{}.ledger", year),
+                format!(
+                    "include \"{}\"\n{}-03-01 * \"Lunch\"\n  Assets:Wallet\n  Expenses:Food  {} IDR\n",
+                    accounts, year, year
+                ),
+            )?);
+        }
+        let main = dir.write(
+            "main.ledger",
+            format!(
+                "option \"duplicate_includes\" \"skip\"\nunit USD\n2022-01-01 open Assets:Bank\n{}",
+                years
+                    .iter()
+                    .map(|year| format!("include \"{}\"\n", year))
+                    .collect::<String>()
+            ),
+        )?;
+
+        let sequential = parser::parse_file(&main, None)?;
+        let parallel = parser::parse_file_parallel(&main, None)?;
+        assert_eq!(parallel.to_ledger_string()?, sequential.to_ledger_string()?);
+        assert_eq!(parallel.included_files(), sequential.included_files());
+        assert_eq!(parallel.units().collect::<Vec<_>>(), vec!["USD", "IDR"]);
+        let date = NaiveDate::from_ymd_opt(2022, 1, 1).ok_or(anyhow!("invalid date"))?;
+        assert_eq!(
+            parallel.account_lookup(&date, &parser::parse_account("Assets:Wallet")?)?,
+            TxnAccount::Assets(vec![1])
+        );
+
+        dir.write("2025.ledger", "2025-03-01 * \"Lunch\"\n  Assets:Nowhere\n")?;
+        let err = parser::parse_file_parallel(&main, None).unwrap_err();
+        let located = err
+            .downcast_ref::<crate::error::RoastedError>()
+            .ok_or(anyhow!("unlocated error: {}", err))?;
+        assert!(located
+            .location()
+            .path
+            .as_ref()
+            .is_some_and(|path| path.ends_with("2025.ledger")));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_strict_directive_order() -> Result<()> {
+        let dir = TempDir::new("order")?;
+
+        let accounts = dir.write(
+            "accounts.ledger",
+            "2022-01-01 open Assets:Wallet\nunit IDR\n",
+        )?;
+        let main = dir.write(
+            "main.ledger",
+            format!(
+                "option \"directive_order\" \"strict\"\nunit USD\ninclude \"{}\"\n",
+                accounts
+            ),
+        )?;
+
+        let err = parser::parse_file(&main, None).unwrap_err();
+        assert!(err.to_string().ends_with(
+            "accounts.ledger:2:1: unit `IDR' has to come before dated statements with option \"directive_order\" set to \"strict\""
+        ));
+        // Late directives are fine otherwise.
+        let ledger = parser::parse_file(&accounts, None)?;
+        assert_eq!(ledger.units().collect::<Vec<_>>(), vec!["IDR"]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_parse_file_if_changed() -> Result<()> {
+        let dir = TempDir::new("changed")?;
+        let path = dir.path("main.ledger");
+        std::fs::write(&path, "unit USD\n2022-01-01 open Assets:Bank\n")?;
+        let (_, fingerprint) =
+            parser::parse_file_if_changed(&path, None)?.ok_or(anyhow!("not parsed"))?;
+
+        std::fs::write(
+            &path,
+            "unit USD\n\n2022-01-01   open Assets:Bank ; touched\n",
+        )?;
+        assert!(parser::parse_file_if_changed(&path, Some(fingerprint))?.is_none());
+
+        std::fs::write(&path, "unit USD\n2022-01-02 open Assets:Bank\n")?;
+        let (ledger, changed) = parser::parse_file_if_changed(&path, Some(fingerprint))?
+            .ok_or(anyhow!("not parsed"))?;
+        assert_ne!(changed, fingerprint);
+        assert_eq!(ledger.fingerprint()?, changed);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
     #[test]
     fn test_ledger_file_not_exist() {
         let err = parser::parse_file("not_exist", None)
@@ -196,4 +1013,59 @@ unit USD
             "The system cannot find the file specified. (os error 2)"
         );
     }
+
+    #[test]
+    fn test_parse_statements() -> Result<()> {
+        let mut ledger = crate::ledger::Ledger::new();
+        for statement in parser::parse_statements(crate::examples::HOUSEHOLD) {
+            ledger.process_statement(statement?)?;
+        }
+        let parsed = crate::examples::household()?;
+        assert_eq!(ledger.to_ledger_string()?, parsed.to_ledger_string()?);
+        assert_eq!(ledger.failed_assertions()?, vec![]);
+
+        let input = r#"; header comment
+option "title" "Stream"
+unit USD
+include "other.ledger"
+
+2022-01-01 open Assets:Bank
+2022-13-01 open Assets:Cash
+2022-01-02 * "Grocer"
+  Assets:Bank
+  Expenses:Food     30 USD
+2022-01-03 bogus
+2022-01-04 * Grocer
+"#;
+        let statements: Vec<_> = parser::parse_statements(input).collect();
+        assert_eq!(statements.len(), 8);
+        assert!(matches!(
+            statements[0],
+            Ok(crate::statement::Statement::Option("title", "Stream"))
+        ));
+        assert!(matches!(
+            statements[2],
+            Ok(crate::statement::Statement::Include("other.ledger"))
+        ));
+        // Errors don't stop the following statements, and tell where they are.
+        let located = |idx: usize| -> Result<usize> {
+            let err = statements[idx].as_ref().err().ok_or(anyhow!("no error"))?;
+            let err = err
+                .downcast_ref::<crate::error::RoastedError>()
+                .ok_or(anyhow!("no location"))?;
+            Ok(err.location().line)
+        };
+        assert_eq!(located(4)?, 7);
+        assert!(matches!(
+            statements[5],
+            Ok(crate::statement::Statement::Transaction(..))
+        ));
+        assert!(matches!(
+            statements[6],
+            Ok(crate::statement::Statement::Raw(..))
+        ));
+        assert_eq!(located(7)?, 12);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,330 @@
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    ledger::Ledger,
+};
+use anyhow::{anyhow, Result};
+use chrono::Datelike;
+use wasmi::{core::TrapCode, Caller, Config, Engine, Extern, Linker, Memory, Module, Store};
+
+/// Module the host functions are imported from.
+const HOST_MODULE: &str = "roasted";
+
+/// Fuel a plugin run starts with, roughly the number of instructions it may execute
+/// before it's stopped, so a plugin stuck in a loop can't hang the host.
+const FUEL: u64 = 100_000_000;
+
+/// A validation or report plugin compiled to WebAssembly, run with
+/// [`Ledger::run_plugin`].
+///
+/// The module exports its `memory` and a `run` function without parameters, and imports
+/// from `roasted`:
+///
+/// - `posting_count() -> i32`
+/// - `posting_date(idx) -> i32`, as `yyyymmdd`, e.g. `20220105`.
+/// - `posting_account(idx, ptr, cap) -> i32`, `posting_title(idx, ptr, cap) -> i32` and
+///   `posting_amount(idx, ptr, cap) -> i32`, copying at most `cap` bytes of the UTF-8
+///   text to `ptr` and returning its full length, so a longer buffer can be retried.
+///   Amounts are written like `-30.50 USD`.
+/// - `emit(severity, ptr, len)`, a diagnostic with the message at `ptr`, a warning for
+///   severity 0 and an error otherwise.
+/// - `write(ptr, len)`, text appended to the report output.
+///
+/// Functions of a posting return -1 when the index is out of range, and
+/// `posting_amount` also when the amount is elided and can't be computed.
+///
+/// A run fails when the plugin traps, passes text outside of its memory, or runs out of
+/// fuel after about 100 million instructions.
+pub struct Plugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+}
+
+/// What a plugin run produced.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PluginOutput {
+    /// Emitted by the plugin, with the `plugin` code and messages prefixed by the plugin
+    /// name.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Everything the plugin wrote.
+    pub output: String,
+}
+
+/// Postings as the plugin sees them, taken before the run.
+struct HostPosting {
+    date: i32,
+    account: String,
+    title: String,
+    amount: Option<String>,
+}
+
+struct Host {
+    name: String,
+    postings: Vec<HostPosting>,
+    output: PluginOutput,
+}
+
+impl Plugin {
+    /// The plugin from the WebAssembly binary, named in its diagnostics.
+    pub fn new(name: &str, wasm: &[u8]) -> Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)
+            .map_err(|err| anyhow!("invalid plugin `{}': {}", name, err))?;
+        Ok(Plugin {
+            name: name.to_string(),
+            engine,
+            module,
+        })
+    }
+
+    /// Load the plugin from a `.wasm` file, named after the file.
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::new(&name, &std::fs::read(path)?)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The plugin memory, trapping when it isn't exported.
+fn memory(caller: &Caller<'_, Host>) -> Result<Memory, wasmi::Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or(wasmi::Error::new("plugin exports no memory"))
+}
+
+/// Copy the text into the plugin memory at `ptr`, see [`Plugin`].
+fn copy_out(
+    caller: &mut Caller<'_, Host>,
+    text: Option<String>,
+    ptr: i32,
+    cap: i32,
+) -> Result<i32, wasmi::Error> {
+    let Some(text) = text else {
+        return Ok(-1);
+    };
+    let len = text.len().min(cap.max(0) as usize);
+    memory(caller)?.write(&mut *caller, ptr as usize, &text.as_bytes()[..len])?;
+    Ok(text.len() as i32)
+}
+
+/// The text at `ptr`, checked to be within the plugin memory before it's copied.
+fn read_text(caller: &Caller<'_, Host>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    let start = ptr as u32 as usize;
+    let text = memory(caller)?
+        .data(caller)
+        .get(start..start.saturating_add(len as u32 as usize))
+        .ok_or(wasmi::Error::new("plugin text is out of memory bounds"))?;
+    String::from_utf8(text.to_vec()).map_err(|_| wasmi::Error::new("plugin text isn't UTF-8"))
+}
+
+fn posting<'c>(caller: &'c Caller<'_, Host>, idx: i32) -> Option<&'c HostPosting> {
+    caller.data().postings.get(usize::try_from(idx).ok()?)
+}
+
+fn linker(engine: &Engine) -> Result<Linker<Host>> {
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap(HOST_MODULE, "posting_count", |caller: Caller<'_, Host>| {
+            caller.data().postings.len() as i32
+        })?
+        .func_wrap(
+            HOST_MODULE,
+            "posting_date",
+            |caller: Caller<'_, Host>, idx: i32| posting(&caller, idx).map_or(-1, |p| p.date),
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "posting_account",
+            |mut caller: Caller<'_, Host>, idx: i32, ptr: i32, cap: i32| {
+                let text = posting(&caller, idx).map(|p| p.account.clone());
+                copy_out(&mut caller, text, ptr, cap)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "posting_title",
+            |mut caller: Caller<'_, Host>, idx: i32, ptr: i32, cap: i32| {
+                let text = posting(&caller, idx).map(|p| p.title.clone());
+                copy_out(&mut caller, text, ptr, cap)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "posting_amount",
+            |mut caller: Caller<'_, Host>, idx: i32, ptr: i32, cap: i32| {
+                let text = posting(&caller, idx).and_then(|p| p.amount.clone());
+                copy_out(&mut caller, text, ptr, cap)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "emit",
+            |mut caller: Caller<'_, Host>,
+             severity: i32,
+             ptr: i32,
+             len: i32|
+             -> Result<(), wasmi::Error> {
+                let message = read_text(&caller, ptr, len)?;
+                let host = caller.data_mut();
+                host.output.diagnostics.push(Diagnostic {
+                    severity: if severity == 0 {
+                        Severity::Warning
+                    } else {
+                        Severity::Error
+                    },
+                    ..Diagnostic::error("plugin", format!("{}: {}", host.name, message))
+                });
+                Ok(())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "write",
+            |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+                let text = read_text(&caller, ptr, len)?;
+                caller.data_mut().output.output.push_str(&text);
+                Ok(())
+            },
+        )?;
+    Ok(linker)
+}
+
+/// Run the plugin over the postings of the ledger, see [`Ledger::run_plugin`].
+pub(crate) fn run(ledger: &Ledger, plugin: &Plugin) -> Result<PluginOutput> {
+    let postings = ledger
+        .postings()
+        .map(|posting| {
+            let date = posting.date;
+            Ok(HostPosting {
+                date: date.year() * 10000 + date.month() as i32 * 100 + date.day() as i32,
+                account: ledger.account_name(&posting.exchange().account)?,
+                title: posting.transaction.title().to_string(),
+                amount: match posting.amount() {
+                    Some(amount) => Some(format!(
+                        "{} {}",
+                        amount.nominal,
+                        ledger.unit_name(amount.unit)?
+                    )),
+                    None => None,
+                },
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let mut store = Store::new(
+        &plugin.engine,
+        Host {
+            name: plugin.name.clone(),
+            postings,
+            output: PluginOutput::default(),
+        },
+    );
+    store
+        .set_fuel(FUEL)
+        .map_err(|err| anyhow!("plugin `{}' failed: {}", plugin.name, err))?;
+    let failed = |err: wasmi::Error| match err.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => anyhow!("plugin `{}' ran out of fuel", plugin.name),
+        _ => anyhow!("plugin `{}' failed: {}", plugin.name, err),
+    };
+    let instance = linker(&plugin.engine)?
+        .instantiate(&mut store, &plugin.module)
+        .and_then(|pre| pre.start(&mut store))
+        .map_err(failed)?;
+    instance
+        .get_typed_func::<(), ()>(&store, "run")
+        .and_then(|run| run.call(&mut store, ()))
+        .map_err(failed)?;
+
+    Ok(store.into_data().output)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Severity;
+    use crate::parser;
+    use crate::plugin::Plugin;
+    use anyhow::Result;
+
+    /// Plugins built from the `.wat` source next to them, e.g. with
+    /// `wasm-tools parse halves.wat -o halves.wasm`.
+    const HALVES: &[u8] = include_bytes!("../fixtures/plugins/halves.wasm");
+    const TRAPPING: &[u8] = include_bytes!("../fixtures/plugins/trapping.wasm");
+    const LOOPING: &[u8] = include_bytes!("../fixtures/plugins/looping.wasm");
+    const OVERSIZED: &[u8] = include_bytes!("../fixtures/plugins/oversized.wasm");
+
+    #[test]
+    fn test_run_plugin() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-07-03 * "Groceries"
+  Expenses:Food            20 USD
+  Assets:Bank
+"#,
+            None,
+        )?;
+
+        let plugin = Plugin::new("halves", HALVES)?;
+        let output = ledger.run_plugin(&plugin)?;
+        assert_eq!(
+            output.output,
+            "Assets:Bank\nExpenses:Food\nExpenses:Food\nAssets:Bank\n"
+        );
+        let found: Vec<_> = output
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.severity, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "plugin",
+                    Severity::Warning,
+                    "halves: posting after 2022-06-30"
+                ),
+                (
+                    "plugin",
+                    Severity::Warning,
+                    "halves: posting after 2022-06-30"
+                ),
+            ]
+        );
+
+        assert!(Plugin::new("broken", b"not wasm").is_err());
+        let failure = |name, wasm| -> Result<String> {
+            Ok(ledger
+                .run_plugin(&Plugin::new(name, wasm)?)
+                .unwrap_err()
+                .to_string())
+        };
+        let err = failure("trapping", TRAPPING)?;
+        assert!(err.starts_with("plugin `trapping' failed"), "{}", err);
+        assert_eq!(
+            failure("looping", LOOPING)?,
+            "plugin `looping' ran out of fuel"
+        );
+        let err = failure("oversized", OVERSIZED)?;
+        assert!(err.contains("out of memory bounds"), "{}", err);
+
+        Ok(())
+    }
+}
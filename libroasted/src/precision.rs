@@ -0,0 +1,178 @@
+use crate::account::TxnAccount;
+use crate::amount::Amount;
+use crate::ledger::Ledger;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::BTreeMap;
+
+/// Decimals used for each unit, inferred from the largest number of decimals written in
+/// the ledger unless declared with the unit, e.g. `unit USD precision=2`, see
+/// [`Ledger::precision`].
+///
+/// Only amounts written by hand count: explicit postings and balance assertions. Elided
+/// postings, pads and prices are left out, their decimals come from arithmetic rather
+/// than from the statements being recorded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Precision {
+    units: BTreeMap<usize, u32>,
+    accounts: BTreeMap<(TxnAccount, usize), u32>,
+}
+
+impl Precision {
+    fn observe(&mut self, account: &TxnAccount, amount: &Amount) {
+        let scale = amount.nominal.scale();
+        let unit = self.units.entry(amount.unit).or_default();
+        *unit = (*unit).max(scale);
+        let account = self
+            .accounts
+            .entry((account.clone(), amount.unit))
+            .or_default();
+        *account = (*account).max(scale);
+    }
+
+    /// Decimals of the unit across the whole ledger, `None` if it's never written.
+    pub fn of_unit(&self, unit: usize) -> Option<u32> {
+        self.units.get(&unit).copied()
+    }
+
+    /// Decimals of the unit in the account, falling back to the whole ledger when the
+    /// account never holds the unit.
+    pub fn of(&self, account: &TxnAccount, unit: usize) -> Option<u32> {
+        self.accounts
+            .get(&(account.clone(), unit))
+            .copied()
+            .or(self.of_unit(unit))
+    }
+
+    /// Half of the smallest written decimal, beancount style, so amounts computed from
+    /// hand-written ones balance when they're within rounding of each other. Zero for
+    /// units never written.
+    pub fn tolerance(&self, account: Option<&TxnAccount>, unit: usize) -> Decimal {
+        let precision = match account {
+            Some(account) => self.of(account, unit),
+            None => self.of_unit(unit),
+        };
+        match precision {
+            Some(precision) => Decimal::new(5, precision + 1),
+            None => Decimal::ZERO,
+        }
+    }
+
+    /// The amount rounded, or padded with zeros, to the decimals of its unit in the
+    /// account, or in the whole ledger without one. Units never written are left as is.
+    pub fn round(&self, account: Option<&TxnAccount>, amount: &Amount) -> Amount {
+        let precision = match account {
+            Some(account) => self.of(account, amount.unit),
+            None => self.of_unit(amount.unit),
+        };
+        let Some(precision) = precision else {
+            return amount.clone();
+        };
+        let mut nominal = amount
+            .nominal
+            .round_dp_with_strategy(precision, RoundingStrategy::MidpointAwayFromZero);
+        nominal.rescale(precision);
+        Amount {
+            nominal,
+            unit: amount.unit,
+        }
+    }
+}
+
+pub(crate) fn precision(ledger: &Ledger) -> Precision {
+    let mut precision = Precision::default();
+    for book in ledger.bookings().values() {
+        for txn in book.transactions() {
+            for exchange in txn.exchanges.iter().filter(|e| !e.elided) {
+                if let Some(amount) = &exchange.amount {
+                    precision.observe(&exchange.account, amount);
+                }
+            }
+        }
+        for assertion in book
+            .balance_assertions()
+            .iter()
+            .chain(book.balance_changes())
+        {
+            for amount in &assertion.amounts {
+                precision.observe(&assertion.account, amount);
+            }
+        }
+    }
+    // A declared precision wins over the written decimals of the unit.
+    for (unit, info) in ledger.unit_infos().enumerate() {
+        if let Some(declared) = info.precision {
+            precision.units.insert(unit, declared);
+        }
+    }
+    precision
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::Amount;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_precision() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit JPY
+unit BTC
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Wallet
+2022-01-01 open Assets:Yen
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Grocer"
+  Assets:Bank
+  Expenses:Food            30.5 USD
+
+2022-01-04 * "Ramen"
+  Assets:Yen
+  Expenses:Food            1200 JPY
+
+2022-01-05 * "Exchange"
+  Assets:Bank
+  Assets:Wallet     0.00123456 BTC
+
+2022-01-06 balance Assets:Bank -30.50 USD
+"#,
+            None,
+        )?;
+        let precision = ledger.precision();
+        let accounts = ledger.accounts();
+        let date = NaiveDate::from_ymd_opt(2022, 1, 6).ok_or(anyhow!("invalid date"))?;
+        let bank = accounts.txnify(&date, &"Assets:Bank".try_into()?)?;
+        let yen = accounts.txnify(&date, &"Assets:Yen".try_into()?)?;
+        let food = accounts.txnify(&date, &"Expenses:Food".try_into()?)?;
+        let wallet = accounts.txnify(&date, &"Assets:Wallet".try_into()?)?;
+        let (usd, jpy, btc) = (0, 1, 2);
+
+        assert_eq!(precision.of_unit(usd), Some(2));
+        assert_eq!(precision.of(&food, usd), Some(1));
+        assert_eq!(precision.of(&food, jpy), Some(0));
+        // The elided posting of the yen account doesn't count, the unit-wide one is used.
+        assert_eq!(precision.of(&yen, jpy), Some(0));
+        assert_eq!(precision.of(&wallet, btc), Some(8));
+        assert_eq!(precision.of(&wallet, usd), Some(2));
+        assert_eq!(precision.tolerance(Some(&bank), usd), dec!(0.005));
+        assert_eq!(precision.tolerance(None, jpy), dec!(0.5));
+
+        let round = |account, nominal, unit| {
+            precision
+                .round(account, &Amount { nominal, unit })
+                .nominal
+                .to_string()
+        };
+        assert_eq!(round(Some(&bank), dec!(10), usd), "10.00");
+        assert_eq!(round(Some(&bank), dec!(1.005), usd), "1.01");
+        assert_eq!(round(None, dec!(1200.4), jpy), "1200");
+        assert_eq!(round(Some(&wallet), dec!(0.1), btc), "0.10000000");
+
+        Ok(())
+    }
+}
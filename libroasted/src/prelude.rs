@@ -0,0 +1,60 @@
+pub use crate::{
+    account::{ParsedAccount, TxnAccount},
+    amount::{Amount, ParsedAmount},
+    balance::Balances,
+    diagnostic::{Diagnostic, Severity},
+    ledger::{DayBook, Ledger, ReferenceLookup},
+    parser::parse,
+    reports::{coverage::CoverageReport, run_all, Report, ReportSpec},
+    statement::Statement,
+    transaction::{BalanceAssertion, Exchange, PadTransaction, Transaction, TransactionState},
+};
+pub use rust_decimal::Decimal;
+
+#[cfg(feature = "fs")]
+pub use crate::parser::parse_file;
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_prelude() -> Result<()> {
+        let ledger: Ledger = parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+"#,
+            None,
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let txn: &Transaction = &ledger
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions()[0];
+        assert_eq!(txn.state, TransactionState::Settled);
+
+        let food: ParsedAccount = "Expenses:Food".try_into()?;
+        let food: TxnAccount = ledger.account_lookup(&date, &food)?;
+        let balances: Balances = ledger.balances_at(&date)?;
+        assert_eq!(balances.get(&food, 0), dec!(30));
+
+        match &run_all(&ledger, &[ReportSpec::Coverage])?[0] {
+            Report::Coverage(CoverageReport { uncategorized, .. }) => {
+                assert!(uncategorized.is_empty())
+            }
+            report => panic!("unexpected report: {:?}", report),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    amount::Amount,
+    ledger::{Ledger, ReferenceLookup},
+    reports::{is_under, posting_amount},
+    transaction::{Exchange, Transaction},
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use pest::iterators::Pair;
+use pest::Parser;
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+
+#[derive(Parser)]
+#[grammar = "query.pest"]
+struct QueryParser;
+
+/// A single posting within the ledger, as seen by filters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostingRef<'l> {
+    pub date: NaiveDate,
+    pub transaction: &'l Transaction,
+    /// Position of the posting within the transaction.
+    pub index: usize,
+}
+
+impl<'l> PostingRef<'l> {
+    pub fn exchange(&self) -> &'l Exchange {
+        &self.transaction.exchanges[self.index]
+    }
+
+    /// Amount of the posting, resolving an elided amount when the rest of the transaction
+    /// is in a single unit.
+    pub fn amount(&self) -> Option<Amount> {
+        posting_amount(self.transaction, self.index)
+    }
+}
+
+/// A filter expression compiled against a ledger, reusable across report runs without
+/// parsing the expression again.
+///
+/// Account and unit names are resolved at compile time, so the filter is only meaningful
+/// for the ledger it was compiled with.
+pub type CompiledFilter = Box<dyn Fn(&PostingRef) -> bool + Send + Sync>;
+
+/// Compile a filter expression, see [`Ledger::compile_filter`].
+pub fn compile(ledger: &Ledger, query: &str) -> Result<CompiledFilter> {
+    let query = QueryParser::parse(Rule::query, query)?
+        .next()
+        .and_then(|query| query.into_inner().next())
+        .ok_or(anyhow!("empty filter: `{}'", query))?;
+    compile_expr(ledger, query)
+}
+
+fn compile_expr(ledger: &Ledger, pair: Pair<Rule>) -> Result<CompiledFilter> {
+    match pair.as_rule() {
+        Rule::expr => {
+            let terms = pair
+                .into_inner()
+                .filter(|pair| pair.as_rule() != Rule::or)
+                .map(|pair| compile_expr(ledger, pair))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Box::new(move |posting| {
+                terms.iter().any(|term| term(posting))
+            }))
+        }
+        Rule::term => {
+            let factors = pair
+                .into_inner()
+                .filter(|pair| pair.as_rule() != Rule::and)
+                .map(|pair| compile_expr(ledger, pair))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Box::new(move |posting| {
+                factors.iter().all(|factor| factor(posting))
+            }))
+        }
+        Rule::factor | Rule::group => {
+            let inner = pair
+                .into_inner()
+                .next()
+                .ok_or(anyhow!("invalid filter expression"))?;
+            compile_expr(ledger, inner)
+        }
+        Rule::negation => {
+            let factor = pair
+                .into_inner()
+                .nth(1)
+                .ok_or(anyhow!("invalid filter negation"))?;
+            let factor = compile_expr(ledger, factor)?;
+            Ok(Box::new(move |posting| !factor(posting)))
+        }
+        Rule::predicate => compile_predicate(ledger, pair),
+        rule => Err(anyhow!("unexpected filter token: {:?}", rule)),
+    }
+}
+
+fn compare(op: &str, ordering: std::cmp::Ordering) -> Result<bool> {
+    use std::cmp::Ordering::*;
+    Ok(match op {
+        ":" | "=" => ordering == Equal,
+        "!=" => ordering != Equal,
+        ">" => ordering == Greater,
+        ">=" => ordering != Less,
+        "<" => ordering == Less,
+        "<=" => ordering != Greater,
+        _ => return Err(anyhow!("invalid comparison `{}'", op)),
+    })
+}
+
+/// Text match, `:` matches a case-insensitive substring, `=` the whole text.
+fn text_matcher(field: &str, op: &str, value: &str) -> Result<impl Fn(&str) -> bool> {
+    let value = value.to_lowercase();
+    let exact = match op {
+        ":" => false,
+        "=" => true,
+        _ => {
+            return Err(anyhow!(
+                "`{}' only supports `:' and `=', got `{}'",
+                field,
+                op
+            ))
+        }
+    };
+    Ok(move |text: &str| {
+        let text = text.to_lowercase();
+        if exact {
+            text == value
+        } else {
+            text.contains(&value)
+        }
+    })
+}
+
+fn compile_predicate(ledger: &Ledger, pair: Pair<Rule>) -> Result<CompiledFilter> {
+    let mut parts = pair.into_inner();
+    let mut next = || parts.next().ok_or(anyhow!("incomplete filter predicate"));
+    let field = next()?.as_str();
+    let op = next()?.as_str().to_string();
+    let value = next()?;
+    let value = match value.as_rule() {
+        Rule::quoted => value
+            .into_inner()
+            .next()
+            .map_or("", |content| content.as_str()),
+        _ => value.as_str(),
+    };
+
+    Ok(match field {
+        "account" => {
+            if op != ":" && op != "=" {
+                return Err(anyhow!("`account' only supports `:' and `=', got `{}'", op));
+            }
+            let mut accounts = HashSet::new();
+            for (account, _) in ledger.accounts().iter() {
+                let name = ledger.account_name(&account)?;
+                if (op == ":" && is_under(&name, value)) || name == value {
+                    accounts.insert(account);
+                }
+            }
+            Box::new(move |posting| accounts.contains(&posting.exchange().account))
+        }
+        "payee" => {
+            let matches = text_matcher(field, &op, value)?;
+            Box::new(move |posting| posting.transaction.payee.as_deref().is_some_and(&matches))
+        }
+        "title" => {
+            let matches = text_matcher(field, &op, value)?;
+            Box::new(move |posting| matches(&posting.transaction.title))
+        }
+        "unit" => {
+            let unit = ledger.unit_lookup(&NaiveDate::MIN, value)?;
+            let equal = match op.as_str() {
+                ":" | "=" => true,
+                "!=" => false,
+                _ => {
+                    return Err(anyhow!(
+                        "`unit' only supports `:', `=', and `!=', got `{}'",
+                        op
+                    ))
+                }
+            };
+            Box::new(move |posting| posting.amount().is_some_and(|a| (a.unit == unit) == equal))
+        }
+        "amount" => {
+            let nominal: Decimal = value.replace('_', "").parse()?;
+            // Reject invalid operators now rather than on every posting.
+            compare(&op, std::cmp::Ordering::Equal)?;
+            Box::new(move |posting| {
+                posting.amount().is_some_and(|amount| {
+                    compare(&op, amount.nominal.cmp(&nominal)).unwrap_or(false)
+                })
+            })
+        }
+        "date" => {
+            let date: NaiveDate = value.parse()?;
+            compare(&op, std::cmp::Ordering::Equal)?;
+            Box::new(move |posting| compare(&op, posting.date.cmp(&date)).unwrap_or(false))
+        }
+        "meta" => {
+            let equal = match op.as_str() {
+                ":" | "=" => true,
+                "!=" => false,
+                _ => {
+                    return Err(anyhow!(
+                        "`meta' only supports `:', `=', and `!=', got `{}'",
+                        op
+                    ))
+                }
+            };
+            let (key, expected) = match value.split_once('=') {
+                Some((key, expected)) => (key.to_string(), Some(expected.to_string())),
+                None => (value.to_string(), None),
+            };
+            Box::new(move |posting| {
+                let found = posting
+                    .exchange()
+                    .metadata
+                    .get(&key)
+                    .or_else(|| posting.transaction.metadata.get(&key))
+                    .is_some_and(|actual| {
+                        expected
+                            .as_ref()
+                            .map_or(true, |expected| actual == expected)
+                    });
+                found == equal
+            })
+        }
+        field => return Err(anyhow!("unknown filter field `{}'", field)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_compiled_filter() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Wallet
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Food:Snacks
+2022-01-01 open Expenses:Travel
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+  Expenses:Food:Snacks      5 USD
+
+2022-01-04 * "Warung" "Lunch"
+  Assets:Wallet
+  Expenses:Food            50_000 IDR
+
+2022-02-10 * "Airline" "Flight"
+  Assets:Bank
+  Expenses:Travel         400 USD
+    reimburse: "ACME"
+"#,
+            None,
+        )?;
+
+        let titles = |query: &str| -> Result<Vec<(String, usize)>> {
+            let filter = ledger.compile_filter(query)?;
+            Ok(ledger
+                .postings()
+                .filter(|posting| filter(posting))
+                .map(|posting| (posting.transaction.title.clone(), posting.index))
+                .collect())
+        };
+        let entry = |title: &str, index| (title.to_string(), index);
+
+        assert_eq!(
+            titles("account:Expenses:Food unit:USD")?,
+            vec![entry("Weekly groceries", 1), entry("Weekly groceries", 2)]
+        );
+        assert_eq!(
+            titles("account=Expenses:Food and not payee:grocer")?,
+            vec![entry("Lunch", 1)]
+        );
+        // The elided posting resolves to -35 USD.
+        assert_eq!(
+            titles("amount<-10 and date<2022-02-01 and unit:USD")?,
+            vec![entry("Weekly groceries", 0)]
+        );
+        assert_eq!(
+            titles(r#"(title:"weekly groc" or meta:reimburse=ACME) -account:Assets"#)?,
+            vec![
+                entry("Weekly groceries", 1),
+                entry("Weekly groceries", 2),
+                entry("Flight", 1)
+            ]
+        );
+
+        assert_eq!(
+            titles("meta!=reimburse=ACME account:Expenses")?,
+            vec![
+                entry("Weekly groceries", 1),
+                entry("Weekly groceries", 2),
+                entry("Lunch", 1)
+            ]
+        );
+        assert_eq!(titles("meta!=reimburse")?.len(), 6);
+
+        assert!(ledger.compile_filter("account>Expenses").is_err());
+        assert!(ledger.compile_filter("meta>reimburse").is_err());
+        assert!(ledger.compile_filter("unit:EUR").is_err());
+        assert!(ledger.compile_filter("color:red").is_err());
+        assert!(ledger.compile_filter("payee:grocer or").is_err());
+
+        Ok(())
+    }
+}
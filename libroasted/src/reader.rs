@@ -0,0 +1,263 @@
+use crate::{
+    account::TxnAccount,
+    annotation::{Annotations, TransactionId},
+    balance::Balances,
+    ledger::Ledger,
+    query::{CompiledFilter, PostingRef},
+    register::{RegisterCursor, RegisterFilter, RegisterPage},
+    reports::{self, Report, ReportSpec},
+    search::SearchHit,
+    transaction::{AssertionFailure, Transaction},
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Read-only queries over a loaded [`Ledger`], see [`Ledger::reader`].
+///
+/// The reader is `Copy`, `Send`, and `Sync`, and nothing behind it uses interior
+/// mutability, so one ledger can be shared by every worker of a thread pool or web server,
+/// e.g. behind an `Arc<Ledger>`, without locking.
+#[derive(Clone, Copy, Debug)]
+pub struct LedgerReader<'l> {
+    ledger: &'l Ledger,
+}
+
+// Checked at compile time, so a field breaking thread safety fails the build instead of
+// the embedding application.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Ledger>();
+    assert_send_sync::<LedgerReader<'static>>();
+    assert_send_sync::<ArcLedger>();
+    assert_send_sync::<CompiledFilter>();
+    assert_send_sync::<Report>();
+};
+
+/// A loaded ledger shared by reference counting, see [`Ledger::into_shared`].
+///
+/// Cloning is cheap and every clone can be moved to another thread. Only the `&self`
+/// queries of the ledger are reachable, through `Deref` or [`ArcLedger::reader`], so a
+/// report server can hand one out to each request without locking.
+#[derive(Clone, Debug)]
+pub struct ArcLedger(Arc<Ledger>);
+
+impl ArcLedger {
+    pub fn new(ledger: Ledger) -> Self {
+        ArcLedger(Arc::new(ledger))
+    }
+
+    pub fn reader(&self) -> LedgerReader<'_> {
+        LedgerReader::new(&self.0)
+    }
+
+    /// The ledger back when this is the last clone, e.g. to apply more statements,
+    /// otherwise `self` is handed back.
+    pub fn try_into_ledger(self) -> Result<Ledger, ArcLedger> {
+        Arc::try_unwrap(self.0).map_err(ArcLedger)
+    }
+}
+
+impl Deref for ArcLedger {
+    type Target = Ledger;
+
+    fn deref(&self) -> &Ledger {
+        &self.0
+    }
+}
+
+impl From<Ledger> for ArcLedger {
+    fn from(ledger: Ledger) -> Self {
+        ArcLedger::new(ledger)
+    }
+}
+
+impl<'l> LedgerReader<'l> {
+    pub fn new(ledger: &'l Ledger) -> Self {
+        Self { ledger }
+    }
+
+    /// The underlying ledger, for queries not exposed by the reader.
+    pub fn ledger(&self) -> &'l Ledger {
+        self.ledger
+    }
+
+    pub fn get_option(&self, key: &str) -> Option<&'l String> {
+        self.ledger.get_option(key)
+    }
+
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        self.ledger.date_range()
+    }
+
+    /// The account with the given name, if it's open at the date.
+    pub fn account(&self, name: &str, date: &NaiveDate) -> Result<TxnAccount> {
+        self.ledger.accounts().txnify(date, &name.try_into()?)
+    }
+
+    pub fn account_name(&self, account: &TxnAccount) -> Result<String> {
+        self.ledger.account_name(account)
+    }
+
+    pub fn unit_name(&self, unit: usize) -> Result<&'l str> {
+        self.ledger.unit_name(unit)
+    }
+
+    /// See [`Ledger::balances_at`].
+    pub fn balances_at(&self, date: &NaiveDate) -> Result<Balances> {
+        self.ledger.balances_at(date)
+    }
+
+    /// See [`Ledger::postings`].
+    pub fn postings(&self) -> impl Iterator<Item = PostingRef<'l>> {
+        self.ledger.postings()
+    }
+
+    /// See [`Ledger::compile_filter`].
+    pub fn compile_filter(&self, query: &str) -> Result<CompiledFilter> {
+        self.ledger.compile_filter(query)
+    }
+
+    /// See [`Ledger::register_page`].
+    pub fn register_page(
+        &self,
+        filter: &RegisterFilter,
+        cursor: Option<&RegisterCursor>,
+        page_size: usize,
+    ) -> Result<RegisterPage<'l>> {
+        self.ledger.register_page(filter, cursor, page_size)
+    }
+
+    /// See [`Ledger::search`].
+    pub fn search(&self, text: &str) -> Vec<SearchHit<'l>> {
+        self.ledger.search(text)
+    }
+
+    /// See [`Ledger::failed_assertions`].
+    pub fn failed_assertions(&self) -> Result<Vec<AssertionFailure>> {
+        self.ledger.failed_assertions()
+    }
+
+    pub fn report(&self, spec: &ReportSpec) -> Result<Report> {
+        reports::run(self.ledger, spec)
+    }
+
+    /// See [`reports::run_all`].
+    pub fn reports(&self, specs: &[ReportSpec]) -> Result<Vec<Report>> {
+        reports::run_all(self.ledger, specs)
+    }
+
+    pub fn transaction_ids(&self, date: &NaiveDate) -> Result<Vec<TransactionId>> {
+        self.ledger.transaction_ids(date)
+    }
+
+    pub fn find_transaction(
+        &self,
+        id: &TransactionId,
+    ) -> Result<Option<(NaiveDate, &'l Transaction)>> {
+        self.ledger.find_transaction(id)
+    }
+
+    pub fn annotations(&self) -> &'l Annotations {
+        self.ledger.annotations()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::thread;
+
+    #[test]
+    fn test_reader_across_threads() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+"#,
+            None,
+        )?;
+        let reader = ledger.reader();
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+
+        let totals = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(move || -> Result<Decimal> {
+                        let food = reader.account("Expenses:Food", &date)?;
+                        let filter = reader.compile_filter("payee:grocer")?;
+                        assert_eq!(reader.postings().filter(|p| filter(p)).count(), 2);
+                        Ok(reader.balances_at(&date)?.get(&food, 0))
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .map_err(|_| anyhow!("reader thread panicked"))?
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+        assert_eq!(totals, vec![dec!(30); 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_arc_ledger() -> Result<()> {
+        let shared = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+"#,
+            None,
+        )?
+        .into_shared();
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let ledger = shared.clone();
+                thread::spawn(move || -> Result<Decimal> {
+                    let bank = ledger.reader().account("Assets:Bank", &date)?;
+                    Ok(ledger.balances_at(&date)?.get(&bank, 0))
+                })
+            })
+            .collect();
+        for handle in handles {
+            let balance = handle
+                .join()
+                .map_err(|_| anyhow!("ledger thread panicked"))??;
+            assert_eq!(balance, dec!(-30));
+        }
+
+        let clone = shared.clone();
+        let Err(shared) = shared.try_into_ledger() else {
+            return Err(anyhow!("ledger taken back while shared"));
+        };
+        drop(clone);
+        let ledger = shared
+            .try_into_ledger()
+            .map_err(|_| anyhow!("ledger still shared"))?;
+        assert_eq!(ledger.transactions_between(..).count(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use crate::calendar::{HolidayCalendar, WeekendCalendar};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Months, NaiveDate};
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+/// Day of the month a recurrence falls on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecurrenceDay {
+    /// The given day, clamped to the last day of shorter months, so `31` falls on the
+    /// 30th in April and on the 28th or 29th in February.
+    Day(u32),
+    /// The last day of the month.
+    Last,
+}
+
+impl RecurrenceDay {
+    /// The day in the given month, `None` only when the month itself is invalid.
+    fn in_month(&self, year: i32, month: u32) -> Option<NaiveDate> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+        match self {
+            RecurrenceDay::Day(day) => last.with_day((*day).min(last.day())),
+            RecurrenceDay::Last => Some(last),
+        }
+    }
+}
+
+/// Where a recurrence falling on a non business day is moved to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusinessDayAdjustment {
+    PreviousBusinessDay,
+    NextBusinessDay,
+}
+
+impl BusinessDayAdjustment {
+    fn adjust<C: HolidayCalendar + ?Sized>(
+        &self,
+        date: NaiveDate,
+        calendar: &C,
+    ) -> Option<NaiveDate> {
+        match self {
+            BusinessDayAdjustment::PreviousBusinessDay => calendar.previous_business_day(date),
+            BusinessDayAdjustment::NextBusinessDay => calendar.next_business_day(date),
+        }
+    }
+}
+
+/// When a recurrence falls within a year, before any business day adjustment.
+///
+/// Days missing from a month are never skipped, they fall on the last day of that month
+/// instead, e.g. `yearly on 02-29` falls on February 28th outside of leap years.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecurrenceRule {
+    Monthly(RecurrenceDay),
+    /// Month of the year, then the day in that month.
+    Yearly(u32, RecurrenceDay),
+}
+
+/// Schedule of a recurring transaction, written as `monthly on 31`, `monthly on last`,
+/// or `yearly on 02-29`, optionally followed by `adjusted previous-business-day` or
+/// `adjusted next-business-day`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Recurrence {
+    pub rule: RecurrenceRule,
+    pub adjustment: Option<BusinessDayAdjustment>,
+}
+
+impl Recurrence {
+    /// Every occurrence within the range, in order, with weekends as the only non
+    /// business days.
+    pub fn occurrences(&self, range: RangeInclusive<NaiveDate>) -> Vec<NaiveDate> {
+        self.occurrences_in(range, &WeekendCalendar)
+    }
+
+    /// Every occurrence within the range, in order, adjusted following the calendar.
+    pub fn occurrences_in<C: HolidayCalendar + ?Sized>(
+        &self,
+        range: RangeInclusive<NaiveDate>,
+        calendar: &C,
+    ) -> Vec<NaiveDate> {
+        let (start, end) = range.into_inner();
+        // Adjustments may move occurrences just outside the range into it.
+        let margin = Months::new(1);
+        let mut month = start
+            .checked_sub_months(margin)
+            .and_then(|date| NaiveDate::from_ymd_opt(date.year(), date.month(), 1));
+        let until = end.checked_add_months(margin).unwrap_or(end);
+
+        let mut dates = Vec::new();
+        while let Some(first) = month.filter(|first| *first <= until) {
+            let date = match self.rule {
+                RecurrenceRule::Monthly(day) => day.in_month(first.year(), first.month()),
+                RecurrenceRule::Yearly(m, day) if m == first.month() => {
+                    day.in_month(first.year(), first.month())
+                }
+                RecurrenceRule::Yearly(..) => None,
+            };
+            let date = match (date, self.adjustment) {
+                (Some(date), Some(adjustment)) => adjustment.adjust(date, calendar),
+                (date, _) => date,
+            };
+            if let Some(date) = date.filter(|date| (start..=end).contains(date)) {
+                dates.push(date);
+            }
+            month = first.checked_add_months(Months::new(1));
+        }
+        dates
+    }
+
+    /// The first occurrence strictly after the given date.
+    pub fn next_after(&self, date: &NaiveDate) -> Option<NaiveDate> {
+        let from = date.succ_opt()?;
+        // Any schedule occurs at least once in a year and a month.
+        let until = from.checked_add_months(Months::new(13))?;
+        self.occurrences(from..=until).into_iter().next()
+    }
+}
+
+fn parse_day(s: &str) -> Result<RecurrenceDay> {
+    if s == "last" {
+        return Ok(RecurrenceDay::Last);
+    }
+    match s.parse()? {
+        day @ 1..=31 => Ok(RecurrenceDay::Day(day)),
+        day => Err(anyhow!("invalid day of month: {}", day)),
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (rule, adjustment) = match words.as_slice() {
+            [rule @ .., "adjusted", adjustment] => (rule, Some(*adjustment)),
+            rule => (rule, None),
+        };
+
+        let rule = match rule {
+            ["monthly", "on", day] => RecurrenceRule::Monthly(parse_day(day)?),
+            ["yearly", "on", date] => {
+                let (month, day) = date
+                    .split_once('-')
+                    .ok_or(anyhow!("invalid yearly recurrence date: `{}'", date))?;
+                match month.parse()? {
+                    month @ 1..=12 => RecurrenceRule::Yearly(month, parse_day(day)?),
+                    month => return Err(anyhow!("invalid month: {}", month)),
+                }
+            }
+            _ => return Err(anyhow!("invalid recurrence: `{}'", s)),
+        };
+        let adjustment = match adjustment {
+            None => None,
+            Some("previous-business-day") => Some(BusinessDayAdjustment::PreviousBusinessDay),
+            Some("next-business-day") => Some(BusinessDayAdjustment::NextBusinessDay),
+            Some(other) => return Err(anyhow!("invalid recurrence adjustment: `{}'", other)),
+        };
+
+        Ok(Recurrence { rule, adjustment })
+    }
+}
+
+impl fmt::Display for RecurrenceDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecurrenceDay::Day(day) => write!(f, "{:02}", day),
+            RecurrenceDay::Last => write!(f, "last"),
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.rule {
+            RecurrenceRule::Monthly(day) => write!(f, "monthly on {}", day)?,
+            RecurrenceRule::Yearly(month, day) => write!(f, "yearly on {:02}-{}", month, day)?,
+        }
+        match self.adjustment {
+            Some(BusinessDayAdjustment::PreviousBusinessDay) => {
+                write!(f, " adjusted previous-business-day")
+            }
+            Some(BusinessDayAdjustment::NextBusinessDay) => {
+                write!(f, " adjusted next-business-day")
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calendar::{HolidayCalendar, WeekendCalendar};
+    use crate::recurrence::{BusinessDayAdjustment, Recurrence, RecurrenceDay, RecurrenceRule};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    fn date(y: i32, m: u32, d: u32) -> Result<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d).ok_or(anyhow!("invalid date"))
+    }
+
+    #[test]
+    fn test_month_end_recurrence() -> Result<()> {
+        let rent: Recurrence = "monthly on 31".parse()?;
+        assert_eq!(
+            rent,
+            Recurrence {
+                rule: RecurrenceRule::Monthly(RecurrenceDay::Day(31)),
+                adjustment: None
+            }
+        );
+        assert_eq!(
+            rent.occurrences(date(2024, 1, 1)?..=date(2024, 4, 30)?),
+            vec![
+                date(2024, 1, 31)?,
+                date(2024, 2, 29)?,
+                date(2024, 3, 31)?,
+                date(2024, 4, 30)?
+            ]
+        );
+        assert_eq!(
+            rent.next_after(&date(2023, 1, 31)?),
+            Some(date(2023, 2, 28)?)
+        );
+
+        let salary: Recurrence = "monthly on last".parse()?;
+        assert_eq!(
+            salary.occurrences(date(2023, 2, 1)?..=date(2023, 3, 31)?),
+            vec![date(2023, 2, 28)?, date(2023, 3, 31)?]
+        );
+        // Occurrences before the start of the range are left out.
+        assert_eq!(
+            "monthly on 15"
+                .parse::<Recurrence>()?
+                .occurrences(date(2024, 1, 20)?..=date(2024, 2, 20)?),
+            vec![date(2024, 2, 15)?]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_leap_day_recurrence() -> Result<()> {
+        let birthday: Recurrence = "yearly on 02-29".parse()?;
+        assert_eq!(birthday.to_string(), "yearly on 02-29");
+        assert_eq!(
+            birthday.occurrences(date(2023, 1, 1)?..=date(2025, 12, 31)?),
+            vec![date(2023, 2, 28)?, date(2024, 2, 29)?, date(2025, 2, 28)?]
+        );
+        assert_eq!(
+            birthday.next_after(&date(2024, 2, 29)?),
+            Some(date(2025, 2, 28)?)
+        );
+
+        assert!("monthly on 32".parse::<Recurrence>().is_err());
+        assert!("yearly on 13-01".parse::<Recurrence>().is_err());
+        assert!("weekly on monday".parse::<Recurrence>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_business_day_adjustment() -> Result<()> {
+        struct Holidays(Vec<NaiveDate>);
+        impl HolidayCalendar for Holidays {
+            fn is_business_day(&self, date: &NaiveDate) -> bool {
+                WeekendCalendar.is_business_day(date) && !self.0.contains(date)
+            }
+        }
+
+        let salary: Recurrence = "monthly on 27 adjusted previous-business-day".parse()?;
+        assert_eq!(
+            salary.adjustment,
+            Some(BusinessDayAdjustment::PreviousBusinessDay)
+        );
+        assert_eq!(
+            salary.to_string(),
+            "monthly on 27 adjusted previous-business-day"
+        );
+        // 2024-04-27 is a Saturday, 2024-07-27 a Saturday too.
+        assert_eq!(
+            salary.occurrences(date(2024, 4, 1)?..=date(2024, 7, 31)?),
+            vec![
+                date(2024, 4, 26)?,
+                date(2024, 5, 27)?,
+                date(2024, 6, 27)?,
+                date(2024, 7, 26)?
+            ]
+        );
+        let holidays = Holidays(vec![date(2024, 5, 27)?]);
+        assert_eq!(
+            salary.occurrences_in(date(2024, 5, 1)?..=date(2024, 5, 31)?, &holidays),
+            vec![date(2024, 5, 24)?]
+        );
+
+        // A debit on the last day of August 2024, a Saturday, moves into September.
+        let debit: Recurrence = "monthly on last adjusted next-business-day".parse()?;
+        assert_eq!(
+            debit.occurrences(date(2024, 9, 1)?..=date(2024, 9, 30)?),
+            vec![date(2024, 9, 2)?, date(2024, 9, 30)?]
+        );
+
+        assert!("monthly on 27 adjusted sometime"
+            .parse::<Recurrence>()
+            .is_err());
+
+        Ok(())
+    }
+}
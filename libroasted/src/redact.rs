@@ -0,0 +1,366 @@
+use crate::{
+    amount::{checked_add, checked_mul, Amount},
+    ledger::Ledger,
+    transaction::{BalanceAssertion, Exchange, Transaction},
+    writer,
+};
+use anyhow::Result;
+use rust_decimal::{prelude::Signed, Decimal};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Account metadata read as presentation hints, kept as is since they carry no personal
+/// data and must stay parseable.
+const HINT_KEYS: &[&str] = &["color", "order", "hidden"];
+
+/// How amounts are disguised in a redacted export.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AmountRedaction {
+    Keep,
+    /// Every amount is multiplied by the factor, balance assertions keep holding.
+    Scale(Decimal),
+    /// Every amount is replaced by its order of magnitude, e.g. `-1234.5` becomes `-1000`.
+    /// Single unit transactions are kept balanced, assertions likely won't hold anymore.
+    Bucket,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Redaction {
+    pub amounts: AmountRedaction,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Redaction {
+            amounts: AmountRedaction::Bucket,
+        }
+    }
+}
+
+/// FNV-1a hash, stable across runs and versions unlike the std hashers.
+pub(crate) fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Stable hash of the text, so the same text is always redacted the same way across
+/// runs and versions.
+fn fingerprint(prefix: &str, text: &str) -> String {
+    format!("{}-{:08x}", prefix, fnv1a(text) as u32)
+}
+
+impl Redaction {
+    fn nominal(&self, nominal: Decimal) -> Result<Decimal> {
+        match self.amounts {
+            AmountRedaction::Keep => Ok(nominal),
+            AmountRedaction::Scale(factor) => checked_mul(nominal, factor),
+            AmountRedaction::Bucket if nominal.is_zero() => Ok(Decimal::ZERO),
+            AmountRedaction::Bucket => {
+                // Digits of the mantissa left of the scale give the order of magnitude.
+                let abs = nominal.abs().normalize();
+                let exponent = i64::from(abs.mantissa().ilog10()) - i64::from(abs.scale());
+                let magnitude = match u32::try_from(exponent) {
+                    Ok(exponent) => Decimal::from_i128_with_scale(10i128.pow(exponent), 0),
+                    Err(_) => Decimal::new(1, exponent.unsigned_abs() as u32),
+                };
+                Ok(magnitude * nominal.signum())
+            }
+        }
+    }
+
+    fn amount(&self, amount: &Amount) -> Result<Amount> {
+        Ok(Amount {
+            nominal: self.nominal(amount.nominal)?,
+            unit: amount.unit,
+        })
+    }
+
+    fn transaction(&self, txn: &Transaction) -> Result<Transaction> {
+        let mut exchanges: Vec<Exchange> = txn
+            .exchanges
+            .iter()
+            .map(|exchange| {
+                Ok(Exchange {
+                    account: exchange.account.clone(),
+                    amount: exchange
+                        .amount
+                        .as_ref()
+                        .map(|amount| self.amount(amount))
+                        .transpose()?,
+                    amount_text: None,
+                    elided: exchange.elided,
+                    metadata: exchange
+                        .metadata
+                        .iter()
+                        .map(|(key, value)| (key.clone(), fingerprint("value", value)))
+                        .collect(),
+                    price: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        // Let the elided posting, or else the last one, absorb what bucketing skewed.
+        let units: Vec<Option<usize>> = exchanges
+            .iter()
+            .map(|e| e.amount.as_ref().map(|a| a.unit))
+            .collect();
+        let single_unit = units.iter().all(|unit| unit.is_some() && *unit == units[0]);
+        if self.amounts == AmountRedaction::Bucket && single_unit && !exchanges.is_empty() {
+            let absorbing = exchanges
+                .iter()
+                .position(|e| e.elided)
+                .unwrap_or(exchanges.len() - 1);
+            let rest = exchanges
+                .iter()
+                .enumerate()
+                .filter(|&(idx, _)| idx != absorbing)
+                .filter_map(|(_, e)| e.amount.as_ref())
+                .try_fold(Decimal::ZERO, |rest, a| checked_add(rest, a.nominal))?;
+            if let Some(amount) = &mut exchanges[absorbing].amount {
+                amount.nominal = -rest;
+            }
+        }
+
+        Ok(Transaction {
+            state: txn.state,
+            payee: txn
+                .payee
+                .as_deref()
+                .map(|payee| fingerprint("payee", payee)),
+            title: fingerprint("title", &txn.title),
+            exchanges,
+            tags: txn.tags.iter().map(|tag| fingerprint("tag", tag)).collect(),
+            links: txn
+                .links
+                .iter()
+                .map(|link| fingerprint("link", link))
+                .collect(),
+            metadata: txn
+                .metadata
+                .iter()
+                .map(|(key, value)| (key.clone(), fingerprint("value", value)))
+                .collect(),
+        })
+    }
+
+    fn assertion(&self, assertion: &BalanceAssertion) -> Result<BalanceAssertion> {
+        Ok(BalanceAssertion {
+            account: assertion.account.clone(),
+            amounts: assertion
+                .amounts
+                .iter()
+                .map(|amount| self.amount(amount))
+                .collect::<Result<_>>()?,
+            severity: assertion.severity,
+        })
+    }
+}
+
+/// Render the ledger with personal data disguised, see [`Ledger::redacted`].
+pub fn redacted(ledger: &Ledger, redaction: &Redaction) -> Result<String> {
+    let mut out = String::new();
+    for (key, value) in ledger.options() {
+        writeln!(out, "option \"{}\" \"{}\"", key, value)?;
+    }
+    for unit in ledger.unit_infos() {
+        writeln!(out, "{}", unit)?;
+    }
+    for (date, prices) in ledger.pricebooks() {
+        for (unit, rates) in prices {
+            for (to, rate) in rates {
+                write!(out, "{} price {} ", date, ledger.unit_name(*unit)?)?;
+                writer::write_amount(
+                    &mut out,
+                    ledger,
+                    &Amount {
+                        nominal: *rate,
+                        unit: *to,
+                    },
+                )?;
+                writeln!(out)?;
+            }
+        }
+    }
+
+    let mut opens: Vec<_> = ledger.accounts().iter().collect();
+    opens.sort_by_key(|(_, activities)| *activities.opened_at());
+    for (account, activities) in &opens {
+        writeln!(
+            out,
+            "{} open {}",
+            activities.opened_at(),
+            ledger.account_name(account)?
+        )?;
+        for (key, value) in ledger.account_metadata(account).into_iter().flatten() {
+            let value = if HINT_KEYS.contains(&key.as_str()) {
+                value.clone()
+            } else {
+                fingerprint("value", value)
+            };
+            writeln!(out, "  {}: \"{}\"", key, value)?;
+        }
+    }
+    out.push('\n');
+
+    let mut dates: Vec<_> = ledger.bookings().keys().copied().collect();
+    dates.extend(ledger.checkpoints().keys().copied());
+    dates.sort();
+    dates.dedup();
+    for date in &dates {
+        if let Some(entries) = ledger.get_checkpoint_on(date) {
+            let entries: Vec<BalanceAssertion> = entries
+                .iter()
+                .map(|entry| redaction.assertion(entry))
+                .collect::<Result<_>>()?;
+            writer::write_checkpoint(&mut out, ledger, date, &entries)?;
+            out.push('\n');
+        }
+        let Some(book) = ledger.get_bookings_on(date) else {
+            continue;
+        };
+        for pad in book.pads() {
+            writer::write_pad(&mut out, ledger, date, pad)?;
+            out.push('\n');
+        }
+        for txn in book.transactions() {
+            writer::write_transaction(&mut out, ledger, date, &redaction.transaction(txn)?)?;
+            out.push('\n');
+        }
+        for assertion in book.balance_assertions() {
+            writer::write_balance_assertion(
+                &mut out,
+                ledger,
+                date,
+                &redaction.assertion(assertion)?,
+            )?;
+            out.push('\n');
+        }
+        for change in book.balance_changes() {
+            writer::write_balance_change(&mut out, ledger, date, &redaction.assertion(change)?)?;
+            out.push('\n');
+        }
+    }
+
+    let mut closes: BTreeMap<_, Vec<String>> = BTreeMap::new();
+    for (account, activities) in &opens {
+        if let Some(closed) = activities.closed_at() {
+            closes
+                .entry(*closed)
+                .or_default()
+                .push(ledger.account_name(account)?);
+        }
+    }
+    for (date, accounts) in closes {
+        for account in accounts {
+            writeln!(out, "{} close {}", date, account)?;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::TxnAccount;
+    use crate::parser;
+    use crate::redact::{AmountRedaction, Redaction};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    const LEDGER: &str = r#"
+unit USD
+2022-01-01 open Assets:Bank
+  order: "1"
+2022-01-01 open Expenses:Rent
+2022-01-01 open Expenses:Food
+2022-01-01 open Income:Salary
+
+2022-01-25 * "ACME Corp" "January salary"
+  Income:Salary       -4321.5 USD
+  Assets:Bank          4321.5 USD
+
+2022-01-28 * "Landlord" "Rent"
+  Assets:Bank
+  Expenses:Rent        1250 USD
+    reimburse: "Bob"
+
+2022-02-01 * "Landlord" "Rent deposit"
+  Assets:Bank
+  Expenses:Rent         320 USD
+
+2022-02-01 balance Assets:Bank 3071.5 USD
+"#;
+
+    #[test]
+    fn test_redacted_scale() -> Result<()> {
+        let ledger = parser::parse(LEDGER, None)?;
+        let redacted = ledger.redacted(&Redaction {
+            amounts: AmountRedaction::Scale(dec!(2)),
+        })?;
+
+        for secret in ["ACME", "January", "Landlord", "Bob", "4321.5", "1250"] {
+            assert!(!redacted.contains(secret), "{} leaked", secret);
+        }
+        assert!(redacted.contains("order: \"1\""));
+
+        let reparsed = parser::parse(&redacted, None)?;
+        assert!(reparsed.failed_assertions()?.is_empty());
+        let date = NaiveDate::from_ymd_opt(2022, 2, 1).ok_or(anyhow!("invalid date"))?;
+        assert_eq!(
+            reparsed
+                .balances_at(&date)?
+                .get(&TxnAccount::Assets(vec![0]), 0),
+            dec!(5503)
+        );
+        // The same payee is always redacted to the same text.
+        assert_eq!(reparsed.payees().count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_redacted_bucket() -> Result<()> {
+        let ledger = parser::parse(LEDGER, None)?;
+        let reparsed = parser::parse(&ledger.redacted(&Redaction::default())?, None)?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 25).ok_or(anyhow!("invalid date"))?;
+        let salary = &reparsed
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions()[0];
+        let amounts: Vec<Option<Decimal>> = salary
+            .exchanges
+            .iter()
+            .map(|e| e.amount.as_ref().map(|a| a.nominal))
+            .collect();
+        assert_eq!(amounts, vec![Some(dec!(-1000)), Some(dec!(1000))]);
+
+        Ok(())
+    }
+    #[test]
+    fn test_redacted_overflow() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Income:Salary
+
+2022-01-25 * "ACME" "Salary"
+  Income:Salary
+  Assets:Bank        50000000000000000000000000000 USD
+"#,
+            None,
+        )?;
+        let err = ledger
+            .redacted(&Redaction {
+                amounts: AmountRedaction::Scale(dec!(2)),
+            })
+            .expect_err("scaled amount overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+}
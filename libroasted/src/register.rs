@@ -0,0 +1,298 @@
+use crate::{
+    ledger::{Ledger, ReferenceLookup},
+    reports::is_under,
+    transaction::{Exchange, Transaction},
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::{fmt, str::FromStr};
+
+/// Narrow down the postings listed in a register, unset fields match everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegisterFilter {
+    /// Account name, subaccounts included.
+    pub account: Option<String>,
+    /// First date included.
+    pub from: Option<NaiveDate>,
+    /// Last date included.
+    pub until: Option<NaiveDate>,
+    /// Payee, matched case-insensitively.
+    pub payee: Option<String>,
+    /// Unit name, e.g. `IDR`. Postings with an elided amount match if their transaction
+    /// posts the unit elsewhere.
+    pub unit: Option<String>,
+}
+
+impl RegisterFilter {
+    fn matches_transaction(&self, txn: &Transaction, unit: Option<usize>) -> bool {
+        if let Some(payee) = &self.payee {
+            if !txn
+                .payee
+                .as_deref()
+                .is_some_and(|p| p.eq_ignore_ascii_case(payee))
+            {
+                return false;
+            }
+        }
+        unit.map_or(true, |unit| {
+            txn.exchanges
+                .iter()
+                .any(|e| e.amount.as_ref().is_some_and(|a| a.unit == unit))
+        })
+    }
+
+    fn matches_posting(
+        &self,
+        ledger: &Ledger,
+        exchange: &Exchange,
+        unit: Option<usize>,
+    ) -> Result<bool> {
+        if unit.is_some_and(|unit| exchange.amount.as_ref().is_some_and(|a| a.unit != unit)) {
+            return Ok(false);
+        }
+        if let Some(account) = &self.account {
+            if !is_under(&ledger.account_name(&exchange.account)?, account) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Position of a posting within the ledger, postings are ordered by date, then by their
+/// order in the ledger file.
+///
+/// Cursors stay valid as long as the ledger isn't reloaded with changes before the
+/// position, and round-trip through their string form, e.g. `2022-01-05/0/1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RegisterCursor {
+    pub date: NaiveDate,
+    pub transaction: usize,
+    pub posting: usize,
+}
+
+impl fmt::Display for RegisterCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.date, self.transaction, self.posting)
+    }
+}
+
+impl FromStr for RegisterCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split('/');
+        let mut next = || {
+            parts
+                .next()
+                .ok_or(anyhow!("invalid register cursor: `{}'", s))
+        };
+        let cursor = RegisterCursor {
+            date: next()?.parse()?,
+            transaction: next()?.parse()?,
+            posting: next()?.parse()?,
+        };
+        if parts.next().is_some() {
+            return Err(anyhow!("invalid register cursor: `{}'", s));
+        }
+        Ok(cursor)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterEntry<'l> {
+    pub cursor: RegisterCursor,
+    pub transaction: &'l Transaction,
+    pub exchange: &'l Exchange,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterPage<'l> {
+    pub entries: Vec<RegisterEntry<'l>>,
+    /// Cursor of the first posting of the next page, `None` on the last page.
+    pub next: Option<RegisterCursor>,
+}
+
+/// List a page of postings matching the filter, see [`Ledger::register_page`].
+pub fn register_page<'l>(
+    ledger: &'l Ledger,
+    filter: &RegisterFilter,
+    cursor: Option<&RegisterCursor>,
+    page_size: usize,
+) -> Result<RegisterPage<'l>> {
+    if page_size == 0 {
+        return Err(anyhow!("page size has to be at least 1"));
+    }
+    let start = match (cursor, filter.from) {
+        (Some(cursor), Some(from)) => cursor.date.max(from),
+        (Some(cursor), None) => cursor.date,
+        (None, Some(from)) => from,
+        (None, None) => NaiveDate::MIN,
+    };
+    let unit = filter
+        .unit
+        .as_deref()
+        .map(|unit| ledger.unit_lookup(&start, unit))
+        .transpose()?;
+
+    let mut entries = Vec::with_capacity(page_size);
+    for (date, book) in ledger.bookings().range(start..) {
+        if filter.until.is_some_and(|until| *date > until) {
+            break;
+        }
+        for (txn_idx, txn) in book.transactions().iter().enumerate() {
+            if !filter.matches_transaction(txn, unit) {
+                continue;
+            }
+            for (posting_idx, exchange) in txn.exchanges.iter().enumerate() {
+                let position = RegisterCursor {
+                    date: *date,
+                    transaction: txn_idx,
+                    posting: posting_idx,
+                };
+                if cursor.is_some_and(|cursor| position < *cursor)
+                    || !filter.matches_posting(ledger, exchange, unit)?
+                {
+                    continue;
+                }
+                if entries.len() == page_size {
+                    return Ok(RegisterPage {
+                        entries,
+                        next: Some(position),
+                    });
+                }
+                entries.push(RegisterEntry {
+                    cursor: position,
+                    transaction: txn,
+                    exchange,
+                });
+            }
+        }
+    }
+
+    Ok(RegisterPage {
+        entries,
+        next: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::register::{RegisterCursor, RegisterFilter};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_register_page() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Food:Snacks
+2022-01-01 open Expenses:Car
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+  Expenses:Food:Snacks      5 USD
+
+2022-01-03 * "Fuel"
+  Assets:Bank
+  Expenses:Car             40 USD
+
+2022-01-10 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            28 USD
+
+2022-02-10 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            32 USD
+"#,
+            None,
+        )?;
+
+        let filter = RegisterFilter {
+            account: Some("Expenses:Food".to_string()),
+            ..Default::default()
+        };
+        let first = ledger.register_page(&filter, None, 2)?;
+        assert_eq!(first.entries.len(), 2);
+        assert_eq!(first.entries[1].cursor.posting, 2);
+
+        let next = first.next.ok_or(anyhow!("no next page"))?;
+        let cursor: RegisterCursor = next.to_string().parse()?;
+        assert_eq!(cursor, next);
+        assert_eq!(cursor.to_string(), "2022-01-10/0/1");
+
+        let second = ledger.register_page(&filter, Some(&cursor), 2)?;
+        assert_eq!(second.entries.len(), 2);
+        assert_eq!(second.next, None);
+        assert_eq!(
+            second.entries[1].cursor.date,
+            NaiveDate::from_ymd_opt(2022, 2, 10).ok_or(anyhow!("invalid date"))?
+        );
+
+        let january = RegisterFilter {
+            payee: Some("grocer".to_string()),
+            until: NaiveDate::from_ymd_opt(2022, 1, 31),
+            ..Default::default()
+        };
+        let page = ledger.register_page(&january, None, 10)?;
+        assert_eq!(page.entries.len(), 5);
+        assert_eq!(page.next, None);
+
+        assert!("2022-01-10/0".parse::<RegisterCursor>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_unit_filter() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Wallet
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Warung" "Lunch"
+  Assets:Wallet
+  Expenses:Food            50_000 IDR
+
+2022-01-04 * "Diner" "Dinner"
+  Assets:Bank
+  Expenses:Food            20 USD
+
+2022-01-05 * "Money changer"
+  Assets:Bank              -100 USD
+  Assets:Wallet            1_500_000 IDR
+"#,
+            None,
+        )?;
+
+        let filter = RegisterFilter {
+            unit: Some("IDR".to_string()),
+            ..Default::default()
+        };
+        let page = ledger.register_page(&filter, None, 10)?;
+        let titles: Vec<&str> = page
+            .entries
+            .iter()
+            .map(|entry| entry.transaction.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Lunch", "Lunch", "Money changer"]);
+
+        let unknown = RegisterFilter {
+            unit: Some("EUR".to_string()),
+            ..Default::default()
+        };
+        assert!(ledger.register_page(&unknown, None, 10).is_err());
+        let err = ledger.register_page(&filter, None, 0).unwrap_err();
+        assert_eq!(err.to_string(), "page size has to be at least 1");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    amount::Amount,
+    balance::Balances,
+    ledger::{Ledger, RatePolicy},
+    transaction::Transaction,
+};
+use anyhow::{anyhow, Result};
+use chrono::{IsoWeek, NaiveDate};
+use rust_decimal::Decimal;
+use std::ops::RangeInclusive;
+use std::thread;
+
+/// Monthly budget per category, with what's left carried into the next month.
+pub mod budget;
+
+/// Balances of several entity ledgers merged together, without transfers between them.
+pub mod consolidation;
+
+/// Balances converted to the operating currency, rounded so they always foot.
+pub mod converted;
+
+/// Transactions posted to catch-all accounts, and categorization coverage per month.
+pub mod coverage;
+
+/// Transfers recorded in several ledgers paired across them, e.g. between partners.
+pub mod interledger;
+
+/// Stretches of days accounts spent below their `min_balance`.
+pub mod overdraft;
+
+/// Reimbursable expenses paired with their reimbursements.
+pub mod reimbursement;
+
+/// Contributions, withdrawals and interest of savings accounts.
+pub mod savings;
+
+/// Per-person positions on shared accounts, and the payments settling them.
+pub mod settlement;
+
+/// Money leaving own accounts per month, transfers between them excluded.
+pub mod spending;
+
+/// Deductible expenses and taxable income of a year per tax category.
+pub mod tax;
+
+/// Compact weekly digest for notification bots: spending, top categories, unusual
+/// expenses and upcoming bills.
+pub mod summary;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReportSpec {
+    Coverage,
+    Balances(NaiveDate),
+    Settlement(NaiveDate),
+    Reimbursements,
+    /// Balances at the date converted to the operating currency, optionally only under
+    /// the given account, with days without a price filled by the policy.
+    Converted(NaiveDate, Option<String>, RatePolicy),
+    Spending,
+    Budget,
+    /// Digest of the ISO week, see [`summary::weekly`].
+    Summary(IsoWeek),
+    Overdrafts,
+    /// Savings over the date range, see [`savings::savings`].
+    Savings(RangeInclusive<NaiveDate>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Report {
+    Coverage(coverage::CoverageReport),
+    Balances(NaiveDate, Balances),
+    Settlement(NaiveDate, settlement::SettlementReport),
+    Reimbursements(reimbursement::ReimbursementReport),
+    Converted(NaiveDate, converted::ConvertedReport),
+    Spending(spending::SpendingReport),
+    Budget(budget::BudgetReport),
+    Summary(summary::WeeklySummary),
+    Overdrafts(overdraft::OverdraftReport),
+    Savings(savings::SavingsReport),
+}
+
+/// Whether `name` is the `prefix` account itself or one of its subaccounts.
+pub(crate) fn is_under(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+}
+
+/// Amount of the posting at `idx`, resolving an elided amount when the rest of the
+/// transaction is in a single unit.
+pub(crate) fn posting_amount(txn: &Transaction, idx: usize) -> Option<Amount> {
+    if let Some(amount) = &txn.exchanges[idx].amount {
+        return Some(amount.clone());
+    }
+
+    let mut others = txn.exchanges.iter().filter_map(|e| e.amount.as_ref());
+    let first = others.next()?.clone();
+    others
+        .try_fold(first, |total, amount| total.checked_add(amount))?
+        .checked_mul(Decimal::NEGATIVE_ONE)
+}
+
+pub fn run(ledger: &Ledger, spec: &ReportSpec) -> Result<Report> {
+    match spec {
+        ReportSpec::Coverage => Ok(Report::Coverage(coverage::coverage(ledger)?)),
+        ReportSpec::Balances(date) => Ok(Report::Balances(*date, ledger.balances_at(date)?)),
+        ReportSpec::Settlement(date) => Ok(Report::Settlement(
+            *date,
+            settlement::settlement(ledger, date)?,
+        )),
+        ReportSpec::Reimbursements => Ok(Report::Reimbursements(reimbursement::reimbursements(
+            ledger,
+        )?)),
+        ReportSpec::Converted(date, root, policy) => Ok(Report::Converted(
+            *date,
+            converted::converted(ledger, date, root.as_deref(), *policy)?,
+        )),
+        ReportSpec::Spending => Ok(Report::Spending(spending::spending(ledger)?)),
+        ReportSpec::Budget => Ok(Report::Budget(budget::budget(ledger)?)),
+        ReportSpec::Summary(week) => Ok(Report::Summary(summary::weekly(ledger, *week)?)),
+        ReportSpec::Overdrafts => Ok(Report::Overdrafts(overdraft::overdrafts(ledger)?)),
+        ReportSpec::Savings(range) => Ok(Report::Savings(savings::savings(ledger, range.clone())?)),
+    }
+}
+
+/// Run every given report spec, spreading them across threads.
+///
+/// Reports only read from the ledger, so they are computed concurrently, the results are
+/// returned in the same order as the specs.
+pub fn run_all(ledger: &Ledger, specs: &[ReportSpec]) -> Result<Vec<Report>> {
+    if specs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = specs.len().div_ceil(workers);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = specs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|spec| run(ledger, spec))
+                        .collect::<Result<Vec<Report>>>()
+                })
+            })
+            .collect();
+
+        let mut reports = Vec::with_capacity(specs.len());
+        for handle in handles {
+            reports.extend(
+                handle
+                    .join()
+                    .map_err(|_| anyhow!("report generation thread panicked"))??,
+            );
+        }
+        Ok(reports)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::TxnAccount;
+    use crate::parser;
+    use crate::reports::{run, run_all, Report, ReportSpec};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_run_all() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-02-03 * "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            20 USD
+"#,
+            None,
+        )?;
+
+        let mut specs = vec![ReportSpec::Coverage];
+        for month in 1..=12 {
+            specs.push(ReportSpec::Balances(
+                NaiveDate::from_ymd_opt(2022, month, 28).ok_or(anyhow!("invalid date"))?,
+            ));
+        }
+
+        let reports = run_all(&ledger, &specs)?;
+        assert_eq!(reports.len(), specs.len());
+        for (spec, report) in specs.iter().zip(&reports) {
+            assert_eq!(&run(&ledger, spec)?, report);
+        }
+
+        match &reports[2] {
+            Report::Balances(_, balances) => {
+                assert_eq!(balances.get(&TxnAccount::Expenses(vec![1]), 0), dec!(50))
+            }
+            report => panic!("unexpected report: {:?}", report),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use super::{is_under, posting_amount};
+use crate::{
+    amount::{checked_add, checked_div, checked_mul, checked_sub},
+    ledger::{Ledger, ReferenceLookup},
+    parser,
+};
+use anyhow::{anyhow, Result};
+use chrono::Datelike;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// Option prefix giving a category its share of the monthly budget, e.g.
+/// `option "budget_weight:Expenses:Food" "3"`.
+const WEIGHT_PREFIX: &str = "budget_weight:";
+
+/// What happens to the budget left in a category at the end of the month.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetRollover {
+    /// Carried into the next month, overspending included.
+    Carry,
+    /// Dropped, every month starts from its own allotment.
+    Reset,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetCategory {
+    pub account: String,
+    /// Left over from the previous month.
+    pub carried: Decimal,
+    pub budgeted: Decimal,
+    pub spent: Decimal,
+    /// What's left to spend, carried plus budgeted minus spent.
+    pub available: Decimal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetMonth {
+    pub year: i32,
+    pub month: u32,
+    pub categories: Vec<BudgetCategory>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BudgetReport {
+    /// Unit of the budget, postings in other units aren't counted.
+    pub unit: usize,
+    pub months: Vec<BudgetMonth>,
+}
+
+/// Category weights from the options, `None` without any.
+fn weights(ledger: &Ledger) -> Result<Option<Vec<(String, Decimal)>>> {
+    let weights = ledger
+        .options()
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix(WEIGHT_PREFIX)?, value)))
+        .map(|(account, weight)| {
+            let weight: Decimal = weight
+                .replace('_', "")
+                .parse()
+                .map_err(|_| anyhow!("invalid budget weight for {}: `{}'", account, weight))?;
+            if weight <= Decimal::ZERO {
+                return Err(anyhow!(
+                    "invalid budget weight for {}: `{}'",
+                    account,
+                    weight
+                ));
+            }
+            Ok((parser::parse_account(account)?.to_string(), weight))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((!weights.is_empty()).then_some(weights))
+}
+
+/// Monthly budget per category, envelope style, from the first to the last month of the
+/// ledger.
+///
+/// `option "budget"` sets the amount budgeted every month, e.g. `"1_000 USD"`, split
+/// across the categories set with `option "budget_weight:<account>"` proportionally to
+/// their weight. Each share is rounded to the decimals of the budget amount, the last
+/// category takes the rounding difference so the shares add up to the budget. Postings
+/// to a category account or its subaccounts count as spent, in the most specific
+/// category when they're nested.
+///
+/// `option "budget_rollover"` set to `reset` drops what's left at the end of each month,
+/// by default it's carried into the next one.
+pub fn budget(ledger: &Ledger) -> Result<BudgetReport> {
+    let total = ledger
+        .get_option("budget")
+        .ok_or(anyhow!("no budget option to report on"))?;
+    let total = parser::parse_amount(total)?;
+    let Some(weights) = weights(ledger)? else {
+        return Err(anyhow!(
+            "no budget categories, set some budget_weight options"
+        ));
+    };
+    let rollover = match ledger.get_option("budget_rollover").map(String::as_str) {
+        None | Some("carry") => BudgetRollover::Carry,
+        Some("reset") => BudgetRollover::Reset,
+        Some(other) => return Err(anyhow!("invalid budget_rollover option: `{}'", other)),
+    };
+
+    let Some((start, end)) = ledger.date_range() else {
+        return Ok(BudgetReport::default());
+    };
+    let unit = ledger.unit_lookup(&start, total.unit())?;
+
+    let scale = total.nominal().scale();
+    let weight_sum = weights
+        .iter()
+        .try_fold(Decimal::ZERO, |sum, (_, weight)| checked_add(sum, *weight))?;
+    let mut budgeted: Vec<Decimal> = weights
+        .iter()
+        .map(|(_, weight)| {
+            let share = checked_div(checked_mul(total.nominal(), *weight)?, weight_sum)?;
+            Ok(share.round_dp(scale))
+        })
+        .collect::<Result<_>>()?;
+    let rounding = checked_sub(
+        total.nominal(),
+        budgeted
+            .iter()
+            .try_fold(Decimal::ZERO, |sum, share| checked_add(sum, *share))?,
+    )?;
+    if let Some(last) = budgeted.last_mut() {
+        *last = checked_add(*last, rounding)?;
+    }
+
+    let mut spent: BTreeMap<((i32, u32), usize), Decimal> = BTreeMap::new();
+    for (date, book) in ledger.bookings() {
+        for txn in book.transactions() {
+            for (idx, exchange) in txn.exchanges.iter().enumerate() {
+                let Some(amount) = posting_amount(txn, idx).filter(|a| a.unit == unit) else {
+                    continue;
+                };
+                let name = ledger.account_name(&exchange.account)?;
+                // Nested categories count toward the most specific one.
+                let Some((category, _)) = weights
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (account, _))| is_under(&name, account))
+                    .max_by_key(|(_, (account, _))| account.len())
+                else {
+                    continue;
+                };
+                let spent = spent
+                    .entry(((date.year(), date.month()), category))
+                    .or_default();
+                *spent = checked_add(*spent, amount.nominal)?;
+            }
+        }
+    }
+
+    let mut report = BudgetReport {
+        unit,
+        months: Vec::new(),
+    };
+    let mut carried = vec![Decimal::ZERO; weights.len()];
+    let (mut year, mut month) = (start.year(), start.month());
+    while (year, month) <= (end.year(), end.month()) {
+        let categories = weights
+            .iter()
+            .enumerate()
+            .map(|(idx, (account, _))| {
+                let spent = spent
+                    .get(&((year, month), idx))
+                    .copied()
+                    .unwrap_or_default();
+                let available = checked_sub(checked_add(carried[idx], budgeted[idx])?, spent)?;
+                let category = BudgetCategory {
+                    account: account.clone(),
+                    carried: carried[idx],
+                    budgeted: budgeted[idx],
+                    spent,
+                    available,
+                };
+                carried[idx] = match rollover {
+                    BudgetRollover::Carry => available,
+                    BudgetRollover::Reset => Decimal::ZERO,
+                };
+                Ok(category)
+            })
+            .collect::<Result<_>>()?;
+        report.months.push(BudgetMonth {
+            year,
+            month,
+            categories,
+        });
+        (year, month) = if month == 12 {
+            (year + 1, 1)
+        } else {
+            (year, month + 1)
+        };
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::budget::{budget, BudgetReport};
+    use anyhow::Result;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    const LEDGER: &str = r#"
+option "budget" "100 USD"
+option "budget_weight:Expenses:Food" "2"
+option "budget_weight:Expenses:Fun" "1"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food:Groceries
+2022-01-01 open Expenses:Fun
+2022-01-01 open Expenses:Rent
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food:Groceries    50 USD
+
+2022-01-05 * "Landlord" "Rent"
+  Assets:Bank
+  Expenses:Rent             500 USD
+
+2022-01-20 * "Cinema"
+  Assets:Bank
+  Expenses:Fun               40 USD
+
+2022-03-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food:Groceries    80 USD
+"#;
+
+    fn columns(
+        report: &BudgetReport,
+        category: usize,
+    ) -> Vec<(Decimal, Decimal, Decimal, Decimal)> {
+        report
+            .months
+            .iter()
+            .map(|month| {
+                let c = &month.categories[category];
+                (c.carried, c.budgeted, c.spent, c.available)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_budget_rollover() -> Result<()> {
+        let report = budget(&parser::parse(LEDGER, None)?)?;
+        assert_eq!(report.months.len(), 3);
+        assert_eq!(report.months[0].categories[0].account, "Expenses:Food");
+
+        // Shares are rounded to whole dollars, the last category takes the difference.
+        assert_eq!(
+            columns(&report, 0),
+            vec![
+                (dec!(0), dec!(67), dec!(50), dec!(17)),
+                (dec!(17), dec!(67), dec!(0), dec!(84)),
+                (dec!(84), dec!(67), dec!(80), dec!(71)),
+            ]
+        );
+        // Overspending is carried too.
+        assert_eq!(
+            columns(&report, 1),
+            vec![
+                (dec!(0), dec!(33), dec!(40), dec!(-7)),
+                (dec!(-7), dec!(33), dec!(0), dec!(26)),
+                (dec!(26), dec!(33), dec!(0), dec!(59)),
+            ]
+        );
+
+        let reset = LEDGER.replace("unit USD", "option \"budget_rollover\" \"reset\"\nunit USD");
+        let report = budget(&parser::parse(&reset, None)?)?;
+        assert_eq!(
+            columns(&report, 0)[2],
+            (dec!(0), dec!(67), dec!(80), dec!(-13))
+        );
+
+        let invalid = LEDGER.replace(
+            "\"budget_weight:Expenses:Fun\" \"1\"",
+            "\"budget_weight:Expenses:Fun\" \"-1\"",
+        );
+        assert!(budget(&parser::parse(&invalid, None)?).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_budget_overflow() -> Result<()> {
+        // Twice the budget for the food share is out of the range of `Decimal`.
+        let ledger = LEDGER.replace(
+            "\"budget\" \"100 USD\"",
+            "\"budget\" \"50000000000000000000000000000 USD\"",
+        );
+        let err = budget(&parser::parse(&ledger, None)?).expect_err("budget share overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    amount::{checked_add, checked_sub},
+    ledger::Ledger,
+    reports::posting_amount,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Posting metadata pairing the sides of a transfer between entities.
+const REFERENCE_KEY: &str = "intercompany";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsolidatedBalance {
+    pub account: String,
+    pub unit: String,
+    pub nominal: Decimal,
+}
+
+/// A posting marked with `intercompany` metadata, in one of the entities.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntercompanyPosting {
+    pub entity: String,
+    pub reference: String,
+    pub date: NaiveDate,
+    pub account: String,
+    pub unit: String,
+    pub nominal: Decimal,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConsolidationReport {
+    /// Balances per account name and unit, summed across entities.
+    pub balances: Vec<ConsolidatedBalance>,
+    /// Postings left out of the balances.
+    pub eliminated: Vec<IntercompanyPosting>,
+    /// Postings whose reference has no counterpart in another entity, or whose sides
+    /// don't cancel out. They are kept in the balances.
+    pub unmatched: Vec<IntercompanyPosting>,
+}
+
+/// Merge the balances of each named entity ledger as of the given date, by account name
+/// and unit name.
+///
+/// Transfers between entities are marked with `intercompany: "<id>"` on the postings
+/// which would otherwise be double counted, e.g. the owner draw expense in the business
+/// books and the matching income in the personal books. Postings sharing an id are
+/// eliminated when they come from at least two entities and cancel out per unit.
+///
+/// Like [`Ledger::balances_at`], postings before the latest checkpoint of an entity are
+/// part of the checkpoint, and can't be eliminated.
+pub fn consolidate(entities: &[(&str, &Ledger)], date: &NaiveDate) -> Result<ConsolidationReport> {
+    let mut balances: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+    let mut postings: Vec<IntercompanyPosting> = Vec::new();
+
+    for (entity, ledger) in entities {
+        for (account, units) in ledger.balances_at(date)?.iter() {
+            let account = ledger.account_name(account)?;
+            for (unit, nominal) in units {
+                let balance = balances
+                    .entry((account.clone(), ledger.unit_name(*unit)?.to_string()))
+                    .or_default();
+                *balance = checked_add(*balance, *nominal)?;
+            }
+        }
+
+        let (_, bookings) = ledger.balance_window(date);
+        for (date, book) in bookings {
+            for txn in book.transactions() {
+                for (idx, exchange) in txn.exchanges.iter().enumerate() {
+                    let Some(reference) = exchange.metadata.get(REFERENCE_KEY) else {
+                        continue;
+                    };
+                    let Some(amount) = posting_amount(txn, idx) else {
+                        continue;
+                    };
+                    postings.push(IntercompanyPosting {
+                        entity: entity.to_string(),
+                        reference: reference.clone(),
+                        date: *date,
+                        account: ledger.account_name(&exchange.account)?,
+                        unit: ledger.unit_name(amount.unit)?.to_string(),
+                        nominal: amount.nominal,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut references: BTreeMap<&str, (BTreeSet<&str>, BTreeMap<&str, Decimal>)> = BTreeMap::new();
+    for posting in &postings {
+        let (entities, totals) = references.entry(&posting.reference).or_default();
+        entities.insert(&posting.entity);
+        let total = totals.entry(&posting.unit).or_default();
+        *total = checked_add(*total, posting.nominal)?;
+    }
+    let eliminated: BTreeSet<String> = references
+        .into_iter()
+        .filter(|(_, (entities, totals))| {
+            entities.len() > 1 && totals.values().all(Decimal::is_zero)
+        })
+        .map(|(reference, _)| reference.to_string())
+        .collect();
+
+    let mut report = ConsolidationReport::default();
+    for posting in postings {
+        if !eliminated.contains(&posting.reference) {
+            report.unmatched.push(posting);
+            continue;
+        }
+        let balance = balances
+            .entry((posting.account.clone(), posting.unit.clone()))
+            .or_default();
+        *balance = checked_sub(*balance, posting.nominal)?;
+        report.eliminated.push(posting);
+    }
+
+    report.balances = balances
+        .into_iter()
+        .filter(|(_, nominal)| !nominal.is_zero())
+        .map(|((account, unit), nominal)| ConsolidatedBalance {
+            account,
+            unit,
+            nominal,
+        })
+        .collect();
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::consolidation::consolidate;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_consolidate() -> Result<()> {
+        let business = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Income:Sales
+2022-01-01 open Expenses:Rent
+
+2022-01-05 * "Client" "Invoice paid"
+  Income:Sales
+  Assets:Bank          5000 USD
+
+2022-01-10 * "Owner" "Office rent"
+  Assets:Bank
+  Expenses:Rent        1000 USD
+    intercompany: "rent-2022-01"
+"#,
+            None,
+        )?;
+        let personal = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Income:Rent
+2022-01-01 open Expenses:Food
+
+2022-01-10 * "Business" "Office rent"
+  Assets:Bank          1000 USD
+  Income:Rent
+    intercompany: "rent-2022-01"
+
+2022-01-12 * "Business" "Overpaid rent"
+  Assets:Bank            50 USD
+  Income:Rent
+    intercompany: "rent-2022-01b"
+
+2022-01-15 * "Grocer" "Groceries"
+  Assets:Bank
+  Expenses:Food         200 USD
+"#,
+            None,
+        )?;
+
+        let report = consolidate(
+            &[("business", &business), ("personal", &personal)],
+            &NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?,
+        )?;
+
+        let balances: Vec<(&str, Decimal)> = report
+            .balances
+            .iter()
+            .map(|balance| (balance.account.as_str(), balance.nominal))
+            .collect();
+        assert_eq!(
+            balances,
+            vec![
+                ("Assets:Bank", dec!(4850)),
+                ("Expenses:Food", dec!(200)),
+                ("Income:Rent", dec!(-50)),
+                ("Income:Sales", dec!(-5000)),
+            ]
+        );
+
+        assert_eq!(report.eliminated.len(), 2);
+        assert_eq!(report.unmatched.len(), 1);
+        assert_eq!(report.unmatched[0].reference, "rent-2022-01b");
+        assert_eq!(report.unmatched[0].entity, "personal");
+
+        Ok(())
+    }
+    #[test]
+    fn test_consolidate_overflow() -> Result<()> {
+        let entity = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Income:Sales
+
+2022-01-05 * "Client" "Invoice paid"
+  Income:Sales
+  Assets:Bank          50000000000000000000000000000 USD
+"#,
+            None,
+        )?;
+
+        let err = consolidate(
+            &[("business", &entity), ("personal", &entity)],
+            &NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?,
+        )
+        .expect_err("consolidated balance overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+}
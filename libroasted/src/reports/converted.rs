@@ -0,0 +1,264 @@
+use super::is_under;
+use crate::amount::{checked_add, checked_mul, checked_sub};
+use crate::ledger::{Ledger, RatePolicy};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::prelude::{Decimal, RoundingStrategy, Signed, ToPrimitive};
+use std::collections::BTreeMap;
+
+/// Decimal places rows are rounded to, unless set with `option "report_precision"`.
+const DEFAULT_PRECISION: u32 = 2;
+
+/// How the difference between the rounded total and the sum of rounded rows is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundingResidue {
+    /// Reported as a separate rounding amount, rows keep their own rounding.
+    Disclose,
+    /// Spread across the rows which lost the most to rounding, one step each.
+    Distribute,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvertedRow {
+    pub account: String,
+    pub nominal: Decimal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvertedReport {
+    /// Operating unit every row is converted to.
+    pub unit: usize,
+    pub rows: Vec<ConvertedRow>,
+    /// Disclosed rounding residue, rows plus rounding always add up to the total.
+    pub rounding: Option<Decimal>,
+    pub total: Decimal,
+}
+
+pub(crate) fn round_to(value: Decimal, precision: u32) -> Decimal {
+    value.round_dp_with_strategy(precision, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// Decimal places of converted amounts, `option "report_precision"`.
+pub(crate) fn report_precision(ledger: &Ledger) -> Result<u32> {
+    match ledger.get_option("report_precision") {
+        Some(precision) => Ok(precision.parse()?),
+        None => Ok(DEFAULT_PRECISION),
+    }
+}
+
+/// Balances as of the given date converted to the operating unit, optionally only for
+/// accounts under `root`, one row per account.
+///
+/// Rows are rounded to `option "report_precision"` decimal places (default 2), and the
+/// total is the rounded sum of the exact amounts. `option "rounding_residue"` set to
+/// `distribute` spreads their difference across the rows, otherwise it's disclosed as a
+/// separate rounding amount, so the report always foots.
+///
+/// Units without a price on the date are converted with a rate picked by `policy`.
+pub fn converted(
+    ledger: &Ledger,
+    date: &NaiveDate,
+    root: Option<&str>,
+    policy: RatePolicy,
+) -> Result<ConvertedReport> {
+    let unit = ledger
+        .operating_unit()
+        .ok_or(anyhow!("no operating currency to convert to"))?;
+    let precision = report_precision(ledger)?;
+    let residue = match ledger.get_option("rounding_residue").map(String::as_str) {
+        None | Some("disclose") => RoundingResidue::Disclose,
+        Some("distribute") => RoundingResidue::Distribute,
+        Some(other) => return Err(anyhow!("invalid rounding_residue option: `{}'", other)),
+    };
+
+    let mut exact: BTreeMap<String, Decimal> = BTreeMap::new();
+    for (account, units) in ledger.balances_at(date)?.iter() {
+        let name = ledger.account_name(account)?;
+        if root.is_some_and(|root| !is_under(&name, root)) {
+            continue;
+        }
+        let mut nominal = Decimal::ZERO;
+        for (&from, &value) in units {
+            let rate = ledger
+                .conversion_rate_with(from, unit, date, policy)?
+                .ok_or(anyhow!(
+                    "no conversion rate from {} to {} at {}",
+                    ledger.unit_name(from)?,
+                    ledger.unit_name(unit)?,
+                    date
+                ))?;
+            nominal = checked_add(nominal, checked_mul(value, rate)?)?;
+        }
+        exact.insert(name, nominal);
+    }
+
+    let total = exact
+        .values()
+        .try_fold(Decimal::ZERO, |total, &nominal| checked_add(total, nominal))?;
+    let total = round_to(total, precision);
+    let mut rows: Vec<ConvertedRow> = exact
+        .iter()
+        .map(|(account, &nominal)| ConvertedRow {
+            account: account.clone(),
+            nominal: round_to(nominal, precision),
+        })
+        .collect();
+    let rounded = rows
+        .iter()
+        .try_fold(Decimal::ZERO, |sum, row| checked_add(sum, row.nominal))?;
+    let difference = round_to(checked_sub(total, rounded)?, precision);
+
+    let mut rounding = None;
+    if difference != Decimal::ZERO {
+        match residue {
+            RoundingResidue::Disclose => rounding = Some(difference),
+            RoundingResidue::Distribute => {
+                let step = Decimal::new(1, precision) * difference.signum();
+                let steps = (difference / step).to_usize().unwrap_or_default();
+                // Rows which lost the most in the direction of the residue go first.
+                let mut order: Vec<usize> = (0..rows.len()).collect();
+                let lost =
+                    |idx: &usize| (exact[&rows[*idx].account] - rows[*idx].nominal) * step.signum();
+                order.sort_by_key(|idx| std::cmp::Reverse(lost(idx)));
+                for idx in order.into_iter().cycle().take(steps) {
+                    rows[idx].nominal = round_to(rows[idx].nominal + step, precision);
+                }
+            }
+        }
+    }
+
+    Ok(ConvertedReport {
+        unit,
+        rows,
+        rounding,
+        total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ledger::RatePolicy;
+    use crate::parser;
+    use crate::reports::converted::converted;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    const LEDGER: &str = r#"
+option "operating_currency" "USD"
+unit USD
+unit EUR
+2022-01-01 open Assets:Bank:A
+2022-01-01 open Assets:Bank:B
+2022-01-01 open Assets:Bank:C
+2022-01-01 open Equity:Opening
+2022-01-01 price EUR 1.00333 USD
+
+2022-01-02 * "Opening balances"
+  Equity:Opening
+  Assets:Bank:A       100 EUR
+  Assets:Bank:B       100 EUR
+  Assets:Bank:C       100 EUR
+"#;
+
+    #[test]
+    fn test_converted_rounding_residue() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?;
+
+        let disclosed = converted(
+            &parser::parse(LEDGER, None)?,
+            &date,
+            Some("Assets"),
+            RatePolicy::Previous,
+        )?;
+        let rows: Vec<Decimal> = disclosed.rows.iter().map(|row| row.nominal).collect();
+        assert_eq!(rows, vec![dec!(100.33), dec!(100.33), dec!(100.33)]);
+        assert_eq!(disclosed.total, dec!(301));
+        assert_eq!(disclosed.rounding, Some(dec!(0.01)));
+
+        let input = format!("option \"rounding_residue\" \"distribute\"\n{}", LEDGER);
+        let distributed = converted(
+            &parser::parse(&input, None)?,
+            &date,
+            Some("Assets"),
+            RatePolicy::Previous,
+        )?;
+        let rows: Vec<Decimal> = distributed.rows.iter().map(|row| row.nominal).collect();
+        assert_eq!(rows, vec![dec!(100.34), dec!(100.33), dec!(100.33)]);
+        assert_eq!(distributed.total, dec!(301));
+        assert_eq!(distributed.rounding, None);
+
+        let input = format!("option \"rounding_residue\" \"ignore\"\n{}", LEDGER);
+        assert!(converted(
+            &parser::parse(&input, None)?,
+            &date,
+            None,
+            RatePolicy::Previous
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_converted_rate_policy() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+option "operating_currency" "USD"
+unit USD
+unit EUR
+2022-01-01 open Assets:Bank
+2022-01-01 open Equity:Opening
+2022-01-01 price EUR 1.0 USD
+2022-01-11 price EUR 1.1 USD
+
+2022-01-01 * "Opening balances"
+  Equity:Opening
+  Assets:Bank         100 EUR
+"#,
+            None,
+        )?;
+        let total = |day, policy| -> Result<Decimal> {
+            let date = NaiveDate::from_ymd_opt(2022, 1, day).ok_or(anyhow!("invalid date"))?;
+            Ok(converted(&ledger, &date, None, policy)?.rows[0].nominal)
+        };
+
+        assert_eq!(total(4, RatePolicy::Previous)?, dec!(100));
+        assert_eq!(total(4, RatePolicy::Nearest)?, dec!(100));
+        assert_eq!(total(8, RatePolicy::Nearest)?, dec!(110));
+        assert_eq!(total(4, RatePolicy::Linear)?, dec!(103));
+        assert_eq!(total(11, RatePolicy::Linear)?, dec!(110));
+        assert_eq!(total(20, RatePolicy::Linear)?, dec!(110));
+
+        Ok(())
+    }
+    #[test]
+    fn test_converted_overflow() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+option "operating_currency" "USD"
+unit USD
+2022-01-01 open Assets:Bank:A
+2022-01-01 open Assets:Bank:B
+2022-01-01 open Equity:Opening
+
+2022-01-02 * "Opening balances"
+  Equity:Opening      -50000000000000000000000000000 USD
+  Assets:Bank:A        50000000000000000000000000000 USD
+
+2022-01-03 * "Opening balances"
+  Equity:Opening      -50000000000000000000000000000 USD
+  Assets:Bank:B        50000000000000000000000000000 USD
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?;
+
+        let err = converted(&ledger, &date, Some("Assets"), RatePolicy::Previous)
+            .expect_err("total overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use super::is_under;
+use crate::ledger::Ledger;
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
+
+const DEFAULT_CATCH_ALL: &str = "Expenses:Uncategorized,Income:Uncategorized";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UncategorizedTransaction {
+    pub date: NaiveDate,
+    pub payee: Option<String>,
+    pub title: String,
+    pub account: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonthlyCoverage {
+    pub year: i32,
+    pub month: u32,
+    pub total: usize,
+    pub categorized: usize,
+}
+
+impl MonthlyCoverage {
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            return 100f64;
+        }
+        self.categorized as f64 * 100f64 / self.total as f64
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    pub uncategorized: Vec<UncategorizedTransaction>,
+    pub months: Vec<MonthlyCoverage>,
+}
+
+/// List transactions posting to a catch-all account, along with per month coverage.
+///
+/// Catch-all accounts are read from `option "catch_all_accounts"` as a comma separated
+/// list of account names, subaccounts included, and default to `Expenses:Uncategorized`
+/// and `Income:Uncategorized`.
+pub fn coverage(ledger: &Ledger) -> Result<CoverageReport> {
+    let catch_all: Vec<&str> = ledger
+        .get_option("catch_all_accounts")
+        .map_or(DEFAULT_CATCH_ALL, String::as_str)
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let mut report = CoverageReport::default();
+    let mut months: BTreeMap<(i32, u32), MonthlyCoverage> = BTreeMap::new();
+
+    for (date, book) in ledger.bookings() {
+        for txn in book.transactions() {
+            let month = months
+                .entry((date.year(), date.month()))
+                .or_insert(MonthlyCoverage {
+                    year: date.year(),
+                    month: date.month(),
+                    total: 0,
+                    categorized: 0,
+                });
+            month.total += 1;
+
+            let mut uncategorized = None;
+            for exchange in &txn.exchanges {
+                let name = ledger.account_name(&exchange.account)?;
+                if catch_all.iter().any(|prefix| is_under(&name, prefix)) {
+                    uncategorized = Some(name);
+                    break;
+                }
+            }
+
+            match uncategorized {
+                Some(account) => report.uncategorized.push(UncategorizedTransaction {
+                    date: *date,
+                    payee: txn.payee.clone(),
+                    title: txn.title.clone(),
+                    account,
+                }),
+                None => month.categorized += 1,
+            }
+        }
+    }
+
+    report.months = months.into_values().collect();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::coverage::coverage;
+    use anyhow::Result;
+
+    #[test]
+    fn test_coverage() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Uncategorized
+2022-01-01 open Expenses:Uncategorized:Card
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-04 * "POS 1123"
+  Assets:Bank
+  Expenses:Uncategorized   12 USD
+
+2022-01-05 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            28 USD
+
+2022-02-01 * "POS 8812"
+  Assets:Bank
+  Expenses:Uncategorized:Card   5 USD
+"#,
+            None,
+        )?;
+
+        let report = coverage(&ledger)?;
+        assert_eq!(report.uncategorized.len(), 2);
+        assert_eq!(report.uncategorized[0].title, "POS 1123");
+        assert_eq!(
+            report.uncategorized[1].account,
+            "Expenses:Uncategorized:Card"
+        );
+
+        assert_eq!(report.months.len(), 2);
+        assert_eq!(
+            (report.months[0].total, report.months[0].categorized),
+            (3, 2)
+        );
+        assert_eq!(report.months[1].percentage(), 0f64);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{ledger::Ledger, reports::posting_amount};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+/// Account metadata naming the other ledger the account moves money with.
+const COUNTERPART_KEY: &str = "transfer_with";
+
+/// A posting to an account marked with `transfer_with`, in one of the ledgers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferPosting {
+    pub ledger: String,
+    /// The ledger the other side is expected in.
+    pub counterpart: String,
+    pub date: NaiveDate,
+    pub payee: Option<String>,
+    pub title: String,
+    pub account: String,
+    pub unit: String,
+    pub nominal: Decimal,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InterledgerReport {
+    /// Both sides of each transfer found, in the order of the ledgers given.
+    pub matched: Vec<(TransferPosting, TransferPosting)>,
+    /// Transfers without their other side, by date.
+    pub unmatched: Vec<TransferPosting>,
+}
+
+/// Pair the transfers recorded in each of the named ledgers, e.g. mine and my partner's,
+/// and report those missing their other side.
+///
+/// Transfers are postings to accounts opened with `transfer_with: "<ledger name>"`, e.g.
+/// `Assets:Owed-By-Partner` in mine and `Liabilities:Owed-To-Me` in theirs. Two postings
+/// are the sides of a transfer when each is marked with the other's ledger, they're in the
+/// same unit with opposite amounts, and at most `max_days` apart. The closest date wins
+/// when several could match.
+pub fn reconcile(ledgers: &[(&str, &Ledger)], max_days: u32) -> Result<InterledgerReport> {
+    let mut postings: Vec<TransferPosting> = Vec::new();
+    for (name, ledger) in ledgers {
+        for (date, txn) in ledger.transactions_between(..) {
+            for (idx, exchange) in txn.exchanges.iter().enumerate() {
+                let Some(counterpart) = ledger
+                    .account_metadata(&exchange.account)
+                    .and_then(|metadata| metadata.get(COUNTERPART_KEY))
+                else {
+                    continue;
+                };
+                let Some(amount) = posting_amount(txn, idx) else {
+                    continue;
+                };
+                postings.push(TransferPosting {
+                    ledger: name.to_string(),
+                    counterpart: counterpart.clone(),
+                    date,
+                    payee: txn.payee.clone(),
+                    title: txn.title.clone(),
+                    account: ledger.account_name(&exchange.account)?,
+                    unit: ledger.unit_name(amount.unit)?.to_string(),
+                    nominal: amount.nominal,
+                });
+            }
+        }
+    }
+    postings.sort_by_key(|posting| posting.date);
+
+    let is_pair = |a: &TransferPosting, b: &TransferPosting| {
+        a.counterpart == b.ledger
+            && b.counterpart == a.ledger
+            && a.unit == b.unit
+            && a.nominal == -b.nominal
+            && (a.date - b.date).num_days().unsigned_abs() <= u64::from(max_days)
+    };
+    let order =
+        |posting: &TransferPosting| ledgers.iter().position(|(name, _)| *name == posting.ledger);
+
+    let mut report = InterledgerReport::default();
+    let mut matched = vec![false; postings.len()];
+    for idx in 0..postings.len() {
+        if matched[idx] {
+            continue;
+        }
+        let posting = &postings[idx];
+        let other = (idx + 1..postings.len())
+            .filter(|&other| !matched[other] && is_pair(posting, &postings[other]))
+            .min_by_key(|&other| (postings[other].date - posting.date).num_days());
+        let Some(other) = other else {
+            report.unmatched.push(posting.clone());
+            continue;
+        };
+        matched[other] = true;
+        let (mut first, mut second) = (posting.clone(), postings[other].clone());
+        if order(&second) < order(&first) {
+            std::mem::swap(&mut first, &mut second);
+        }
+        report.matched.push((first, second));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::interledger::reconcile;
+    use anyhow::Result;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_reconcile() -> Result<()> {
+        let mine = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Owed-By-Partner
+  transfer_with: "partner"
+
+2022-01-03 * "Groceries for both"
+  Assets:Bank
+  Assets:Owed-By-Partner        60 USD
+
+2022-01-20 * "Rent share"
+  Assets:Bank
+  Assets:Owed-By-Partner       500 USD
+"#,
+            None,
+        )?;
+        let partner = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Checking
+2022-01-01 open Liabilities:Owed-To-Partner
+  transfer_with: "mine"
+
+2022-01-05 * "Paid back groceries"
+  Assets:Checking
+  Liabilities:Owed-To-Partner  -60 USD
+
+2022-01-06 * "Utilities share"
+  Assets:Checking
+  Liabilities:Owed-To-Partner  -80 USD
+"#,
+            None,
+        )?;
+
+        let report = reconcile(&[("mine", &mine), ("partner", &partner)], 3)?;
+        assert_eq!(report.matched.len(), 1);
+        let (left, right) = &report.matched[0];
+        assert_eq!((left.ledger.as_str(), left.nominal), ("mine", dec!(60)));
+        assert_eq!(
+            (right.ledger.as_str(), right.nominal),
+            ("partner", dec!(-60))
+        );
+        let unmatched: Vec<_> = report
+            .unmatched
+            .iter()
+            .map(|posting| posting.title.as_str())
+            .collect();
+        assert_eq!(unmatched, vec!["Utilities share", "Rent share"]);
+
+        // Too far apart.
+        let report = reconcile(&[("mine", &mine), ("partner", &partner)], 1)?;
+        assert!(report.matched.is_empty());
+        assert_eq!(report.unmatched.len(), 4);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    account::TxnAccount,
+    amount::{checked_add, Amount},
+    ledger::Ledger,
+    parser,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A stretch of days an account spent below its `min_balance`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overdraft {
+    pub account: String,
+    /// First date ending below the threshold.
+    pub start: NaiveDate,
+    /// First date ending back at or above the threshold, `None` when the account is still
+    /// below it at the end of the ledger.
+    pub end: Option<NaiveDate>,
+    /// Lowest end of day balance in the stretch.
+    pub lowest: Amount,
+    pub threshold: Amount,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OverdraftReport {
+    /// By start date, then account.
+    pub overdrafts: Vec<Overdraft>,
+}
+
+struct Watch {
+    name: String,
+    threshold: Amount,
+    balance: Decimal,
+    dip: Option<Overdraft>,
+}
+
+/// The `min_balance` of every account having one, e.g. `min_balance: "100 USD"`.
+fn thresholds(ledger: &Ledger) -> Result<BTreeMap<TxnAccount, Watch>> {
+    let mut watches = BTreeMap::new();
+    for (account, _) in ledger.accounts().iter() {
+        let Some(min_balance) = ledger
+            .account_metadata(&account)
+            .and_then(|metadata| metadata.get("min_balance"))
+        else {
+            continue;
+        };
+        let name = ledger.account_name(&account)?;
+        let parsed = parser::parse_amount(min_balance)
+            .map_err(|err| anyhow!("invalid min_balance of `{}': {}", name, err))?;
+        let unit = ledger
+            .units()
+            .position(|unit| unit == parsed.unit)
+            .ok_or(anyhow!(
+                "unit `{}' of the min_balance of `{}' is not declared",
+                parsed.unit,
+                name
+            ))?;
+        watches.insert(
+            account,
+            Watch {
+                name,
+                threshold: Amount {
+                    nominal: parsed.nominal,
+                    unit,
+                },
+                balance: Decimal::ZERO,
+                dip: None,
+            },
+        );
+    }
+    Ok(watches)
+}
+
+/// List the stretches of days accounts ended below their threshold, set with a
+/// `min_balance: "<amount>"` metadata on their `open` statement.
+///
+/// Thresholds are signed, so a credit card held as a liability gets its limit as a
+/// negative minimum, e.g. `min_balance: "-5000 USD"`. Balances are taken at the end of
+/// each day, from checkpoints and transactions like [`Ledger::balances_at`], future
+/// dated and recurring transactions included, so a forecast written in the ledger warns
+/// about an overdraft before it happens. Only the unit of the threshold is checked.
+pub fn overdrafts(ledger: &Ledger) -> Result<OverdraftReport> {
+    let mut watches = thresholds(ledger)?;
+    let mut report = OverdraftReport::default();
+    if watches.is_empty() {
+        return Ok(report);
+    }
+
+    let dates: BTreeSet<&NaiveDate> = ledger
+        .bookings()
+        .keys()
+        .chain(ledger.checkpoints().keys())
+        .collect();
+    for date in dates {
+        if let Some(entries) = ledger.get_checkpoint_on(date) {
+            for (account, watch) in watches.iter_mut() {
+                watch.balance = entries
+                    .iter()
+                    .filter(|entry| &entry.account == account)
+                    .flat_map(|entry| &entry.amounts)
+                    .filter(|amount| amount.unit == watch.threshold.unit)
+                    .try_fold(Decimal::ZERO, |total, amount| {
+                        checked_add(total, amount.nominal)
+                    })?;
+            }
+        }
+        for txn in ledger
+            .get_bookings_on(date)
+            .into_iter()
+            .flat_map(|book| book.transactions())
+        {
+            for exchange in &txn.exchanges {
+                let (Some(watch), Some(amount)) =
+                    (watches.get_mut(&exchange.account), &exchange.amount)
+                else {
+                    continue;
+                };
+                if amount.unit == watch.threshold.unit {
+                    watch.balance = checked_add(watch.balance, amount.nominal)?;
+                }
+            }
+        }
+
+        for watch in watches.values_mut() {
+            let below = watch.balance < watch.threshold.nominal;
+            match (&mut watch.dip, below) {
+                (Some(dip), true) => {
+                    dip.lowest.nominal = dip.lowest.nominal.min(watch.balance);
+                }
+                (Some(_), false) => {
+                    if let Some(mut dip) = watch.dip.take() {
+                        dip.end = Some(*date);
+                        report.overdrafts.push(dip);
+                    }
+                }
+                (None, true) => {
+                    watch.dip = Some(Overdraft {
+                        account: watch.name.clone(),
+                        start: *date,
+                        end: None,
+                        lowest: Amount {
+                            nominal: watch.balance,
+                            unit: watch.threshold.unit,
+                        },
+                        threshold: watch.threshold.clone(),
+                    });
+                }
+                (None, false) => {}
+            }
+        }
+    }
+
+    report
+        .overdrafts
+        .extend(watches.into_values().filter_map(|watch| watch.dip));
+    report
+        .overdrafts
+        .sort_by(|a, b| (a.start, &a.account).cmp(&(b.start, &b.account)));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::overdraft::overdrafts;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_overdrafts() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+  min_balance: "100 USD"
+2022-01-01 open Liabilities:Card
+  min_balance: "-1000 USD"
+2022-01-01 open Income:Salary
+2022-01-01 open Expenses:Rent
+
+2022-01-01 * "Salary"
+  Income:Salary
+  Assets:Bank             1500 USD
+
+2022-01-03 * "Rent"
+  Assets:Bank
+  Expenses:Rent           1450 USD
+
+2022-01-04 * "Deposit"
+  Liabilities:Card
+  Expenses:Rent           1200 USD
+
+2022-01-05 * "Refund"
+  Expenses:Rent
+  Assets:Bank               20 USD
+
+2022-02-01 * "Salary"
+  Income:Salary
+  Assets:Bank             1500 USD
+"#,
+            None,
+        )?;
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        let report = overdrafts(&ledger)?;
+        assert_eq!(report.overdrafts.len(), 2);
+        let bank = &report.overdrafts[0];
+        assert_eq!(bank.account, "Assets:Bank");
+        assert_eq!((bank.start, bank.end), (date(1, 3)?, Some(date(2, 1)?)));
+        assert_eq!(bank.lowest.nominal, dec!(50));
+        let card = &report.overdrafts[1];
+        assert_eq!(card.account, "Liabilities:Card");
+        assert_eq!((card.start, card.end), (date(1, 4)?, None));
+        assert_eq!(card.threshold.nominal, dec!(-1000));
+
+        let err = parser::parse(
+            "unit USD\n2022-01-01 open Assets:Bank\n  min_balance: \"100 EUR\"\n",
+            None,
+        )
+        .and_then(|ledger| overdrafts(&ledger))
+        .unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "unit `EUR' of the min_balance of `Assets:Bank' is not declared"
+        );
+
+        Ok(())
+    }
+    #[test]
+    fn test_overdrafts_overflow() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+  min_balance: "100 USD"
+2022-01-01 open Income:Salary
+
+2022-01-01 * "Salary"
+  Income:Salary
+  Assets:Bank             50000000000000000000000000000 USD
+
+2022-02-01 * "Salary"
+  Income:Salary
+  Assets:Bank             50000000000000000000000000000 USD
+"#,
+            None,
+        )?;
+
+        let err = overdrafts(&ledger).expect_err("balance overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{amount::Amount, ledger::Ledger, reports::posting_amount};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// Posting metadata naming the person or employer expected to pay the amount back.
+const PARTY_KEY: &str = "reimburse";
+/// Posting metadata pairing a reimbursement with a specific expense.
+const REFERENCE_KEY: &str = "reimburse-ref";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReimbursableExpense {
+    pub date: NaiveDate,
+    pub payee: Option<String>,
+    pub title: String,
+    pub account: String,
+    pub party: String,
+    pub reference: Option<String>,
+    pub amount: Amount,
+    pub reimbursed: Decimal,
+}
+
+impl ReimbursableExpense {
+    pub fn outstanding(&self) -> Decimal {
+        self.amount.nominal - self.reimbursed
+    }
+}
+
+/// Reimbursed amount which couldn't be paired with any outstanding expense.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnmatchedReimbursement {
+    pub date: NaiveDate,
+    pub party: String,
+    pub reference: Option<String>,
+    pub amount: Amount,
+}
+
+/// Total amount still owed by a party, in a single unit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outstanding {
+    pub party: String,
+    pub amount: Amount,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReimbursementReport {
+    pub expenses: Vec<ReimbursableExpense>,
+    pub outstanding: Vec<Outstanding>,
+    pub unmatched: Vec<UnmatchedReimbursement>,
+}
+
+/// Pair reimbursable expenses with their reimbursements, and total what is still owed
+/// per party.
+///
+/// Postings marked with `reimburse: "<party>"` are reimbursable expenses when positive,
+/// and reimbursements from that party when negative. A reimbursement carrying
+/// `reimburse-ref: "<id>"` only settles expenses with the same reference, otherwise it
+/// settles the party's oldest outstanding expenses first.
+pub fn reimbursements(ledger: &Ledger) -> Result<ReimbursementReport> {
+    let mut report = ReimbursementReport::default();
+
+    for (date, book) in ledger.bookings() {
+        for txn in book.transactions() {
+            for (idx, exchange) in txn.exchanges.iter().enumerate() {
+                let Some(party) = exchange.metadata.get(PARTY_KEY) else {
+                    continue;
+                };
+                let Some(amount) = posting_amount(txn, idx) else {
+                    continue;
+                };
+                let reference = exchange.metadata.get(REFERENCE_KEY);
+
+                if amount.nominal > Decimal::ZERO {
+                    report.expenses.push(ReimbursableExpense {
+                        date: *date,
+                        payee: txn.payee.clone(),
+                        title: txn.title.clone(),
+                        account: ledger.account_name(&exchange.account)?,
+                        party: party.clone(),
+                        reference: reference.cloned(),
+                        amount,
+                        reimbursed: Decimal::ZERO,
+                    });
+                    continue;
+                }
+
+                let mut remaining = -amount.nominal;
+                for expense in report.expenses.iter_mut().filter(|expense| {
+                    &expense.party == party
+                        && expense.amount.unit == amount.unit
+                        && reference.map_or(true, |r| expense.reference.as_ref() == Some(r))
+                }) {
+                    let settled = expense.outstanding().min(remaining);
+                    if settled <= Decimal::ZERO {
+                        continue;
+                    }
+                    expense.reimbursed += settled;
+                    remaining -= settled;
+                }
+
+                if remaining > Decimal::ZERO {
+                    report.unmatched.push(UnmatchedReimbursement {
+                        date: *date,
+                        party: party.clone(),
+                        reference: reference.cloned(),
+                        amount: Amount {
+                            nominal: remaining,
+                            unit: amount.unit,
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    let mut outstanding: BTreeMap<(&str, usize), Decimal> = BTreeMap::new();
+    for expense in &report.expenses {
+        *outstanding
+            .entry((expense.party.as_str(), expense.amount.unit))
+            .or_default() += expense.outstanding();
+    }
+    report.outstanding = outstanding
+        .into_iter()
+        .filter(|(_, nominal)| *nominal > Decimal::ZERO)
+        .map(|((party, unit), nominal)| Outstanding {
+            party: party.to_string(),
+            amount: Amount { nominal, unit },
+        })
+        .collect();
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::reimbursement::reimbursements;
+    use anyhow::Result;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_reimbursements() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-03-01 open Assets:Bank
+2022-03-01 open Assets:Cash
+2022-03-01 open Liabilities:Card
+2022-03-01 open Expenses:Travel
+2022-03-01 open Expenses:Food
+
+2022-03-01 * "Airline" "Flight to client"
+  Liabilities:Card
+  Expenses:Travel     400 USD
+    reimburse: "ACME"
+    reimburse-ref: "trip-1"
+
+2022-03-02 * "Hotel" "Client visit"
+  Liabilities:Card
+  Expenses:Travel     300 USD
+    reimburse: "ACME"
+
+2022-03-05 * "Team dinner"
+  Assets:Cash
+  Expenses:Food        90 USD
+    reimburse: "Bob"
+
+2022-03-20 * "ACME" "Expense claim"
+  Assets:Bank         500 USD
+  Expenses:Travel
+    reimburse: "ACME"
+
+2022-03-25 * "ACME" "Flight refund"
+  Assets:Bank          50 USD
+  Expenses:Travel     -50 USD
+    reimburse: "ACME"
+    reimburse-ref: "trip-1"
+"#,
+            None,
+        )?;
+
+        let report = reimbursements(&ledger)?;
+
+        let expenses: Vec<(&str, Decimal)> = report
+            .expenses
+            .iter()
+            .map(|expense| (expense.title.as_str(), expense.outstanding()))
+            .collect();
+        assert_eq!(
+            expenses,
+            vec![
+                ("Flight to client", dec!(0)),
+                ("Client visit", dec!(200)),
+                ("Team dinner", dec!(90))
+            ]
+        );
+
+        let outstanding: Vec<(&str, Decimal)> = report
+            .outstanding
+            .iter()
+            .map(|o| (o.party.as_str(), o.amount.nominal))
+            .collect();
+        assert_eq!(outstanding, vec![("ACME", dec!(200)), ("Bob", dec!(90))]);
+
+        assert_eq!(report.unmatched.len(), 1);
+        assert_eq!(report.unmatched[0].amount.nominal, dec!(50));
+        assert_eq!(report.unmatched[0].reference.as_deref(), Some("trip-1"));
+
+        Ok(())
+    }
+}
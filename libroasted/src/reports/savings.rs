@@ -0,0 +1,172 @@
+use super::posting_amount;
+use crate::{account::TxnAccount, ledger::Ledger};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::RangeInclusive;
+
+/// Running totals of a unit at the end of a date, for charting.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SavingsPoint {
+    pub date: NaiveDate,
+    pub contributions: Decimal,
+    pub withdrawals: Decimal,
+    pub interest: Decimal,
+}
+
+impl SavingsPoint {
+    /// What was actually saved: contributions and interest, less withdrawals.
+    pub fn saved(&self) -> Decimal {
+        self.contributions + self.interest - self.withdrawals
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SavingsSeries {
+    pub unit: usize,
+    /// Cumulative totals on every date with savings activity, the last one being the
+    /// totals of the whole period.
+    pub points: Vec<SavingsPoint>,
+}
+
+impl SavingsSeries {
+    pub fn totals(&self) -> Option<&SavingsPoint> {
+        self.points.last()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SavingsReport {
+    /// Savings accounts, by name.
+    pub accounts: Vec<String>,
+    /// One series per unit, by unit index.
+    pub series: Vec<SavingsSeries>,
+}
+
+/// Split what went in and out of savings accounts over the date range into
+/// contributions, withdrawals and interest, with running totals for charting.
+///
+/// Savings accounts are `Assets` accounts with a `savings: "true"` metadata. Postings
+/// balanced by an `Income` account are interest, other postings are contributions when
+/// positive and withdrawals when negative. Moves between savings accounts are left out,
+/// they don't change what's saved. Withdrawals are positive amounts.
+pub fn savings(ledger: &Ledger, range: RangeInclusive<NaiveDate>) -> Result<SavingsReport> {
+    let mut report = SavingsReport::default();
+    let mut accounts = BTreeSet::new();
+    for (account, _) in ledger.accounts().iter() {
+        if matches!(account, TxnAccount::Assets(_))
+            && ledger
+                .account_metadata(&account)
+                .and_then(|metadata| metadata.get("savings"))
+                .is_some_and(|savings| savings == "true")
+        {
+            report.accounts.push(ledger.account_name(&account)?);
+            accounts.insert(account);
+        }
+    }
+    report.accounts.sort();
+
+    let mut series: BTreeMap<usize, SavingsSeries> = BTreeMap::new();
+    for (date, txn) in ledger.transactions_between(range) {
+        let (saving, counter): (Vec<_>, Vec<_>) = (0..txn.exchanges.len())
+            .partition(|&idx| accounts.contains(&txn.exchanges[idx].account));
+        if saving.is_empty() || counter.is_empty() {
+            continue;
+        }
+        let interest = counter
+            .iter()
+            .any(|&idx| matches!(txn.exchanges[idx].account, TxnAccount::Income(_)));
+
+        for amount in saving
+            .into_iter()
+            .filter_map(|idx| posting_amount(txn, idx))
+        {
+            let unit = series.entry(amount.unit).or_insert_with(|| SavingsSeries {
+                unit: amount.unit,
+                points: Vec::new(),
+            });
+            if unit.points.last().map(|point| point.date) != Some(date) {
+                let mut point = unit.points.last().cloned().unwrap_or_default();
+                point.date = date;
+                unit.points.push(point);
+            }
+            let Some(point) = unit.points.last_mut() else {
+                continue;
+            };
+            match (interest, amount.nominal.is_sign_negative()) {
+                (true, _) => point.interest += amount.nominal,
+                (false, false) => point.contributions += amount.nominal,
+                (false, true) => point.withdrawals -= amount.nominal,
+            }
+        }
+    }
+    report.series = series.into_values().collect();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::savings::savings;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_savings() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2021-12-01 open Assets:Bank
+2021-12-01 open Assets:Savings
+  savings: "true"
+2022-01-01 open Assets:Emergency
+  savings: "true"
+2022-01-01 open Income:Interest
+2022-01-01 open Expenses:Car
+
+2021-12-15 * "Old contribution"
+  Assets:Bank
+  Assets:Savings           999 USD
+
+2022-01-05 * "Contribution"
+  Assets:Bank
+  Assets:Savings           500 USD
+
+2022-01-05 * "Emergency fund"
+  Assets:Savings          -200 USD
+  Assets:Emergency
+
+2022-01-31 * "Interest"
+  Income:Interest
+  Assets:Savings             2.5 USD
+
+2022-02-10 * "Car repair"
+  Assets:Emergency        -150 USD
+  Expenses:Car
+"#,
+            None,
+        )?;
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).ok_or(anyhow!("invalid date"));
+
+        let report = savings(&ledger, date(2022, 1, 1)?..=date(2022, 12, 31)?)?;
+        assert_eq!(report.accounts, vec!["Assets:Emergency", "Assets:Savings"]);
+        assert_eq!(report.series.len(), 1);
+        let points = &report.series[0].points;
+        assert_eq!(
+            points.iter().map(|point| point.date).collect::<Vec<_>>(),
+            vec![date(2022, 1, 5)?, date(2022, 1, 31)?, date(2022, 2, 10)?]
+        );
+        let totals = report.series[0]
+            .totals()
+            .ok_or(anyhow!("no savings totals"))?;
+        assert_eq!(totals.contributions, dec!(500));
+        assert_eq!(totals.withdrawals, dec!(150));
+        assert_eq!(totals.interest, dec!(2.5));
+        assert_eq!(totals.saved(), dec!(352.5));
+        assert_eq!(points[0].saved(), dec!(500));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{amount::Amount, ledger::Ledger};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// Net position of a person in a single unit, positive when the person is owed money.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SharePosition {
+    pub person: String,
+    pub amount: Amount,
+}
+
+/// A single payment, `from` pays `to` the given amount.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settlement {
+    pub from: String,
+    pub to: String,
+    pub amount: Amount,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SettlementReport {
+    pub positions: Vec<SharePosition>,
+    pub settlements: Vec<Settlement>,
+}
+
+/// Compute per-person positions on shared accounts as of the given date, and a plan
+/// settling them with as few payments as possible.
+///
+/// Shared accounts are read from `option "shared_accounts"` as a comma separated list of
+/// account names. The segment right below a shared account names the person, e.g.
+/// `Equity:Shared:Alice:Rent` belongs to `Alice` under `Equity:Shared`. A person's account
+/// is credited with what they paid and debited with their share of the expense.
+pub fn settlement(ledger: &Ledger, date: &NaiveDate) -> Result<SettlementReport> {
+    let roots: Vec<&str> = ledger
+        .get_option("shared_accounts")
+        .map_or("", String::as_str)
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let mut positions: BTreeMap<(usize, String), Decimal> = BTreeMap::new();
+    for (account, units) in ledger.balances_at(date)?.iter() {
+        let name = ledger.account_name(account)?;
+        let person = roots.iter().find_map(|root| {
+            name.strip_prefix(root)?
+                .strip_prefix(':')?
+                .split(':')
+                .next()
+                .map(str::to_string)
+        });
+        let Some(person) = person else {
+            continue;
+        };
+
+        for (unit, nominal) in units {
+            *positions.entry((*unit, person.clone())).or_default() -= nominal;
+        }
+    }
+
+    let mut report = SettlementReport::default();
+    let mut by_unit: BTreeMap<usize, Vec<(String, Decimal)>> = BTreeMap::new();
+    for ((unit, person), nominal) in positions {
+        if nominal.is_zero() {
+            continue;
+        }
+        report.positions.push(SharePosition {
+            person: person.clone(),
+            amount: Amount { nominal, unit },
+        });
+        by_unit.entry(unit).or_default().push((person, nominal));
+    }
+    report.positions.sort_by(|a, b| {
+        a.person
+            .cmp(&b.person)
+            .then(a.amount.unit.cmp(&b.amount.unit))
+    });
+
+    for (unit, mut open) in by_unit {
+        // Largest debtor pays the largest creditor until everything is settled, this
+        // takes at most one payment less than the number of people involved.
+        loop {
+            let creditor = open
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, n))| *n > Decimal::ZERO)
+                .max_by(|(_, (_, a)), (_, (_, b))| a.cmp(b))
+                .map(|(idx, _)| idx);
+            let debtor = open
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, n))| *n < Decimal::ZERO)
+                .min_by(|(_, (_, a)), (_, (_, b))| a.cmp(b))
+                .map(|(idx, _)| idx);
+            let (Some(creditor), Some(debtor)) = (creditor, debtor) else {
+                break;
+            };
+
+            let nominal = open[creditor].1.min(-open[debtor].1);
+            open[creditor].1 -= nominal;
+            open[debtor].1 += nominal;
+            report.settlements.push(Settlement {
+                from: open[debtor].0.clone(),
+                to: open[creditor].0.clone(),
+                amount: Amount { nominal, unit },
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amount::Amount;
+    use crate::parser;
+    use crate::reports::settlement::{settlement, Settlement};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_settlement() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+option "shared_accounts" "Equity:Shared"
+unit USD
+2022-01-01 open Equity:Shared:Alice:Paid
+2022-01-01 open Equity:Shared:Alice:Share
+2022-01-01 open Equity:Shared:Bob:Paid
+2022-01-01 open Equity:Shared:Bob:Share
+2022-01-01 open Equity:Shared:Carol:Share
+
+2022-01-05 * "Landlord" "Rent, paid by Alice"
+  Equity:Shared:Alice:Paid     -900 USD
+  Equity:Shared:Alice:Share     300 USD
+  Equity:Shared:Bob:Share       300 USD
+  Equity:Shared:Carol:Share     300 USD
+
+2022-01-10 * "Grocer" "Groceries, paid by Bob"
+  Equity:Shared:Bob:Paid       -150 USD
+  Equity:Shared:Alice:Share      50 USD
+  Equity:Shared:Bob:Share        50 USD
+  Equity:Shared:Carol:Share      50 USD
+"#,
+            None,
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 31).ok_or(anyhow!("invalid date"))?;
+        let report = settlement(&ledger, &date)?;
+
+        let positions: Vec<(&str, Decimal)> = report
+            .positions
+            .iter()
+            .map(|p| (p.person.as_str(), p.amount.nominal))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                ("Alice", dec!(550)),
+                ("Bob", dec!(-200)),
+                ("Carol", dec!(-350))
+            ]
+        );
+
+        assert_eq!(
+            report.settlements,
+            vec![
+                Settlement {
+                    from: "Carol".to_string(),
+                    to: "Alice".to_string(),
+                    amount: Amount {
+                        nominal: dec!(350),
+                        unit: 0
+                    },
+                },
+                Settlement {
+                    from: "Bob".to_string(),
+                    to: "Alice".to_string(),
+                    amount: Amount {
+                        nominal: dec!(200),
+                        unit: 0
+                    },
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use super::posting_amount;
+use crate::{account::TxnAccount, balance::UnitBalances, ledger::Ledger};
+use anyhow::Result;
+use chrono::Datelike;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonthlySpending {
+    pub year: i32,
+    pub month: u32,
+    /// Money that left own accounts, per unit.
+    pub spent: UnitBalances,
+    /// Transactions skipped for only moving money between own accounts.
+    pub transfers: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpendingReport {
+    pub months: Vec<MonthlySpending>,
+}
+
+/// Sum the money leaving `Assets` and `Liabilities` accounts per month.
+///
+/// Transfers between own accounts, such as paying off a credit card or moving money to
+/// savings, aren't spending and are left out, they are only counted.
+pub fn spending(ledger: &Ledger) -> Result<SpendingReport> {
+    let mut months: BTreeMap<(i32, u32), MonthlySpending> = BTreeMap::new();
+
+    for (date, book) in ledger.bookings() {
+        for txn in book.transactions() {
+            let month = months
+                .entry((date.year(), date.month()))
+                .or_insert(MonthlySpending {
+                    year: date.year(),
+                    month: date.month(),
+                    spent: UnitBalances::new(),
+                    transfers: 0,
+                });
+
+            if txn.is_transfer() {
+                month.transfers += 1;
+                continue;
+            }
+
+            for (idx, exchange) in txn.exchanges.iter().enumerate() {
+                if !matches!(
+                    exchange.account,
+                    TxnAccount::Assets(_) | TxnAccount::Liabilities(_)
+                ) {
+                    continue;
+                }
+                match posting_amount(txn, idx) {
+                    Some(amount) if amount.nominal < Decimal::ZERO => {
+                        *month.spent.entry(amount.unit).or_default() -= amount.nominal;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(SpendingReport {
+        months: months.into_values().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::spending::spending;
+    use anyhow::Result;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_spending_excludes_transfers() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Savings
+2022-01-01 open Liabilities:Card
+2022-01-01 open Expenses:Food
+2022-01-01 open Income:Salary
+
+2022-01-02 * "Salary"
+  Income:Salary          -1000 USD
+  Assets:Bank
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Liabilities:Card
+  Expenses:Food             30 USD
+
+2022-01-10 * "Card payment"
+  Assets:Bank              -30 USD
+  Liabilities:Card
+
+2022-01-11 * "Move to savings"
+  Assets:Bank             -500 USD
+  Assets:Savings
+
+2022-02-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food             20 USD
+"#,
+            None,
+        )?;
+
+        let report = spending(&ledger)?;
+        assert_eq!(report.months.len(), 2);
+        assert_eq!(report.months[0].spent.get(&0), Some(&dec!(30)));
+        assert_eq!(report.months[0].transfers, 2);
+        assert_eq!(report.months[1].spent.get(&0), Some(&dec!(20)));
+        assert_eq!(report.months[1].transfers, 0);
+
+        Ok(())
+    }
+}
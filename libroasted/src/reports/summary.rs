@@ -0,0 +1,320 @@
+use super::converted::{report_precision, round_to};
+use super::posting_amount;
+use crate::{
+    account::TxnAccount,
+    amount::{checked_add, checked_div, checked_mul, Amount},
+    balance::UnitBalances,
+    ledger::{Ledger, RatePolicy},
+    recurrence::Recurrence,
+    transaction::Transaction,
+};
+use anyhow::{anyhow, Result};
+use chrono::{Days, IsoWeek, NaiveDate, Weekday};
+use rust_decimal::Decimal;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Number of categories kept in [`WeeklySummary::top_categories`].
+const TOP_CATEGORIES: usize = 5;
+/// How far back postings count toward the usual amount of an account.
+const HISTORY_DAYS: u64 = 90;
+/// Postings needed in the history before anything is considered unusual.
+const MIN_HISTORY: usize = 3;
+/// How many times the usual amount makes a posting unusual.
+const UNUSUAL_FACTOR: Decimal = Decimal::TWO;
+/// How far after the week bills are announced.
+const UPCOMING_DAYS: u64 = 7;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CategorySpending {
+    pub account: String,
+    /// Spent in the operating unit.
+    pub spent: Decimal,
+}
+
+/// An expense much larger than what its account usually gets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnusualItem {
+    pub date: NaiveDate,
+    pub payee: Option<String>,
+    pub title: String,
+    pub account: String,
+    pub amount: Amount,
+    /// Average of the account postings in the same unit over the previous 90 days.
+    pub usual: Decimal,
+}
+
+/// The next occurrence of a transaction with a `recurrence` metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpcomingBill {
+    pub date: NaiveDate,
+    pub payee: Option<String>,
+    pub title: String,
+    /// Expense of the latest occurrence, if it has one.
+    pub amount: Option<Amount>,
+}
+
+/// What happened over a week, compact enough for a notification, see [`weekly`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeeklySummary {
+    /// Monday of the week.
+    pub start: NaiveDate,
+    /// Sunday of the week.
+    pub end: NaiveDate,
+    /// The operating unit every converted amount is in.
+    pub unit: usize,
+    /// Expenses of the week converted to the operating unit.
+    pub spent: Decimal,
+    /// Expenses without a price to convert them, per unit.
+    pub unconverted: UnitBalances,
+    /// Expense accounts by converted amount spent, largest first.
+    pub top_categories: Vec<CategorySpending>,
+    pub unusual: Vec<UnusualItem>,
+    /// Bills due in the week after this one, by date.
+    pub upcoming: Vec<UpcomingBill>,
+}
+
+/// Monday to Sunday of the week.
+pub(crate) fn days(week: IsoWeek) -> Vec<NaiveDate> {
+    NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon)
+        .map(|monday| monday.iter_days().take(7).collect())
+        .unwrap_or_default()
+}
+
+/// Postings to expense accounts with a positive amount, the transfers between own
+/// accounts left out, in date order.
+fn expenses(
+    ledger: &Ledger,
+    range: Range<NaiveDate>,
+) -> impl Iterator<Item = (NaiveDate, usize, &Transaction, Amount)> {
+    ledger
+        .transactions_between(range)
+        .filter(|(_, txn)| !txn.is_transfer())
+        .flat_map(|(date, txn)| {
+            txn.exchanges
+                .iter()
+                .enumerate()
+                .filter(|(_, exchange)| matches!(exchange.account, TxnAccount::Expenses(_)))
+                .filter_map(move |(idx, _)| {
+                    let amount = posting_amount(txn, idx)?;
+                    (amount.nominal > Decimal::ZERO).then_some((date, idx, txn, amount))
+                })
+        })
+}
+
+/// Summarize the ISO week for a weekly report, e.g. sent by a bot by email or chat:
+/// what was spent, converted to the operating currency with the latest price of each
+/// day, the categories it went to, the expenses standing out, and the bills coming next.
+///
+/// An expense stands out when it's more than twice the average of its account over the
+/// previous 90 days, once the account has at least 3 postings in that period. Bills are
+/// transactions with a `recurrence: "<rule>"` metadata, e.g. `monthly on 1`, the latest
+/// one of each payee and title being announced for its occurrences in the following
+/// week. Amounts are rounded to `option "report_precision"` decimals (default 2).
+pub fn weekly(ledger: &Ledger, week: IsoWeek) -> Result<WeeklySummary> {
+    let days = days(week);
+    let (Some(&start), Some(&end)) = (days.first(), days.last()) else {
+        return Err(anyhow!("invalid week: {:?}", week));
+    };
+    let after = end + Days::new(1);
+    let unit = ledger
+        .operating_unit()
+        .ok_or(anyhow!("no operating currency to summarize in"))?;
+    let precision = report_precision(ledger)?;
+
+    let mut spent = Decimal::ZERO;
+    let mut unconverted = UnitBalances::new();
+    let mut categories: BTreeMap<String, Decimal> = BTreeMap::new();
+    let mut unusual = Vec::new();
+    let history = start - Days::new(HISTORY_DAYS);
+    for (date, idx, txn, amount) in expenses(ledger, start..after) {
+        let account = &txn.exchanges[idx].account;
+        let name = ledger.account_name(account)?;
+        let rate = match amount.unit == unit {
+            true => Some(Decimal::ONE),
+            false => ledger.conversion_rate_with(amount.unit, unit, &date, RatePolicy::Previous)?,
+        };
+        match rate {
+            Some(rate) => {
+                let converted = checked_mul(amount.nominal, rate)?;
+                spent = checked_add(spent, converted)?;
+                let category = categories.entry(name.clone()).or_default();
+                *category = checked_add(*category, converted)?;
+            }
+            None => {
+                let total = unconverted.entry(amount.unit).or_default();
+                *total = checked_add(*total, amount.nominal)?;
+            }
+        }
+
+        let past: Vec<Decimal> = ledger
+            .postings_for_account(account, history..start)
+            .filter_map(|posting| posting.amount())
+            .filter(|past| past.unit == amount.unit && past.nominal > Decimal::ZERO)
+            .map(|past| past.nominal)
+            .collect();
+        if past.len() < MIN_HISTORY {
+            continue;
+        }
+        let total = past
+            .iter()
+            .try_fold(Decimal::ZERO, |total, &nominal| checked_add(total, nominal))?;
+        let usual = checked_div(total, Decimal::from(past.len()))?;
+        if amount.nominal > checked_mul(usual, UNUSUAL_FACTOR)? {
+            unusual.push(UnusualItem {
+                date,
+                payee: txn.payee.clone(),
+                title: txn.title.clone(),
+                account: name,
+                amount,
+                usual: round_to(usual, precision),
+            });
+        }
+    }
+
+    let mut top_categories: Vec<CategorySpending> = categories
+        .into_iter()
+        .map(|(account, spent)| CategorySpending {
+            account,
+            spent: round_to(spent, precision),
+        })
+        .collect();
+    top_categories.sort_by_key(|category| Reverse(category.spent));
+    top_categories.truncate(TOP_CATEGORIES);
+
+    // The latest occurrence of every bill, by payee and title.
+    let mut bills = BTreeMap::new();
+    for (date, txn) in ledger.transactions_between(..after) {
+        if let Some(rule) = txn.metadata.get("recurrence") {
+            let recurrence: Recurrence = rule.parse().map_err(|err| {
+                anyhow!("invalid recurrence of `{}' at {}: {}", txn.title, date, err)
+            })?;
+            bills.insert((txn.payee.clone(), txn.title.clone()), (recurrence, txn));
+        }
+    }
+    let mut upcoming: Vec<UpcomingBill> = bills
+        .into_values()
+        .flat_map(|(recurrence, txn)| {
+            let amount = (0..txn.exchanges.len())
+                .filter(|&idx| matches!(txn.exchanges[idx].account, TxnAccount::Expenses(_)))
+                .find_map(|idx| posting_amount(txn, idx));
+            recurrence
+                .occurrences(after..=end + Days::new(UPCOMING_DAYS))
+                .into_iter()
+                .map(move |date| UpcomingBill {
+                    date,
+                    payee: txn.payee.clone(),
+                    title: txn.title.clone(),
+                    amount: amount.clone(),
+                })
+        })
+        .collect();
+    upcoming.sort_by(|a, b| (a.date, &a.title).cmp(&(b.date, &b.title)));
+
+    Ok(WeeklySummary {
+        start,
+        end,
+        unit,
+        spent: round_to(spent, precision),
+        unconverted,
+        top_categories,
+        unusual,
+        upcoming,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::reports::summary::weekly;
+    use crate::Amount;
+    use anyhow::{anyhow, Result};
+    use chrono::{Datelike, NaiveDate};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_weekly_summary() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+option "operating_currency" "USD"
+unit USD
+unit EUR
+unit JPY
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Savings
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Rent
+2022-01-01 open Expenses:Travel
+
+2022-01-01 price EUR 1.10 USD
+
+2022-01-01 * "Landlord" "Rent"
+  recurrence: "monthly on 1"
+  Assets:Bank
+  Expenses:Rent        1000 USD
+
+2022-01-03 * "Grocer"
+  Assets:Bank
+  Expenses:Food          30 USD
+
+2022-01-10 * "Grocer"
+  Assets:Bank
+  Expenses:Food          40 USD
+
+2022-01-17 * "Grocer"
+  Assets:Bank
+  Expenses:Food          35 USD
+
+2022-01-24 * "Grocer" "Party supplies"
+  Assets:Bank
+  Expenses:Food         120 USD
+
+2022-01-25 * "Bistro"
+  Assets:Bank
+  Expenses:Food          20 EUR
+
+2022-01-26 * "Ryokan"
+  Assets:Bank
+  Expenses:Travel      9000 JPY
+
+2022-01-27 * "Move to savings"
+  Assets:Bank          -500 USD
+  Assets:Savings
+"#,
+            None,
+        )?;
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        let summary = weekly(&ledger, date(1, 26)?.iso_week())?;
+        assert_eq!((summary.start, summary.end), (date(1, 24)?, date(1, 30)?));
+        // 120 USD and 20 EUR, the JPY has no price and the transfer isn't spending.
+        assert_eq!(summary.spent, dec!(142));
+        assert_eq!(summary.unconverted.get(&2), Some(&dec!(9000)));
+        assert_eq!(summary.top_categories.len(), 1);
+        assert_eq!(summary.top_categories[0].account, "Expenses:Food");
+
+        assert_eq!(summary.unusual.len(), 1);
+        assert_eq!(summary.unusual[0].title, "Party supplies");
+        assert_eq!(summary.unusual[0].usual, dec!(35));
+        assert_eq!(
+            summary.unusual[0].amount,
+            Amount {
+                nominal: dec!(120),
+                unit: 0
+            }
+        );
+
+        assert_eq!(summary.upcoming.len(), 1);
+        assert_eq!(summary.upcoming[0].date, date(2, 1)?);
+        assert_eq!(summary.upcoming[0].title, "Rent");
+
+        // Nothing unusual without enough history, and no bill due the week after.
+        let summary = weekly(&ledger, date(1, 10)?.iso_week())?;
+        assert_eq!(summary.spent, dec!(40));
+        assert!(summary.unusual.is_empty());
+        assert!(summary.upcoming.is_empty());
+
+        Ok(())
+    }
+}
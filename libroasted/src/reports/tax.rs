@@ -0,0 +1,179 @@
+use super::posting_amount;
+use crate::{account::TxnAccount, amount::Amount, balance::UnitBalances, ledger::Ledger};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// Metadata key holding the tax category of an account or a posting.
+const TAX_KEY: &str = "tax";
+/// Posting metadata value opting a posting out of its account's category.
+const UNTAXED: &str = "none";
+
+/// A posting counted in a tax category.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaxPosting {
+    pub date: NaiveDate,
+    pub payee: Option<String>,
+    pub title: String,
+    pub account: String,
+    /// As posted, income is negative.
+    pub amount: Amount,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaxCategory {
+    pub category: String,
+    /// Expenses of the category, per unit.
+    pub deductible: UnitBalances,
+    /// Income of the category per unit, as positive amounts.
+    pub taxable: UnitBalances,
+    /// Every posting counted, in date order, to back the totals up.
+    pub postings: Vec<TaxPosting>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaxReport {
+    pub year: i32,
+    /// By category name.
+    pub categories: Vec<TaxCategory>,
+}
+
+/// Category of the account, from its own metadata or the closest ancestor having one.
+fn account_category<'l>(ledger: &'l Ledger, account: &TxnAccount) -> Option<&'l String> {
+    let mut account = Some(account.clone());
+    while let Some(current) = account {
+        if let Some(category) = ledger
+            .account_metadata(&current)
+            .and_then(|metadata| metadata.get(TAX_KEY))
+        {
+            return Some(category);
+        }
+        account = current.parent();
+    }
+    None
+}
+
+/// Expenses and income of the year per tax category, see [`Ledger::tax_report`].
+pub fn tax_report(ledger: &Ledger, year: i32) -> Result<TaxReport> {
+    let start = NaiveDate::from_ymd_opt(year, 1, 1).ok_or(anyhow!("invalid year: {}", year))?;
+    let end = NaiveDate::from_ymd_opt(year, 12, 31).ok_or(anyhow!("invalid year: {}", year))?;
+
+    let mut categories: BTreeMap<String, TaxCategory> = BTreeMap::new();
+    for (date, txn) in ledger.transactions_between(start..=end) {
+        for (idx, exchange) in txn.exchanges.iter().enumerate() {
+            let income = match exchange.account {
+                TxnAccount::Expenses(_) => false,
+                TxnAccount::Income(_) => true,
+                _ => continue,
+            };
+            let category = match exchange.metadata.get(TAX_KEY) {
+                Some(category) if category == UNTAXED => continue,
+                Some(category) => category,
+                None => match account_category(ledger, &exchange.account) {
+                    Some(category) => category,
+                    None => continue,
+                },
+            };
+            let Some(amount) = posting_amount(txn, idx) else {
+                continue;
+            };
+
+            let entry = categories
+                .entry(category.clone())
+                .or_insert_with(|| TaxCategory {
+                    category: category.clone(),
+                    ..Default::default()
+                });
+            match income {
+                true => {
+                    *entry.taxable.entry(amount.unit).or_insert(Decimal::ZERO) -= amount.nominal
+                }
+                false => {
+                    *entry.deductible.entry(amount.unit).or_insert(Decimal::ZERO) += amount.nominal
+                }
+            }
+            entry.postings.push(TaxPosting {
+                date,
+                payee: txn.payee.clone(),
+                title: txn.title.clone(),
+                account: ledger.account_name(&exchange.account)?,
+                amount,
+            });
+        }
+    }
+
+    Ok(TaxReport {
+        year,
+        categories: categories.into_values().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use anyhow::Result;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_tax_report() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Medical
+  tax: "medical"
+2022-01-01 open Expenses:Medical:Dental
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Gifts
+2022-01-01 open Income:Freelance
+  tax: "self-employment"
+
+2022-02-01 * "Clinic"
+  Assets:Bank
+  Expenses:Medical:Dental      300 USD
+
+2022-03-01 * "Pharmacy"
+  Assets:Bank
+  Expenses:Medical              40 USD
+    tax: "none"
+
+2022-04-01 * "Red Cross"
+  Assets:Bank
+  Expenses:Gifts               100 USD
+    tax: "charity"
+  Expenses:Food                 20 USD
+
+2022-05-01 * "Client"
+  Income:Freelance
+  Assets:Bank                 2000 USD
+
+2023-01-10 * "Clinic"
+  Assets:Bank
+  Expenses:Medical:Dental      500 USD
+"#,
+            None,
+        )?;
+
+        let report = ledger.tax_report(2022)?;
+        let names: Vec<_> = report
+            .categories
+            .iter()
+            .map(|category| category.category.as_str())
+            .collect();
+        assert_eq!(names, vec!["charity", "medical", "self-employment"]);
+
+        let medical = &report.categories[1];
+        // Inherited from the parent account, the opted out posting left out.
+        assert_eq!(medical.deductible.get(&0), Some(&dec!(300)));
+        assert_eq!(medical.postings.len(), 1);
+        assert_eq!(medical.postings[0].account, "Expenses:Medical:Dental");
+
+        assert_eq!(report.categories[0].deductible.get(&0), Some(&dec!(100)));
+        let freelance = &report.categories[2];
+        assert_eq!(freelance.taxable.get(&0), Some(&dec!(2000)));
+        assert!(freelance.deductible.is_empty());
+
+        Ok(())
+    }
+}
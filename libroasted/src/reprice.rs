@@ -0,0 +1,187 @@
+use crate::{
+    ledger::{Ledger, RatePolicy, ReferenceLookup},
+    reports::{summary, ReportSpec},
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::BTreeSet;
+
+/// A price of one `unit` in `price_unit` as of the date, like a `price` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quote {
+    pub date: NaiveDate,
+    pub unit: String,
+    pub price: Decimal,
+    pub price_unit: String,
+}
+
+/// What a batch of quotes changed, see [`Ledger::reprice`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Repriced {
+    /// Number of quotes which set a new price or changed an existing one.
+    pub changed: usize,
+    /// Indexes of the given report specs whose result may differ after the batch.
+    pub dirty: Vec<usize>,
+}
+
+impl Repriced {
+    pub fn is_clean(&self) -> bool {
+        self.dirty.is_empty()
+    }
+}
+
+/// The dates and rate policy a report converts amounts with, only those depend on
+/// prices.
+fn priced_at(spec: &ReportSpec) -> Vec<(NaiveDate, RatePolicy)> {
+    match spec {
+        ReportSpec::Converted(date, _, policy) => vec![(*date, *policy)],
+        ReportSpec::Summary(week) => summary::days(*week)
+            .into_iter()
+            .map(|date| (date, RatePolicy::Previous))
+            .collect(),
+        ReportSpec::Coverage
+        | ReportSpec::Balances(_)
+        | ReportSpec::Settlement(_)
+        | ReportSpec::Reimbursements
+        | ReportSpec::Spending
+        | ReportSpec::Budget
+        | ReportSpec::Overdrafts
+        | ReportSpec::Savings(_) => Vec::new(),
+    }
+}
+
+pub(crate) fn reprice(
+    ledger: &mut Ledger,
+    quotes: &[Quote],
+    specs: &[ReportSpec],
+) -> Result<Repriced> {
+    // Resolve every unit first, so a bad quote leaves the ledger untouched.
+    let resolved = quotes
+        .iter()
+        .map(|quote| {
+            Ok((
+                quote.date,
+                ledger.unit_lookup(&quote.date, &quote.unit)?,
+                ledger.unit_lookup(&quote.date, &quote.price_unit)?,
+                quote.price,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Converted reports only look up rates into the operating unit.
+    let operating_unit = ledger.operating_unit();
+    let units: BTreeSet<usize> = resolved
+        .iter()
+        .flat_map(|&(_, unit, price_unit, _)| [unit, price_unit])
+        .collect();
+    let rates = |ledger: &Ledger| -> Result<Vec<Vec<Option<Decimal>>>> {
+        specs
+            .iter()
+            .map(|spec| match operating_unit {
+                Some(to) => priced_at(spec)
+                    .into_iter()
+                    .flat_map(|(date, policy)| {
+                        units
+                            .iter()
+                            .map(move |&from| ledger.conversion_rate_with(from, to, &date, policy))
+                    })
+                    .collect(),
+                None => Ok(Vec::new()),
+            })
+            .collect()
+    };
+
+    let before = rates(ledger)?;
+    let mut changed = 0;
+    for (date, unit, price_unit, price) in resolved {
+        if ledger.set_price(date, unit, price_unit, price) != Some(price) {
+            changed += 1;
+        }
+    }
+    let after = rates(ledger)?;
+
+    Ok(Repriced {
+        changed,
+        dirty: (0..specs.len())
+            .filter(|&idx| before[idx] != after[idx])
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ledger::RatePolicy;
+    use crate::parser;
+    use crate::reports::ReportSpec;
+    use crate::reprice::Quote;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_reprice() -> Result<()> {
+        let mut ledger = parser::parse(
+            r#"
+unit IDR
+unit USD
+unit JPY
+2022-01-01 open Assets:Bank
+2022-01-01 open Equity:Opening
+
+2022-01-01 price USD 15_000 IDR
+
+2022-01-02 * "Opening"
+  Equity:Opening
+  Assets:Bank              10 USD
+"#,
+            None,
+        )?;
+        let date = |d| NaiveDate::from_ymd_opt(2022, 1, d).ok_or(anyhow!("invalid date"));
+        let quote = |d, unit: &str, price| -> Result<Quote> {
+            Ok(Quote {
+                date: date(d)?,
+                unit: unit.to_string(),
+                price,
+                price_unit: "IDR".to_string(),
+            })
+        };
+        let specs = [
+            ReportSpec::Converted(date(5)?, None, RatePolicy::Previous),
+            ReportSpec::Converted(date(15)?, None, RatePolicy::Previous),
+            ReportSpec::Balances(date(15)?),
+        ];
+
+        // A price after the first report only dirties the later one.
+        let repriced = ledger.reprice(&[quote(10, "USD", dec!(15500))?], &specs)?;
+        assert_eq!((repriced.changed, repriced.dirty), (1, vec![1]));
+
+        // Quoting the same price again changes nothing.
+        let repriced = ledger.reprice(&[quote(10, "USD", dec!(15500))?], &specs)?;
+        assert_eq!(repriced.changed, 0);
+        assert!(repriced.is_clean());
+
+        // Overridden by a later price, the earlier report is the only one affected.
+        let repriced = ledger.reprice(&[quote(3, "USD", dec!(14900))?], &specs)?;
+        assert_eq!(repriced.dirty, vec![0]);
+
+        // Prices between other units aren't used to convert into IDR.
+        let cross = Quote {
+            price_unit: "JPY".to_string(),
+            ..quote(12, "USD", dec!(130))?
+        };
+        let repriced = ledger.reprice(&[cross], &specs)?;
+        assert_eq!((repriced.changed, repriced.dirty), (1, vec![]));
+
+        // An unknown unit rejects the whole batch.
+        assert!(ledger
+            .reprice(
+                &[quote(4, "USD", dec!(1))?, quote(4, "EUR", dec!(1))?],
+                &specs
+            )
+            .is_err());
+        assert_eq!(ledger.conversion_rate(1, 0, &date(4)?)?, Some(dec!(14900)));
+
+        Ok(())
+    }
+}
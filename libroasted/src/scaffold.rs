@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::{fmt::Write, str::FromStr};
+
+/// Kind of books a new ledger is started for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScaffoldProfile {
+    /// Day to day cash, a bank account, and a credit card.
+    PersonalCash,
+    /// Personal accounts along with client income, business expenses, and taxes.
+    Freelancer,
+    /// Shared household accounts split between its members.
+    Household,
+}
+
+impl FromStr for ScaffoldProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "personal-cash" => Ok(ScaffoldProfile::PersonalCash),
+            "freelancer" => Ok(ScaffoldProfile::Freelancer),
+            "household" => Ok(ScaffoldProfile::Household),
+            _ => Err(anyhow!(
+                "unknown profile `{}', expected personal-cash, freelancer, or household",
+                s
+            )),
+        }
+    }
+}
+
+const PERSONAL_ACCOUNTS: &[&str] = &[
+    "Assets:Cash",
+    "Assets:Bank:Checking",
+    "Liabilities:CreditCard",
+    "Income:Salary",
+    "Expenses:Food:Groceries",
+    "Expenses:Food:Dining",
+    "Expenses:Housing:Rent",
+    "Expenses:Utilities",
+    "Expenses:Transport",
+    "Expenses:Health",
+    "Expenses:Uncategorized",
+];
+
+const FREELANCER_ACCOUNTS: &[&str] = &[
+    "Assets:Bank:Business",
+    "Assets:Receivable",
+    "Liabilities:Tax:Income",
+    "Income:Clients",
+    "Expenses:Business:Software",
+    "Expenses:Business:Equipment",
+    "Expenses:Business:Fees",
+    "Expenses:Tax",
+];
+
+const HOUSEHOLD_ACCOUNTS: &[&str] = &[
+    "Assets:Bank:Joint",
+    "Equity:Shared:Alice",
+    "Equity:Shared:Bob",
+    "Expenses:Household:Groceries",
+    "Expenses:Household:Rent",
+    "Expenses:Household:Utilities",
+    "Expenses:Household:Internet",
+    "Expenses:Uncategorized",
+];
+
+/// Starter ledger text for the profile, with its account tree opened at the given date
+/// and amounts in the given currency.
+pub fn scaffold(profile: ScaffoldProfile, date: &NaiveDate, currency: &str) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "option \"operating_currency\" \"{}\"", currency)?;
+    writeln!(
+        out,
+        "option \"opening_balance_account\" \"Equity:Opening-Balances\""
+    )?;
+
+    let accounts: Vec<&str> = match profile {
+        ScaffoldProfile::PersonalCash => PERSONAL_ACCOUNTS.to_vec(),
+        ScaffoldProfile::Freelancer => [PERSONAL_ACCOUNTS, FREELANCER_ACCOUNTS].concat(),
+        ScaffoldProfile::Household => {
+            writeln!(out, "option \"shared_accounts\" \"Equity:Shared\"")?;
+            HOUSEHOLD_ACCOUNTS.to_vec()
+        }
+    };
+    writeln!(out, "unit {}", currency)?;
+    out.push('\n');
+
+    writeln!(out, "{} open Equity:Opening-Balances", date)?;
+    for account in accounts {
+        writeln!(out, "{} open {}", date, account)?;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::scaffold::{scaffold, ScaffoldProfile};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_scaffold() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).ok_or(anyhow!("invalid date"))?;
+
+        for profile in ["personal-cash", "freelancer", "household"] {
+            let text = scaffold(profile.parse()?, &date, "EUR")?;
+            let ledger = parser::parse(&text, None)?;
+            assert_eq!(ledger.units().collect::<Vec<_>>(), vec!["EUR"]);
+            assert!(ledger.accounts().iter().count() > 5);
+        }
+
+        let freelancer = scaffold(ScaffoldProfile::Freelancer, &date, "USD")?;
+        assert!(freelancer.contains("2024-01-01 open Income:Clients\n"));
+        assert!(freelancer.contains("2024-01-01 open Assets:Cash\n"));
+        assert!("business".parse::<ScaffoldProfile>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{diagnostic::Diagnostic, ledger::Ledger, reports::is_under};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Structural requirements for a ledger, declared with `custom "schema"` statements or
+/// built in code.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LedgerSchema {
+    pub required_options: Vec<String>,
+    /// Account names, at least one account under each of them must be opened.
+    pub required_accounts: Vec<String>,
+    /// Units allowed to be declared, any unit is allowed when empty.
+    pub allowed_units: Vec<String>,
+}
+
+/// `custom "schema" "require-option" "key"`, `custom "schema" "require-account" "Name"`,
+/// and `custom "schema" "allow-unit" "USD" ...` add to the ledger schema.
+pub fn schema_directive(ledger: &mut Ledger, date: &NaiveDate, args: &[&str]) -> Result<()> {
+    let schema = ledger.schema_mut();
+    match args {
+        ["require-option", key] => schema.required_options.push(key.to_string()),
+        ["require-account", account] => schema.required_accounts.push(account.to_string()),
+        ["allow-unit", units @ ..] if !units.is_empty() => schema
+            .allowed_units
+            .extend(units.iter().map(|unit| unit.to_string())),
+        _ => {
+            return Err(anyhow!(
+                "schema at {} expects require-option, require-account, or allow-unit, got `{}'",
+                date,
+                args.join(" ")
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Check the ledger against the schema, see [`Ledger::validate_schema`].
+pub fn validate(ledger: &Ledger, schema: &LedgerSchema) -> Result<Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+
+    for key in &schema.required_options {
+        if ledger.get_option(key).is_none() {
+            diagnostics.push(Diagnostic::error(
+                "missing-option",
+                format!("required option `{}' is not set", key),
+            ));
+        }
+    }
+
+    let accounts = ledger
+        .accounts()
+        .iter()
+        .map(|(account, _)| ledger.account_name(&account))
+        .collect::<Result<Vec<String>>>()?;
+    for required in &schema.required_accounts {
+        if !accounts.iter().any(|name| is_under(name, required)) {
+            diagnostics.push(Diagnostic::error(
+                "missing-account",
+                format!("no account opened under required account `{}'", required),
+            ));
+        }
+    }
+
+    if !schema.allowed_units.is_empty() {
+        for unit in ledger.units() {
+            if !schema.allowed_units.iter().any(|allowed| allowed == unit) {
+                diagnostics.push(Diagnostic::error(
+                    "unit-not-allowed",
+                    format!("unit `{}' is not allowed by the schema", unit),
+                ));
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::schema::LedgerSchema;
+    use anyhow::Result;
+
+    #[test]
+    fn test_validate_schema() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+option "operating_currency" "USD"
+unit USD
+unit JPY
+2022-01-01 custom "schema" "require-option" "operating_currency"
+2022-01-01 custom "schema" "require-option" "fx_gain_account"
+2022-01-01 custom "schema" "require-account" "Assets:Bank"
+2022-01-01 custom "schema" "require-account" "Liabilities:Card"
+2022-01-01 custom "schema" "allow-unit" "USD" "EUR"
+2022-01-01 open Assets:Bank:Checking
+"#,
+            None,
+        )?;
+
+        let codes: Vec<(&str, String)> = ledger
+            .validate_schema(ledger.schema())?
+            .into_iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.message))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                (
+                    "missing-option",
+                    "required option `fx_gain_account' is not set".to_string()
+                ),
+                (
+                    "missing-account",
+                    "no account opened under required account `Liabilities:Card'".to_string()
+                ),
+                (
+                    "unit-not-allowed",
+                    "unit `JPY' is not allowed by the schema".to_string()
+                ),
+            ]
+        );
+
+        assert!(ledger.validate_schema(&LedgerSchema::default())?.is_empty());
+        assert!(parser::parse(r#"2022-01-01 custom "schema" "allow-unit""#, None).is_err());
+
+        Ok(())
+    }
+}
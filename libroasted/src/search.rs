@@ -0,0 +1,224 @@
+use crate::{ledger::Ledger, transaction::Transaction};
+use chrono::NaiveDate;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchField {
+    Payee,
+    Title,
+    /// A metadata value of the transaction or one of its postings.
+    Metadata,
+}
+
+/// How well a field matched the search text, better matches sort first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    Exact,
+    Prefix,
+    WordStart,
+    Substring,
+    Fuzzy,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit<'l> {
+    pub date: NaiveDate,
+    pub transaction: &'l Transaction,
+    pub field: SearchField,
+    pub kind: MatchKind,
+    /// Byte range of the matched text within the field.
+    pub span: Range<usize>,
+}
+
+fn chars_eq(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Case-insensitively match `needle` as a substring of `haystack` starting at byte `start`,
+/// returning the end byte offset.
+fn match_at(haystack: &str, start: usize, needle: &str) -> Option<usize> {
+    let mut hay = haystack[start..].char_indices();
+    for n in needle.chars() {
+        let (_, h) = hay.next()?;
+        if !chars_eq(h, n) {
+            return None;
+        }
+    }
+    Some(hay.next().map_or(haystack.len(), |(idx, _)| start + idx))
+}
+
+fn fuzzy_match(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    let mut needle_chars = needle.chars().peekable();
+    let mut span: Option<Range<usize>> = None;
+    for (idx, h) in haystack.char_indices() {
+        let Some(&n) = needle_chars.peek() else {
+            break;
+        };
+        if chars_eq(h, n) {
+            needle_chars.next();
+            let end = idx + h.len_utf8();
+            span = Some(span.map_or(idx..end, |s| s.start..end));
+        }
+    }
+
+    if needle_chars.peek().is_some() {
+        return None;
+    }
+    span
+}
+
+/// Match a single field, picking the best kind of match found.
+pub fn match_field(haystack: &str, needle: &str) -> Option<(MatchKind, Range<usize>)> {
+    if needle.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(MatchKind, Range<usize>)> = None;
+    let mut prev: Option<char> = None;
+    for (start, ch) in haystack.char_indices() {
+        if let Some(end) = match_at(haystack, start, needle) {
+            let kind = match (start, prev) {
+                (0, _) if end == haystack.len() => MatchKind::Exact,
+                (0, _) => MatchKind::Prefix,
+                (_, Some(p)) if !p.is_alphanumeric() => MatchKind::WordStart,
+                _ => MatchKind::Substring,
+            };
+            if best.as_ref().map_or(true, |(k, _)| kind < *k) {
+                best = Some((kind, start..end));
+            }
+        }
+        prev = Some(ch);
+    }
+
+    best.or_else(|| fuzzy_match(haystack, needle).map(|span| (MatchKind::Fuzzy, span)))
+}
+
+/// Search payees, titles and metadata values of every transaction, see [`Ledger::search`].
+pub fn search<'l>(ledger: &'l Ledger, text: &str) -> Vec<SearchHit<'l>> {
+    let text = text.trim();
+    let mut hits = Vec::new();
+
+    for (date, book) in ledger.bookings() {
+        for txn in book.transactions() {
+            let fields = txn
+                .payee
+                .as_deref()
+                .map(|payee| (SearchField::Payee, payee))
+                .into_iter()
+                .chain([(SearchField::Title, txn.title.as_str())])
+                .chain(
+                    txn.metadata
+                        .values()
+                        .chain(txn.exchanges.iter().flat_map(|e| e.metadata.values()))
+                        .map(|value| (SearchField::Metadata, value.as_str())),
+                );
+
+            let best = fields
+                .filter_map(|(field, value)| {
+                    match_field(value, text).map(|(kind, span)| (field, kind, span))
+                })
+                .min_by_key(|(_, kind, _)| *kind);
+
+            if let Some((field, kind, span)) = best {
+                hits.push(SearchHit {
+                    date: *date,
+                    transaction: txn,
+                    field,
+                    kind,
+                    span,
+                });
+            }
+        }
+    }
+
+    // Stable sort keeps hits of the same kind in date order, most recent first.
+    hits.reverse();
+    hits.sort_by_key(|hit| hit.kind);
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::search::{match_field, MatchKind, SearchField};
+    use anyhow::Result;
+
+    #[test]
+    fn test_match_field() {
+        assert_eq!(
+            match_field("Tires", "tires"),
+            Some((MatchKind::Exact, 0..5))
+        );
+        assert_eq!(
+            match_field("Tires and oil", "TIRES"),
+            Some((MatchKind::Prefix, 0..5))
+        );
+        assert_eq!(
+            match_field("New tires", "tires"),
+            Some((MatchKind::WordStart, 4..9))
+        );
+        assert_eq!(
+            match_field("Retires", "tires"),
+            Some((MatchKind::Substring, 2..7))
+        );
+        assert_eq!(
+            match_field("Tire service", "tsrv"),
+            Some((MatchKind::Fuzzy, 0..9))
+        );
+        assert_eq!(
+            match_field("Café Ünter", "ÜNTER"),
+            Some((MatchKind::WordStart, 6..12))
+        );
+        assert_eq!(match_field("Groceries", "tires"), None);
+        assert_eq!(match_field("Groceries", ""), None);
+    }
+
+    #[test]
+    fn test_search() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Car
+
+2022-01-03 * "Tire Shop" "Winter swap"
+  Assets:Bank
+  Expenses:Car            30 USD
+
+2022-03-03 * "Garage" "New tires"
+  Assets:Bank
+  Expenses:Car            400 USD
+
+2022-04-03 * "Fuel"
+  Assets:Bank
+  Expenses:Car            40 USD
+
+2022-05-03 * "Garage" "Service"
+  Assets:Bank
+  Expenses:Car            120 USD
+    note: "rotated tyres, parking sensor"
+"#,
+            None,
+        )?;
+
+        let hits = ledger.search("tire");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].field, SearchField::Payee);
+        assert_eq!(hits[0].kind, MatchKind::Prefix);
+        assert_eq!(hits[0].transaction.title, "Winter swap");
+        assert_eq!(hits[1].field, SearchField::Title);
+        assert_eq!(hits[1].span, 4..8);
+
+        // Only a posting metadata value mentions it.
+        let hits = ledger.search("parking");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Metadata);
+        assert_eq!(hits[0].kind, MatchKind::WordStart);
+        assert_eq!(hits[0].transaction.title, "Service");
+        assert_eq!(hits[0].span, 15..22);
+
+        assert!(ledger.search("carwash").is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    account::ParsedAccount, amount::Amount, diagnostic::Severity, ledger::Ledger,
+    recurrence::Recurrence, transaction::BalanceAssertion, writer,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::ops::RangeInclusive;
+
+/// Render `balance` statements for the accounts, see [`Ledger::seed_balance_assertions`].
+pub fn seed_balance_assertions(
+    ledger: &Ledger,
+    accounts: &[&str],
+    recurrence: &Recurrence,
+    range: Option<RangeInclusive<NaiveDate>>,
+) -> Result<String> {
+    let accounts = accounts
+        .iter()
+        .map(|&name| {
+            let parsed: ParsedAccount<'_> = name.try_into()?;
+            ledger
+                .accounts()
+                .lookup(&parsed)
+                .ok_or(anyhow!("unknown account `{}'", name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let Some(range) = range.or(ledger.date_range().map(|(first, last)| first..=last)) else {
+        return Ok(String::new());
+    };
+
+    let pads = ledger.resolve_pads()?;
+    let mut out = String::new();
+    for date in recurrence.occurrences(range) {
+        let Some(previous_day) = date.pred_opt() else {
+            continue;
+        };
+        let balances = ledger.balances_with_pads(&previous_day, &pads)?;
+        for account in &accounts {
+            let is_open = ledger.accounts().is_open_at(account, &date);
+            let asserted = ledger.get_bookings_on(&date).is_some_and(|book| {
+                book.balance_assertions()
+                    .iter()
+                    .any(|assertion| assertion.account == *account)
+            });
+            let Some(units) = balances.units(account) else {
+                continue;
+            };
+            if !is_open || asserted || units.is_empty() {
+                continue;
+            }
+
+            let assertion = BalanceAssertion {
+                account: account.clone(),
+                amounts: units
+                    .iter()
+                    .map(|(&unit, &nominal)| Amount { nominal, unit })
+                    .collect(),
+                severity: Severity::Error,
+            };
+            writer::write_balance_assertion(&mut out, ledger, &date, &assertion)?;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_seed_balance_assertions() -> Result<()> {
+        let input = r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Wallet
+2022-01-01 open Income:Salary
+2022-01-01 open Expenses:Food
+
+2022-01-25 * "Salary"
+  Income:Salary
+  Assets:Bank          1000 USD
+
+2022-02-03 * "Lunch"
+  Assets:Bank
+  Expenses:Food          20 USD
+
+2022-02-05 * "Money changer"
+  Assets:Bank          -100 USD
+  Assets:Wallet         1_500_000 IDR
+
+2022-03-01 balance Assets:Bank 880 USD
+
+2022-03-10 * "Dinner"
+  Assets:Wallet
+  Expenses:Food         200_000 IDR
+"#;
+        let ledger = parser::parse(input, None)?;
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        let seeded = ledger.seed_balance_assertions(
+            &["Assets:Bank", "Assets:Wallet"],
+            &"monthly on 01".parse()?,
+            Some(date(1, 1)?..=date(4, 1)?),
+        )?;
+        assert_eq!(
+            seeded,
+            "2022-02-01 balance Assets:Bank 1000 USD\n\
+             2022-03-01 balance Assets:Wallet 1500000 IDR\n\
+             2022-04-01 balance Assets:Bank 880 USD\n\
+             2022-04-01 balance Assets:Wallet 1300000 IDR\n"
+        );
+
+        assert!(ledger
+            .seed_balance_assertions(&["Assets:Savings"], &"monthly on 01".parse()?, None)
+            .is_err());
+
+        // Seeded statements hold once added to the ledger.
+        let seeded = parser::parse(&format!("{}\n{}", input, seeded), None)?;
+        assert!(seeded.failed_assertions()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_padded() -> Result<()> {
+        let input = r#"
+unit USD
+
+2022-01-01 open Assets:Bank
+2022-01-01 open Equity:Opening-Balances
+2022-01-01 open Expenses:Food
+
+2022-01-02 pad Assets:Bank Equity:Opening-Balances
+2022-01-05 balance Assets:Bank 1000 USD
+
+2022-02-10 * "Grocer"
+  Assets:Bank
+  Expenses:Food          10 USD
+"#;
+        let ledger = parser::parse(input, None)?;
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).ok_or(anyhow!("invalid date"));
+
+        // The 01-03 assertion lands between the pad and the one it was meant for.
+        for (recurrence, expected) in [
+            ("monthly on 15", "2022-02-15 balance Assets:Bank 990 USD\n"),
+            ("monthly on 03", "2022-01-03 balance Assets:Bank 1000 USD\n"),
+        ] {
+            let seeded = ledger.seed_balance_assertions(
+                &["Assets:Bank"],
+                &recurrence.parse()?,
+                Some(date(1, 1)?..=date(3, 1)?),
+            )?;
+            assert!(seeded.contains(expected), "{}", seeded);
+
+            let seeded = parser::parse(&format!("{}\n{}", input, seeded), None)?;
+            assert!(seeded.failed_assertions()?.is_empty());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use crate::ledger::{Ledger, RatePolicy, ReferenceLookup};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PricePoint {
+    pub date: NaiveDate,
+    /// Price of one unit in the quoting unit.
+    pub rate: Decimal,
+}
+
+/// Daily prices of a unit, see [`Ledger::price_series`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PriceSeries {
+    pub unit: String,
+    pub in_unit: String,
+    pub points: Vec<PricePoint>,
+}
+
+/// `text` as a JSON string literal, quotes included.
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl PriceSeries {
+    /// Render the series as CSV, with a `date,<unit>/<in_unit>` header.
+    pub fn to_csv(&self) -> Result<String> {
+        let mut out = String::new();
+        writeln!(out, "date,{}/{}", self.unit, self.in_unit)?;
+        for point in &self.points {
+            writeln!(out, "{},{}", point.date, point.rate)?;
+        }
+        Ok(out)
+    }
+
+    /// Render the series as a JSON object, rates are strings so no precision is lost.
+    pub fn to_json(&self) -> Result<String> {
+        let mut out = String::new();
+        write!(
+            out,
+            "{{\"unit\":{},\"in_unit\":{},\"points\":[",
+            json_string(&self.unit),
+            json_string(&self.in_unit)
+        )?;
+        for (idx, point) in self.points.iter().enumerate() {
+            if idx > 0 {
+                out.push(',');
+            }
+            write!(
+                out,
+                "{{\"date\":\"{}\",\"rate\":\"{}\"}}",
+                point.date, point.rate
+            )?;
+        }
+        out.push_str("]}");
+        Ok(out)
+    }
+}
+
+pub(crate) fn price_series(
+    ledger: &Ledger,
+    unit: &str,
+    in_unit: &str,
+    range: RangeInclusive<NaiveDate>,
+    policy: RatePolicy,
+) -> Result<PriceSeries> {
+    if range.is_empty() {
+        return Err(anyhow!(
+            "invalid date range: {} is after {}",
+            range.start(),
+            range.end()
+        ));
+    }
+    let from = ledger.unit_lookup(range.start(), unit)?;
+    let to = ledger.unit_lookup(range.start(), in_unit)?;
+
+    let points = range
+        .start()
+        .iter_days()
+        .take_while(|date| date <= range.end())
+        .filter_map(|date| {
+            ledger
+                .conversion_rate_with(from, to, &date, policy)
+                .transpose()
+                .map(|rate| {
+                    rate.map(|rate| PricePoint {
+                        date,
+                        rate: rate.normalize(),
+                    })
+                })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(PriceSeries {
+        unit: unit.to_string(),
+        in_unit: in_unit.to_string(),
+        points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ledger::RatePolicy;
+    use crate::parser;
+    use crate::series::{json_string, PriceSeries};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_price_series() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit IDR
+unit USD
+2022-01-02 price USD 15_000 IDR
+2022-01-05 price USD 15_300 IDR
+"#,
+            None,
+        )?;
+        let date = |d| NaiveDate::from_ymd_opt(2022, 1, d).ok_or(anyhow!("invalid date"));
+
+        // Nothing is known before the first price with the previous quote policy.
+        let series =
+            ledger.price_series("USD", "IDR", date(1)?..=date(5)?, RatePolicy::Previous)?;
+        assert_eq!(
+            series.to_csv()?,
+            concat!(
+                "date,USD/IDR\n",
+                "2022-01-02,15000\n",
+                "2022-01-03,15000\n",
+                "2022-01-04,15000\n",
+                "2022-01-05,15300\n",
+            )
+        );
+
+        let series = ledger.price_series("USD", "IDR", date(3)?..=date(4)?, RatePolicy::Linear)?;
+        assert_eq!(
+            series.to_json()?,
+            concat!(
+                r#"{"unit":"USD","in_unit":"IDR","points":["#,
+                r#"{"date":"2022-01-03","rate":"15100"},"#,
+                r#"{"date":"2022-01-04","rate":"15200"}]}"#,
+            )
+        );
+
+        // Inverted prices work the other way around.
+        let series =
+            ledger.price_series("IDR", "USD", date(2)?..=date(2)?, RatePolicy::Previous)?;
+        assert_eq!(series.points.len(), 1);
+        assert!(ledger
+            .price_series("USD", "EUR", date(2)?..=date(2)?, RatePolicy::Previous)
+            .is_err());
+        assert!(ledger
+            .price_series("USD", "IDR", date(5)?..=date(2)?, RatePolicy::Previous)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_price_series_overflow() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit IDR
+unit USD
+2022-01-02 price USD 1 IDR
+2022-01-05 price USD 50000000000000000000000000000 IDR
+"#,
+            None,
+        )?;
+        let date = |d| NaiveDate::from_ymd_opt(2022, 1, d).ok_or(anyhow!("invalid date"));
+
+        // Interpolating multiplies the price difference by the days elapsed.
+        let err = ledger
+            .price_series("USD", "IDR", date(3)?..=date(4)?, RatePolicy::Linear)
+            .expect_err("interpolated rate overflows");
+        assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_escaping() -> Result<()> {
+        assert_eq!(
+            json_string("say \"hi\"\\\n\u{7}é"),
+            r#""say \"hi\"\\\n\u0007é""#
+        );
+
+        let series = PriceSeries {
+            unit: "US\"D".to_string(),
+            in_unit: "\u{1}IDR\\".to_string(),
+            points: Vec::new(),
+        };
+        let json: serde_json::Value = serde_json::from_str(&series.to_json()?)?;
+        assert_eq!(json["unit"], "US\"D");
+        assert_eq!(json["in_unit"], "\u{1}IDR\\");
+
+        Ok(())
+    }
+}
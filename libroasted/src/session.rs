@@ -0,0 +1,142 @@
+use crate::{
+    amount::Amount, diagnostic::Severity, ledger::Ledger, parser, transaction::AssertionKind,
+    writer,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::fmt::Write;
+
+/// Ledger built up one input at a time, the backend of a REPL.
+///
+/// Every input is checked against the statements accepted so far, and is only kept if
+/// it parses, applies cleanly, and doesn't break any balance assertion, pending ones
+/// flagged with `!` aside. A rejected input
+/// leaves the session untouched.
+#[derive(Debug, Default)]
+pub struct Session {
+    source: String,
+    ledger: Ledger,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Evaluate one or more statements, options, or unit declarations.
+    pub fn eval(&mut self, input: &str) -> Result<()> {
+        let mut source = self.source.clone();
+        source.push_str(input);
+        if !source.ends_with('\n') {
+            source.push('\n');
+        }
+        // Keep statements apart, a transaction right before would otherwise swallow the
+        // next input as one of its postings.
+        source.push('\n');
+
+        let ledger = parser::parse(&source, None)?;
+        let known = self.ledger.failed_assertions()?;
+        let failures: Vec<String> = ledger
+            .failed_assertions()?
+            .into_iter()
+            .filter(|failure| failure.severity == Severity::Error && !known.contains(failure))
+            .map(|failure| {
+                let keyword = match failure.kind {
+                    AssertionKind::Balance => "balance",
+                    AssertionKind::Change => "balance-change",
+                };
+                Ok(format!(
+                    "{} {} {} expected {}, got {}",
+                    failure.date,
+                    keyword,
+                    ledger.account_name(&failure.account)?,
+                    failure.expected.nominal,
+                    failure.actual
+                ))
+            })
+            .collect::<Result<_>>()?;
+        if !failures.is_empty() {
+            return Err(anyhow!("balance assertion failed: {}", failures.join("; ")));
+        }
+
+        self.source = source;
+        self.ledger = ledger;
+        Ok(())
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    /// Every accepted input, in order.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Render balances as of the given date, or the latest booked date, one account and
+    /// unit per line in presentation order, with the decimals written for the account.
+    pub fn write_balances<W: Write>(&self, w: &mut W, date: Option<NaiveDate>) -> Result<()> {
+        let Some(date) = date.or(self.ledger.date_range().map(|(_, last)| last)) else {
+            return Ok(());
+        };
+
+        let balances = self.ledger.balances_at(&date)?;
+        let precision = self.ledger.precision();
+        for (account, units) in balances.layout(&self.ledger, false)? {
+            for (&unit, &nominal) in units {
+                write!(w, "{} ", self.ledger.account_name(account)?)?;
+                writer::write_amount_with_precision(
+                    w,
+                    &self.ledger,
+                    &precision,
+                    Some(account),
+                    &Amount { nominal, unit },
+                )?;
+                writeln!(w)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::session::Session;
+    use anyhow::Result;
+
+    #[test]
+    fn test_session_eval() -> Result<()> {
+        let mut session = Session::new();
+        session.eval("unit USD")?;
+        session.eval("2024-05-01 open Assets:Cash\n2024-05-01 open Expenses:Coffee")?;
+        session.eval(
+            r#"2024-05-01 * "Coffee"
+  Assets:Cash
+  Expenses:Coffee     4 USD"#,
+        )?;
+
+        // Unknown account, a broken balance assertion, and a syntax error are rejected.
+        assert!(session
+            .eval(
+                r#"2024-05-02 * "Tea"
+  Assets:Cash
+  Expenses:Tea        3 USD"#
+            )
+            .is_err());
+        assert!(session
+            .eval("2024-05-03 balance Assets:Cash -10 USD")
+            .is_err());
+        assert!(session.eval("2024-05-03 balance Assets:Cash").is_err());
+
+        session.eval("2024-05-03 balance Assets:Cash -4 USD")?;
+        // Pending assertions only warn.
+        session.eval("2024-05-03 ! balance Assets:Cash -10 USD")?;
+
+        let mut out = String::new();
+        session.write_balances(&mut out, None)?;
+        assert_eq!(out, "Assets:Cash -4 USD\nExpenses:Coffee 4 USD\n");
+        assert_eq!(session.ledger().bookings().len(), 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{ledger::Ledger, reports::posting_amount, transaction::TransactionState};
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Bumped whenever a table or column is changed in a way existing queries could notice.
+pub const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = r#"
+CREATE TABLE schema_version (version INTEGER NOT NULL);
+CREATE TABLE units (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE accounts (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    opened_at TEXT NOT NULL,
+    closed_at TEXT
+);
+CREATE TABLE transactions (
+    id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL,
+    state TEXT NOT NULL,
+    payee TEXT,
+    title TEXT NOT NULL
+);
+CREATE TABLE postings (
+    id INTEGER PRIMARY KEY,
+    transaction_id INTEGER NOT NULL REFERENCES transactions (id),
+    account_id INTEGER NOT NULL REFERENCES accounts (id),
+    amount REAL,
+    unit_id INTEGER REFERENCES units (id),
+    elided INTEGER NOT NULL
+);
+CREATE TABLE posting_metadata (
+    posting_id INTEGER NOT NULL REFERENCES postings (id),
+    key TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE prices (
+    date TEXT NOT NULL,
+    unit_id INTEGER NOT NULL REFERENCES units (id),
+    price REAL NOT NULL,
+    price_unit_id INTEGER NOT NULL REFERENCES units (id)
+);
+"#;
+
+fn state_name(state: TransactionState) -> &'static str {
+    match state {
+        TransactionState::Settled => "settled",
+        TransactionState::Unsettled => "unsettled",
+        TransactionState::Recurring => "recurring",
+        TransactionState::Virtual => "virtual",
+    }
+}
+
+/// Write the ledger into a new SQLite database at `path`, see [`Ledger::export_sqlite`].
+pub fn export(ledger: &Ledger, path: &Path) -> Result<()> {
+    let mut conn = Connection::open(path)?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+    tx.execute(
+        "INSERT INTO schema_version (version) VALUES (?1)",
+        params![SCHEMA_VERSION],
+    )?;
+
+    for (id, name) in ledger.units().enumerate() {
+        tx.execute(
+            "INSERT INTO units (id, name) VALUES (?1, ?2)",
+            params![id as i64, name],
+        )?;
+    }
+
+    let mut account_ids = HashMap::new();
+    for (id, (account, activities)) in ledger.accounts().iter().enumerate() {
+        tx.execute(
+            "INSERT INTO accounts (id, name, opened_at, closed_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                id as i64,
+                ledger.account_name(&account)?,
+                activities.opened_at().to_string(),
+                activities.closed_at().map(|date| date.to_string()),
+            ],
+        )?;
+        account_ids.insert(account, id as i64);
+    }
+
+    let mut txn_id = 0i64;
+    let mut posting_id = 0i64;
+    for (date, book) in ledger.bookings() {
+        for txn in book.transactions() {
+            tx.execute(
+                "INSERT INTO transactions (id, date, state, payee, title)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    txn_id,
+                    date.to_string(),
+                    state_name(txn.state),
+                    txn.payee,
+                    txn.title
+                ],
+            )?;
+
+            for (idx, exchange) in txn.exchanges.iter().enumerate() {
+                let amount = posting_amount(txn, idx);
+                tx.execute(
+                    "INSERT INTO postings (id, transaction_id, account_id, amount, unit_id, elided)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        posting_id,
+                        txn_id,
+                        account_ids.get(&exchange.account).copied(),
+                        amount.as_ref().and_then(|amount| amount.nominal.to_f64()),
+                        amount.as_ref().map(|amount| amount.unit as i64),
+                        exchange.amount.is_none() || exchange.elided,
+                    ],
+                )?;
+                for (key, value) in &exchange.metadata {
+                    tx.execute(
+                        "INSERT INTO posting_metadata (posting_id, key, value) VALUES (?1, ?2, ?3)",
+                        params![posting_id, key, value],
+                    )?;
+                }
+                posting_id += 1;
+            }
+            txn_id += 1;
+        }
+    }
+
+    for (date, book) in ledger.pricebooks() {
+        for (unit, prices) in book {
+            for (price_unit, price) in prices {
+                tx.execute(
+                    "INSERT INTO prices (date, unit_id, price, price_unit_id)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        date.to_string(),
+                        *unit as i64,
+                        price.to_f64(),
+                        *price_unit as i64
+                    ],
+                )?;
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::sqlite::SCHEMA_VERSION;
+    use crate::tempdir::TempDir;
+    use anyhow::Result;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_export_sqlite() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-02 price USD 15_000 IDR
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+    receipt: "r-001"
+"#,
+            None,
+        )?;
+
+        let dir = TempDir::new("export")?;
+        let path = dir.path("ledger.db");
+        ledger.export_sqlite(&path)?;
+
+        let conn = Connection::open(&path)?;
+        let version: i64 =
+            conn.query_row("SELECT version FROM schema_version", [], |row| row.get(0))?;
+        assert_eq!(version, SCHEMA_VERSION);
+
+        let mut stmt = conn.prepare(
+            "SELECT a.name, p.amount, u.name, p.elided FROM postings p
+             JOIN accounts a ON a.id = p.account_id
+             JOIN units u ON u.id = p.unit_id
+             ORDER BY p.id",
+        )?;
+        let postings = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, f64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, bool>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        assert_eq!(
+            postings,
+            vec![
+                ("Assets:Bank".to_string(), -30f64, "USD".to_string(), true),
+                ("Expenses:Food".to_string(), 30f64, "USD".to_string(), false),
+            ]
+        );
+
+        let receipt: String = conn.query_row(
+            "SELECT value FROM posting_metadata WHERE key = 'receipt'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(receipt, "r-001");
+
+        let price: f64 = conn.query_row(
+            "SELECT price FROM prices p JOIN units u ON u.id = p.unit_id WHERE u.name = 'USD'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(price, 15_000f64);
+
+        // The database is written once, exporting over it again fails.
+        assert!(ledger.export_sqlite(&path).is_err());
+
+        Ok(())
+    }
+}
@@ -1,22 +1,71 @@
 use crate::account::ParsedAccount;
 use crate::amount::ParsedAmount;
+use crate::diagnostic::Severity;
 use crate::parser::{inner_str, Rule};
 use crate::transaction::{ParsedTransaction, TxnHeader};
+use crate::unit::UnitInfo;
 use anyhow::{anyhow, Result};
 use chrono::NaiveDate;
 use pest::iterators::Pair;
 
 use std::convert::TryFrom;
+use std::ops::Range;
+
+/// `key: "value"` lines attached to a statement, in file order.
+pub type Metadata<'s> = Vec<(&'s str, &'s str)>;
 
 #[derive(Debug, PartialEq)]
 pub enum Statement<'s> {
+    /// `option "key" "value"`, only produced by
+    /// [`parse_statements`][crate::parser::parse_statements].
+    Option(&'s str, &'s str),
+    /// `unit USD` and its attributes, only produced by [`parse_statements`][crate::parser::parse_statements].
+    Unit(UnitInfo),
+    /// `include "path"`, only produced by
+    /// [`parse_statements`][crate::parser::parse_statements]. Loading it is left to the
+    /// caller, e.g. with [`parse_file`][crate::parser::parse_file].
+    Include(&'s str),
     Custom(NaiveDate, Vec<&'s str>),
-    OpenAccount(NaiveDate, ParsedAccount<'s>),
+    /// Opening of an account, along with its opening balance if any, e.g.
+    /// `open Assets:Bank 5000 USD`.
+    OpenAccount(
+        NaiveDate,
+        ParsedAccount<'s>,
+        Vec<ParsedAmount<'s>>,
+        Metadata<'s>,
+    ),
     CloseAccount(NaiveDate, ParsedAccount<'s>),
     Pad(NaiveDate, ParsedAccount<'s>, ParsedAccount<'s>),
-    Balance(NaiveDate, ParsedAccount<'s>, ParsedAmount<'s>),
+    /// Balance assertion, failing with a warning instead of an error when flagged with `!`.
+    Balance(
+        NaiveDate,
+        ParsedAccount<'s>,
+        Vec<ParsedAmount<'s>>,
+        Severity,
+    ),
+    /// Net change of an account since its previous assertion, e.g.
+    /// `balance-change Assets:Salary 3000 USD`, flagged with `!` like a balance assertion.
+    BalanceChange(
+        NaiveDate,
+        ParsedAccount<'s>,
+        Vec<ParsedAmount<'s>>,
+        Severity,
+    ),
     Transaction(NaiveDate, TxnHeader<'s>, ParsedTransaction<'s>),
     Price(NaiveDate, &'s str, ParsedAmount<'s>),
+    Checkpoint(NaiveDate, Vec<(ParsedAccount<'s>, ParsedAmount<'s>)>),
+    /// `note Assets:Bank "text"`.
+    Note(NaiveDate, ParsedAccount<'s>, &'s str),
+    /// `document Assets:Bank "path"`, a file attached to the account.
+    Document(NaiveDate, ParsedAccount<'s>, &'s str),
+    /// `event "name" "value"`.
+    Event(NaiveDate, &'s str, &'s str),
+    /// Directive not known to this version of roasted, kept as its source text after the
+    /// date, continuation lines included.
+    Raw(NaiveDate, &'s str),
+    /// Source text that failed to parse, along with its byte range in the input. Only
+    /// produced in permissive mode.
+    Unparsed(Range<usize>, &'s str),
 }
 
 impl<'s> TryFrom<Pair<'s, Rule>> for Statement<'s> {
@@ -39,6 +88,51 @@ macro_rules! parse_next {
 pub(crate) use parse_next;
 
 impl<'s> Statement<'s> {
+    pub fn date(&self) -> Option<&NaiveDate> {
+        let date = match self {
+            Self::Custom(date, _)
+            | Self::OpenAccount(date, _, _, _)
+            | Self::CloseAccount(date, _)
+            | Self::Pad(date, _, _)
+            | Self::Balance(date, _, _, _)
+            | Self::BalanceChange(date, _, _, _)
+            | Self::Transaction(date, _, _)
+            | Self::Price(date, _, _)
+            | Self::Checkpoint(date, _)
+            | Self::Note(date, _, _)
+            | Self::Document(date, _, _)
+            | Self::Event(date, _, _)
+            | Self::Raw(date, _) => date,
+            Self::Option(..) | Self::Unit(..) | Self::Include(..) | Self::Unparsed(..) => {
+                return None
+            }
+        };
+        Some(date)
+    }
+
+    /// Accounts referenced by this statement.
+    pub fn accounts(&self) -> Vec<&ParsedAccount<'s>> {
+        match self {
+            Self::Option(..)
+            | Self::Unit(..)
+            | Self::Include(..)
+            | Self::Custom(..)
+            | Self::Price(..)
+            | Self::Event(..)
+            | Self::Raw(..)
+            | Self::Unparsed(..) => Vec::new(),
+            Self::OpenAccount(_, account, _, _)
+            | Self::CloseAccount(_, account)
+            | Self::Balance(_, account, _, _)
+            | Self::BalanceChange(_, account, _, _)
+            | Self::Note(_, account, _)
+            | Self::Document(_, account, _) => vec![account],
+            Self::Pad(_, target, source) => vec![target, source],
+            Self::Transaction(_, _, txn) => txn.accounts.iter().collect(),
+            Self::Checkpoint(_, entries) => entries.iter().map(|(account, _)| account).collect(),
+        }
+    }
+
     fn into_statement(statement: Pair<'s, Rule>) -> Result<Self> {
         let mut pairs = statement.into_inner();
         let datestr = pairs
@@ -50,28 +144,48 @@ impl<'s> Statement<'s> {
             .next()
             .ok_or(anyhow!("Statement: invalid statement"))?;
         let tag = statement_pair.as_rule();
+        let text = statement_pair.as_str();
 
         let mut pairs = statement_pair.into_inner();
 
         let stmt = match tag {
             Rule::custom_statement => Self::Custom(date, pairs.map(inner_str).collect()),
-            Rule::open_statement => Self::OpenAccount(date, parse_next!(ParsedAccount, pairs)),
+            Rule::open_statement => {
+                let account = parse_next!(ParsedAccount, pairs);
+                let mut amounts = Vec::new();
+                while pairs.peek().map(|pair| pair.as_rule()) == Some(Rule::amount) {
+                    amounts.push(parse_next!(ParsedAmount, pairs));
+                }
+                Self::OpenAccount(date, account, amounts, parse_metadata(pairs)?)
+            }
             Rule::close_statement => Self::CloseAccount(date, parse_next!(ParsedAccount, pairs)),
             Rule::pad_statement => Self::Pad(
                 date,
                 parse_next!(ParsedAccount, pairs),
                 parse_next!(ParsedAccount, pairs),
             ),
-            Rule::balance_statement => Self::Balance(
-                date,
-                parse_next!(ParsedAccount, pairs),
-                parse_next!(ParsedAmount, pairs),
-            ),
-            Rule::transaction => Self::Transaction(
-                date,
-                parse_next!(TxnHeader, pairs),
-                parse_next!(ParsedTransaction, pairs),
-            ),
+            Rule::balance_statement | Rule::balance_change_statement => {
+                let mut severity = Severity::Error;
+                if pairs.peek().map(|pair| pair.as_rule()) == Some(Rule::pending_flag) {
+                    pairs.next();
+                    severity = Severity::Warning;
+                }
+                let account = parse_next!(ParsedAccount, pairs);
+                let amounts = pairs.map(ParsedAmount::parse).collect::<Result<_>>()?;
+                match tag {
+                    Rule::balance_statement => Self::Balance(date, account, amounts, severity),
+                    _ => Self::BalanceChange(date, account, amounts, severity),
+                }
+            }
+            Rule::transaction => {
+                let mut header = parse_next!(TxnHeader, pairs);
+                let mut metadata = Vec::new();
+                while pairs.peek().map(|pair| pair.as_rule()) == Some(Rule::metadata) {
+                    metadata.extend(pairs.next());
+                }
+                header.metadata = parse_metadata(metadata.into_iter())?;
+                Self::Transaction(date, header, parse_next!(ParsedTransaction, pairs))
+            }
             Rule::price_statement => Self::Price(
                 date,
                 pairs
@@ -82,6 +196,41 @@ impl<'s> Statement<'s> {
                     .as_str(),
                 parse_next!(ParsedAmount, pairs),
             ),
+            Rule::checkpoint_statement => Self::Checkpoint(
+                date,
+                pairs
+                    .map(|entry| {
+                        let mut entry = entry.into_inner();
+                        Ok((
+                            parse_next!(ParsedAccount, entry),
+                            parse_next!(ParsedAmount, entry),
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            Rule::note_statement => Self::Note(
+                date,
+                parse_next!(ParsedAccount, pairs),
+                pairs
+                    .next()
+                    .map(inner_str)
+                    .ok_or(anyhow!("Statement: invalid next token, expected note text"))?,
+            ),
+            Rule::document_statement => Self::Document(
+                date,
+                parse_next!(ParsedAccount, pairs),
+                pairs.next().map(inner_str).ok_or(anyhow!(
+                    "Statement: invalid next token, expected document path"
+                ))?,
+            ),
+            Rule::event_statement => {
+                let mut strings = pairs.map(inner_str);
+                match (strings.next(), strings.next()) {
+                    (Some(name), Some(value)) => Self::Event(date, name, value),
+                    _ => return Err(anyhow!("Statement: invalid event, expected name and value")),
+                }
+            }
+            Rule::raw_statement => Self::Raw(date, text),
             _ => unreachable!(),
         };
 
@@ -89,15 +238,37 @@ impl<'s> Statement<'s> {
     }
 }
 
+pub(crate) fn parse_metadata<'s>(
+    pairs: impl Iterator<Item = Pair<'s, Rule>>,
+) -> Result<Metadata<'s>> {
+    pairs
+        .map(|pair| {
+            let mut pair = pair.into_inner();
+            let key = pair
+                .next()
+                .ok_or(anyhow!(
+                    "Statement: invalid next token, expected metadata key"
+                ))?
+                .as_str();
+            let value = pair.next().ok_or(anyhow!(
+                "Statement: invalid next token, expected metadata value"
+            ))?;
+            Ok((key, inner_str(value)))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::account::ParsedAccount;
     use crate::amount::ParsedAmount;
+    use crate::diagnostic::Severity;
     use crate::parser::{LedgerParser, Rule};
     use crate::statement::Statement;
     use crate::transaction::{ParsedTransaction, TransactionState, TxnHeader};
     use chrono::NaiveDate;
     use pest::Parser;
+    use rust_decimal_macros::dec;
 
     use anyhow::{anyhow, Result};
 
@@ -126,7 +297,60 @@ mod tests {
             statement,
             Statement::OpenAccount(
                 NaiveDate::from_ymd_opt(2021, 2, 2).ok_or(anyhow!("invalid date"))?,
-                ParsedAccount::Assets(vec!["Bank", "Jago"])
+                ParsedAccount::Assets(vec!["Bank", "Jago"]),
+                Vec::new(),
+                Vec::new()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_open_statement_with_metadata() -> Result<()> {
+        let mut ast = LedgerParser::parse(
+            Rule::statement,
+            "2021-02-02 open Assets:Bank:Jago\n  color: \"#00aa88\"\n  order:\"1\"",
+        )?;
+        let statement = Statement::try_from(ast.next().ok_or(anyhow!("empty ast"))?)?;
+        assert_eq!(
+            statement,
+            Statement::OpenAccount(
+                NaiveDate::from_ymd_opt(2021, 2, 2).ok_or(anyhow!("invalid date"))?,
+                ParsedAccount::Assets(vec!["Bank", "Jago"]),
+                Vec::new(),
+                vec![("color", "#00aa88"), ("order", "1")]
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_open_statement_with_balance() -> Result<()> {
+        let mut ast = LedgerParser::parse(
+            Rule::statement,
+            "2021-02-02 open Assets:Bank:Jago 5000 USD, 10 EUR\n  order: \"1\"",
+        )?;
+        let statement = Statement::try_from(ast.next().ok_or(anyhow!("empty ast"))?)?;
+        assert_eq!(
+            statement,
+            Statement::OpenAccount(
+                NaiveDate::from_ymd_opt(2021, 2, 2).ok_or(anyhow!("invalid date"))?,
+                ParsedAccount::Assets(vec!["Bank", "Jago"]),
+                vec![
+                    ParsedAmount {
+                        nominal: dec!(5000),
+                        unit: "USD",
+                        text: Some("5000 USD"),
+                        price: None,
+                    },
+                    ParsedAmount {
+                        nominal: dec!(10),
+                        unit: "EUR",
+                        text: Some("10 EUR"),
+                        price: None,
+                    },
+                ],
+                vec![("order", "1")]
             )
         );
         Ok(())
@@ -179,10 +403,69 @@ mod tests {
             Statement::Balance(
                 NaiveDate::from_ymd_opt(2021, 2, 28).ok_or(anyhow!("invalid date"))?,
                 ParsedAccount::Assets(vec!["Cash", "OnHand"]),
-                ParsedAmount {
-                    nominal: 65750.55f64,
+                vec![ParsedAmount {
+                    nominal: dec!(65750.55),
                     unit: "USD",
-                }
+                    text: Some("65750.55\tUSD"),
+                    price: None,
+                }],
+                Severity::Error,
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_multi_unit_balance_statement() -> Result<()> {
+        let mut ast = LedgerParser::parse(
+            Rule::statement,
+            "2021-02-28 balance Assets:Wallet 100 USD, 1_500_000 IDR",
+        )?;
+        let statement = Statement::try_from(ast.next().ok_or(anyhow!("empty ast"))?)?;
+        assert_eq!(
+            statement,
+            Statement::Balance(
+                NaiveDate::from_ymd_opt(2021, 2, 28).ok_or(anyhow!("invalid date"))?,
+                ParsedAccount::Assets(vec!["Wallet"]),
+                vec![
+                    ParsedAmount {
+                        nominal: dec!(100),
+                        unit: "USD",
+                        text: Some("100 USD"),
+                        price: None,
+                    },
+                    ParsedAmount {
+                        nominal: dec!(1500000),
+                        unit: "IDR",
+                        text: Some("1_500_000 IDR"),
+                        price: None,
+                    },
+                ],
+                Severity::Error,
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_pending_balance_statement() -> Result<()> {
+        let mut ast = LedgerParser::parse(
+            Rule::statement,
+            "2021-02-28 ! balance Assets:Wallet 100 USD",
+        )?;
+        let statement = Statement::try_from(ast.next().ok_or(anyhow!("empty ast"))?)?;
+        assert_eq!(
+            statement,
+            Statement::Balance(
+                NaiveDate::from_ymd_opt(2021, 2, 28).ok_or(anyhow!("invalid date"))?,
+                ParsedAccount::Assets(vec!["Wallet"]),
+                vec![ParsedAmount {
+                    nominal: dec!(100),
+                    unit: "USD",
+                    text: Some("100 USD"),
+                    price: None,
+                }],
+                Severity::Warning,
             )
         );
         Ok(())
@@ -206,6 +489,9 @@ mod tests {
                     state: TransactionState::Settled,
                     payee: Some("Gubuk mang Engking"),
                     title: "Splurge @ diner",
+                    tags: Vec::new(),
+                    links: Vec::new(),
+                    metadata: Vec::new(),
                 },
                 ParsedTransaction {
                     accounts: vec![
@@ -215,13 +501,52 @@ mod tests {
                     exchanges: vec![
                         None,
                         Some(ParsedAmount {
-                            nominal: 50f64,
+                            nominal: dec!(50),
                             unit: "USD",
+                            text: Some("50 USD"),
+                            price: None,
                         }),
                     ],
+                    metadata: vec![Vec::new(), Vec::new()],
                 }
             )
         );
         Ok(())
     }
+
+    #[test]
+    fn parse_checkpoint_statement() -> Result<()> {
+        let mut ast = LedgerParser::parse(
+            Rule::statement,
+            "2022-01-01 checkpoint\n  Assets:Cash  150.5 USD\n  Liabilities:CC:Visa  -20 USD",
+        )?;
+        let statement = Statement::try_from(ast.next().ok_or(anyhow!("empty ast"))?)?;
+        assert_eq!(
+            statement,
+            Statement::Checkpoint(
+                NaiveDate::from_ymd_opt(2022, 1, 1).ok_or(anyhow!("invalid date"))?,
+                vec![
+                    (
+                        ParsedAccount::Assets(vec!["Cash"]),
+                        ParsedAmount {
+                            nominal: dec!(150.5),
+                            unit: "USD",
+                            text: Some("150.5 USD"),
+                            price: None,
+                        }
+                    ),
+                    (
+                        ParsedAccount::Liabilities(vec!["CC", "Visa"]),
+                        ParsedAmount {
+                            nominal: dec!(-20),
+                            unit: "USD",
+                            text: Some("-20 USD"),
+                            price: None,
+                        }
+                    ),
+                ]
+            )
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,93 @@
+use indexmap::IndexSet;
+use std::borrow::Borrow;
+use std::hash::Hash;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Interned values, each one is given a stable index in insertion order.
+///
+/// Indices never change once assigned, so they can be stored instead of the values and
+/// resolved back later, and a table rebuilt from [`SymbolTable::iter`] in the same order
+/// gives out the same indices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SymbolTable<T: Hash + Eq> {
+    symbols: IndexSet<T>,
+}
+
+impl<T: Hash + Eq> Default for SymbolTable<T> {
+    fn default() -> Self {
+        SymbolTable {
+            symbols: IndexSet::new(),
+        }
+    }
+}
+
+impl<T: Hash + Eq> SymbolTable<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Index of the symbol, adding it first if it's not known yet.
+    pub fn intern(&mut self, symbol: T) -> usize {
+        self.symbols.insert_full(symbol).0
+    }
+
+    /// Index of the symbol, if it's known.
+    pub fn get<Q>(&self, symbol: &Q) -> Option<usize>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.symbols.get_index_of(symbol)
+    }
+
+    /// Symbol interned at the given index.
+    pub fn resolve(&self, idx: usize) -> Option<&T> {
+        self.symbols.get_index(idx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Symbols in index order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.symbols.iter()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for SymbolTable<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        SymbolTable {
+            symbols: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::symbol::SymbolTable;
+
+    #[test]
+    fn test_symbol_table() {
+        let mut table = SymbolTable::new();
+        assert_eq!(table.intern("USD".to_string()), 0);
+        assert_eq!(table.intern("IDR".to_string()), 1);
+        assert_eq!(table.intern("USD".to_string()), 0);
+
+        assert_eq!(table.get("IDR"), Some(1));
+        assert_eq!(table.get("EUR"), None);
+        assert_eq!(table.resolve(1).map(String::as_str), Some("IDR"));
+        assert_eq!(table.resolve(2), None);
+
+        let rebuilt: SymbolTable<String> = table.iter().cloned().collect();
+        assert_eq!(rebuilt, table);
+        assert_eq!(rebuilt.len(), 2);
+    }
+}
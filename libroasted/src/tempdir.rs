@@ -0,0 +1,35 @@
+//! Scratch directories for tests touching the filesystem.
+
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+/// A directory under the system temp dir, unique to the test process and `name`,
+/// removed with its content when dropped, including when the test fails.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Result<TempDir> {
+        let dir = std::env::temp_dir().join(format!("roasted-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        Ok(TempDir(dir))
+    }
+
+    pub(crate) fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    /// Writes `content` to the file `name` and returns its path.
+    pub(crate) fn write(&self, name: &str, content: impl AsRef<[u8]>) -> Result<String> {
+        let path = self.path(name);
+        fs::write(&path, content)?;
+        Ok(path.display().to_string())
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -1,24 +1,62 @@
 use crate::parser::{inner_str, Rule};
 use crate::{
     account::{ParsedAccount, TxnAccount},
-    amount::{Amount, ParsedAmount},
+    amount::{checked_add, Amount, ParsedAmount, ParsedPrice, Price},
+    balance::UnitBalances,
+    diagnostic::Severity,
     ledger::ReferenceLookup,
-    statement,
+    statement::{self, Metadata},
 };
+use indexmap::IndexSet;
+use std::collections::BTreeMap;
 
 use chrono::NaiveDate;
 use pest::iterators::Pair;
 
 use anyhow::{anyhow, Result};
 
+use rust_decimal::Decimal;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, PartialEq)]
 pub struct TxnHeader<'th> {
     pub(crate) state: TransactionState,
     pub(crate) payee: Option<&'th str>,
     pub(crate) title: &'th str,
+    /// `#tags` following the title, without the `#`.
+    pub(crate) tags: Vec<&'th str>,
+    /// `^links` following the title, without the `^`.
+    pub(crate) links: Vec<&'th str>,
+    /// Metadata lines between the header and the first posting.
+    pub(crate) metadata: Metadata<'th>,
 }
 
 impl<'th> TxnHeader<'th> {
+    pub fn state(&self) -> TransactionState {
+        self.state
+    }
+
+    pub fn payee(&self) -> Option<&'th str> {
+        self.payee
+    }
+
+    pub fn title(&self) -> &'th str {
+        self.title
+    }
+
+    pub fn tags(&self) -> &[&'th str] {
+        &self.tags
+    }
+
+    pub fn links(&self) -> &[&'th str] {
+        &self.links
+    }
+
+    pub fn metadata(&self) -> &Metadata<'th> {
+        &self.metadata
+    }
+
     pub fn parse(token: Pair<'th, Rule>) -> Result<TxnHeader<'th>> {
         let mut token = token.into_inner();
 
@@ -46,17 +84,29 @@ impl<'th> TxnHeader<'th> {
                 ))?,
         );
         let mut payee = None;
-        if let Some(actual_title) = token.next() {
-            payee = Some(title);
-            title = inner_str(actual_title.into_inner().next().ok_or(anyhow::Error::msg(
-                "invalid next token, expected title inner str",
-            ))?);
+        let mut tags = Vec::new();
+        let mut links = Vec::new();
+        for next in token {
+            match next.as_rule() {
+                Rule::trx_title => {
+                    payee = Some(title);
+                    title = inner_str(next.into_inner().next().ok_or(anyhow::Error::msg(
+                        "invalid next token, expected title inner str",
+                    ))?);
+                }
+                Rule::trx_tag => tags.push(&next.as_str()[1..]),
+                Rule::trx_link => links.push(&next.as_str()[1..]),
+                _ => return Err(anyhow!("invalid next token: {}", next.as_str())),
+            }
         }
 
         Ok(TxnHeader {
             state,
             payee,
             title,
+            tags,
+            links,
+            metadata: Vec::new(),
         })
     }
 }
@@ -65,25 +115,43 @@ impl<'th> TxnHeader<'th> {
 pub struct ParsedTransaction<'tl> {
     pub(crate) accounts: Vec<ParsedAccount<'tl>>,
     pub(crate) exchanges: Vec<Option<ParsedAmount<'tl>>>,
+    pub(crate) metadata: Vec<Metadata<'tl>>,
 }
 
 impl<'tl> ParsedTransaction<'tl> {
+    /// Posted accounts along with their amounts, `None` for the elided one.
+    pub fn postings(
+        &self,
+    ) -> impl Iterator<Item = (&ParsedAccount<'tl>, Option<&ParsedAmount<'tl>>)> {
+        self.accounts
+            .iter()
+            .zip(self.exchanges.iter().map(Option::as_ref))
+    }
+
     pub fn parse(token: Pair<'tl, Rule>) -> Result<ParsedTransaction<'tl>> {
         let pairs = token.into_inner();
         let mut txnlist = ParsedTransaction {
             accounts: Vec::new(),
             exchanges: Vec::new(),
+            metadata: Vec::new(),
         };
 
         for pair in pairs {
-            let mut tpairs = pair.into_inner();
+            let mut tpairs = pair.into_inner().peekable();
             txnlist
                 .accounts
                 .push(statement::parse_next!(ParsedAccount, tpairs));
-            let exchg = tpairs
-                .next()
-                .map(|amount_token| ParsedAmount::parse(amount_token).unwrap());
+            let mut exchg = tpairs
+                .next_if(|pair| pair.as_rule() != Rule::metadata)
+                .map(ParsedAmount::parse)
+                .transpose()?;
+            if let Some(price) = tpairs.next_if(|pair| pair.as_rule() == Rule::price) {
+                if let Some(amount) = exchg.as_mut() {
+                    amount.price = Some(ParsedPrice::parse(price)?);
+                }
+            }
             txnlist.exchanges.push(exchg);
+            txnlist.metadata.push(statement::parse_metadata(tpairs)?);
         }
 
         let elided_count = txnlist
@@ -100,6 +168,7 @@ impl<'tl> ParsedTransaction<'tl> {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TransactionState {
     Settled,   // '*'
     Unsettled, // '!'
@@ -109,20 +178,223 @@ pub enum TransactionState {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Exchange {
-    pub account: TxnAccount,
-    pub amount: Option<Amount>,
+    pub(crate) account: TxnAccount,
+    pub(crate) amount: Option<Amount>,
+    /// The amount as written in the ledger, reused when writing the posting back while
+    /// its amount is unchanged.
+    pub(crate) amount_text: Option<String>,
+    /// Whether the amount was left out in the ledger and computed from the other postings,
+    /// so it's left out again when the posting is written back.
+    pub(crate) elided: bool,
+    /// `key: "value"` lines written below the posting.
+    pub(crate) metadata: BTreeMap<String, String>,
+    /// `@` or `@@` annotation of the amount.
+    pub(crate) price: Option<Price>,
+}
+
+impl Exchange {
+    /// A posting to the account, without an amount when it's `None`, to be computed from
+    /// the other postings of the transaction.
+    pub fn new(account: TxnAccount, amount: Option<Amount>) -> Self {
+        Exchange {
+            account,
+            amount,
+            amount_text: None,
+            elided: false,
+            metadata: BTreeMap::new(),
+            price: None,
+        }
+    }
+
+    pub fn with_price(mut self, price: Price) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn account(&self) -> &TxnAccount {
+        &self.account
+    }
+
+    /// The amount of the posting, computed for elided postings when the rest of the
+    /// transaction is in a single unit.
+    pub fn amount(&self) -> Option<&Amount> {
+        self.amount.as_ref()
+    }
+
+    /// The amount as written in the ledger, `None` for postings not read from a ledger.
+    pub fn amount_text(&self) -> Option<&str> {
+        self.amount_text.as_deref()
+    }
+
+    /// Whether the amount was left out in the ledger.
+    pub fn is_elided(&self) -> bool {
+        self.elided
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    pub fn price(&self) -> Option<&Price> {
+        self.price.as_ref()
+    }
+
+    /// What the posting counts for in the balance of the transaction: its amount, or the
+    /// cost of the amount in the price unit when it's annotated with a price.
+    pub fn weight(&self) -> Result<Option<Amount>> {
+        let Some(amount) = &self.amount else {
+            return Ok(None);
+        };
+        Ok(Some(match &self.price {
+            Some(price) => price.weight(amount)?,
+            None => amount.clone(),
+        }))
+    }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transaction {
-    pub state: TransactionState,
-    pub payee: Option<String>,
-    pub title: String,
-    pub exchanges: Vec<Exchange>,
+    pub(crate) state: TransactionState,
+    pub(crate) payee: Option<String>,
+    pub(crate) title: String,
+    pub(crate) exchanges: Vec<Exchange>,
+    /// `#tags` of the header, without the `#`, in the order written.
+    pub(crate) tags: IndexSet<String>,
+    /// `^links` of the header, without the `^`, tying related transactions together.
+    pub(crate) links: IndexSet<String>,
+    /// Transaction-wide metadata, from the ledger or attached by an
+    /// [`Enricher`][crate::Enricher].
+    pub(crate) metadata: BTreeMap<String, String>,
 }
 
 impl Transaction {
+    /// A transaction without payee, postings, tags, links or metadata, add them with the
+    /// `with_` methods.
+    pub fn new(state: TransactionState, title: &str) -> Self {
+        Transaction {
+            state,
+            payee: None,
+            title: title.to_string(),
+            exchanges: Vec::new(),
+            tags: IndexSet::new(),
+            links: IndexSet::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_payee(mut self, payee: &str) -> Self {
+        self.payee = Some(payee.to_string());
+        self
+    }
+
+    /// Add the posting. A posting without an amount gets the negated sum of the others
+    /// once they're all added, see [`Exchange::new`]. It's left without an amount when
+    /// the sum overflows, which [`totals`][Self::totals] then reports.
+    pub fn with_exchange(mut self, exchange: Exchange) -> Self {
+        if let Some(elided) = self.exchanges.iter_mut().find(|e| e.elided) {
+            elided.amount = None;
+            elided.elided = false;
+        }
+        self.exchanges.push(exchange);
+        let _ = self.fill_elided();
+        self
+    }
+
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.insert(tag.to_string());
+        self
+    }
+
+    pub fn with_link(mut self, link: &str) -> Self {
+        self.links.insert(link.to_string());
+        self
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn state(&self) -> TransactionState {
+        self.state
+    }
+
+    pub fn payee(&self) -> Option<&str> {
+        self.payee.as_deref()
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The postings, in the order written.
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    pub fn tags(&self) -> &IndexSet<String> {
+        &self.tags
+    }
+
+    pub fn links(&self) -> &IndexSet<String> {
+        &self.links
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Per-unit sum of the weights of the postings with an explicit amount, see
+    /// [`Exchange::weight`].
+    pub(crate) fn explicit_totals(&self) -> Result<UnitBalances> {
+        let mut totals = UnitBalances::new();
+        for exchange in &self.exchanges {
+            if let Some(amount) = exchange.weight()? {
+                let total = totals.entry(amount.unit).or_insert(Decimal::ZERO);
+                *total = checked_add(*total, amount.nominal)?;
+            }
+        }
+        Ok(totals)
+    }
+
+    /// Per-unit sum of every posting, the elided posting (if any) receiving the negated
+    /// sum of each unit. Fails when a sum is out of the range of `Decimal`.
+    pub fn totals(&self) -> Result<UnitBalances> {
+        let mut totals = self.explicit_totals()?;
+        if self.exchanges.iter().any(|e| e.amount.is_none()) {
+            totals.values_mut().for_each(|total| *total = Decimal::ZERO);
+        }
+        Ok(totals)
+    }
+
+    /// Whether every unit sums to zero, within the tolerance.
+    pub fn is_balanced(&self, tolerance: Decimal) -> Result<bool> {
+        Ok(self
+            .totals()?
+            .values()
+            .all(|total| total.abs() <= tolerance))
+    }
+
+    /// Whether the transaction only moves money between own accounts, every posting being
+    /// on an `Assets` or `Liabilities` account.
+    pub fn is_transfer(&self) -> bool {
+        !self.exchanges.is_empty()
+            && self.exchanges.iter().all(|e| {
+                matches!(
+                    e.account,
+                    TxnAccount::Assets(_) | TxnAccount::Liabilities(_)
+                )
+            })
+    }
+
     pub fn create<RL: ReferenceLookup>(
         ledger: &RL,
         date: NaiveDate,
@@ -147,26 +419,452 @@ impl Transaction {
                         unit: ledger.unit_lookup(&date, amount.unit)?,
                     }),
                 },
+                amount_text: parsed_trx.exchanges[idx]
+                    .as_ref()
+                    .and_then(|amount| amount.text)
+                    .map(str::to_string),
+                elided: false,
+                metadata: parsed_trx
+                    .metadata
+                    .get(idx)
+                    .into_iter()
+                    .flatten()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+                price: match parsed_trx.exchanges[idx]
+                    .as_ref()
+                    .and_then(|amount| amount.price.as_ref())
+                {
+                    None => None,
+                    Some(price) => Some(Price {
+                        amount: Amount {
+                            nominal: price.nominal,
+                            unit: ledger.unit_lookup(&date, price.unit)?,
+                        },
+                        total: price.total,
+                    }),
+                },
             });
         }
 
-        Ok(Transaction {
+        let mut transaction = Transaction {
             state: header.state,
             payee: header.payee.map(|p| p.to_string()),
             title: header.title.to_string(),
             exchanges,
-        })
+            tags: header.tags.iter().map(|tag| tag.to_string()).collect(),
+            links: header.links.iter().map(|link| link.to_string()).collect(),
+            metadata: header
+                .metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        };
+        transaction.fill_elided()?;
+        Ok(transaction)
+    }
+
+    /// Give the posting without an amount the negated sum of the other postings, flagged as
+    /// elided. A single amount can't hold several units, so the posting keeps no amount
+    /// when the other postings use more than one.
+    pub(crate) fn fill_elided(&mut self) -> Result<()> {
+        let totals = self.explicit_totals()?;
+        let (Some((&unit, &total)), 1) = (totals.iter().next(), totals.len()) else {
+            return Ok(());
+        };
+        if let Some(exchange) = self.exchanges.iter_mut().find(|e| e.amount.is_none()) {
+            exchange.amount = Some(Amount {
+                nominal: -total,
+                unit,
+            });
+            exchange.elided = true;
+        }
+        Ok(())
     }
 }
 
+/// Expected balances of an account, at most one amount per unit.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BalanceAssertion {
+    pub(crate) account: TxnAccount,
+    pub(crate) amounts: Vec<Amount>,
+    /// `Warning` for pending assertions, flagged with `!`, which are still being
+    /// reconciled and shouldn't fail verification.
+    pub(crate) severity: Severity,
+}
+
+impl BalanceAssertion {
+    /// An assertion failing verification when it doesn't match, without any amount yet.
+    pub fn new(account: TxnAccount) -> Self {
+        BalanceAssertion {
+            account,
+            amounts: Vec::new(),
+            severity: Severity::Error,
+        }
+    }
+
+    /// Expect the amount, replacing the one already expected in its unit.
+    pub fn with_amount(mut self, amount: Amount) -> Self {
+        self.amounts.retain(|expected| expected.unit != amount.unit);
+        self.amounts.push(amount);
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn account(&self) -> &TxnAccount {
+        &self.account
+    }
+
+    pub fn amounts(&self) -> &[Amount] {
+        &self.amounts
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+/// What a balance assertion checks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AssertionKind {
+    /// The balance at the start of the date, from `balance`.
+    #[default]
+    Balance,
+    /// The net change since the previous assertion of the account, from
+    /// `balance-change`.
+    Change,
+}
+
+/// A balance assertion unit that didn't match the computed balance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssertionFailure {
+    pub date: NaiveDate,
     pub account: TxnAccount,
-    pub amount: Amount,
+    pub expected: Amount,
+    /// The balance, or the change for [`AssertionKind::Change`].
+    pub actual: Decimal,
+    pub severity: Severity,
+    pub kind: AssertionKind,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PadTransaction {
-    pub target: TxnAccount,
-    pub source: TxnAccount,
+    pub(crate) target: TxnAccount,
+    pub(crate) source: TxnAccount,
+}
+
+impl PadTransaction {
+    pub fn new(target: TxnAccount, source: TxnAccount) -> Self {
+        PadTransaction { target, source }
+    }
+
+    /// The account brought to its next balance assertion.
+    pub fn target(&self) -> &TxnAccount {
+        &self.target
+    }
+
+    /// The account the difference is taken from.
+    pub fn source(&self) -> &TxnAccount {
+        &self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amount::Amount;
+    use crate::diagnostic::Severity;
+    use crate::transaction::{
+        BalanceAssertion, Exchange, PadTransaction, Transaction, TransactionState,
+    };
+    use crate::{parser, writer};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_transaction_totals() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Wallet
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Lunch"
+  Assets:Wallet
+  Expenses:Food            50_000 IDR
+  Expenses:Food             2 USD
+
+2022-01-03 * "Dinner"
+  Assets:Bank              -20 USD
+  Expenses:Food             19.5 USD
+
+2022-01-03 * "Money changer"
+  Assets:Bank              -100 USD
+  Assets:Wallet            1_500_000 IDR
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let txns = ledger
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions();
+
+        assert_eq!(
+            txns[0].totals()?.into_iter().collect::<Vec<_>>(),
+            vec![(0, dec!(0)), (1, dec!(0))]
+        );
+        assert!(txns[0].is_balanced(dec!(0))?);
+
+        assert_eq!(txns[1].totals()?.get(&0), Some(&dec!(-0.5)));
+        assert!(!txns[1].is_balanced(dec!(0.1))?);
+        assert!(txns[1].is_balanced(dec!(0.5))?);
+
+        assert_eq!(txns[2].totals()?.get(&1), Some(&dec!(1500000)));
+        assert!(!txns[2].is_balanced(dec!(0.01))?);
+
+        assert!(!txns[0].is_transfer());
+        assert!(txns[2].is_transfer());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_amount_overflow() -> Result<()> {
+        let header = r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+"#;
+        for postings in [
+            "  Expenses:Food  50000000000000000000000000000 USD\n  Expenses:Food  50000000000000000000000000000 USD\n  Assets:Bank",
+            "  Expenses:Food  50000000000000000000000000000 USD @ 10 IDR\n  Assets:Bank",
+        ] {
+            let source = format!("{}2022-01-03 * \"Lunch\"\n{}\n", header, postings);
+            let err = parser::parse(&source, None).unwrap_err();
+            assert!(err.to_string().contains("amount overflow at `"), "{}", err);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_price_annotations() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Assets:Wallet
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Money changer"
+  Assets:Wallet             100 USD @ 15_000 IDR
+  Assets:Bank
+
+2022-01-03 * "Money changer"
+  Assets:Wallet             -50 USD @@ 760_000 IDR
+  Assets:Bank               760_000 IDR
+
+2022-01-03 * "Lunch abroad"
+  Assets:Bank              -100 IDR
+  Expenses:Food               2 USD @ 45 IDR
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let txns = ledger
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions();
+        let idr = |nominal| Amount { nominal, unit: 1 };
+
+        // The elided posting gets the cost, in the price unit.
+        assert_eq!(txns[0].exchanges()[1].amount(), Some(&idr(dec!(-1500000))));
+        assert_eq!(txns[0].exchanges()[0].weight()?, Some(idr(dec!(1500000))));
+        assert!(txns[0].is_balanced(dec!(0))?);
+        // The total cost takes the sign of the amount.
+        assert_eq!(txns[1].exchanges()[0].weight()?, Some(idr(dec!(-760000))));
+        assert!(txns[1].is_balanced(dec!(0))?);
+        assert_eq!(txns[2].totals()?.get(&1), Some(&dec!(-10)));
+        assert!(!txns[2].totals()?.contains_key(&0));
+
+        let mut out = String::new();
+        writer::write_transaction(&mut out, &ledger, &date, &txns[1])?;
+        assert!(out.contains("-50 USD @@ 760000 IDR"));
+
+        assert!(parser::parse(
+            "unit USD\n2022-01-01 open Assets:Bank\n2022-01-01 open Assets:Cash\n\n2022-01-02 * \"Bad\"\n  Assets:Bank  1 USD @ -1 USD\n  Assets:Cash\n",
+            None
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builders() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-01 open Expenses:Tips
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let accounts = ledger.accounts();
+        let bank = accounts.txnify(&date, &"Assets:Bank".try_into()?)?;
+        let food = accounts.txnify(&date, &"Expenses:Food".try_into()?)?;
+        let tips = accounts.txnify(&date, &"Expenses:Tips".try_into()?)?;
+        let usd = |nominal| Amount { nominal, unit: 0 };
+
+        // The elided posting is computed again as postings are added after it.
+        let txn = Transaction::new(TransactionState::Settled, "Dinner")
+            .with_payee("Bistro")
+            .with_exchange(Exchange::new(bank.clone(), None))
+            .with_exchange(Exchange::new(food.clone(), Some(usd(dec!(20)))))
+            .with_exchange(Exchange::new(tips, Some(usd(dec!(3)))).with_metadata("note", "cash"))
+            .with_tag("trip");
+        assert_eq!((txn.payee(), txn.title()), (Some("Bistro"), "Dinner"));
+        assert_eq!(txn.exchanges()[0].amount(), Some(&usd(dec!(-23))));
+        assert!(txn.exchanges()[0].is_elided());
+        assert_eq!(
+            txn.exchanges()[2]
+                .metadata()
+                .get("note")
+                .map(String::as_str),
+            Some("cash")
+        );
+        assert!(txn.tags().contains("trip"));
+        assert!(txn.is_balanced(dec!(0))?);
+
+        let assertion = BalanceAssertion::new(food)
+            .with_amount(usd(dec!(10)))
+            .with_amount(usd(dec!(20)))
+            .with_severity(Severity::Warning);
+        assert_eq!(assertion.amounts(), &[usd(dec!(20))]);
+        assert_eq!(assertion.severity(), Severity::Warning);
+
+        let pad = PadTransaction::new(bank.clone(), bank);
+        assert_eq!(pad.target(), pad.source());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_elided() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Dinner"
+  Assets:Bank
+  Expenses:Food             19.5 USD
+  Expenses:Food              2 USD
+
+2022-01-03 * "Lunch"
+  Assets:Bank
+  Expenses:Food            50_000 IDR
+  Expenses:Food             2 USD
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2022, 1, 3).ok_or(anyhow!("invalid date"))?;
+        let txns = ledger
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions();
+
+        let elided = &txns[0].exchanges[0];
+        assert!(elided.elided);
+        assert_eq!(
+            elided.amount,
+            Some(Amount {
+                nominal: dec!(-21.5),
+                unit: 0
+            })
+        );
+        let mut out = String::new();
+        writer::write_transaction(&mut out, &ledger, &date, &txns[0])?;
+        assert!(out.contains("\n  Assets:Bank\n"));
+
+        // Several units can't be held by a single amount.
+        assert!(!txns[1].exchanges[0].elided);
+        assert_eq!(txns[1].exchanges[0].amount, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tags_and_links() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Travel
+
+2022-03-01 * "Airline" "Flight to Bali" #trip-bali ^booking-17
+  Assets:Bank
+  Expenses:Travel     400 USD
+
+2022-03-05 * "Hotel" #trip-bali #lodging
+  Assets:Bank
+  Expenses:Travel     250 USD
+
+2022-03-09 * "Airline" "Refund" ^booking-17
+  Assets:Bank
+  Expenses:Travel    -100 USD
+"#,
+            None,
+        )?;
+
+        let tagged: Vec<(NaiveDate, &str)> = ledger
+            .transactions_with_tag("#trip-bali")
+            .map(|(date, txn)| (date, txn.title.as_str()))
+            .collect();
+        let date = |d| NaiveDate::from_ymd_opt(2022, 3, d).ok_or(anyhow!("invalid date"));
+        assert_eq!(
+            tagged,
+            vec![(date(1)?, "Flight to Bali"), (date(5)?, "Hotel")]
+        );
+        assert_eq!(ledger.transactions_with_tag("lodging").count(), 1);
+        assert_eq!(ledger.transactions_with_link("booking-17").count(), 2);
+
+        let (_, txn) = ledger
+            .transactions_with_tag("lodging")
+            .next()
+            .ok_or(anyhow!("no transaction"))?;
+        assert_eq!(
+            txn.tags.iter().collect::<Vec<_>>(),
+            vec!["trip-bali", "lodging"]
+        );
+        let mut out = String::new();
+        writer::write_transaction(&mut out, &ledger, &date(5)?, txn)?;
+        assert!(out.starts_with("2022-03-05 * \"Hotel\" #trip-bali #lodging\n"));
+
+        let (_, txn) = ledger
+            .transactions_with_link("^booking-17")
+            .next()
+            .ok_or(anyhow!("no transaction"))?;
+        let mut out = String::new();
+        writer::write_transaction(&mut out, &ledger, &date(1)?, txn)?;
+        assert!(
+            out.starts_with("2022-03-01 * \"Airline\" \"Flight to Bali\" #trip-bali ^booking-17\n")
+        );
+
+        Ok(())
+    }
 }
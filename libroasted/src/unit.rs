@@ -0,0 +1,271 @@
+use crate::locale::DisplayLocale;
+use crate::parser::{inner_str, Rule};
+use crate::symbol::SymbolTable;
+use anyhow::{anyhow, Result};
+use pest::iterators::Pair;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A declared unit along with how its amounts are displayed, from the attributes of its
+/// `unit` statement, e.g. `unit USD precision=2 symbol="$" thousands=","`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnitInfo {
+    pub name: String,
+    /// Decimals amounts are displayed with, as written when unset.
+    pub precision: Option<u32>,
+    /// Written before amounts instead of the unit name after them, e.g. `$`.
+    pub symbol: Option<String>,
+    /// Separator between groups of thousands, none when unset.
+    pub thousands_separator: Option<char>,
+}
+
+impl UnitInfo {
+    pub fn new(name: &str) -> Self {
+        UnitInfo {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn parse(token: Pair<'_, Rule>) -> Result<UnitInfo> {
+        let mut pairs = token.into_inner();
+        let mut info = UnitInfo::new(
+            pairs
+                .next()
+                .ok_or(anyhow!("invalid unit declaration"))?
+                .as_str(),
+        );
+        for attribute in pairs {
+            let mut attribute = attribute.into_inner();
+            let (Some(key), Some(value)) = (attribute.next(), attribute.next()) else {
+                return Err(anyhow!("invalid unit attribute"));
+            };
+            let value = match value.as_rule() {
+                Rule::string => inner_str(value),
+                _ => value.as_str(),
+            };
+            info.set(key.as_str(), value)?;
+        }
+        Ok(info)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "precision" => {
+                self.precision =
+                    Some(value.parse().map_err(|_| {
+                        anyhow!("invalid precision of unit `{}': {}", self.name, value)
+                    })?)
+            }
+            "symbol" => self.symbol = Some(value.to_string()),
+            "thousands" => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(separator), None) => self.thousands_separator = Some(separator),
+                    _ => {
+                        return Err(anyhow!(
+                            "thousands separator of unit `{}' has to be a single character: `{}'",
+                            self.name,
+                            value
+                        ))
+                    }
+                }
+            }
+            _ => {
+                return Err(anyhow!(
+                    "unknown attribute `{}' of unit `{}'",
+                    key,
+                    self.name
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the attributes set in `other`, a later declaration of the same unit.
+    fn merge(&mut self, other: UnitInfo) {
+        self.precision = other.precision.or(self.precision);
+        self.symbol = other.symbol.or(self.symbol.take());
+        self.thousands_separator = other.thousands_separator.or(self.thousands_separator);
+    }
+
+    /// The amount as displayed in the unit, e.g. `$1,234.50` or `-1234.5 JPY`.
+    pub fn format(&self, nominal: Decimal) -> String {
+        self.format_in(&DisplayLocale::default(), nominal)
+    }
+
+    /// The amount as displayed in the unit with the separators of the locale.
+    pub fn format_in(&self, locale: &DisplayLocale, nominal: Decimal) -> String {
+        let mut nominal = nominal;
+        if let Some(precision) = self.precision {
+            nominal =
+                nominal.round_dp_with_strategy(precision, RoundingStrategy::MidpointAwayFromZero);
+            nominal.rescale(precision);
+        }
+        let text = nominal.abs().to_string();
+        let (integral, fraction) = match text.split_once('.') {
+            Some((integral, fraction)) => (integral, Some(fraction)),
+            None => (text.as_str(), None),
+        };
+
+        let thousands_separator = match self.thousands_separator {
+            Some(separator) if separator == locale.decimal_separator => {
+                locale.thousands_separator.or(Some(separator))
+            }
+            Some(separator) => Some(separator),
+            None => locale.thousands_separator,
+        };
+        let mut digits = String::new();
+        for (idx, digit) in integral.chars().enumerate() {
+            if idx > 0 && (integral.len() - idx) % 3 == 0 {
+                if let Some(separator) = thousands_separator {
+                    digits.push(separator);
+                }
+            }
+            digits.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            digits.push(locale.decimal_separator);
+            digits.push_str(fraction);
+        }
+
+        let sign = if nominal.is_sign_negative() && !nominal.is_zero() {
+            "-"
+        } else {
+            ""
+        };
+        match &self.symbol {
+            Some(symbol) => format!("{}{}{}", sign, symbol, digits),
+            None => format!("{}{} {}", sign, digits, self.name),
+        }
+    }
+}
+
+/// The `unit` statement declaring the unit, with its attributes.
+impl fmt::Display for UnitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unit {}", self.name)?;
+        if let Some(precision) = self.precision {
+            write!(f, " precision={}", precision)?;
+        }
+        if let Some(symbol) = &self.symbol {
+            write!(f, " symbol=\"{}\"", symbol)?;
+        }
+        if let Some(separator) = self.thousands_separator {
+            write!(f, " thousands=\"{}\"", separator)?;
+        }
+        Ok(())
+    }
+}
+
+/// Declared units, indexed in declaration order like a [`SymbolTable`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct UnitStore {
+    names: SymbolTable<String>,
+    infos: Vec<UnitInfo>,
+}
+
+impl UnitStore {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Declare the unit, or update the attributes of an already declared one. Returns its
+    /// index, along with whether it's newly declared.
+    pub fn declare(&mut self, info: UnitInfo) -> (usize, bool) {
+        match self.names.get(info.name.as_str()) {
+            Some(idx) => {
+                self.infos[idx].merge(info);
+                (idx, false)
+            }
+            None => {
+                let idx = self.names.intern(info.name.clone());
+                self.infos.push(info);
+                (idx, true)
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.names.get(name)
+    }
+
+    pub fn resolve(&self, idx: usize) -> Option<&String> {
+        self.names.resolve(idx)
+    }
+
+    pub fn info(&self, idx: usize) -> Option<&UnitInfo> {
+        self.infos.get(idx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Unit names in index order.
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.names.iter()
+    }
+
+    /// Every unit in index order.
+    pub fn infos(&self) -> impl Iterator<Item = &UnitInfo> {
+        self.infos.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::unit::UnitInfo;
+    use anyhow::Result;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_unit_info() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD precision=2 symbol="$" thousands=","
+unit JPY precision=0
+unit IDR
+unit IDR thousands="."
+"#,
+            None,
+        )?;
+        let usd = ledger.unit_info(0)?;
+        assert_eq!(usd.format(dec!(1234567.5)), "$1,234,567.50");
+        assert_eq!(usd.format(dec!(-0.004)), "$0.00");
+        assert_eq!(usd.format(dec!(-12)), "-$12.00");
+        assert_eq!(ledger.unit_info(1)?.format(dec!(1500.5)), "1501 JPY");
+        // Later declarations add to the earlier ones.
+        let idr = ledger.unit_info(2)?;
+        assert_eq!(idr.format(dec!(1500000)), "1.500.000 IDR");
+        assert_eq!(idr.to_string(), "unit IDR thousands=\".\"");
+        assert_eq!(
+            usd.to_string(),
+            "unit USD precision=2 symbol=\"$\" thousands=\",\""
+        );
+        assert_eq!(UnitInfo::new("EUR").format(dec!(1000.10)), "1000.10 EUR");
+
+        let err = parser::parse("unit USD precision=\"two\"\n", None).unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "1:1: invalid precision of unit `USD': two"
+        );
+        let err = parser::parse("unit USD color=\"green\"\n", None).unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "1:1: unknown attribute `color' of unit `USD'"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    error::{RoastedError, StatementError},
+    ledger::Ledger,
+    parser,
+    statement::Statement,
+    transaction::AssertionKind,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Every problem found while loading a ledger source, see [`Ledger::validate`].
+#[derive(Debug)]
+pub struct ValidationReport {
+    /// The ledger as loaded, without the statements that couldn't be applied.
+    pub ledger: Ledger,
+    /// Problems of single statements in source order, then those found checking the
+    /// ledger as a whole, e.g. failed balance assertions.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+/// Diagnostic code of an error applying a statement.
+fn statement_code(err: &anyhow::Error) -> &'static str {
+    match err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<StatementError>())
+    {
+        Some(StatementError::UnopenedAccount { .. }) => "unopened-account",
+        Some(StatementError::UndeclaredUnit { .. }) => "undeclared-unit",
+        Some(StatementError::LateDirective { .. }) => "late-directive",
+        None => "invalid-statement",
+    }
+}
+
+/// Source spans of the transactions and assertions applied, by date in the order they
+/// were booked, to locate the problems found checking the ledger as a whole.
+#[derive(Default)]
+struct EntrySpans {
+    transactions: HashMap<NaiveDate, Vec<Range<usize>>>,
+    balances: HashMap<NaiveDate, Vec<Range<usize>>>,
+    changes: HashMap<NaiveDate, Vec<Range<usize>>>,
+}
+
+impl EntrySpans {
+    /// Locate the entries the statement at `span` added to the daybook of the date.
+    fn record(&mut self, ledger: &Ledger, date: &NaiveDate, span: &Range<usize>) {
+        let Some(book) = ledger.get_bookings_on(date) else {
+            return;
+        };
+        for (spans, len) in [
+            (&mut self.transactions, book.transactions().len()),
+            (&mut self.balances, book.balance_assertions().len()),
+            (&mut self.changes, book.balance_changes().len()),
+        ] {
+            spans.entry(*date).or_default().resize(len, span.clone());
+        }
+    }
+
+    fn get(
+        spans: &HashMap<NaiveDate, Vec<Range<usize>>>,
+        date: &NaiveDate,
+        idx: usize,
+    ) -> Option<Range<usize>> {
+        spans.get(date).and_then(|spans| spans.get(idx)).cloned()
+    }
+}
+
+/// The diagnostic, pointing at the span when known.
+fn located(diagnostic: Diagnostic, span: Option<Range<usize>>) -> Diagnostic {
+    match span {
+        Some(span) => diagnostic.with_span(span),
+        None => diagnostic,
+    }
+}
+
+/// The error without its location, the diagnostic span points there already.
+fn unlocated(err: &anyhow::Error) -> String {
+    match err.downcast_ref::<RoastedError>() {
+        Some(RoastedError::Syntax { message, .. }) => message.clone(),
+        Some(RoastedError::Statement { error, .. }) => error.to_string(),
+        None => err.to_string(),
+    }
+}
+
+/// A warning when the statement opens an account which is already open.
+fn duplicate_open(ledger: &Ledger, statement: &Statement) -> Option<Diagnostic> {
+    let Statement::OpenAccount(date, account, ..) = statement else {
+        return None;
+    };
+    let activities = ledger
+        .accounts()
+        .lookup(account)
+        .and_then(|txn_account| ledger.accounts().activities(&txn_account))?;
+    if activities.closed_at().is_some() {
+        return None;
+    }
+    Some(Diagnostic::warning(
+        "duplicate-open",
+        format!(
+            "account `{}' is opened at {}, it's already open since {}",
+            account,
+            date,
+            activities.opened_at()
+        ),
+    ))
+}
+
+fn apply(
+    ledger: &mut Ledger,
+    input: &str,
+    span: Range<usize>,
+    statement: Result<Statement>,
+    diagnostics: &mut Vec<Diagnostic>,
+    spans: &mut EntrySpans,
+) {
+    let text = input[span.clone()].trim_end();
+    let span = span.start..span.start + text.len();
+    let statement = match statement {
+        Ok(statement) => statement,
+        Err(err) => {
+            diagnostics.push(Diagnostic::error("syntax-error", unlocated(&err)).with_span(span));
+            return;
+        }
+    };
+    if let Statement::Include(path) = statement {
+        diagnostics.push(
+            Diagnostic::warning(
+                "unchecked-include",
+                format!("included `{}' isn't checked", path),
+            )
+            .with_span(span),
+        );
+        return;
+    }
+    if let Some(diagnostic) = duplicate_open(ledger, &statement) {
+        diagnostics.push(diagnostic.with_span(span.clone()));
+    }
+    let date = statement.date().copied();
+    match ledger.process_statement(statement) {
+        Ok(()) => {
+            ledger.record_audit(text);
+            if let Some(date) = date {
+                spans.record(ledger, &date, &span);
+            }
+        }
+        Err(err) => diagnostics
+            .push(Diagnostic::error(statement_code(&err), unlocated(&err)).with_span(span)),
+    }
+}
+
+/// Load the source statement by statement, see [`Ledger::validate`].
+pub(crate) fn validate(input: &str) -> ValidationReport {
+    let mut ledger = Ledger::new();
+    let mut diagnostics = Vec::new();
+    let mut spans = EntrySpans::default();
+    for (span, statement) in parser::spanned_statements(input) {
+        apply(
+            &mut ledger,
+            input,
+            span,
+            statement,
+            &mut diagnostics,
+            &mut spans,
+        );
+    }
+
+    for (date, book) in ledger.bookings() {
+        for (idx, txn) in book.transactions().iter().enumerate() {
+            let span = EntrySpans::get(&spans.transactions, date, idx);
+            // Without prices, transactions in several units can't be told apart from
+            // exchanges.
+            let totals = match txn.totals() {
+                Ok(totals) => totals,
+                Err(err) => {
+                    diagnostics.push(located(
+                        Diagnostic::error(
+                            "unbalanced-transaction",
+                            format!("transaction `{}' at {}: {}", txn.title, date, err),
+                        ),
+                        span,
+                    ));
+                    continue;
+                }
+            };
+            let (1, Some((&unit, &total))) = (totals.len(), totals.iter().next()) else {
+                continue;
+            };
+            if total.is_zero() {
+                continue;
+            }
+            diagnostics.push(located(
+                Diagnostic::error(
+                    "unbalanced-transaction",
+                    format!(
+                        "transaction `{}' at {} is off by {} {}",
+                        txn.title,
+                        date,
+                        total,
+                        ledger.unit_name(unit).unwrap_or_default()
+                    ),
+                ),
+                span,
+            ));
+        }
+    }
+    let failures = ledger.failed_assertions().unwrap_or_else(|err| {
+        diagnostics.push(Diagnostic::error("failed-assertion", err.to_string()));
+        Vec::new()
+    });
+    for failure in failures {
+        let account = ledger.account_name(&failure.account).unwrap_or_default();
+        let unit = ledger.unit_name(failure.expected.unit).unwrap_or_default();
+        let (what, asserted, spans) = match failure.kind {
+            AssertionKind::Balance => (
+                "balance",
+                ledger
+                    .get_bookings_on(&failure.date)
+                    .map(|book| book.balance_assertions()),
+                &spans.balances,
+            ),
+            AssertionKind::Change => (
+                "change since the previous assertion",
+                ledger
+                    .get_bookings_on(&failure.date)
+                    .map(|book| book.balance_changes()),
+                &spans.changes,
+            ),
+        };
+        let span = asserted
+            .and_then(|asserted| {
+                asserted
+                    .iter()
+                    .position(|assertion| assertion.account == failure.account)
+            })
+            .and_then(|idx| EntrySpans::get(spans, &failure.date, idx));
+        let diagnostic = Diagnostic {
+            severity: failure.severity,
+            ..Diagnostic::error(
+                "failed-assertion",
+                format!(
+                    "{} of `{}' at {} is {} {}, asserted {} {}",
+                    what,
+                    account,
+                    failure.date,
+                    failure.actual,
+                    unit,
+                    failure.expected.nominal,
+                    unit
+                ),
+            )
+        };
+        diagnostics.push(located(diagnostic, span));
+    }
+    if let Ok(pads) = ledger.pad_diagnostics() {
+        diagnostics.extend(pads);
+    }
+
+    ValidationReport {
+        ledger,
+        diagnostics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Severity;
+    use crate::ledger::Ledger;
+
+    #[test]
+    fn test_validate() {
+        let source = r#"unit USD
+2022-01-01 open Assets:Bank
+2022-01-01 open Expenses:Food
+2022-01-02 open Assets:Bank
+
+2022-01-03 * "Groceries"
+  Assets:Bank             -30 USD
+  Expenses:Food            30 USD
+
+2022-01-04 * "Taxi"
+  Assets:Bank
+  Expenses:Transport       12 USD
+
+2022-01-05 * "Lunch"
+  Assets:Bank             -15 USD
+  Expenses:Food            12 USD
+
+2022-01-06 * "Coffee"
+  Assets:Bank
+  Expenses:Food             3 EUR
+
+2022-01-07 balance Assets:Bank  -50 USD
+
+2022-01-08 * broken
+"#;
+
+        let report = Ledger::validate(source);
+        let found: Vec<(&str, Severity, &str)> = report
+            .diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.code,
+                    d.severity,
+                    d.span.clone().map_or("", |span| &source[span]),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "duplicate-open",
+                    Severity::Warning,
+                    "2022-01-02 open Assets:Bank"
+                ),
+                (
+                    "unopened-account",
+                    Severity::Error,
+                    "2022-01-04 * \"Taxi\"\n  Assets:Bank\n  Expenses:Transport       12 USD"
+                ),
+                (
+                    "undeclared-unit",
+                    Severity::Error,
+                    "2022-01-06 * \"Coffee\"\n  Assets:Bank\n  Expenses:Food             3 EUR"
+                ),
+                ("syntax-error", Severity::Error, "2022-01-08 * broken"),
+                (
+                    "unbalanced-transaction",
+                    Severity::Error,
+                    "2022-01-05 * \"Lunch\"\n  Assets:Bank             -15 USD\n  Expenses:Food            12 USD"
+                ),
+                (
+                    "failed-assertion",
+                    Severity::Error,
+                    "2022-01-07 balance Assets:Bank  -50 USD"
+                ),
+            ]
+        );
+        assert_eq!(
+            report.diagnostics[4].message,
+            "transaction `Lunch' at 2022-01-05 is off by -3 USD"
+        );
+        assert_eq!(
+            report.diagnostics[5].message,
+            "balance of `Assets:Bank' at 2022-01-07 is -45 USD, asserted -50 USD"
+        );
+        assert!(report.has_errors());
+        assert_eq!(report.errors().count(), 5);
+        assert_eq!(report.ledger.transactions_between(..).count(), 2);
+
+        let source =
+            "option \"directive_order\" \"strict\"\n2022-01-01 open Assets:Bank\nunit USD\n";
+        let report = Ledger::validate(source);
+        let late = &report.diagnostics[0];
+        assert_eq!(late.code, "late-directive");
+        assert_eq!(
+            late.span.clone().map(|span| &source[span]),
+            Some("unit USD")
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    amount::Amount,
+    ledger::Ledger,
+    register::{RegisterCursor, RegisterFilter},
+    transaction::TransactionState,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AmountView {
+    pub nominal: Decimal,
+    pub unit: String,
+}
+
+impl AmountView {
+    pub fn new(ledger: &Ledger, amount: &Amount) -> Result<Self> {
+        Ok(AmountView {
+            nominal: amount.nominal,
+            unit: ledger.unit_name(amount.unit)?.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AccountView {
+    pub name: String,
+    pub opened_at: NaiveDate,
+    pub closed_at: Option<NaiveDate>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PostingView {
+    /// Opaque position of the posting, usable as the `after` page input.
+    pub cursor: String,
+    pub date: NaiveDate,
+    /// `settled`, `unsettled`, `recurring`, or `virtual`, the same as in the SQLite
+    /// export.
+    pub state: String,
+    pub payee: Option<String>,
+    pub title: String,
+    pub account: String,
+    /// `None` when the amount is elided and can't be computed, see
+    /// [`Exchange::elided`][crate::Exchange::elided].
+    pub amount: Option<AmountView>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BalanceView {
+    pub account: String,
+    pub amounts: Vec<AmountView>,
+}
+
+/// Pagination arguments, `first` items after the `after` cursor.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PageInput {
+    pub first: usize,
+    pub after: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Page<T> {
+    pub nodes: Vec<T>,
+    /// Cursor to pass as `after` for the next page, `None` on the last page.
+    pub end_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn has_next_page(&self) -> bool {
+        self.end_cursor.is_some()
+    }
+}
+
+fn state_name(state: TransactionState) -> &'static str {
+    match state {
+        TransactionState::Settled => "settled",
+        TransactionState::Virtual => "virtual",
+        TransactionState::Unsettled => "unsettled",
+        TransactionState::Recurring => "recurring",
+    }
+}
+
+/// Every declared account, whether it's currently open or not.
+pub fn accounts(ledger: &Ledger) -> Result<Vec<AccountView>> {
+    ledger
+        .accounts()
+        .iter()
+        .map(|(account, activities)| {
+            Ok(AccountView {
+                name: ledger.account_name(&account)?,
+                opened_at: *activities.opened_at(),
+                closed_at: activities.closed_at().copied(),
+                metadata: ledger
+                    .account_metadata(&account)
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// A page of postings matching the filter, see [`Ledger::register_page`].
+pub fn postings(
+    ledger: &Ledger,
+    filter: &RegisterFilter,
+    page: &PageInput,
+) -> Result<Page<PostingView>> {
+    let after = page
+        .after
+        .as_deref()
+        .map(str::parse::<RegisterCursor>)
+        .transpose()?;
+    let register = ledger.register_page(filter, after.as_ref(), page.first)?;
+
+    let nodes = register
+        .entries
+        .iter()
+        .map(|entry| {
+            Ok(PostingView {
+                cursor: entry.cursor.to_string(),
+                date: entry.cursor.date,
+                state: state_name(entry.transaction.state).to_string(),
+                payee: entry.transaction.payee.clone(),
+                title: entry.transaction.title.clone(),
+                account: ledger.account_name(&entry.exchange.account)?,
+                amount: entry
+                    .exchange
+                    .amount
+                    .as_ref()
+                    .map(|amount| AmountView::new(ledger, amount))
+                    .transpose()?,
+                metadata: entry.exchange.metadata.clone(),
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Page {
+        nodes,
+        end_cursor: register.next.map(|cursor| cursor.to_string()),
+    })
+}
+
+/// Balances at the end of the date, in presentation order, hidden accounts left out.
+pub fn balances(ledger: &Ledger, date: &NaiveDate) -> Result<Vec<BalanceView>> {
+    let balances = ledger.balances_at(date)?;
+    balances
+        .layout(ledger, false)?
+        .into_iter()
+        .map(|(account, units)| {
+            Ok(BalanceView {
+                account: ledger.account_name(account)?,
+                amounts: units
+                    .iter()
+                    .map(|(&unit, &nominal)| AmountView::new(ledger, &Amount { nominal, unit }))
+                    .collect::<Result<_>>()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::register::RegisterFilter;
+    use crate::transaction::TransactionState;
+    use crate::viewmodel::{self, AmountView, PageInput};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_viewmodel() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2022-01-01 open Assets:Bank
+  order: "1"
+2022-01-01 open Expenses:Food
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-04 ! "Diner" "Lunch"
+  Assets:Bank
+  Expenses:Food            12 USD
+"#,
+            None,
+        )?;
+
+        let accounts = viewmodel::accounts(&ledger)?;
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].name, "Assets:Bank");
+        assert_eq!(accounts[0].metadata.get("order"), Some(&"1".to_string()));
+
+        let filter = RegisterFilter {
+            account: Some("Expenses".to_string()),
+            ..Default::default()
+        };
+        let first = viewmodel::postings(
+            &ledger,
+            &filter,
+            &PageInput {
+                first: 1,
+                after: None,
+            },
+        )?;
+        assert_eq!(first.nodes[0].payee.as_deref(), Some("Grocer"));
+        assert!(first.has_next_page());
+
+        let second = viewmodel::postings(
+            &ledger,
+            &filter,
+            &PageInput {
+                first: 1,
+                after: first.end_cursor,
+            },
+        )?;
+        assert_eq!(second.nodes[0].state, "unsettled");
+        assert_eq!(viewmodel::state_name(TransactionState::Virtual), "virtual");
+        assert_eq!(
+            second.nodes[0].amount,
+            Some(AmountView {
+                nominal: dec!(12),
+                unit: "USD".to_string()
+            })
+        );
+        assert!(!second.has_next_page());
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 4).ok_or(anyhow!("invalid date"))?;
+        let balances = viewmodel::balances(&ledger, &date)?;
+        let bank = balances
+            .iter()
+            .find(|balance| balance.account == "Assets:Bank")
+            .ok_or(anyhow!("no balance"))?;
+        assert_eq!(bank.amounts[0].nominal, dec!(-42));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,386 @@
+use crate::parser;
+use crate::statement::Statement;
+use anyhow::{anyhow, Result};
+
+#[cfg(feature = "fs")]
+use chrono::NaiveDate;
+#[cfg(feature = "fs")]
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Leading bytes of every encoded batch.
+const MAGIC: &[u8; 4] = b"RSTW";
+
+/// Version written by [`encode`]. Batches of any other version are rejected by
+/// [`decode`], so a client and a server disagreeing on the format fail loudly instead of
+/// appending garbage.
+pub const WIRE_VERSION: u8 = 1;
+
+/// Name of the `custom` statement [`append`] records batch ids with, e.g.
+/// `2022-01-03 custom "sync-batch" "phone-42"`.
+pub const BATCH_MARKER: &str = "sync-batch";
+
+/// Longest batch id accepted.
+const MAX_BATCH_ID: usize = 128;
+
+/// Statements sent together, identified by an id the client picks, e.g. a UUID, and
+/// keeps when sending the batch again.
+#[derive(Debug, PartialEq)]
+pub struct Batch<'s> {
+    pub id: &'s str,
+    pub statements: Vec<Statement<'s>>,
+}
+
+/// Encode a batch of statements, each written in ledger syntax, e.g. as captured by a
+/// mobile app.
+///
+/// The batch is framed text rather than a binary encoding of each statement, so any
+/// statement the grammar knows can be synced:
+///
+/// - `RSTW`, then the version byte, [`WIRE_VERSION`].
+/// - The batch id, as its length, an unsigned LEB128 varint, followed by its ASCII text.
+///   Ids are 1 to 128 ASCII letters, digits, `.`, `_`, `:` or `-`.
+/// - The number of statements, a varint.
+/// - Each statement as its length, a varint, followed by its UTF-8 text.
+///
+/// Statements are checked to parse on their own and stored without blank or comment
+/// lines, and with their indentation trimmed. Includes are rejected, the files they
+/// point to only exist on the capturing device.
+pub fn encode<'s, I>(batch_id: &str, statements: I) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = &'s str>,
+{
+    check_batch_id(batch_id)?;
+    let texts = statements
+        .into_iter()
+        .map(|text| {
+            let text = compact(text);
+            parse_one(&text)?;
+            Ok(text)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(WIRE_VERSION);
+    write_text(&mut bytes, batch_id);
+    write_varint(&mut bytes, texts.len() as u64);
+    for text in texts {
+        write_text(&mut bytes, &text);
+    }
+    Ok(bytes)
+}
+
+/// Decode a batch written by [`encode`], the statements borrowing their text from it.
+pub fn decode(bytes: &[u8]) -> Result<Batch<'_>> {
+    let (id, texts) = texts(bytes)?;
+    Ok(Batch {
+        id,
+        statements: texts.into_iter().map(parse_one).collect::<Result<_>>()?,
+    })
+}
+
+/// Append the encoded batch to the ledger file, returning how many statements were
+/// written.
+///
+/// The batch id is recorded along with the statements as a [`BATCH_MARKER`] `custom`
+/// statement, dated like the latest statement of the batch. A batch whose id is
+/// already recorded in the ledger, e.g. sent again after a lost acknowledgment, is
+/// skipped as a whole and `0` is returned, so a client can retry a batch until it
+/// succeeds. Statements aren't compared, two identical purchases on the same day are
+/// both kept.
+///
+/// The whole batch is applied to the ledger loaded from the file first and nothing is
+/// written when any statement fails, e.g. refers to an account closed on the server in
+/// the meantime.
+///
+/// A `.lock` file is created next to the ledger file from loading until writing, so
+/// concurrent appends don't check against a stale ledger or interleave. An append
+/// finding it fails, to be retried later, the lock file has to be removed by hand when
+/// left behind by a crash.
+#[cfg(feature = "fs")]
+pub fn append<P: AsRef<Path>>(path: P, bytes: &[u8]) -> Result<usize> {
+    let path = path.as_ref();
+    let (id, texts) = texts(bytes)?;
+
+    let _lock = AppendLock::acquire(path)?;
+    let mut file = fs::OpenOptions::new().append(true).open(path)?;
+    let content = fs::read_to_string(path)?;
+    let mut ledger = parser::parse_file(path, None)?;
+    let recorded = ledger.bookings().values().any(|book| {
+        book.custom().iter().any(
+            |args| matches!(args.as_slice(), [name, batch] if name == BATCH_MARKER && batch == id),
+        )
+    });
+    if recorded {
+        return Ok(0);
+    }
+
+    let mut appended = String::new();
+    let mut last_date: Option<NaiveDate> = None;
+    for text in &texts {
+        let statement = parse_one(text)?;
+        last_date = last_date.max(statement.date().copied());
+        ledger
+            .process_statement(statement)
+            .map_err(|err| anyhow!("can't append `{}': {}", first_line(text), err))?;
+        appended.push('\n');
+        appended.push_str(text);
+        appended.push('\n');
+    }
+    let date = last_date.ok_or(anyhow!(
+        "batch `{}' has no dated statement to record it with",
+        id
+    ))?;
+    appended.push_str(&format!(
+        "\n{} custom \"{}\" \"{}\"\n",
+        date, BATCH_MARKER, id
+    ));
+
+    if !content.is_empty() && !content.ends_with('\n') {
+        appended.insert(0, '\n');
+    }
+    file.write_all(appended.as_bytes())?;
+    Ok(texts.len())
+}
+
+/// Lock file held while appending to a ledger file, removed when dropped.
+#[cfg(feature = "fs")]
+struct AppendLock(PathBuf);
+
+#[cfg(feature = "fs")]
+impl AppendLock {
+    fn acquire(ledger: &Path) -> Result<Self> {
+        let mut path = ledger.as_os_str().to_owned();
+        path.push(".lock");
+        let path = PathBuf::from(path);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => Ok(AppendLock(path)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Err(anyhow!(
+                "`{}' is locked by another append, remove `{}' if none is running",
+                ledger.display(),
+                path.display()
+            )),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Drop for AppendLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn check_batch_id(id: &str) -> Result<()> {
+    let valid = (1..=MAX_BATCH_ID).contains(&id.len())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | ':' | '-'));
+    match valid {
+        true => Ok(()),
+        false => Err(anyhow!("invalid batch id: `{}'", id)),
+    }
+}
+
+/// The batch id and the statement texts of the batch, checked for the header and the
+/// framing.
+fn texts(bytes: &[u8]) -> Result<(&str, Vec<&str>)> {
+    let rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or(anyhow!("not an encoded statement batch"))?;
+    let (&version, mut rest) = rest
+        .split_first()
+        .ok_or(anyhow!("truncated statement batch"))?;
+    if version != WIRE_VERSION {
+        return Err(anyhow!(
+            "unsupported wire format version {}, expected {}",
+            version,
+            WIRE_VERSION
+        ));
+    }
+
+    let id = read_text(&mut rest)?;
+    check_batch_id(id)?;
+    let count = read_varint(&mut rest)?;
+    let mut texts = Vec::new();
+    for _ in 0..count {
+        texts.push(read_text(&mut rest)?);
+    }
+    if !rest.is_empty() {
+        return Err(anyhow!(
+            "{} trailing bytes after the statements",
+            rest.len()
+        ));
+    }
+    Ok((id, texts))
+}
+
+/// The text as a single statement, see [`encode`].
+fn parse_one(text: &str) -> Result<Statement<'_>> {
+    let mut statements = parser::parse_statements(text);
+    match (statements.next(), statements.next()) {
+        (Some(statement), None) => match statement? {
+            Statement::Include(path) => Err(anyhow!("includes can't be synced: `{}'", path)),
+            statement => Ok(statement),
+        },
+        _ => Err(anyhow!("not a single statement: `{}'", first_line(text))),
+    }
+}
+
+/// The statement without blank lines, comments or trailing whitespace, and its indented
+/// lines indented by two spaces, so the same statement is always encoded the same way.
+fn compact(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(';'))
+        .enumerate()
+        .map(|(idx, line)| match idx {
+            0 => line.to_string(),
+            _ => format!("  {}", line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn first_line(text: &str) -> &str {
+    text.trim().lines().next().unwrap_or_default()
+}
+
+fn write_text(bytes: &mut Vec<u8>, text: &str) {
+    write_varint(bytes, text.len() as u64);
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+fn read_text<'b>(bytes: &mut &'b [u8]) -> Result<&'b str> {
+    let len = usize::try_from(read_varint(bytes)?)?;
+    if len > bytes.len() {
+        return Err(anyhow!("truncated statement batch"));
+    }
+    let (text, rest) = bytes.split_at(len);
+    *bytes = rest;
+    std::str::from_utf8(text).map_err(|_| anyhow!("statement batch text isn't UTF-8"))
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or(anyhow!("truncated statement batch"))?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(anyhow!("invalid length in statement batch"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    #[cfg(feature = "fs")]
+    use crate::tempdir::TempDir;
+    use crate::wire;
+    use anyhow::Result;
+
+    const LUNCH: &str = r#"2022-01-03 * "Lunch"
+    Assets:Wallet                -50 USD   ; cash
+    Expenses:Food
+"#;
+
+    #[test]
+    fn test_encode_decode() -> Result<()> {
+        let bytes = wire::encode("phone-1", [LUNCH, "2022-01-05 close Assets:Wallet"])?;
+        assert_eq!(&bytes[..14], b"RSTW\x01\x07phone-1\x02");
+
+        let batch = wire::decode(&bytes)?;
+        assert_eq!(batch.id, "phone-1");
+        assert_eq!(batch.statements.len(), 2);
+        assert_eq!(batch.statements[0], parser::parse_statement(LUNCH)?);
+
+        assert!(wire::encode("phone-1", ["include \"other.ledger\""]).is_err());
+        assert!(wire::encode(
+            "phone-1",
+            ["2022-01-05 close Assets:Wallet\n2022-01-06 close Assets:Bank"]
+        )
+        .is_err());
+        assert!(wire::encode("phone \"1\"", [LUNCH]).is_err());
+        assert!(wire::encode("", [LUNCH]).is_err());
+
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        let err = wire::decode(&newer).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unsupported wire format version 2"));
+        assert!(wire::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(wire::decode(b"RSTW").is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_append() -> Result<()> {
+        let dir = TempDir::new("wire")?;
+        let path = dir.path("main.ledger");
+        let initial = "unit USD\n2022-01-01 open Assets:Wallet\n2022-01-01 open Expenses:Food";
+        std::fs::write(&path, initial)?;
+        const COFFEE: &str = "2022-01-04 * \"Coffee\"\n  Assets:Wallet  -5 USD\n  Expenses:Food";
+
+        // Two identical purchases on the same day are both kept.
+        let batch = wire::encode("phone-1", [LUNCH, COFFEE, COFFEE])?;
+        assert_eq!(wire::append(&path, &batch)?, 3);
+        // Retried after a lost acknowledgment.
+        assert_eq!(wire::append(&path, &batch)?, 0);
+        let content = std::fs::read_to_string(&path)?;
+        assert!(content.starts_with(&format!("{}\n\n2022-01-03 * \"Lunch\"\n", initial)));
+        assert!(content.ends_with("\n2022-01-04 custom \"sync-batch\" \"phone-1\"\n"));
+        assert_eq!(parser::parse_file(&path, None)?.postings().count(), 6);
+
+        // The same statements in another batch are new entries.
+        assert_eq!(wire::append(&path, &wire::encode("phone-2", [COFFEE])?)?, 1);
+        let content = std::fs::read_to_string(&path)?;
+
+        let rejected = wire::encode(
+            "phone-3",
+            [
+                "2022-01-04 * \"Dinner\"\n  Assets:Wallet  -20 USD\n  Expenses:Food",
+                "2022-01-04 * \"Taxi\"\n  Assets:Wallet  -5 USD\n  Expenses:Transport",
+            ],
+        )?;
+        let err = wire::append(&path, &rejected).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can't append `2022-01-04 * \"Taxi\"'"));
+        assert_eq!(std::fs::read_to_string(&path)?, content);
+
+        let lock = path.with_extension("ledger.lock");
+        std::fs::write(&lock, "")?;
+        let retried = wire::encode("phone-4", [COFFEE])?;
+        let err = wire::append(&path, &retried).unwrap_err();
+        assert!(err.to_string().contains("is locked by another append"));
+        std::fs::remove_file(&lock)?;
+        assert_eq!(wire::append(&path, &retried)?, 1);
+        assert!(!lock.exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,725 @@
+use crate::{
+    account::TxnAccount,
+    amount::Amount,
+    diagnostic::Severity,
+    ledger::{DayBook, Ledger},
+    parser,
+    precision::Precision,
+    transaction::{BalanceAssertion, Exchange, PadTransaction, Transaction, TransactionState},
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Escape the quotes and backslashes of text written as a ledger string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn state_symbol(state: TransactionState) -> &'static str {
+    match state {
+        TransactionState::Settled | TransactionState::Virtual => "*",
+        TransactionState::Unsettled => "!",
+        TransactionState::Recurring => "#",
+    }
+}
+
+/// How amounts are written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AmountStyle {
+    /// As written in the source when known, keeping e.g. trailing zeros.
+    Source,
+    /// The value only, e.g. `30 USD` for `30.00 USD`, so formatting-only edits write
+    /// the same text, see [`Ledger::fingerprint`].
+    Normalized,
+}
+
+pub fn write_amount<W: Write>(w: &mut W, ledger: &Ledger, amount: &Amount) -> Result<()> {
+    write_styled_amount(w, ledger, AmountStyle::Source, amount)
+}
+
+fn write_styled_amount<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+    amount: &Amount,
+) -> Result<()> {
+    let unit = ledger.unit_name(amount.unit)?;
+    match style {
+        AmountStyle::Source => write!(w, "{} {}", amount.nominal, unit)?,
+        AmountStyle::Normalized => write!(w, "{} {}", amount.nominal.normalize(), unit)?,
+    }
+    Ok(())
+}
+
+/// Write the amount with the decimals inferred for its unit in the account, see
+/// [`Precision::round`].
+pub fn write_amount_with_precision<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    precision: &Precision,
+    account: Option<&TxnAccount>,
+    amount: &Amount,
+) -> Result<()> {
+    write_amount(w, ledger, &precision.round(account, amount))
+}
+
+/// Write the amount of the posting as it was written in the ledger, unless it was changed
+/// since.
+fn write_exchange_amount<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+    exchange: &Exchange,
+) -> Result<()> {
+    let Some(amount) = &exchange.amount else {
+        return Ok(());
+    };
+    let source = exchange.amount_text.as_deref();
+    let unchanged = source
+        .filter(|_| style == AmountStyle::Source)
+        .filter(|text| {
+            parser::parse_amount(text).is_ok_and(|parsed| {
+                parsed.nominal() == amount.nominal
+                    && ledger
+                        .unit_name(amount.unit)
+                        .is_ok_and(|unit| unit == parsed.unit())
+            })
+        });
+    match unchanged {
+        Some(text) => write!(w, "{}", text)?,
+        None => write_styled_amount(w, ledger, style, amount)?,
+    }
+    if let Some(price) = &exchange.price {
+        write!(w, " {} ", if price.total { "@@" } else { "@" })?;
+        write_styled_amount(w, ledger, style, &price.amount)?;
+    }
+    Ok(())
+}
+
+pub fn write_open<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    account: &TxnAccount,
+) -> Result<()> {
+    writeln!(w, "{} open {}", date, ledger.account_name(account)?)?;
+    for (key, value) in ledger.account_metadata(account).into_iter().flatten() {
+        writeln!(w, "  {}: \"{}\"", key, value)?;
+    }
+    Ok(())
+}
+
+pub fn write_transaction<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    txn: &Transaction,
+) -> Result<()> {
+    write_styled_transaction(w, ledger, AmountStyle::Source, date, txn)
+}
+
+fn write_styled_transaction<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+    date: &NaiveDate,
+    txn: &Transaction,
+) -> Result<()> {
+    write!(w, "{} {}", date, state_symbol(txn.state))?;
+    if let Some(payee) = &txn.payee {
+        write!(w, " \"{}\"", payee)?;
+    }
+    write!(w, " \"{}\"", txn.title)?;
+    for tag in &txn.tags {
+        write!(w, " #{}", tag)?;
+    }
+    for link in &txn.links {
+        write!(w, " ^{}", link)?;
+    }
+    writeln!(w)?;
+    for (key, value) in &txn.metadata {
+        writeln!(w, "  {}: \"{}\"", key, value)?;
+    }
+
+    for exchange in &txn.exchanges {
+        write!(w, "  {}", ledger.account_name(&exchange.account)?)?;
+        if exchange.amount.is_some() && !exchange.elided {
+            write!(w, "  ")?;
+            write_exchange_amount(w, ledger, style, exchange)?;
+        }
+        writeln!(w)?;
+        for (key, value) in &exchange.metadata {
+            writeln!(w, "    {}: \"{}\"", key, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_balance_assertion<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    assertion: &BalanceAssertion,
+) -> Result<()> {
+    write_assertion(w, ledger, AmountStyle::Source, date, "balance", assertion)
+}
+
+/// Write a `balance-change` statement, see [`DayBook::balance_changes`].
+pub fn write_balance_change<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    change: &BalanceAssertion,
+) -> Result<()> {
+    write_assertion(
+        w,
+        ledger,
+        AmountStyle::Source,
+        date,
+        "balance-change",
+        change,
+    )
+}
+
+fn write_assertion<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+    date: &NaiveDate,
+    keyword: &str,
+    assertion: &BalanceAssertion,
+) -> Result<()> {
+    let flag = match assertion.severity {
+        Severity::Warning => "! ",
+        Severity::Error => "",
+    };
+    write!(
+        w,
+        "{} {}{} {} ",
+        date,
+        flag,
+        keyword,
+        ledger.account_name(&assertion.account)?
+    )?;
+    for (idx, amount) in assertion.amounts.iter().enumerate() {
+        if idx > 0 {
+            write!(w, ", ")?;
+        }
+        write_styled_amount(w, ledger, style, amount)?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+pub fn write_pad<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    pad: &PadTransaction,
+) -> Result<()> {
+    writeln!(
+        w,
+        "{} pad {} {}",
+        date,
+        ledger.account_name(&pad.target)?,
+        ledger.account_name(&pad.source)?
+    )?;
+    Ok(())
+}
+
+pub fn write_checkpoint<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    entries: &[BalanceAssertion],
+) -> Result<()> {
+    write_styled_checkpoint(w, ledger, AmountStyle::Source, date, entries)
+}
+
+fn write_styled_checkpoint<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+    date: &NaiveDate,
+    entries: &[BalanceAssertion],
+) -> Result<()> {
+    writeln!(w, "{} checkpoint", date)?;
+    for entry in entries {
+        for amount in &entry.amounts {
+            write!(w, "  {}  ", ledger.account_name(&entry.account)?)?;
+            write_styled_amount(w, ledger, style, amount)?;
+            writeln!(w)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write every statement recorded in the given daybook, separated by blank lines.
+pub fn write_daybook<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    book: &DayBook,
+) -> Result<()> {
+    write_styled_daybook(w, ledger, AmountStyle::Source, date, book)
+}
+
+fn write_styled_daybook<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+    date: &NaiveDate,
+    book: &DayBook,
+) -> Result<()> {
+    for custom in book.custom() {
+        write!(w, "{} custom", date)?;
+        for arg in custom {
+            write!(w, " \"{}\"", arg)?;
+        }
+        writeln!(w, "\n")?;
+    }
+
+    for pad in book.pads() {
+        write_pad(w, ledger, date, pad)?;
+        writeln!(w)?;
+    }
+
+    for txn in book.transactions() {
+        write_styled_transaction(w, ledger, style, date, txn)?;
+        writeln!(w)?;
+    }
+
+    for assertion in book.balance_assertions() {
+        write_assertion(w, ledger, style, date, "balance", assertion)?;
+        writeln!(w)?;
+    }
+
+    for change in book.balance_changes() {
+        write_assertion(w, ledger, style, date, "balance-change", change)?;
+        writeln!(w)?;
+    }
+
+    for note in book.notes() {
+        writeln!(
+            w,
+            "{} note {} \"{}\"\n",
+            date,
+            ledger.account_name(note.account())?,
+            note.text()
+        )?;
+    }
+
+    for document in book.documents() {
+        writeln!(
+            w,
+            "{} document {} \"{}\"\n",
+            date,
+            ledger.account_name(document.account())?,
+            document.path()
+        )?;
+    }
+
+    for event in book.events() {
+        writeln!(
+            w,
+            "{} event \"{}\" \"{}\"\n",
+            date,
+            event.name(),
+            event.value()
+        )?;
+    }
+
+    for raw in book.raw() {
+        writeln!(w, "{} {}\n", date, raw)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_price<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    date: &NaiveDate,
+    unit: usize,
+    price: &Amount,
+) -> Result<()> {
+    write_styled_price(w, ledger, AmountStyle::Source, date, unit, price)
+}
+
+fn write_styled_price<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+    date: &NaiveDate,
+    unit: usize,
+    price: &Amount,
+) -> Result<()> {
+    write!(w, "{} price {} ", date, ledger.unit_name(unit)?)?;
+    write_styled_amount(w, ledger, style, price)?;
+    writeln!(w)?;
+    Ok(())
+}
+
+/// Write the whole ledger back as roasted syntax: options and units first, then day by
+/// day the accounts opened, prices, checkpoints, the daybook, and the accounts closed.
+///
+/// Included files are written inline, and only the latest `open` and `close` of reopened
+/// accounts are kept. Parsing the output gives back the same accounts, units, and
+/// entries.
+pub fn write_ledger<W: Write>(w: &mut W, ledger: &Ledger) -> Result<()> {
+    write_styled_ledger(w, ledger, AmountStyle::Source)
+}
+
+pub(crate) fn write_styled_ledger<W: Write>(
+    w: &mut W,
+    ledger: &Ledger,
+    style: AmountStyle,
+) -> Result<()> {
+    for (key, value) in ledger.options() {
+        writeln!(w, "option \"{}\" \"{}\"", escape(key), escape(value))?;
+    }
+    for unit in ledger.unit_infos() {
+        writeln!(w, "{}", unit)?;
+    }
+    writeln!(w)?;
+
+    let mut opens: BTreeMap<NaiveDate, Vec<TxnAccount>> = BTreeMap::new();
+    let mut closes: BTreeMap<NaiveDate, Vec<TxnAccount>> = BTreeMap::new();
+    for (account, activities) in ledger.accounts().iter() {
+        if let Some(closed) = activities.closed_at() {
+            closes.entry(*closed).or_default().push(account.clone());
+        }
+        opens
+            .entry(*activities.opened_at())
+            .or_default()
+            .push(account);
+    }
+
+    let dates: BTreeSet<&NaiveDate> = opens
+        .keys()
+        .chain(closes.keys())
+        .chain(ledger.pricebooks().keys())
+        .chain(ledger.checkpoints().keys())
+        .chain(ledger.bookings().keys())
+        .collect();
+    for date in dates {
+        if let Some(accounts) = opens.get(date) {
+            for account in accounts {
+                write_open(w, ledger, date, account)?;
+            }
+            writeln!(w)?;
+        }
+        if let Some(book) = ledger.pricebooks().get(date) {
+            for (&unit, prices) in book {
+                for (&price_unit, &nominal) in prices {
+                    let price = Amount {
+                        nominal,
+                        unit: price_unit,
+                    };
+                    write_styled_price(w, ledger, style, date, unit, &price)?;
+                }
+            }
+            writeln!(w)?;
+        }
+        if let Some(entries) = ledger.checkpoints().get(date) {
+            write_styled_checkpoint(w, ledger, style, date, entries)?;
+            writeln!(w)?;
+        }
+        if let Some(book) = ledger.bookings().get(date) {
+            write_styled_daybook(w, ledger, style, date, book)?;
+        }
+        if let Some(accounts) = closes.get(date) {
+            for account in accounts {
+                writeln!(w, "{} close {}", date, ledger.account_name(account)?)?;
+            }
+            writeln!(w)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amount::Amount;
+    use crate::parser;
+    use crate::transaction::{Exchange, Transaction};
+    use crate::writer::{write_daybook, write_transaction};
+    use anyhow::{anyhow, Result};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_write_daybook() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2021-03-01 open Assets:Cash
+2021-03-01 open Expenses:Dining
+
+2021-03-02 * "Gubuk mang Engking" "Splurge @ diner"
+  Assets:Cash
+  Expenses:Dining     50.25 USD
+
+2021-03-02 balance Assets:Cash -50.25 USD
+"#,
+            None,
+        )?;
+
+        let date = NaiveDate::from_ymd_opt(2021, 3, 2).ok_or(anyhow!("invalid date"))?;
+        let mut out = String::new();
+        write_daybook(
+            &mut out,
+            &ledger,
+            &date,
+            ledger.get_bookings_on(&date).ok_or(anyhow!("no daybook"))?,
+        )?;
+
+        assert_eq!(
+            out,
+            concat!(
+                "2021-03-02 * \"Gubuk mang Engking\" \"Splurge @ diner\"\n",
+                "  Assets:Cash\n",
+                "  Expenses:Dining  50.25 USD\n",
+                "\n",
+                "2021-03-02 balance Assets:Cash -50.25 USD\n",
+                "\n",
+            )
+        );
+
+        let reparsed = parser::parse(
+            &format!(
+                "unit USD\n2021-03-01 open Assets:Cash\n2021-03-01 open Expenses:Dining\n{}",
+                out
+            ),
+            None,
+        )?;
+        assert_eq!(
+            reparsed.get_bookings_on(&date).map(|b| b.transactions()),
+            ledger.get_bookings_on(&date).map(|b| b.transactions())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_raw_directive() -> Result<()> {
+        let input = concat!(
+            "2021-03-02 query \"dining\" \"SELECT *\"\n",
+            "  limit: 10\n",
+            "\n",
+        );
+        let ledger = parser::parse(input, None)?;
+
+        let date = NaiveDate::from_ymd_opt(2021, 3, 2).ok_or(anyhow!("invalid date"))?;
+        let book = ledger.get_bookings_on(&date).ok_or(anyhow!("no daybook"))?;
+        assert_eq!(
+            book.raw(),
+            &vec!["query \"dining\" \"SELECT *\"\n  limit: 10".to_string()]
+        );
+
+        let mut out = String::new();
+        write_daybook(&mut out, &ledger, &date, book)?;
+        assert_eq!(out, input);
+
+        assert!(parser::parse("2021-03-02 open Assets:Bank Assets:Cash\n", None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_notes_documents_events() -> Result<()> {
+        let input = concat!(
+            "2022-01-01 open Assets:Bank\n",
+            "2022-01-05 note Assets:Bank \"Asked about the \\\"premium\\\" fee\"\n",
+            "2022-01-05 document Assets:Bank \"receipts/2022-01-05.pdf\"\n",
+            "2022-01-05 event \"location\" \"Bandung\"\n",
+        );
+        let ledger = parser::parse(input, None)?;
+
+        let date = NaiveDate::from_ymd_opt(2022, 1, 5).ok_or(anyhow!("invalid date"))?;
+        let book = ledger.get_bookings_on(&date).ok_or(anyhow!("no daybook"))?;
+        assert_eq!(
+            book.notes()[0].text(),
+            "Asked about the \\\"premium\\\" fee"
+        );
+        assert_eq!(
+            ledger.account_name(book.documents()[0].account())?,
+            "Assets:Bank"
+        );
+        assert_eq!(book.documents()[0].path(), "receipts/2022-01-05.pdf");
+        assert_eq!(
+            (book.events()[0].name(), book.events()[0].value()),
+            ("location", "Bandung")
+        );
+
+        let mut out = String::new();
+        write_daybook(&mut out, &ledger, &date, book)?;
+        assert_eq!(
+            out,
+            concat!(
+                "2022-01-05 note Assets:Bank \"Asked about the \\\"premium\\\" fee\"\n\n",
+                "2022-01-05 document Assets:Bank \"receipts/2022-01-05.pdf\"\n\n",
+                "2022-01-05 event \"location\" \"Bandung\"\n\n",
+            )
+        );
+
+        let err = parser::parse("2022-01-05 note Assets:Cash \"Counted\"\n", None).unwrap_err();
+        assert!(err.to_string().contains("is not opened at"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_amount_text() -> Result<()> {
+        let ledger = parser::parse(
+            r#"
+unit USD
+2021-03-01 open Assets:Bank
+2021-03-01 open Expenses:Rent
+
+2021-03-02 * "Landlord" "Rent"
+  Assets:Bank        -1_250.00 USD
+  Expenses:Rent      50% of 2_500 USD
+"#,
+            None,
+        )?;
+        let date = NaiveDate::from_ymd_opt(2021, 3, 2).ok_or(anyhow!("invalid date"))?;
+        let txn = &ledger
+            .get_bookings_on(&date)
+            .ok_or(anyhow!("no daybook"))?
+            .transactions()[0];
+
+        let mut out = String::new();
+        write_transaction(&mut out, &ledger, &date, txn)?;
+        assert_eq!(
+            out,
+            concat!(
+                "2021-03-02 * \"Landlord\" \"Rent\"\n",
+                "  Assets:Bank  -1_250.00 USD\n",
+                "  Expenses:Rent  50% of 2_500 USD\n",
+            )
+        );
+
+        // Once the amount changes, its original text no longer applies.
+        let edited = Transaction {
+            state: txn.state,
+            payee: txn.payee.clone(),
+            title: txn.title.clone(),
+            tags: txn.tags.clone(),
+            links: txn.links.clone(),
+            exchanges: vec![Exchange {
+                account: txn.exchanges[0].account.clone(),
+                amount: Some(Amount {
+                    nominal: dec!(-1300),
+                    unit: 0,
+                }),
+                amount_text: txn.exchanges[0].amount_text.clone(),
+                elided: false,
+                metadata: BTreeMap::new(),
+                price: None,
+            }],
+            metadata: BTreeMap::new(),
+        };
+        let mut out = String::new();
+        write_transaction(&mut out, &ledger, &date, &edited)?;
+        assert!(out.ends_with("  Assets:Bank  -1300 USD\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_ledger() -> Result<()> {
+        let input = r#"
+option "operating_unit" "USD"
+unit USD
+unit IDR
+2022-01-01 open Assets:Bank 1_000 USD
+  institution: "ACME Bank"
+2022-01-01 open Assets:Cash
+2022-01-01 open Expenses:Food
+2022-01-02 price USD 15_000 IDR
+
+2022-01-03 * "Grocer" "Weekly groceries"
+  receipt: "g-1"
+  Assets:Bank
+  Expenses:Food            30 USD
+
+2022-01-04 pad Assets:Cash Assets:Bank
+2022-01-05 ! balance Assets:Cash 20 USD
+2022-01-05 custom "assert-option" "operating_unit" "USD"
+2022-01-06 checkpoint
+  Assets:Bank  950 USD
+2022-01-31 close Assets:Cash
+"#;
+        let ledger = parser::parse(input, None)?;
+        let out = ledger.to_ledger_string()?;
+        assert_eq!(out, ledger.to_string());
+        assert_eq!(
+            out,
+            concat!(
+                "option \"operating_unit\" \"USD\"\n",
+                "unit USD\n",
+                "unit IDR\n",
+                "\n",
+                "2022-01-01 open Assets:Bank\n",
+                "  institution: \"ACME Bank\"\n",
+                "2022-01-01 open Assets:Cash\n",
+                "2022-01-01 open Equity:Opening-Balances\n",
+                "2022-01-01 open Expenses:Food\n",
+                "\n",
+                "2022-01-01 * \"Opening balance\"\n",
+                "  Assets:Bank  1_000 USD\n",
+                "  Equity:Opening-Balances\n",
+                "\n",
+                "2022-01-02 price USD 15000 IDR\n",
+                "\n",
+                "2022-01-03 * \"Grocer\" \"Weekly groceries\"\n",
+                "  receipt: \"g-1\"\n",
+                "  Assets:Bank\n",
+                "  Expenses:Food  30 USD\n",
+                "\n",
+                "2022-01-04 pad Assets:Cash Assets:Bank\n",
+                "\n",
+                "2022-01-05 custom \"assert-option\" \"operating_unit\" \"USD\"\n",
+                "\n",
+                "2022-01-05 ! balance Assets:Cash 20 USD\n",
+                "\n",
+                "2022-01-06 checkpoint\n",
+                "  Assets:Bank  950 USD\n",
+                "\n",
+                "2022-01-31 close Assets:Cash\n",
+                "\n",
+            )
+        );
+
+        let reparsed = parser::parse(&out, None)?;
+        assert_eq!(reparsed.to_ledger_string()?, out);
+        assert_eq!(reparsed.failed_assertions()?, ledger.failed_assertions()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_escaped_options() -> Result<()> {
+        let mut ledger = parser::parse(r#"option "title" "The \"Family\" books""#, None)?;
+        assert_eq!(
+            ledger.get_option("title").map(String::as_str),
+            Some(r#"The "Family" books"#)
+        );
+        ledger.set_option("documents", r"C:\Ledger\docs");
+
+        let out = ledger.to_ledger_string()?;
+        assert!(out.contains(r#"option "title" "The \"Family\" books""#));
+        assert!(out.contains(r#"option "documents" "C:\\Ledger\\docs""#));
+        let reparsed = parser::parse(&out, None)?;
+        assert_eq!(reparsed.options(), ledger.options());
+        assert_eq!(reparsed.to_ledger_string()?, out);
+
+        Ok(())
+    }
+}